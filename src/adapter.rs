@@ -0,0 +1,634 @@
+//! An [`object_store::ObjectStore`] over [`R2Client`], for the Arrow
+//! ecosystem (DataFusion, Polars, delta-rs and friends).
+
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::DateTime;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use object_store::path::Path;
+use object_store::{
+    Attribute, AttributeValue, Attributes, CopyMode, CopyOptions, GetOptions, GetResult,
+    GetResultPayload, ListResult, MultipartUpload, ObjectMeta, PutMode, PutMultipartOptions,
+    PutPayload, PutResult, UploadPart,
+};
+
+use crate::client::R2Client;
+use crate::error::Error;
+use crate::object::{validate_key, ReadConditions};
+use crate::types::{CompletedPart, ListOptions, PutOptions, MAX_DELETE_BATCH};
+
+/// Name reported in `object_store::Error::Generic` for failures from R2.
+const STORE: &str = "R2";
+
+/// Adapts an [`R2Client`] to the `object_store` crate's `ObjectStore` trait.
+///
+/// Every operation goes through the wrapped client, so its rate limit, retry
+/// configuration, metrics observers, tracing spans, decompression and
+/// encryption all apply. Object
+/// paths are keys in the client's bucket; listing a prefix lists the keys
+/// below it as a directory (`a/b` lists `a/b/...`), as `object_store`
+/// expects.
+///
+/// Supported:
+///
+/// - `get_opts` with ranges (bounded, offset and suffix), `If-Match`,
+///   `If-None-Match`, `If-Modified-Since` and `If-Unmodified-Since`, and
+///   `head`;
+/// - `put_opts` in every `PutMode`, carrying the content type, cache
+///   control, disposition, encoding, language and user metadata attributes.
+///   `Create` sends `If-None-Match: *` and fails with
+///   `object_store::Error::AlreadyExists` when the key is taken; `Update`
+///   sends `If-Match` with the given ETag and fails with
+///   `object_store::Error::Precondition` when the object has changed;
+/// - `put_multipart_opts`, through
+///   [`create_multipart_upload`](R2Client::create_multipart_upload) and
+///   friends;
+/// - `list`, `list_with_offset` and `list_with_delimiter`;
+/// - `copy_opts` in `CopyMode::Overwrite`, and `delete_stream` in batches of
+///   [`MAX_DELETE_BATCH`](crate::MAX_DELETE_BATCH).
+///
+/// `CopyMode::Create` and object versions fail with
+/// `object_store::Error::NotImplemented`, since
+/// [`copy_object`](R2Client::copy_object) sets no precondition on the
+/// destination and R2 buckets are unversioned. A missing object surfaces as
+/// `object_store::Error::NotFound`.
+///
+/// On a client with a key provider, bodies are encrypted on `put_opts` and
+/// decrypted on `get_opts`, and sizes are plaintext sizes. A ranged
+/// `get_opts` of an encrypted object fails; read it with
+/// [`get_object_range`](R2Client::get_object_range) instead.
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use cloudflare_r2_rs::{R2Client, R2ObjectStore};
+/// use object_store::ObjectStore;
+///
+/// # fn run() -> cloudflare_r2_rs::Result<()> {
+/// let store: Arc<dyn ObjectStore> = Arc::new(R2ObjectStore::new(R2Client::from_env()?));
+/// # let _ = store;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct R2ObjectStore {
+    client: R2Client,
+}
+
+impl R2ObjectStore {
+    /// Wraps a client. Objects are read from and written to its bucket.
+    pub fn new(client: R2Client) -> Self {
+        Self { client }
+    }
+
+    /// The wrapped client.
+    pub fn client(&self) -> &R2Client {
+        &self.client
+    }
+
+    /// One `GetObject` (or `HeadObject`) request, with every option that
+    /// maps onto a request header.
+    async fn get(&self, location: &Path, options: GetOptions) -> object_store::Result<GetResult> {
+        let key = location.as_ref();
+        validate_key(key).map_err(|err| store_error(err, key))?;
+        if options.version.is_some() {
+            return Err(not_implemented("get_opts with a version"));
+        }
+        if let Some(range) = &options.range {
+            range
+                .is_valid()
+                .map_err(|err| object_store::Error::Generic {
+                    store: STORE,
+                    source: Box::new(err),
+                })?;
+        }
+
+        let conditions = ReadConditions {
+            if_match: options.if_match,
+            if_none_match: options.if_none_match,
+            if_modified_since: options
+                .if_modified_since
+                .map(|t| DateTime::from_secs(t.timestamp())),
+            if_unmodified_since: options
+                .if_unmodified_since
+                .map(|t| DateTime::from_secs(t.timestamp())),
+        };
+
+        if options.head {
+            let response = self
+                .client
+                .head_object_output(key, conditions)
+                .await
+                .map_err(|err| store_error(err, key))?;
+
+            let size = response.content_length.unwrap_or_default() as u64;
+            let attributes = attributes(
+                [
+                    (Attribute::ContentType, response.content_type),
+                    (Attribute::CacheControl, response.cache_control),
+                    (Attribute::ContentDisposition, response.content_disposition),
+                    (Attribute::ContentEncoding, response.content_encoding),
+                    (Attribute::ContentLanguage, response.content_language),
+                ],
+                response.metadata,
+            );
+            return Ok(GetResult {
+                payload: GetResultPayload::Stream(stream::empty().boxed()),
+                meta: ObjectMeta {
+                    location: location.clone(),
+                    last_modified: system_time(response.last_modified).into(),
+                    size,
+                    e_tag: response.e_tag,
+                    version: None,
+                },
+                range: 0..size,
+                attributes,
+            });
+        }
+
+        let response = self
+            .client
+            .get_object_output(
+                key,
+                options.range.as_ref().map(ToString::to_string),
+                conditions,
+            )
+            .await
+            .map_err(|err| store_error(err, key))?;
+
+        let length = response.content_length.unwrap_or_default() as u64;
+        let (range, size) = match response
+            .content_range
+            .as_deref()
+            .and_then(parse_content_range)
+        {
+            Some((range, size)) => (range, size),
+            None => (0..length, length),
+        };
+        let attributes = attributes(
+            [
+                (Attribute::ContentType, response.content_type),
+                (Attribute::CacheControl, response.cache_control),
+                (Attribute::ContentDisposition, response.content_disposition),
+                (Attribute::ContentEncoding, response.content_encoding),
+                (Attribute::ContentLanguage, response.content_language),
+            ],
+            response.metadata,
+        );
+
+        let meta = ObjectMeta {
+            location: location.clone(),
+            last_modified: system_time(response.last_modified).into(),
+            size,
+            e_tag: response.e_tag,
+            version: None,
+        };
+        let body = response.body;
+        let payload = stream::try_unfold(body, |mut body| async move {
+            match body.try_next().await {
+                Ok(Some(chunk)) => Ok(Some((chunk, body))),
+                Ok(None) => Ok(None),
+                Err(err) => Err(object_store::Error::Generic {
+                    store: STORE,
+                    source: Box::new(Error::Body(Box::new(err))),
+                }),
+            }
+        });
+
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(payload.boxed()),
+            meta,
+            range,
+            attributes,
+        })
+    }
+}
+
+impl From<R2Client> for R2ObjectStore {
+    fn from(client: R2Client) -> Self {
+        Self::new(client)
+    }
+}
+
+impl fmt::Display for R2ObjectStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "R2({})", self.client.bucket())
+    }
+}
+
+#[async_trait]
+impl object_store::ObjectStore for R2ObjectStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        opts: object_store::PutOptions,
+    ) -> object_store::Result<PutResult> {
+        let key = location.as_ref();
+        let mut options = put_options(&opts.attributes);
+        match opts.mode {
+            PutMode::Overwrite => {}
+            PutMode::Create => options.if_none_match = Some("*".to_string()),
+            PutMode::Update(version) => {
+                let etag = version.e_tag.ok_or_else(|| object_store::Error::Generic {
+                    store: STORE,
+                    source: "PutMode::Update needs an ETag".into(),
+                })?;
+                options.if_match = Some(etag);
+            }
+        }
+        let create = options.if_none_match.is_some();
+
+        let outcome = self
+            .client
+            .put_object_with(key, Bytes::from(payload), options)
+            .await
+            .map_err(|err| match store_error(err, key) {
+                object_store::Error::Precondition { path, source } if create => {
+                    object_store::Error::AlreadyExists { path, source }
+                }
+                err => err,
+            })?;
+        Ok(PutResult {
+            e_tag: outcome.etag,
+            version: None,
+        })
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &Path,
+        opts: PutMultipartOptions,
+    ) -> object_store::Result<Box<dyn MultipartUpload>> {
+        let key = location.as_ref();
+        let upload = self
+            .client
+            .create_multipart_upload(key, put_options(&opts.attributes))
+            .await
+            .map_err(|err| store_error(err, key))?;
+
+        Ok(Box::new(R2MultipartUpload {
+            client: self.client.clone(),
+            key: upload.key,
+            upload_id: upload.upload_id,
+            next_part: 1,
+            parts: Arc::default(),
+        }))
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        self.get(location, options).await
+    }
+
+    fn delete_stream(
+        &self,
+        locations: BoxStream<'static, object_store::Result<Path>>,
+    ) -> BoxStream<'static, object_store::Result<Path>> {
+        let client = self.client.clone();
+        locations
+            .try_chunks(MAX_DELETE_BATCH)
+            .map_err(|err| err.1)
+            .and_then(move |batch| {
+                let client = client.clone();
+                async move {
+                    let report = client
+                        .delete_objects(batch.iter().map(Path::as_ref))
+                        .await
+                        .map_err(|err| store_error(err, ""))?;
+                    let results: Vec<object_store::Result<Path>> = batch
+                        .into_iter()
+                        .map(|path| {
+                            match report
+                                .failed
+                                .iter()
+                                .find(|failure| failure.key == path.as_ref())
+                            {
+                                None => Ok(path),
+                                Some(failure) => Err(object_store::Error::Generic {
+                                    store: STORE,
+                                    source: format!(
+                                        "could not delete {}: {}",
+                                        failure.key,
+                                        failure
+                                            .message
+                                            .as_deref()
+                                            .or(failure.code.as_deref())
+                                            .unwrap_or("unknown error")
+                                    )
+                                    .into(),
+                                }),
+                            }
+                        })
+                        .collect();
+                    Ok(stream::iter(results))
+                }
+            })
+            .try_flatten()
+            .boxed()
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'static, object_store::Result<ObjectMeta>> {
+        self.list_pages(ListOptions {
+            prefix: directory_prefix(prefix),
+            ..ListOptions::new()
+        })
+    }
+
+    fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> BoxStream<'static, object_store::Result<ObjectMeta>> {
+        self.list_pages(ListOptions {
+            prefix: directory_prefix(prefix),
+            start_after: Some(offset.to_string()),
+            ..ListOptions::new()
+        })
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let mut result = ListResult {
+            common_prefixes: Vec::new(),
+            objects: Vec::new(),
+        };
+        let mut options = ListOptions {
+            prefix: directory_prefix(prefix),
+            delimiter: Some("/".to_string()),
+            ..ListOptions::new()
+        };
+
+        loop {
+            let page = self
+                .client
+                .list_objects(options.clone())
+                .await
+                .map_err(|err| store_error(err, prefix.map_or("", Path::as_ref)))?;
+            for common in page.common_prefixes {
+                result
+                    .common_prefixes
+                    .push(Path::parse(common.trim_end_matches('/'))?);
+            }
+            for object in page.objects {
+                result.objects.push(object_meta(object)?);
+            }
+            match page.next_continuation_token {
+                Some(token) if page.is_truncated => options.continuation_token = Some(token),
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    async fn copy_opts(
+        &self,
+        from: &Path,
+        to: &Path,
+        options: CopyOptions,
+    ) -> object_store::Result<()> {
+        if !matches!(options.mode, CopyMode::Overwrite) {
+            return Err(not_implemented("copy_opts with CopyMode::Create"));
+        }
+        self.client
+            .copy_object(from.as_ref(), to.as_ref())
+            .await
+            .map_err(|err| store_error(err, from.as_ref()))
+    }
+}
+
+impl R2ObjectStore {
+    /// Streams every object of a listing, one `ListObjectsV2` page at a time.
+    fn list_pages(
+        &self,
+        options: ListOptions,
+    ) -> BoxStream<'static, object_store::Result<ObjectMeta>> {
+        let prefix = options.prefix.clone().unwrap_or_default();
+        self.client
+            .list_stream(options)
+            .map(move |object| {
+                object
+                    .map_err(|err| store_error(err, &prefix))
+                    .and_then(object_meta)
+            })
+            .boxed()
+    }
+}
+
+/// A multipart upload started through [`R2ObjectStore`].
+///
+/// Parts are numbered in the order `put_part` is called and may upload
+/// concurrently; `complete` sorts them before finishing the upload.
+#[derive(Debug)]
+struct R2MultipartUpload {
+    client: R2Client,
+    key: String,
+    upload_id: String,
+    next_part: i32,
+    parts: Arc<Mutex<Vec<CompletedPart>>>,
+}
+
+#[async_trait]
+impl MultipartUpload for R2MultipartUpload {
+    fn put_part(&mut self, data: PutPayload) -> UploadPart {
+        let part_number = self.next_part;
+        self.next_part += 1;
+
+        let client = self.client.clone();
+        let key = self.key.clone();
+        let upload_id = self.upload_id.clone();
+        let parts = Arc::clone(&self.parts);
+        Box::pin(async move {
+            let part = client
+                .upload_part(&key, &upload_id, part_number, Bytes::from(data))
+                .await
+                .map_err(|err| store_error(err, &key))?;
+            parts
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(part);
+            Ok(())
+        })
+    }
+
+    async fn complete(&mut self) -> object_store::Result<PutResult> {
+        let mut parts =
+            std::mem::take(&mut *self.parts.lock().unwrap_or_else(PoisonError::into_inner));
+        parts.sort_by_key(|part| part.part_number);
+
+        let outcome = self
+            .client
+            .complete_multipart_upload(&self.key, &self.upload_id, parts)
+            .await
+            .map_err(|err| store_error(err, &self.key))?;
+        Ok(PutResult {
+            e_tag: outcome.etag,
+            version: None,
+        })
+    }
+
+    async fn abort(&mut self) -> object_store::Result<()> {
+        self.client
+            .abort_multipart_upload(&self.key, &self.upload_id)
+            .await
+            .map_err(|err| store_error(err, &self.key))
+    }
+}
+
+/// Maps a crate error to the `object_store` variant callers match on.
+fn store_error(err: Error, path: &str) -> object_store::Error {
+    let path = path.to_string();
+    let source = Box::new(err);
+    if source.is_not_found() {
+        return object_store::Error::NotFound { path, source };
+    }
+    match source.status() {
+        Some(304) => object_store::Error::NotModified { path, source },
+        Some(412) => object_store::Error::Precondition { path, source },
+        Some(401) => object_store::Error::Unauthenticated { path, source },
+        Some(403) => object_store::Error::PermissionDenied { path, source },
+        _ if source.is_precondition_failed() => object_store::Error::Precondition { path, source },
+        _ => object_store::Error::Generic {
+            store: STORE,
+            source,
+        },
+    }
+}
+
+fn not_implemented(operation: &str) -> object_store::Error {
+    object_store::Error::NotImplemented {
+        operation: operation.to_string(),
+        implementer: "R2ObjectStore".to_string(),
+    }
+}
+
+/// `object_store` prefixes name a directory: `a/b` means keys under `a/b/`.
+fn directory_prefix(prefix: Option<&Path>) -> Option<String> {
+    prefix
+        .map(Path::as_ref)
+        .filter(|prefix| !prefix.is_empty())
+        .map(|prefix| format!("{prefix}/"))
+}
+
+fn object_meta(object: crate::types::ObjectSummary) -> object_store::Result<ObjectMeta> {
+    Ok(ObjectMeta {
+        location: Path::parse(&object.key)?,
+        last_modified: system_time(object.last_modified).into(),
+        size: object.size.max(0) as u64,
+        e_tag: object.etag,
+        version: None,
+    })
+}
+
+fn system_time(time: Option<DateTime>) -> SystemTime {
+    time.and_then(|time| SystemTime::try_from(time).ok())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Parses `bytes <start>-<end>/<size>` into the half-open range it covers and
+/// the object's total size.
+fn parse_content_range(header: &str) -> Option<(Range<u64>, u64)> {
+    let (span, size) = header.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = span.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end: u64 = end.trim().parse().ok()?;
+    Some((start..end + 1, size.trim().parse().ok()?))
+}
+
+/// Translates `object_store` attributes into the headers a put sets.
+///
+/// Attributes R2 has no equivalent for here (the storage class) are ignored.
+fn put_options(attributes: &Attributes) -> PutOptions {
+    let mut options = PutOptions::new();
+    for (attribute, value) in attributes {
+        let value = value.as_ref().to_string();
+        match attribute {
+            Attribute::ContentType => options.content_type = Some(value),
+            Attribute::CacheControl => options.cache_control = Some(value),
+            Attribute::ContentDisposition => options.content_disposition = Some(value),
+            Attribute::ContentEncoding => options.content_encoding = Some(value),
+            Attribute::ContentLanguage => options.content_language = Some(value),
+            Attribute::Metadata(name) => {
+                options.metadata.insert(name.to_string(), value);
+            }
+            _ => {}
+        }
+    }
+    options
+}
+
+/// Builds `object_store` attributes from response headers.
+fn attributes<const N: usize>(
+    headers: [(Attribute, Option<String>); N],
+    metadata: Option<std::collections::HashMap<String, String>>,
+) -> Attributes {
+    let mut attributes = Attributes::new();
+    for (attribute, value) in headers {
+        if let Some(value) = value {
+            attributes.insert(attribute, AttributeValue::from(value));
+        }
+    }
+    for (name, value) in metadata.unwrap_or_default() {
+        attributes.insert(
+            Attribute::Metadata(name.into()),
+            AttributeValue::from(value),
+        );
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_ranges_become_half_open() {
+        assert_eq!(parse_content_range("bytes 0-4/11"), Some((0..5, 11)));
+        assert_eq!(parse_content_range("bytes 6-10/11"), Some((6..11, 11)));
+        assert_eq!(parse_content_range("bytes */11"), None);
+    }
+
+    #[test]
+    fn prefixes_name_directories() {
+        assert_eq!(
+            directory_prefix(Some(&Path::from("a/b"))).as_deref(),
+            Some("a/b/")
+        );
+        assert_eq!(directory_prefix(Some(&Path::from(""))), None);
+        assert_eq!(directory_prefix(None), None);
+    }
+
+    #[test]
+    fn missing_objects_map_to_not_found() {
+        let err = store_error(
+            Error::ObjectNotFound {
+                bucket: "b".into(),
+                key: "k".into(),
+            },
+            "k",
+        );
+        assert!(
+            matches!(err, object_store::Error::NotFound { .. }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn attributes_become_put_headers() {
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::ContentType, "text/csv".into());
+        attributes.insert(Attribute::Metadata("owner".into()), "ops".into());
+        let options = put_options(&attributes);
+        assert_eq!(options.content_type.as_deref(), Some("text/csv"));
+        assert_eq!(
+            options.metadata.get("owner").map(String::as_str),
+            Some("ops")
+        );
+    }
+}
@@ -0,0 +1,741 @@
+//! `r2`: a command-line client for Cloudflare R2, built on `cloudflare-r2-rs`.
+//!
+//! Configuration comes from the same environment variables as
+//! `R2Client::from_env`. `R2_BUCKET` is the bucket for commands that do not
+//! name one; `--bucket` overrides it, and `r2://bucket/key` locations name
+//! their own. Every command accepts `--json` for output meant for scripts.
+
+mod rules;
+
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use cloudflare_r2_rs::{
+    DateTime, DeleteReport, ListOptions, ObjectSummary, R2Client, R2Config, Result,
+};
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+
+use crate::rules::{CorsConfiguration, LifecycleConfiguration};
+
+pub(crate) type CliResult<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+const SCHEME: &str = "r2://";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "r2",
+    version,
+    about = "A command-line client for Cloudflare R2"
+)]
+struct Cli {
+    /// Print JSON instead of text, for scripts.
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// The bucket for commands that do not name one. Defaults to `R2_BUCKET`.
+    #[arg(long, short, global = true, value_name = "BUCKET")]
+    bucket: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List buckets, or the objects under an `r2://bucket/prefix`.
+    Ls {
+        location: Option<String>,
+        /// List every object under the prefix rather than one level.
+        #[arg(long, short)]
+        recursive: bool,
+    },
+    /// Copy between a local file and R2, or between two R2 locations.
+    Cp { source: String, destination: String },
+    /// Copy, then delete the source.
+    Mv { source: String, destination: String },
+    /// Delete an object, or everything under a prefix.
+    Rm {
+        location: String,
+        /// Delete every object whose key starts with the location's key, or
+        /// every object in the bucket when the location names only a bucket.
+        #[arg(long, short)]
+        recursive: bool,
+    },
+    /// Write an object's body to standard output.
+    Cat { location: String },
+    /// Show an object's metadata.
+    Head { location: String },
+    /// Create a presigned URL for an object.
+    Presign {
+        location: String,
+        /// How long the URL stays valid, in seconds.
+        #[arg(long, default_value_t = 3600)]
+        expires: u64,
+        /// The HTTP method the URL is signed for.
+        #[arg(long, value_enum, default_value_t = Method::Get)]
+        method: Method,
+    },
+    /// Create a bucket.
+    Mb { bucket: String },
+    /// Delete an empty bucket.
+    Rb { bucket: String },
+    /// Read or replace the bucket's CORS rules.
+    #[command(subcommand)]
+    Cors(RulesCommand),
+    /// Read or replace the bucket's lifecycle rules.
+    #[command(subcommand)]
+    Lifecycle(RulesCommand),
+    /// List or abort in-progress multipart uploads.
+    #[command(subcommand)]
+    Multipart(MultipartCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum RulesCommand {
+    /// Print the rules as JSON, in the AWS CLI's format.
+    Get,
+    /// Replace the rules with those in a JSON file (`-` for standard input).
+    Put { file: PathBuf },
+}
+
+#[derive(Debug, Subcommand)]
+enum MultipartCommand {
+    /// List the bucket's in-progress uploads.
+    Ls,
+    /// Abort an upload, discarding its parts.
+    Abort { location: String, upload_id: String },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Method {
+    Get,
+    Put,
+    Delete,
+    Head,
+}
+
+/// A command-line path: a file on disk, or an object or prefix in R2.
+#[derive(Debug, PartialEq, Eq)]
+enum Location {
+    Local(PathBuf),
+    Remote { bucket: String, key: String },
+}
+
+impl Location {
+    fn parse(text: &str) -> CliResult<Self> {
+        let Some(rest) = text.strip_prefix(SCHEME) else {
+            return Ok(Location::Local(PathBuf::from(text)));
+        };
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(format!("{text} names no bucket; write r2://<bucket>/<key>").into());
+        }
+        Ok(Location::Remote {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        })
+    }
+
+    fn remote(text: &str) -> CliResult<(String, String)> {
+        match Location::parse(text)? {
+            Location::Remote { bucket, key } => Ok((bucket, key)),
+            Location::Local(_) => Err(format!("{text} is not an {SCHEME} location").into()),
+        }
+    }
+
+    /// Like [`Location::remote`], but the key must be non-empty.
+    fn object(text: &str) -> CliResult<(String, String)> {
+        let (bucket, key) = Location::remote(text)?;
+        if key.is_empty() {
+            return Err(format!("{text} names a bucket, not an object").into());
+        }
+        Ok((bucket, key))
+    }
+}
+
+fn display(bucket: &str, key: &str) -> String {
+    format!("{SCHEME}{bucket}/{key}")
+}
+
+/// The last path segment of `key`, for naming a copy of it.
+fn file_name(key: &str) -> &str {
+    key.rsplit('/').next().unwrap_or(key)
+}
+
+fn timestamp(date: Option<&DateTime>) -> Option<String> {
+    date.map(DateTime::to_string)
+}
+
+struct App {
+    client: R2Client,
+    json: bool,
+    out: std::io::Stdout,
+}
+
+impl App {
+    fn bucket(&self, bucket: &str) -> R2Client {
+        if bucket == self.client.bucket() {
+            self.client.clone()
+        } else {
+            self.client.with_bucket(bucket)
+        }
+    }
+
+    /// The client for the default bucket, for commands that do not name one.
+    fn default_bucket(&self) -> CliResult<&R2Client> {
+        if self.client.bucket() == NO_BUCKET {
+            return Err("no bucket given; pass --bucket or set R2_BUCKET".into());
+        }
+        Ok(&self.client)
+    }
+
+    /// Prints `value` in JSON mode, or `text` otherwise.
+    fn emit(&self, value: Value, text: impl FnOnce() -> String) -> CliResult<()> {
+        let mut out = self.out.lock();
+        if self.json {
+            serde_json::to_writer_pretty(&mut out, &value)?;
+            writeln!(out)?;
+        } else {
+            let text = text();
+            if !text.is_empty() {
+                writeln!(out, "{text}")?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn run(&self, command: Command) -> CliResult<()> {
+        match command {
+            Command::Ls {
+                location,
+                recursive,
+            } => match location {
+                None => self.list_buckets().await,
+                Some(location) => {
+                    let (bucket, prefix) = Location::remote(&location)?;
+                    self.list(&bucket, &prefix, recursive).await
+                }
+            },
+            Command::Cp {
+                source,
+                destination,
+            } => self.copy(&source, &destination, false).await,
+            Command::Mv {
+                source,
+                destination,
+            } => self.copy(&source, &destination, true).await,
+            Command::Rm {
+                location,
+                recursive,
+            } => self.remove(&location, recursive).await,
+            Command::Cat { location } => self.cat(&location).await,
+            Command::Head { location } => self.head(&location).await,
+            Command::Presign {
+                location,
+                expires,
+                method,
+            } => self.presign(&location, expires, method).await,
+            Command::Mb { bucket } => {
+                let bucket = bucket.strip_prefix(SCHEME).unwrap_or(&bucket);
+                self.bucket(bucket).create_bucket().await?;
+                self.emit(json!({ "bucket": bucket, "created": true }), || {
+                    format!("make_bucket: {bucket}")
+                })
+            }
+            Command::Rb { bucket } => {
+                let bucket = bucket.strip_prefix(SCHEME).unwrap_or(&bucket);
+                self.bucket(bucket).delete_bucket().await?;
+                self.emit(json!({ "bucket": bucket, "deleted": true }), || {
+                    format!("remove_bucket: {bucket}")
+                })
+            }
+            Command::Cors(RulesCommand::Get) => {
+                let rules = self.default_bucket()?.get_cors().await?;
+                self.print_rules(&CorsConfiguration::from_rules(&rules))
+            }
+            Command::Cors(RulesCommand::Put { file }) => {
+                let configuration: CorsConfiguration = read_json(&file).await?;
+                let rules = configuration.into_rules()?;
+                let count = rules.len();
+                self.default_bucket()?.put_cors(rules).await?;
+                self.emit(json!({ "rules": count }), || {
+                    format!("put {count} CORS rule(s)")
+                })
+            }
+            Command::Lifecycle(RulesCommand::Get) => {
+                let rules = self.default_bucket()?.get_lifecycle().await?;
+                self.print_rules(&LifecycleConfiguration::from_rules(&rules))
+            }
+            Command::Lifecycle(RulesCommand::Put { file }) => {
+                let configuration: LifecycleConfiguration = read_json(&file).await?;
+                let rules = configuration.into_rules()?;
+                let count = rules.len();
+                self.default_bucket()?.put_lifecycle(rules).await?;
+                self.emit(json!({ "rules": count }), || {
+                    format!("put {count} lifecycle rule(s)")
+                })
+            }
+            Command::Multipart(MultipartCommand::Ls) => {
+                let uploads = self.default_bucket()?.list_multipart_uploads().await?;
+                let value = uploads
+                    .iter()
+                    .map(|upload| {
+                        json!({
+                            "key": upload.key,
+                            "upload_id": upload.upload_id,
+                            "initiated": timestamp(upload.initiated.as_ref()),
+                        })
+                    })
+                    .collect();
+                self.emit(Value::Array(value), || {
+                    uploads
+                        .iter()
+                        .map(|upload| {
+                            format!(
+                                "{:<20}  {}  {}",
+                                timestamp(upload.initiated.as_ref()).unwrap_or_default(),
+                                upload.upload_id,
+                                upload.key
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+            }
+            Command::Multipart(MultipartCommand::Abort {
+                location,
+                upload_id,
+            }) => {
+                let (bucket, key) = Location::object(&location)?;
+                self.bucket(&bucket)
+                    .abort_multipart_upload(&key, &upload_id)
+                    .await?;
+                self.emit(
+                    json!({ "key": key, "upload_id": upload_id, "aborted": true }),
+                    || format!("abort: {} {upload_id}", display(&bucket, &key)),
+                )
+            }
+        }
+    }
+
+    /// Rules are always printed as JSON: it is the only format `put` reads.
+    fn print_rules(&self, rules: &impl serde::Serialize) -> CliResult<()> {
+        let mut out = self.out.lock();
+        serde_json::to_writer_pretty(&mut out, rules)?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    async fn list_buckets(&self) -> CliResult<()> {
+        let buckets = self.client.list_buckets().await?;
+        let value = buckets
+            .iter()
+            .map(|bucket| {
+                json!({
+                    "name": bucket.name,
+                    "creation_date": timestamp(bucket.creation_date.as_ref()),
+                })
+            })
+            .collect();
+        self.emit(Value::Array(value), || {
+            buckets
+                .iter()
+                .map(|bucket| {
+                    format!(
+                        "{:<20}  {}",
+                        timestamp(bucket.creation_date.as_ref()).unwrap_or_default(),
+                        bucket.name
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    async fn list(&self, bucket: &str, prefix: &str, recursive: bool) -> CliResult<()> {
+        let client = self.bucket(bucket);
+        let (prefixes, objects) = if recursive {
+            let prefix = Some(prefix).filter(|prefix| !prefix.is_empty());
+            (Vec::new(), client.list_all_objects(prefix).await?)
+        } else {
+            let mut prefixes = Vec::new();
+            let mut objects = Vec::new();
+            let mut token = None;
+            loop {
+                let mut options = ListOptions::new().prefix(prefix).delimiter("/");
+                if let Some(token) = token {
+                    options = options.continuation_token(token);
+                }
+                let page = client.list_objects(options).await?;
+                prefixes.extend(page.common_prefixes);
+                objects.extend(page.objects);
+                match page.next_continuation_token {
+                    Some(next) if page.is_truncated => token = Some(next),
+                    _ => break,
+                }
+            }
+            (prefixes, objects)
+        };
+
+        self.emit(
+            json!({
+                "prefixes": prefixes,
+                "objects": objects.iter().map(summary_json).collect::<Vec<_>>(),
+            }),
+            || {
+                let prefixes = prefixes
+                    .iter()
+                    .map(|prefix| format!("{:>32}  {prefix}", "PRE"));
+                let objects = objects.iter().map(|object| {
+                    format!(
+                        "{:<20}  {:>10}  {}",
+                        timestamp(object.last_modified.as_ref()).unwrap_or_default(),
+                        object.size,
+                        object.key
+                    )
+                });
+                prefixes.chain(objects).collect::<Vec<_>>().join("\n")
+            },
+        )
+    }
+
+    async fn copy(&self, source: &str, destination: &str, remove_source: bool) -> CliResult<()> {
+        let (verb, target, etag) = match (Location::parse(source)?, Location::parse(destination)?) {
+            (Location::Local(path), Location::Remote { bucket, key }) => {
+                let key = destination_key(&key, &path.to_string_lossy());
+                let outcome = self.bucket(&bucket).upload_file(&key, &path).await?;
+                if remove_source {
+                    tokio::fs::remove_file(&path).await?;
+                }
+                ("upload", display(&bucket, &key), outcome.etag)
+            }
+            (Location::Remote { bucket, key }, Location::Local(path)) => {
+                if key.is_empty() {
+                    return Err(format!("{source} names a bucket, not an object").into());
+                }
+                let client = self.bucket(&bucket);
+                let path = if path.is_dir() || destination.ends_with(std::path::MAIN_SEPARATOR) {
+                    path.join(file_name(&key))
+                } else {
+                    path
+                };
+                client.download_to(&key, &path).await?;
+                if remove_source {
+                    client.delete_object(&key).await?;
+                }
+                ("download", path.display().to_string(), None)
+            }
+            (
+                Location::Remote {
+                    bucket: source_bucket,
+                    key: source_key,
+                },
+                Location::Remote { bucket, key },
+            ) => {
+                if source_key.is_empty() {
+                    return Err(format!("{source} names a bucket, not an object").into());
+                }
+                let key = destination_key(&key, &source_key);
+                // Copying an object onto itself and then deleting the source
+                // would delete the only copy.
+                if remove_source && (source_bucket.as_str(), source_key.as_str()) == (&bucket, &key)
+                {
+                    return Err(format!(
+                        "{source} and {} are the same object",
+                        display(&bucket, &key)
+                    )
+                    .into());
+                }
+                self.bucket(&bucket)
+                    .copy_object_from(&source_bucket, &source_key, &key)
+                    .await?;
+                if remove_source {
+                    self.bucket(&source_bucket)
+                        .delete_object(&source_key)
+                        .await?;
+                }
+                ("copy", display(&bucket, &key), None)
+            }
+            (Location::Local(_), Location::Local(_)) => {
+                return Err(format!(
+                    "one of {source} and {destination} must be an {SCHEME} location"
+                )
+                .into())
+            }
+        };
+
+        let verb = if remove_source { "move" } else { verb };
+        self.emit(
+            json!({ "source": source, "destination": target, "etag": etag }),
+            || format!("{verb}: {source} to {target}"),
+        )
+    }
+
+    async fn remove(&self, location: &str, recursive: bool) -> CliResult<()> {
+        let (bucket, key) = Location::remote(location)?;
+        let client = self.bucket(&bucket);
+        let report = if recursive && key.is_empty() {
+            // `delete_prefix` refuses an empty prefix so a library caller
+            // cannot empty a bucket by accident; naming the bucket with
+            // `--recursive` is this command's explicit way to do it.
+            let keys = client
+                .list_all_objects(None)
+                .await?
+                .into_iter()
+                .map(|object| object.key);
+            client.delete_objects(keys).await?
+        } else if recursive {
+            client.delete_prefix(&key).await?
+        } else {
+            if key.is_empty() {
+                return Err(format!(
+                    "{location} names a bucket; use --recursive to empty it, or rb to delete it"
+                )
+                .into());
+            }
+            client.delete_object(&key).await?;
+            let mut report = DeleteReport::default();
+            report.deleted.push(key);
+            report
+        };
+
+        self.emit(
+            json!({
+                "deleted": report.deleted,
+                "failed": report.failed.iter().map(|failure| json!({
+                    "key": failure.key,
+                    "code": failure.code,
+                    "message": failure.message,
+                })).collect::<Vec<_>>(),
+            }),
+            || {
+                report
+                    .deleted
+                    .iter()
+                    .map(|key| format!("delete: {}", display(&bucket, key)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            },
+        )?;
+        if report.all_succeeded() {
+            Ok(())
+        } else {
+            for failure in &report.failed {
+                eprintln!(
+                    "r2: could not delete {}: {}",
+                    display(&bucket, &failure.key),
+                    failure
+                        .message
+                        .as_deref()
+                        .or(failure.code.as_deref())
+                        .unwrap_or("unknown error")
+                );
+            }
+            Err(format!("{} object(s) could not be deleted", report.failed.len()).into())
+        }
+    }
+
+    async fn cat(&self, location: &str) -> CliResult<()> {
+        let (bucket, key) = Location::object(location)?;
+        let mut body = self.bucket(&bucket).get_object_stream(&key).await?;
+        let mut stdout = tokio::io::stdout();
+        while let Some(chunk) = body.try_next().await? {
+            stdout.write_all(&chunk).await?;
+        }
+        stdout.flush().await?;
+        Ok(())
+    }
+
+    async fn head(&self, location: &str) -> CliResult<()> {
+        let (bucket, key) = Location::object(location)?;
+        let head = self.bucket(&bucket).head_object(&key).await?;
+        let mut metadata: Vec<_> = head.metadata.iter().collect();
+        metadata.sort();
+
+        self.emit(
+            json!({
+                "key": key,
+                "content_length": head.content_length,
+                "content_type": head.content_type,
+                "etag": head.etag,
+                "last_modified": timestamp(head.last_modified.as_ref()),
+                "cache_control": head.cache_control,
+                "content_disposition": head.content_disposition,
+                "content_encoding": head.content_encoding,
+                "content_language": head.content_language,
+                "metadata": head.metadata,
+            }),
+            || {
+                let fields = [
+                    ("key", Some(key.clone())),
+                    ("content-length", Some(head.content_length.to_string())),
+                    ("content-type", head.content_type.clone()),
+                    ("etag", head.etag.clone()),
+                    ("last-modified", timestamp(head.last_modified.as_ref())),
+                    ("cache-control", head.cache_control.clone()),
+                    ("content-disposition", head.content_disposition.clone()),
+                    ("content-encoding", head.content_encoding.clone()),
+                    ("content-language", head.content_language.clone()),
+                ];
+                fields
+                    .into_iter()
+                    .filter_map(|(name, value)| Some(format!("{name}: {}", value?)))
+                    .chain(
+                        metadata
+                            .iter()
+                            .map(|(name, value)| format!("x-amz-meta-{name}: {value}")),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            },
+        )
+    }
+
+    async fn presign(&self, location: &str, expires: u64, method: Method) -> CliResult<()> {
+        let (bucket, key) = Location::object(location)?;
+        let client = self.bucket(&bucket);
+        let expires = Duration::from_secs(expires);
+        let request = match method {
+            Method::Get => client.presign_get(&key, expires).await?,
+            Method::Put => client.presign_put(&key, expires).await?,
+            Method::Delete => client.presign_delete(&key, expires).await?,
+            Method::Head => client.presign_head(&key, expires).await?,
+        };
+        self.emit(
+            json!({
+                "url": request.url,
+                "method": request.method,
+                "headers": request.headers,
+            }),
+            || request.url.clone(),
+        )
+    }
+}
+
+/// Where a copy of `source` lands under `key`: `key` itself, or `source`'s
+/// file name appended when `key` is empty or ends in `/`.
+fn destination_key(key: &str, source: &str) -> String {
+    if key.is_empty() || key.ends_with('/') {
+        let name = Path::new(source)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file_name(source).to_string());
+        format!("{key}{name}")
+    } else {
+        key.to_string()
+    }
+}
+
+fn summary_json(object: &ObjectSummary) -> Value {
+    json!({
+        "key": object.key,
+        "size": object.size,
+        "etag": object.etag,
+        "last_modified": timestamp(object.last_modified.as_ref()),
+    })
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(file: &Path) -> CliResult<T> {
+    let contents = if file == Path::new("-") {
+        let mut contents = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut tokio::io::stdin(), &mut contents).await?;
+        contents
+    } else {
+        tokio::fs::read(file)
+            .await
+            .map_err(|err| format!("could not read {}: {err}", file.display()))?
+    };
+    serde_json::from_slice(&contents)
+        .map_err(|err| format!("{} is not valid: {err}", file.display()).into())
+}
+
+/// Stands in for the default bucket when neither `--bucket` nor `R2_BUCKET`
+/// names one. Not a valid bucket name, so no `r2://` location matches it.
+const NO_BUCKET: &str = "-";
+
+fn client(bucket: Option<String>) -> Result<R2Client> {
+    let config = match bucket {
+        Some(bucket) => R2Config::from_env_with_bucket(bucket)?,
+        None => match R2Config::from_env() {
+            Err(cloudflare_r2_rs::Error::MissingConfig("R2_BUCKET")) => {
+                R2Config::from_env_with_bucket(NO_BUCKET)?
+            }
+            config => config?,
+        },
+    };
+    R2Client::from_config(config)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match client(cli.bucket) {
+        Ok(client) => {
+            let app = App {
+                client,
+                json: cli.json,
+                out: std::io::stdout(),
+            };
+            app.run(cli.command).await
+        }
+        Err(err) => Err(err.into()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // `r2 ls | head` closing the pipe early is not a failure.
+        Err(err)
+            if err
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|err| err.kind() == std::io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("r2: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_parse() {
+        assert_eq!(
+            Location::parse("r2://photos/2026/a.jpg").unwrap(),
+            Location::Remote {
+                bucket: "photos".into(),
+                key: "2026/a.jpg".into()
+            }
+        );
+        assert_eq!(
+            Location::parse("r2://photos").unwrap(),
+            Location::Remote {
+                bucket: "photos".into(),
+                key: String::new()
+            }
+        );
+        assert_eq!(
+            Location::parse("./a.jpg").unwrap(),
+            Location::Local(PathBuf::from("./a.jpg"))
+        );
+        assert!(Location::parse("r2:///a.jpg").is_err());
+        assert!(Location::object("r2://photos/").is_err());
+        assert!(Location::remote("a.jpg").is_err());
+    }
+
+    #[test]
+    fn copies_into_a_prefix_keep_the_source_name() {
+        assert_eq!(destination_key("", "/tmp/a.txt"), "a.txt");
+        assert_eq!(destination_key("docs/", "notes/a.txt"), "docs/a.txt");
+        assert_eq!(destination_key("docs/b.txt", "a.txt"), "docs/b.txt");
+    }
+}
@@ -0,0 +1,317 @@
+//! CORS and lifecycle rules as JSON, in the shapes the AWS CLI's
+//! `put-bucket-cors` and `put-bucket-lifecycle-configuration` accept, so
+//! existing rule files work unchanged and `get` output can be fed to `put`.
+
+use std::time::Duration;
+
+use aws_sdk_s3::types::{
+    AbortIncompleteMultipartUpload, ExpirationStatus, LifecycleExpiration,
+    LifecycleRule as SdkLifecycleRule, LifecycleRuleFilter, Transition, TransitionStorageClass,
+};
+use aws_smithy_types::date_time::Format;
+use cloudflare_r2_rs::{CorsMethod, CorsRule, DateTime, LifecycleRule};
+use serde::{Deserialize, Serialize};
+
+use crate::CliResult;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsConfiguration {
+    #[serde(rename = "CORSRules")]
+    pub rules: Vec<Cors>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct Cors {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expose_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<i32>,
+}
+
+impl CorsConfiguration {
+    pub fn from_rules(rules: &[CorsRule]) -> Self {
+        let rules = rules
+            .iter()
+            .map(|rule| Cors {
+                id: rule.id.clone(),
+                allowed_origins: rule.allowed_origins.clone(),
+                allowed_methods: rule
+                    .allowed_methods
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+                allowed_headers: rule.allowed_headers.clone(),
+                expose_headers: rule.expose_headers.clone(),
+                max_age_seconds: rule
+                    .max_age
+                    .map(|max_age| max_age.as_secs().try_into().unwrap_or(i32::MAX)),
+            })
+            .collect();
+        CorsConfiguration { rules }
+    }
+
+    pub fn into_rules(self) -> CliResult<Vec<CorsRule>> {
+        self.rules
+            .into_iter()
+            .map(|rule| {
+                let mut cors = CorsRule::new().allow_methods(
+                    rule.allowed_methods
+                        .iter()
+                        .map(|method| method.parse::<CorsMethod>())
+                        .collect::<Result<Vec<_>, _>>()?,
+                );
+                cors.id = rule.id;
+                cors.allowed_origins = rule.allowed_origins;
+                cors.allowed_headers = rule.allowed_headers;
+                cors.expose_headers = rule.expose_headers;
+                if let Some(seconds) = rule.max_age_seconds {
+                    let seconds = u64::try_from(seconds)
+                        .map_err(|_| format!("MaxAgeSeconds {seconds} is negative"))?;
+                    cors = cors.max_age(Duration::from_secs(seconds));
+                }
+                Ok(cors)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct LifecycleConfiguration {
+    pub rules: Vec<Lifecycle>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct Lifecycle {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Filter>,
+    /// The pre-`Filter` way of scoping a rule, still accepted by the API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<Expiration>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transitions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_incomplete_multipart_upload: Option<AbortIncomplete>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct Filter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct Expiration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct Transitions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    pub storage_class: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct AbortIncomplete {
+    pub days_after_initiation: i32,
+}
+
+impl LifecycleConfiguration {
+    pub fn from_rules(rules: &[LifecycleRule]) -> Self {
+        let rules = rules
+            .iter()
+            .map(|rule| SdkLifecycleRule::from(rule.clone()))
+            .map(|rule| Lifecycle {
+                id: rule.id().map(str::to_string),
+                status: rule.status().as_str().to_string(),
+                filter: rule.filter().map(|filter| Filter {
+                    prefix: filter.prefix().map(str::to_string),
+                }),
+                #[allow(deprecated)]
+                prefix: rule.prefix().map(str::to_string),
+                expiration: rule.expiration().map(|expiration| Expiration {
+                    days: expiration.days(),
+                    date: expiration.date().map(DateTime::to_string),
+                }),
+                transitions: rule
+                    .transitions()
+                    .iter()
+                    .map(|transition| Transitions {
+                        days: transition.days(),
+                        date: transition.date().map(DateTime::to_string),
+                        storage_class: transition
+                            .storage_class()
+                            .map(|class| class.as_str().to_string())
+                            .unwrap_or_default(),
+                    })
+                    .collect(),
+                abort_incomplete_multipart_upload: rule
+                    .abort_incomplete_multipart_upload()
+                    .and_then(AbortIncompleteMultipartUpload::days_after_initiation)
+                    .map(|days| AbortIncomplete {
+                        days_after_initiation: days,
+                    }),
+            })
+            .collect();
+        LifecycleConfiguration { rules }
+    }
+
+    pub fn into_rules(self) -> CliResult<Vec<LifecycleRule>> {
+        self.rules
+            .into_iter()
+            .map(|rule| {
+                let status = match rule.status.as_str() {
+                    "Enabled" => ExpirationStatus::Enabled,
+                    "Disabled" => ExpirationStatus::Disabled,
+                    other => {
+                        return Err(format!(
+                            "Status must be \"Enabled\" or \"Disabled\", not {other:?}"
+                        )
+                        .into())
+                    }
+                };
+                let expiration = rule
+                    .expiration
+                    .map(|expiration| -> CliResult<_> {
+                        Ok(LifecycleExpiration::builder()
+                            .set_days(expiration.days)
+                            .set_date(expiration.date.as_deref().map(parse_date).transpose()?)
+                            .build())
+                    })
+                    .transpose()?;
+                let transitions = rule
+                    .transitions
+                    .into_iter()
+                    .map(|transition| -> CliResult<_> {
+                        Ok(Transition::builder()
+                            .set_days(transition.days)
+                            .set_date(transition.date.as_deref().map(parse_date).transpose()?)
+                            .storage_class(TransitionStorageClass::from(
+                                transition.storage_class.as_str(),
+                            ))
+                            .build())
+                    })
+                    .collect::<CliResult<Vec<_>>>()?;
+
+                #[allow(deprecated)]
+                let builder = SdkLifecycleRule::builder().set_prefix(rule.prefix);
+                let rule = builder
+                    .set_id(rule.id)
+                    .status(status)
+                    .set_filter(rule.filter.map(|filter| {
+                        LifecycleRuleFilter::builder()
+                            .set_prefix(filter.prefix)
+                            .build()
+                    }))
+                    .set_expiration(expiration)
+                    .set_transitions(Some(transitions).filter(|t| !t.is_empty()))
+                    .set_abort_incomplete_multipart_upload(
+                        rule.abort_incomplete_multipart_upload.map(|abort| {
+                            AbortIncompleteMultipartUpload::builder()
+                                .days_after_initiation(abort.days_after_initiation)
+                                .build()
+                        }),
+                    )
+                    .build()?;
+                Ok(LifecycleRule::try_from(rule)?)
+            })
+            .collect()
+    }
+}
+
+fn parse_date(date: &str) -> CliResult<DateTime> {
+    DateTime::from_str(date, Format::DateTime)
+        .map_err(|err| format!("{date:?} is not an RFC 3339 date: {err}").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use cloudflare_r2_rs::LifecycleTrigger;
+
+    use super::*;
+
+    #[test]
+    fn cors_rules_round_trip_through_json() {
+        let json = r#"{
+            "CORSRules": [{
+                "ID": "uploads",
+                "AllowedOrigins": ["https://example.com"],
+                "AllowedMethods": ["GET", "PUT"],
+                "AllowedHeaders": ["*"],
+                "MaxAgeSeconds": 3600
+            }]
+        }"#;
+        let parsed: CorsConfiguration = serde_json::from_str(json).unwrap();
+        let rules = parsed.into_rules().unwrap();
+        assert_eq!(rules[0].allowed_methods, [CorsMethod::Get, CorsMethod::Put]);
+        assert_eq!(rules[0].max_age, Some(Duration::from_secs(3600)));
+
+        let back = serde_json::to_value(CorsConfiguration::from_rules(&rules)).unwrap();
+        assert_eq!(
+            back,
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn lifecycle_rules_round_trip_through_json() {
+        let json = r#"{
+            "Rules": [{
+                "ID": "expire-logs",
+                "Status": "Enabled",
+                "Filter": { "Prefix": "logs/" },
+                "Expiration": { "Days": 30 },
+                "Transitions": [{ "Days": 7, "StorageClass": "STANDARD_IA" }],
+                "AbortIncompleteMultipartUpload": { "DaysAfterInitiation": 2 }
+            }]
+        }"#;
+        let parsed: LifecycleConfiguration = serde_json::from_str(json).unwrap();
+        let rules = parsed.into_rules().unwrap();
+        assert!(rules[0].enabled);
+        assert_eq!(rules[0].expiration, Some(LifecycleTrigger::AfterDays(30)));
+
+        let back = serde_json::to_value(LifecycleConfiguration::from_rules(&rules)).unwrap();
+        assert_eq!(
+            back,
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        let typo = r#"{ "Rules": [{ "Status": "Enabled", "Expiry": { "Days": 1 } }] }"#;
+        assert!(serde_json::from_str::<LifecycleConfiguration>(typo).is_err());
+
+        let status = r#"{ "Rules": [{ "Status": "enabled" }] }"#;
+        let parsed: LifecycleConfiguration = serde_json::from_str(status).unwrap();
+        assert!(parsed.into_rules().is_err());
+
+        let date = r#"{ "Rules": [{ "Status": "Enabled", "Expiration": { "Date": "soon" } }] }"#;
+        let parsed: LifecycleConfiguration = serde_json::from_str(date).unwrap();
+        assert!(parsed.into_rules().is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! A synchronous client, for build scripts, CLIs and other code that does
+//! not run an async runtime.
+//!
+//! Enabled by the `blocking` feature. [`R2Client`] wraps the async
+//! [`crate::R2Client`] together with a current-thread Tokio runtime, and runs
+//! each call to completion on it. Methods take the same arguments and return
+//! the same [`Error`](crate::Error) as their async counterparts, whose docs
+//! describe them in full.
+//!
+//! ```no_run
+//! use cloudflare_r2_rs::blocking::R2Client;
+//! use cloudflare_r2_rs::ListOptions;
+//!
+//! # fn main() -> cloudflare_r2_rs::Result<()> {
+//! let client = R2Client::from_env()?;
+//!
+//! client.put_object("reports/q1.csv", "region,total\n")?;
+//! let body = client.get_object("reports/q1.csv")?;
+//! let page = client.list_objects(ListOptions::new().prefix("reports/"))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Panics
+//!
+//! Every method panics if called from within an async runtime, where
+//! blocking the thread would stall every other task on it: use the async
+//! client there. A client dropped from within an async runtime panics for
+//! the same reason, since dropping it shuts its runtime down.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::runtime::Runtime;
+
+use crate::body::IntoBody;
+use crate::config::R2Config;
+use crate::error::Result;
+use crate::presign::PresignedRequest;
+use crate::types::{
+    DeleteReport, ListOptions, ListPage, MultipartOptions, ObjectMetadata, ObjectSummary,
+    PresignOptions, PutOptions, PutOutcome,
+};
+
+/// A synchronous R2 client bound to one bucket. See the [module docs](self).
+///
+/// Cloning is cheap: clones, and [`with_bucket`](R2Client::with_bucket)
+/// children, share the connection pool and the runtime.
+#[derive(Debug, Clone)]
+pub struct R2Client {
+    inner: crate::R2Client,
+    runtime: Arc<Runtime>,
+}
+
+impl R2Client {
+    /// Wraps an async client, so that everything its builder configures —
+    /// retries, timeouts, rate limits, metrics — applies here too.
+    ///
+    /// ```no_run
+    /// # fn main() -> cloudflare_r2_rs::Result<()> {
+    /// let client = cloudflare_r2_rs::blocking::R2Client::new(
+    ///     cloudflare_r2_rs::R2Client::builder()
+    ///         .account_id("your-account-id")
+    ///         .bucket("my-bucket")
+    ///         .access_key_id("key")
+    ///         .secret_access_key("secret")
+    ///         .build()?,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Returns [`Error::Io`](crate::Error::Io) if the runtime cannot be
+    /// started.
+    pub fn new(client: crate::R2Client) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(R2Client {
+            inner: client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Builds a client from fully resolved configuration. See
+    /// [`crate::R2Client::from_config`].
+    pub fn from_config(config: R2Config) -> Result<Self> {
+        Self::new(crate::R2Client::from_config(config)?)
+    }
+
+    /// Builds a client from environment variables. See
+    /// [`crate::R2Client::from_env`].
+    pub fn from_env() -> Result<Self> {
+        Self::new(crate::R2Client::from_env()?)
+    }
+
+    /// The bucket this client is bound to.
+    pub fn bucket(&self) -> &str {
+        self.inner.bucket()
+    }
+
+    /// The endpoint URL this client sends requests to.
+    pub fn endpoint(&self) -> &str {
+        self.inner.endpoint()
+    }
+
+    /// A client for another bucket, sharing this one's connection pool,
+    /// configuration and runtime.
+    pub fn with_bucket(&self, bucket: impl Into<String>) -> Self {
+        R2Client {
+            inner: self.inner.with_bucket(bucket),
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+
+    /// The async client underneath, for the operations not mirrored here.
+    /// Its futures can be run with [`block_on`](R2Client::block_on).
+    pub fn as_async(&self) -> &crate::R2Client {
+        &self.inner
+    }
+
+    /// Runs `future` to completion on the client's runtime.
+    ///
+    /// # Panics
+    ///
+    /// If called from within an async runtime.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            panic!(
+                "the blocking R2Client cannot be used from within an async runtime; \
+                 use cloudflare_r2_rs::R2Client there instead"
+            );
+        }
+        self.runtime.block_on(future)
+    }
+
+    /// Uploads an object. See [`crate::R2Client::put_object`].
+    pub fn put_object(&self, key: &str, body: impl IntoBody) -> Result<PutOutcome> {
+        self.block_on(self.inner.put_object(key, body))
+    }
+
+    /// Uploads an object with headers and metadata. See
+    /// [`crate::R2Client::put_object_with`].
+    pub fn put_object_with(
+        &self,
+        key: &str,
+        body: impl IntoBody,
+        options: PutOptions,
+    ) -> Result<PutOutcome> {
+        self.block_on(self.inner.put_object_with(key, body, options))
+    }
+
+    /// Stores a value as a JSON document. See [`crate::R2Client::put_json`].
+    #[cfg(feature = "serde")]
+    pub fn put_json<T>(&self, key: &str, value: &T) -> Result<PutOutcome>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        self.block_on(self.inner.put_json(key, value))
+    }
+
+    /// Fetches a JSON document and deserializes it. See
+    /// [`crate::R2Client::get_json`].
+    #[cfg(feature = "serde")]
+    pub fn get_json<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<T> {
+        self.block_on(self.inner.get_json(key))
+    }
+
+    /// Downloads an object into memory. See [`crate::R2Client::get_object`].
+    pub fn get_object(&self, key: &str) -> Result<Vec<u8>> {
+        self.block_on(self.inner.get_object(key))
+    }
+
+    /// Downloads an inclusive byte range of an object. See
+    /// [`crate::R2Client::get_object_range`].
+    pub fn get_object_range(&self, key: &str, start: u64, end: Option<u64>) -> Result<Vec<u8>> {
+        self.block_on(self.inner.get_object_range(key, start, end))
+    }
+
+    /// Fetches an object's metadata. See [`crate::R2Client::head_object`].
+    pub fn head_object(&self, key: &str) -> Result<ObjectMetadata> {
+        self.block_on(self.inner.head_object(key))
+    }
+
+    /// Whether an object exists. See [`crate::R2Client::object_exists`].
+    pub fn object_exists(&self, key: &str) -> Result<bool> {
+        self.block_on(self.inner.object_exists(key))
+    }
+
+    /// Copies an object within the bucket. See
+    /// [`crate::R2Client::copy_object`].
+    pub fn copy_object(&self, source_key: &str, destination_key: &str) -> Result<()> {
+        self.block_on(self.inner.copy_object(source_key, destination_key))
+    }
+
+    /// Deletes an object. See [`crate::R2Client::delete_object`].
+    pub fn delete_object(&self, key: &str) -> Result<()> {
+        self.block_on(self.inner.delete_object(key))
+    }
+
+    /// Deletes many objects in batches. See
+    /// [`crate::R2Client::delete_objects`].
+    pub fn delete_objects<I, K>(&self, keys: I) -> Result<DeleteReport>
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.block_on(self.inner.delete_objects(keys))
+    }
+
+    /// Deletes every object under a prefix. See
+    /// [`crate::R2Client::delete_prefix`].
+    pub fn delete_prefix(&self, prefix: &str) -> Result<DeleteReport> {
+        self.block_on(self.inner.delete_prefix(prefix))
+    }
+
+    /// Lists one page of objects. See [`crate::R2Client::list_objects`].
+    pub fn list_objects(&self, options: ListOptions) -> Result<ListPage> {
+        self.block_on(self.inner.list_objects(options))
+    }
+
+    /// Lists every object, following continuation tokens. See
+    /// [`crate::R2Client::list_all_objects`].
+    pub fn list_all_objects(&self, prefix: Option<&str>) -> Result<Vec<ObjectSummary>> {
+        self.block_on(self.inner.list_all_objects(prefix))
+    }
+
+    /// Lists the "folders" directly under a prefix. See
+    /// [`crate::R2Client::list_prefixes`].
+    pub fn list_prefixes(&self, prefix: &str) -> Result<Vec<String>> {
+        self.block_on(self.inner.list_prefixes(prefix))
+    }
+
+    /// Uploads a file, switching to multipart for large ones. See
+    /// [`crate::R2Client::upload_file`].
+    pub fn upload_file(&self, key: &str, path: &Path) -> Result<PutOutcome> {
+        self.block_on(self.inner.upload_file(key, path))
+    }
+
+    /// Uploads a file with explicit multipart settings. See
+    /// [`crate::R2Client::upload_file_with`].
+    pub fn upload_file_with(
+        &self,
+        key: &str,
+        path: &Path,
+        options: MultipartOptions,
+    ) -> Result<PutOutcome> {
+        self.block_on(self.inner.upload_file_with(key, path, options))
+    }
+
+    /// Downloads an object into a directory, named after its key. See
+    /// [`crate::R2Client::download_file`].
+    pub fn download_file(&self, key: &str, directory: &Path) -> Result<PathBuf> {
+        self.block_on(self.inner.download_file(key, directory))
+    }
+
+    /// Downloads an object to a file path, atomically. See
+    /// [`crate::R2Client::download_to`].
+    pub fn download_to(&self, key: &str, destination: &Path) -> Result<u64> {
+        self.block_on(self.inner.download_to(key, destination))
+    }
+
+    /// A presigned download URL. See [`crate::R2Client::presign_get`].
+    pub fn presign_get(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
+        self.block_on(self.inner.presign_get(key, expires_in))
+    }
+
+    /// A presigned download URL with response overrides. See
+    /// [`crate::R2Client::presign_get_with`].
+    pub fn presign_get_with(&self, key: &str, options: PresignOptions) -> Result<PresignedRequest> {
+        self.block_on(self.inner.presign_get_with(key, options))
+    }
+
+    /// A presigned upload URL. See [`crate::R2Client::presign_put`].
+    pub fn presign_put(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
+        self.block_on(self.inner.presign_put(key, expires_in))
+    }
+
+    /// A presigned upload URL, optionally pinning the content type. See
+    /// [`crate::R2Client::presign_put_with`].
+    pub fn presign_put_with(&self, key: &str, options: PresignOptions) -> Result<PresignedRequest> {
+        self.block_on(self.inner.presign_put_with(key, options))
+    }
+
+    /// A presigned delete URL. See [`crate::R2Client::presign_delete`].
+    pub fn presign_delete(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
+        self.block_on(self.inner.presign_delete(key, expires_in))
+    }
+
+    /// A presigned metadata URL. See [`crate::R2Client::presign_head`].
+    pub fn presign_head(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
+        self.block_on(self.inner.presign_head(key, expires_in))
+    }
+
+    /// Whether the bucket exists. See [`crate::R2Client::bucket_exists`].
+    pub fn bucket_exists(&self) -> Result<bool> {
+        self.block_on(self.inner.bucket_exists())
+    }
+}
+
+impl From<R2Client> for crate::R2Client {
+    fn from(client: R2Client) -> Self {
+        client.inner
+    }
+}
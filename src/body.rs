@@ -102,6 +102,41 @@ impl IntoBody for std::borrow::Cow<'_, [u8]> {
     }
 }
 
+/// A body that yields `stream`'s chunks, for response bodies transformed as
+/// they arrive.
+#[cfg(any(feature = "compression", feature = "encryption"))]
+pub(crate) fn stream_body<S>(stream: S) -> ByteStream
+where
+    S: futures::Stream<Item = std::io::Result<bytes::Bytes>> + Send + Sync + Unpin + 'static,
+{
+    ByteStream::new(SdkBody::from_body_1_x(StreamBody(stream)))
+}
+
+#[cfg(any(feature = "compression", feature = "encryption"))]
+struct StreamBody<S>(S);
+
+#[cfg(any(feature = "compression", feature = "encryption"))]
+impl<S> http_body::Body for StreamBody<S>
+where
+    S: futures::Stream<Item = std::io::Result<bytes::Bytes>> + Unpin,
+{
+    type Data = bytes::Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<std::io::Result<http_body::Frame<bytes::Bytes>>>> {
+        futures::StreamExt::poll_next_unpin(&mut self.0, cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(http_body::Frame::data)))
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        // A transformed body's length is unknown until it ends.
+        http_body::SizeHint::default()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
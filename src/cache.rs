@@ -0,0 +1,288 @@
+//! A read-through cache of objects on local disk.
+
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use aws_sdk_s3::primitives::ByteStream;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use crate::cas::BlobHash;
+use crate::client::R2Client;
+use crate::error::{Error, Result};
+use crate::object::temporary_path;
+
+/// Starts every cache entry, ahead of the ETag it was stored at.
+const ENTRY_MAGIC: &[u8; 4] = b"R2C1";
+
+/// Prefix of the in-progress files entries land in before being renamed.
+const TEMPORARY_PREFIX: &str = ".r2partial.";
+
+/// How old an in-progress file must be before eviction treats it as
+/// abandoned by a process that died mid-download.
+const ABANDONED_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// An [`R2Client`] wrapper that keeps the objects it reads in a size-bounded
+/// cache on local disk, shared by every process on the machine that points
+/// at the same directory.
+///
+/// Each read revalidates: if the object is cached, the GET carries
+/// `If-None-Match` with the cached ETag, and a `304 Not Modified` is served
+/// from disk without transferring the body. A changed object is downloaded
+/// the way [`download_to`](R2Client::download_to) does it, into a temporary
+/// file beside the entry that is renamed over it once complete, so a reader
+/// in any process sees either the old entry or the new one, never a partial
+/// write. An object deleted from R2 is dropped from the cache.
+///
+/// Entries are keyed by bucket and key, and record the ETag they were stored
+/// at. Once the directory grows past `max_bytes`, the least recently used
+/// entries are deleted, judged by file modification times, which every read
+/// refreshes. The directory should hold nothing but the cache. Bodies are
+/// stored as decoded: with client-side encryption, cached entries are
+/// plaintext.
+///
+/// ```no_run
+/// # async fn run(client: cloudflare_r2_rs::R2Client) -> cloudflare_r2_rs::Result<()> {
+/// use cloudflare_r2_rs::CachedClient;
+///
+/// let cached = CachedClient::new(client, "/var/cache/models", 10 * 1024 * 1024 * 1024);
+/// let weights = cached.get_object("models/classifier.onnx").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CachedClient {
+    client: R2Client,
+    directory: PathBuf,
+    max_bytes: u64,
+}
+
+impl CachedClient {
+    /// Caches `client`'s objects in `directory`, created on first use, and
+    /// keeps it under `max_bytes`.
+    pub fn new(client: R2Client, directory: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        CachedClient {
+            client,
+            directory: directory.into(),
+            max_bytes,
+        }
+    }
+
+    /// The client objects are fetched with.
+    pub fn client(&self) -> &R2Client {
+        &self.client
+    }
+
+    /// The cache directory.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Fetches an object's body, from the cache when it is still current.
+    ///
+    /// Fails with [`Error::ObjectNotFound`] when the key does not exist.
+    pub async fn get_object(&self, key: &str) -> Result<Vec<u8>> {
+        let mut file = self.open(key).await?;
+        let mut body = Vec::new();
+        file.read_to_end(&mut body).await.map_err(|err| {
+            Error::file(
+                &self.entry_path(key),
+                "could not read the cache entry",
+                Some(err),
+            )
+        })?;
+        Ok(body)
+    }
+
+    /// Opens the cached copy of an object, fetching or refreshing it first
+    /// if needed. The file is positioned at the start of the body, and stays
+    /// readable even if the entry is replaced or evicted while it is open.
+    pub async fn open(&self, key: &str) -> Result<File> {
+        let path = self.entry_path(key);
+        let cached = read_entry(&path).await;
+        let fetched = match &cached {
+            Some((_, etag)) => self.client.open_object_if_none_match(key, etag).await,
+            None => self.client.open_object(key).await.map(Some),
+        };
+        let (body, etag) = match (fetched, cached) {
+            (Ok(Some(opened)), _) => opened,
+            (Ok(None), Some((file, _))) => {
+                touch(&file).await;
+                return Ok(file);
+            }
+            (Ok(None), None) => unreachable!("R2 answered 304 to an unconditional GET"),
+            (Err(err), _) => {
+                if err.is_not_found() {
+                    let _ = fs::remove_file(&path).await;
+                }
+                return Err(err);
+            }
+        };
+        let file = self.store(key, &path, body, etag).await?;
+        self.evict().await;
+        Ok(file)
+    }
+
+    /// Drops an object's cached copy, if there is one.
+    pub async fn invalidate(&self, key: &str) -> Result<()> {
+        let path = self.entry_path(key);
+        match fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::file(
+                &path,
+                "could not remove the cache entry",
+                Some(err),
+            )),
+        }
+    }
+
+    /// Where an object's entry lives: a hash of bucket and key, so any key
+    /// maps to a safe, fixed-length file name.
+    fn entry_path(&self, key: &str) -> PathBuf {
+        let name = BlobHash::of(format!("{}\0{key}", self.client.bucket()));
+        self.directory.join(name.to_string())
+    }
+
+    /// Writes a fetched body to `path` through a temporary file, returning
+    /// the written file positioned at the body.
+    async fn store(
+        &self,
+        key: &str,
+        path: &Path,
+        body: ByteStream,
+        etag: Option<String>,
+    ) -> Result<File> {
+        fs::create_dir_all(&self.directory).await.map_err(|err| {
+            Error::file(
+                &self.directory,
+                "could not create the cache directory",
+                Some(err),
+            )
+        })?;
+
+        let temporary = temporary_path(path);
+        let (mut file, header_len) = match write_entry(key, &temporary, body, etag).await {
+            Ok(written) => written,
+            Err(err) => {
+                let _ = fs::remove_file(&temporary).await;
+                return Err(err);
+            }
+        };
+        if let Err(err) = fs::rename(&temporary, path).await {
+            let _ = fs::remove_file(&temporary).await;
+            return Err(Error::file(
+                path,
+                "could not move the cache entry into place",
+                Some(err),
+            ));
+        }
+        file.seek(SeekFrom::Start(header_len))
+            .await
+            .map_err(|err| Error::file(path, "could not read the cache entry", Some(err)))?;
+        Ok(file)
+    }
+
+    /// Deletes the least recently used entries until the cache fits, along
+    /// with in-progress files abandoned long ago. Best effort: other
+    /// processes may be evicting at the same time.
+    async fn evict(&self) {
+        let Ok(mut entries) = fs::read_dir(&self.directory).await else {
+            return;
+        };
+        let mut files = Vec::new();
+        let mut total = 0u64;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            if entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(TEMPORARY_PREFIX)
+            {
+                if modified.elapsed().is_ok_and(|age| age > ABANDONED_AFTER) {
+                    let _ = fs::remove_file(entry.path()).await;
+                }
+                continue;
+            }
+            total += metadata.len();
+            files.push((modified, metadata.len(), entry.path()));
+        }
+
+        files.sort();
+        for (_, len, path) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            log::debug!("evicting {} from the object cache", path.display());
+            if let Err(err) = fs::remove_file(&path).await {
+                log::debug!("could not evict {}: {err}", path.display());
+            }
+            total -= len;
+        }
+    }
+}
+
+/// Opens an entry and reads its ETag, leaving the file at the body. `None`
+/// if there is no usable entry.
+async fn read_entry(path: &Path) -> Option<(File, String)> {
+    let mut file = File::open(path).await.ok()?;
+    let mut header = [0u8; 6];
+    file.read_exact(&mut header).await.ok()?;
+    if header[..4] != ENTRY_MAGIC[..] {
+        return None;
+    }
+    let mut etag = vec![0; usize::from(u16::from_be_bytes([header[4], header[5]]))];
+    file.read_exact(&mut etag).await.ok()?;
+    let etag = String::from_utf8(etag)
+        .ok()
+        .filter(|etag| !etag.is_empty())?;
+    Some((file, etag))
+}
+
+/// Writes an entry: the magic, the ETag's length and the ETag, then the
+/// body. Returns the file and the header's length.
+async fn write_entry(
+    key: &str,
+    path: &Path,
+    mut body: ByteStream,
+    etag: Option<String>,
+) -> Result<(File, u64)> {
+    // An entry without an ETag is written all the same, but can never be
+    // revalidated, so the next read fetches the object again.
+    let etag = etag
+        .filter(|etag| etag.len() <= usize::from(u16::MAX))
+        .unwrap_or_default();
+    let write_error = |err| Error::file(path, "could not write the cache entry", Some(err));
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await
+        .map_err(|err| Error::file(path, "could not create the cache entry", Some(err)))?;
+    let mut header = ENTRY_MAGIC.to_vec();
+    header.extend_from_slice(&(etag.len() as u16).to_be_bytes());
+    header.extend_from_slice(etag.as_bytes());
+    file.write_all(&header).await.map_err(write_error)?;
+
+    while let Some(chunk) = body.try_next().await.map_err(|err| Error::body(key, err))? {
+        file.write_all(&chunk).await.map_err(write_error)?;
+    }
+    file.flush().await.map_err(write_error)?;
+    Ok((file, header.len() as u64))
+}
+
+/// Marks an entry as just used, for eviction's sake.
+async fn touch(file: &File) {
+    if let Ok(file) = file.try_clone().await {
+        let _ = file.into_std().await.set_modified(SystemTime::now());
+    }
+}
@@ -0,0 +1,449 @@
+//! Content-addressed blob storage.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+use crate::client::R2Client;
+use crate::error::{Error, Result};
+use crate::object::temporary_path;
+use crate::types::{DateTime, DeleteFailure, MultipartOptions, PutOptions};
+
+/// The SHA-256 of a blob's content, which is also its address.
+///
+/// Displays and parses as 64 lowercase hex digits.
+///
+/// ```
+/// use cloudflare_r2_rs::BlobHash;
+///
+/// let hash = BlobHash::of(b"hello");
+/// assert_eq!(
+///     hash.to_string(),
+///     "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+/// );
+/// assert_eq!(hash.to_string().parse::<BlobHash>().unwrap(), hash);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlobHash([u8; 32]);
+
+impl BlobHash {
+    /// Hashes `data`.
+    pub fn of(data: impl AsRef<[u8]>) -> Self {
+        BlobHash(Sha256::digest(data.as_ref()).into())
+    }
+
+    /// The raw digest.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Hashes a local file, reading it in chunks rather than whole.
+    async fn of_file(path: &Path) -> Result<Self> {
+        let mut file = tokio::fs::File::open(path)
+            .await
+            .map_err(|err| Error::file(path, "could not open the file to hash it", Some(err)))?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).await.map_err(|err| {
+                Error::file(path, "could not read the file to hash it", Some(err))
+            })?;
+            if read == 0 {
+                return Ok(BlobHash(hasher.finalize().into()));
+            }
+            hasher.update(&buffer[..read]);
+        }
+    }
+}
+
+impl From<[u8; 32]> for BlobHash {
+    fn from(digest: [u8; 32]) -> Self {
+        BlobHash(digest)
+    }
+}
+
+impl fmt::Display for BlobHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl fmt::Debug for BlobHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BlobHash({self})")
+    }
+}
+
+impl FromStr for BlobHash {
+    type Err = Error;
+
+    /// Parses 64 hex digits, in either case.
+    fn from_str(text: &str) -> Result<Self> {
+        let invalid = || {
+            Error::invalid_argument(
+                "hash",
+                format!("`{text}` is not a SHA-256 digest in 64 hex digits"),
+            )
+        };
+        if text.len() != 64 || !text.is_ascii() {
+            return Err(invalid());
+        }
+        let mut digest = [0u8; 32];
+        for (byte, pair) in digest.iter_mut().zip(text.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+        Ok(BlobHash(digest))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for BlobHash {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BlobHash {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A blob after [`BlobStore::put`] or [`BlobStore::put_file`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct StoredBlob {
+    /// The content's hash.
+    pub hash: BlobHash,
+    /// The key it is stored under.
+    pub key: String,
+    /// The content's length in bytes.
+    pub size: u64,
+    /// `false` when the blob was already stored and nothing was uploaded.
+    pub uploaded: bool,
+}
+
+/// What [`BlobStore::collect_garbage`] did.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct GcReport {
+    /// Blobs kept, because they are live or too young to collect.
+    pub kept: usize,
+    /// Keys of the blobs deleted.
+    pub deleted: Vec<String>,
+    /// Unreferenced blobs that could not be deleted, with the reason.
+    pub failed: Vec<DeleteFailure>,
+}
+
+/// Blobs stored under their SHA-256, so that identical content is stored
+/// once however many times it is put.
+///
+/// A blob with hash `2cf24d…` lives at `<prefix>/2c/2cf24d…`; the two-digit
+/// fan-out keeps listings of one shard short. Puts send `If-None-Match: *`,
+/// so content that is already stored is never overwritten, and reads check
+/// the body against the hash before returning it.
+///
+/// ```no_run
+/// # async fn run(client: cloudflare_r2_rs::R2Client) -> cloudflare_r2_rs::Result<()> {
+/// use std::collections::HashSet;
+/// use std::time::Duration;
+///
+/// use cloudflare_r2_rs::BlobStore;
+///
+/// let blobs = BlobStore::new(client, "artifacts");
+/// let stored = blobs.put_file("target/release/app".as_ref()).await?;
+/// let bytes = blobs.get(&stored.hash).await?;
+///
+/// let live = HashSet::from([stored.hash]);
+/// blobs.collect_garbage(&live, Duration::from_secs(24 * 3600)).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    client: R2Client,
+    prefix: String,
+    put_options: PutOptions,
+}
+
+impl BlobStore {
+    /// Stores blobs under `prefix` in `client`'s bucket. Leading and
+    /// trailing slashes are ignored; an empty prefix uses the bucket root.
+    pub fn new(client: R2Client, prefix: impl Into<String>) -> Self {
+        BlobStore {
+            client,
+            prefix: prefix.into().trim_matches('/').to_string(),
+            put_options: PutOptions::new(),
+        }
+    }
+
+    /// Headers, metadata and (with the `compression` feature) a codec for
+    /// every blob uploaded. The precondition is always `If-None-Match: *`.
+    ///
+    /// Reads hash the body the client returns, so a codec needs a client
+    /// built with [`decompress(true)`](crate::R2ClientBuilder::decompress);
+    /// otherwise every put fails rather than store blobs that would never
+    /// verify.
+    #[must_use]
+    pub fn put_options(mut self, options: PutOptions) -> Self {
+        self.put_options = options;
+        self
+    }
+
+    /// The client blobs are stored with.
+    pub fn client(&self) -> &R2Client {
+        &self.client
+    }
+
+    /// The key a blob with `hash` is stored under.
+    pub fn key(&self, hash: &BlobHash) -> String {
+        let hash = hash.to_string();
+        if self.prefix.is_empty() {
+            format!("{}/{hash}", &hash[..2])
+        } else {
+            format!("{}/{}/{hash}", self.prefix, &hash[..2])
+        }
+    }
+
+    /// The hash a key addresses, if it is a blob key under this prefix.
+    fn hash_of_key(&self, key: &str) -> Option<BlobHash> {
+        let rest = if self.prefix.is_empty() {
+            key
+        } else {
+            key.strip_prefix(&self.prefix)?.strip_prefix('/')?
+        };
+        let (shard, text) = rest.split_once('/')?;
+        let hash: BlobHash = text.parse().ok()?;
+        // Only the key `key()` would produce: an upper-case spelling is some
+        // other object that happens to parse.
+        let canonical = hash.to_string();
+        (canonical == text && canonical.get(..2) == Some(shard)).then_some(hash)
+    }
+
+    fn options(&self) -> Result<PutOptions> {
+        #[cfg(feature = "compression")]
+        if self.put_options.compression.is_some() && !self.client.decompress {
+            return Err(Error::invalid_argument(
+                "compression",
+                "blobs are verified as read, so compressing them needs a client built with \
+                 decompress(true)",
+            ));
+        }
+        Ok(self.put_options.clone().if_none_match("*"))
+    }
+
+    /// Stores `data` under its hash, unless it is already stored.
+    pub async fn put(&self, data: impl Into<Bytes>) -> Result<StoredBlob> {
+        let data = data.into();
+        let hash = BlobHash::of(&data);
+        let key = self.key(&hash);
+        let size = data.len() as u64;
+
+        let uploaded = match self
+            .client
+            .put_object_with(&key, data, self.options()?)
+            .await
+        {
+            Ok(_) => true,
+            Err(err) if err.is_precondition_failed() => false,
+            Err(err) => return Err(err),
+        };
+        Ok(StoredBlob {
+            hash,
+            key,
+            size,
+            uploaded,
+        })
+    }
+
+    /// Stores a local file under its hash, unless it is already stored.
+    ///
+    /// The file is read twice, once to hash it and once to upload it, and
+    /// never held whole; large files go up in parts. A `HEAD` request comes
+    /// first, so a blob that is already stored costs no upload at all. The
+    /// file must not change in between.
+    pub async fn put_file(&self, path: &Path) -> Result<StoredBlob> {
+        let hash = BlobHash::of_file(path).await?;
+        let key = self.key(&hash);
+        let size = tokio::fs::metadata(path)
+            .await
+            .map_err(|err| Error::file(path, "could not read the file's metadata", Some(err)))?
+            .len();
+
+        let options = MultipartOptions::new().put_options(self.options()?);
+        let uploaded = if self.client.object_exists(&key).await? {
+            false
+        } else {
+            // Boxed: the multipart future is deep enough already.
+            match Box::pin(self.client.upload_file_with(&key, path, options)).await {
+                Ok(_) => true,
+                Err(err) if err.is_precondition_failed() => false,
+                Err(err) => return Err(err),
+            }
+        };
+        Ok(StoredBlob {
+            hash,
+            key,
+            size,
+            uploaded,
+        })
+    }
+
+    /// Reports whether a blob is stored.
+    pub async fn contains(&self, hash: &BlobHash) -> Result<bool> {
+        self.client.object_exists(&self.key(hash)).await
+    }
+
+    /// Fetches a blob, checking its content against the hash.
+    ///
+    /// Fails with [`Error::ObjectNotFound`] when it is not stored, and with
+    /// [`Error::Integrity`] when the body does not hash to `hash`.
+    pub async fn get(&self, hash: &BlobHash) -> Result<Vec<u8>> {
+        let key = self.key(hash);
+        let data = self.client.get_object(&key).await?;
+        verify(&key, hash, BlobHash::of(&data))?;
+        Ok(data)
+    }
+
+    /// Downloads a blob to `destination`, checking its content against the
+    /// hash before moving it into place, as
+    /// [`R2Client::download_to`] does with a complete body. Returns the
+    /// number of bytes written.
+    pub async fn download_to(&self, hash: &BlobHash, destination: &Path) -> Result<u64> {
+        let key = self.key(hash);
+        let temporary = temporary_path(destination);
+        let written = self.client.download_to(&key, &temporary).await?;
+
+        let checked = match BlobHash::of_file(&temporary).await {
+            Ok(actual) => verify(&key, hash, actual),
+            Err(err) => Err(err),
+        };
+        let moved = match checked {
+            Ok(()) => tokio::fs::rename(&temporary, destination)
+                .await
+                .map_err(|err| {
+                    Error::file(
+                        destination,
+                        "could not move the verified download into place",
+                        Some(err),
+                    )
+                }),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = moved {
+            let _ = tokio::fs::remove_file(&temporary).await;
+            return Err(err);
+        }
+        Ok(written)
+    }
+
+    /// Deletes every blob whose hash is not in `live`.
+    ///
+    /// Blobs uploaded less than `grace` ago are kept regardless, so that a
+    /// writer that has stored a blob but not yet recorded a reference to it
+    /// does not lose it. A put that finds its blob already stored does not
+    /// refresh that blob's age, so `grace` must also cover the time between
+    /// computing `live` and this call. Keys under the prefix that are not
+    /// blob keys are left alone.
+    pub async fn collect_garbage(
+        &self,
+        live: &HashSet<BlobHash>,
+        grace: Duration,
+    ) -> Result<GcReport> {
+        let cutoff = DateTime::from(SystemTime::now() - grace);
+        let prefix = (!self.prefix.is_empty()).then(|| format!("{}/", self.prefix));
+        let objects = self.client.list_all_objects(prefix.as_deref()).await?;
+
+        let mut report = GcReport::default();
+        let mut dead = Vec::new();
+        for object in objects {
+            let Some(hash) = self.hash_of_key(&object.key) else {
+                continue;
+            };
+            let young = object
+                .last_modified
+                .is_none_or(|modified| modified.secs() > cutoff.secs());
+            if live.contains(&hash) || young {
+                report.kept += 1;
+            } else {
+                dead.push(object.key);
+            }
+        }
+
+        if !dead.is_empty() {
+            let deleted = self.client.delete_objects(dead).await?;
+            report.deleted = deleted.deleted;
+            report.failed = deleted.failed;
+        }
+        Ok(report)
+    }
+}
+
+fn verify(key: &str, expected: &BlobHash, actual: BlobHash) -> Result<()> {
+    if actual == *expected {
+        return Ok(());
+    }
+    Err(Error::Integrity {
+        key: key.to_string(),
+        expected: expected.to_string(),
+        actual: actual.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(prefix: &str) -> BlobStore {
+        let client = R2Client::builder()
+            .endpoint("http://localhost:9000")
+            .bucket("blobs")
+            .access_key_id("key")
+            .secret_access_key("secret")
+            .build()
+            .unwrap();
+        BlobStore::new(client, prefix)
+    }
+
+    #[test]
+    fn hashes_parse_in_either_case_and_display_lowercase() {
+        let hash = BlobHash::of(b"");
+        let upper = hash.to_string().to_ascii_uppercase();
+        assert_eq!(upper.parse::<BlobHash>().unwrap(), hash);
+        assert!("abc".parse::<BlobHash>().is_err());
+        assert!("zz".repeat(32).parse::<BlobHash>().is_err());
+    }
+
+    #[test]
+    fn keys_fan_out_by_the_first_byte() {
+        let hash = BlobHash::of(b"hello");
+        let blobs = store("/artifacts/");
+        let key = blobs.key(&hash);
+        assert_eq!(key, format!("artifacts/2c/{hash}"));
+        assert_eq!(blobs.hash_of_key(&key), Some(hash));
+        assert_eq!(store("").key(&hash), format!("2c/{hash}"));
+
+        assert_eq!(blobs.hash_of_key(&format!("artifacts/3d/{hash}")), None);
+        assert_eq!(blobs.hash_of_key("artifacts/2c/readme.txt"), None);
+        assert_eq!(blobs.hash_of_key(&format!("other/2c/{hash}")), None);
+        let upper = hash.to_string().to_ascii_uppercase();
+        assert_eq!(blobs.hash_of_key(&format!("artifacts/2c/{upper}")), None);
+        assert_eq!(blobs.hash_of_key(&format!("artifacts/2C/{hash}")), None);
+    }
+}
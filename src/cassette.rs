@@ -0,0 +1,528 @@
+//! Recorded HTTP interactions, so tests written against a live bucket can be
+//! replayed offline.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use aws_sdk_s3::config::{RuntimeComponents, SharedHttpClient};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::{HttpRequest, HttpResponse};
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use aws_smithy_runtime_api::http::{Headers, StatusCode};
+use aws_smithy_types::body::SdkBody;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::transport::HttpOptions;
+
+/// Written in place of anything that could authenticate a request.
+const SCRUBBED: &str = "[scrubbed]";
+
+/// Request headers that carry credentials or signatures.
+const SECRET_HEADERS: &[&str] = &["authorization", "x-amz-security-token", "cookie"];
+
+/// Response headers that carry session state.
+const SECRET_RESPONSE_HEADERS: &[&str] = &["set-cookie"];
+
+/// Query parameters of presigned requests that carry credentials or
+/// signatures.
+const SECRET_PARAMETERS: &[&str] = &[
+    "x-amz-credential",
+    "x-amz-security-token",
+    "x-amz-signature",
+];
+
+/// The version written to new cassettes; older or newer files are refused
+/// rather than misread.
+const FORMAT_VERSION: u32 = 1;
+
+/// A file of recorded HTTP interactions: written while tests run against R2,
+/// then served back in place of it.
+///
+/// Install one with [`R2ClientBuilder::cassette`](crate::R2ClientBuilder::cassette).
+/// A recording cassette sends every request to R2 as usual and appends the
+/// exchange to its file, rewriting it after each one so a failing test still
+/// leaves a usable cassette. `Authorization` and session-token headers, and
+/// the signature and credential of presigned requests, are replaced with
+/// `[scrubbed]` before anything is written.
+///
+/// A replaying cassette sends nothing. Each request is answered with the
+/// first recorded response, not yet served, whose request had the same
+/// method, path and query string; query parameters may come in any order.
+/// Because requests are matched in recorded order, a key written, read,
+/// overwritten and read again replays both reads faithfully. A request with
+/// nothing left to match fails with an error naming it.
+///
+/// The path includes the bucket, so the replaying client must be bound to
+/// the bucket that was recorded. Its endpoint and credentials are never
+/// used and can be placeholders:
+///
+/// ```no_run
+/// use cloudflare_r2_rs::{Cassette, R2Client};
+///
+/// # fn run() -> cloudflare_r2_rs::Result<()> {
+/// let client = R2Client::builder()
+///     .endpoint("https://replay.invalid")
+///     .bucket("my-bucket")
+///     .access_key_id("replay")
+///     .secret_access_key("replay")
+///     .cassette(Cassette::replay("tests/cassettes/round_trip.json")?)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Responses are matched, not simulated: a test that makes different
+/// requests than the ones recorded — a timestamped key, a random upload —
+/// needs re-recording.
+///
+/// Clones share the recorded interactions.
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    path: PathBuf,
+    recording: bool,
+    state: Arc<Mutex<CassetteState>>,
+}
+
+#[derive(Debug, Default)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    /// Parallel to `interactions`: whether a replay has served each one.
+    served: Vec<bool>,
+}
+
+impl Cassette {
+    /// A cassette that records into `path`, replacing whatever is there.
+    ///
+    /// The file, and its parent directories, are created on the first
+    /// request.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Cassette {
+            path: path.into(),
+            recording: true,
+            state: Arc::default(),
+        }
+    }
+
+    /// A cassette that replays the interactions recorded in `path`.
+    ///
+    /// Returns [`Error::File`] if the file cannot be read or is not a
+    /// cassette.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let contents = std::fs::read(&path)
+            .map_err(|err| Error::file(&path, "could not read the cassette", Some(err)))?;
+        let file: CassetteFile = serde_json::from_slice(&contents)
+            .map_err(|err| Error::file(&path, "is not a valid cassette", Some(err)))?;
+        if file.version != FORMAT_VERSION {
+            return Err(Error::file::<std::io::Error>(
+                &path,
+                format!(
+                    "is a version {} cassette; this release reads version {FORMAT_VERSION}",
+                    file.version
+                ),
+                None,
+            ));
+        }
+
+        let served = vec![false; file.interactions.len()];
+        Ok(Cassette {
+            path,
+            recording: false,
+            state: Arc::new(Mutex::new(CassetteState {
+                interactions: file.interactions,
+                served,
+            })),
+        })
+    }
+
+    /// The cassette's file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether requests are sent to R2 and recorded, rather than replayed.
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// How many interactions the cassette holds.
+    pub fn len(&self) -> usize {
+        self.lock().interactions.len()
+    }
+
+    /// Whether the cassette holds no interactions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How many recorded interactions a replay has not served yet. Zero at
+    /// the end of a test means it made every request it made when recorded.
+    pub fn remaining(&self) -> usize {
+        self.lock().served.iter().filter(|served| !**served).count()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CassetteState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The HTTP client that records through `inner`, or replays without
+    /// it.
+    pub(crate) fn install(self, inner: Option<SharedHttpClient>) -> Result<SharedHttpClient> {
+        if !self.recording {
+            return Ok(SharedHttpClient::new(CassetteClient {
+                cassette: self,
+                inner: None,
+            }));
+        }
+        let inner = match inner {
+            Some(inner) => inner,
+            None => HttpOptions::default().into_http_client()?,
+        };
+        Ok(SharedHttpClient::new(CassetteClient {
+            cassette: self,
+            inner: Some(inner),
+        }))
+    }
+
+    /// Appends an interaction and rewrites the file.
+    fn append(&self, interaction: Interaction) -> std::io::Result<()> {
+        let mut state = self.lock();
+        state.interactions.push(interaction);
+        state.served.push(true);
+
+        let json = serde_json::to_vec_pretty(&serde_json::json!({
+            "version": FORMAT_VERSION,
+            "interactions": &state.interactions,
+        }))
+        .map_err(std::io::Error::other)?;
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        // Written under the lock, so concurrent requests cannot interleave
+        // an older snapshot over a newer one.
+        std::fs::write(&self.path, json)
+    }
+
+    /// The next unserved response recorded for `request`.
+    fn take(&self, request: &RecordedRequest) -> Option<RecordedResponse> {
+        let key = request.match_key();
+        let mut state = self.lock();
+        let index = state
+            .interactions
+            .iter()
+            .zip(&state.served)
+            .position(|(interaction, served)| !served && interaction.request.match_key() == key)?;
+        state.served[index] = true;
+        Some(state.interactions[index].response.clone())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CassetteFile {
+    version: u32,
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    path: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    query: String,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+}
+
+impl RecordedRequest {
+    /// The request with its secrets scrubbed. The scheme and host are
+    /// dropped: they are the endpoint's, which replay does not use.
+    fn from_request(request: &HttpRequest) -> Self {
+        let uri = request.uri();
+        let after_scheme = uri.find("://").map_or(uri, |index| &uri[index + 3..]);
+        let path_and_query = after_scheme
+            .find('/')
+            .map_or("/", |index| &after_scheme[index..]);
+        let (path, query) = path_and_query
+            .split_once('?')
+            .unwrap_or((path_and_query, ""));
+
+        RecordedRequest {
+            method: request.method().to_string(),
+            path: path.to_string(),
+            query: scrub_query(query),
+            headers: scrub_headers(request.headers(), SECRET_HEADERS),
+        }
+    }
+
+    /// What two requests must share to match: the method, the path, and the
+    /// query parameters in any order.
+    fn match_key(&self) -> (&str, &str, Vec<&str>) {
+        let mut parameters: Vec<&str> = self
+            .query
+            .split('&')
+            .filter(|parameter| !parameter.is_empty())
+            .collect();
+        parameters.sort_unstable();
+        (&self.method, &self.path, parameters)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    /// The body, when it is UTF-8, as nearly every R2 response body is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    /// The body, base64-encoded, when it is not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+impl RecordedResponse {
+    fn new(status: u16, headers: &Headers, body: &[u8]) -> Self {
+        let (body, body_base64) = match std::str::from_utf8(body) {
+            Ok(_) if body.is_empty() => (None, None),
+            Ok(text) => (Some(text.to_string()), None),
+            Err(_) => (None, Some(aws_smithy_types::base64::encode(body))),
+        };
+        RecordedResponse {
+            status,
+            headers: scrub_headers(headers, SECRET_RESPONSE_HEADERS),
+            body,
+            body_base64,
+        }
+    }
+
+    fn to_response(&self) -> std::result::Result<HttpResponse, ConnectorError> {
+        let body = match (&self.body, &self.body_base64) {
+            (Some(text), _) => text.clone().into_bytes(),
+            (None, Some(encoded)) => aws_smithy_types::base64::decode(encoded)
+                .map_err(|err| ConnectorError::other(err.into(), None))?,
+            (None, None) => Vec::new(),
+        };
+        let status = StatusCode::try_from(self.status)
+            .map_err(|err| ConnectorError::other(err.into(), None))?;
+        let mut response = HttpResponse::new(status, SdkBody::from(body));
+        for (name, value) in &self.headers {
+            response
+                .headers_mut()
+                .try_append(name.clone(), value.clone())
+                .map_err(|err| ConnectorError::other(err.into(), None))?;
+        }
+        Ok(response)
+    }
+}
+
+fn scrub_headers(headers: &Headers, secret: &[&str]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if secret
+                .iter()
+                .any(|secret| name.eq_ignore_ascii_case(secret))
+            {
+                SCRUBBED
+            } else {
+                value
+            };
+            (name.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+fn scrub_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|parameter| {
+            let name = parameter
+                .split_once('=')
+                .map_or(parameter, |(name, _)| name);
+            if SECRET_PARAMETERS
+                .iter()
+                .any(|secret| name.eq_ignore_ascii_case(secret))
+            {
+                format!("{name}={SCRUBBED}")
+            } else {
+                parameter.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Hands out [`CassetteConnector`]s, around the real transport's connectors
+/// when recording.
+#[derive(Debug)]
+struct CassetteClient {
+    cassette: Cassette,
+    inner: Option<SharedHttpClient>,
+}
+
+impl HttpClient for CassetteClient {
+    fn http_connector(
+        &self,
+        settings: &HttpConnectorSettings,
+        components: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(CassetteConnector {
+            cassette: self.cassette.clone(),
+            inner: self
+                .inner
+                .as_ref()
+                .map(|inner| inner.http_connector(settings, components)),
+        })
+    }
+}
+
+/// Records each exchange through `inner`, or replays one when there is no
+/// `inner`.
+#[derive(Debug)]
+struct CassetteConnector {
+    cassette: Cassette,
+    inner: Option<SharedHttpConnector>,
+}
+
+impl HttpConnector for CassetteConnector {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let recorded = RecordedRequest::from_request(&request);
+        let cassette = self.cassette.clone();
+
+        let Some(inner) = self.inner.clone() else {
+            return HttpConnectorFuture::ready(match cassette.take(&recorded) {
+                Some(response) => response.to_response(),
+                None => Err(ConnectorError::other(
+                    format!(
+                        "{} has no unserved interaction for {} {}{}{}",
+                        cassette.path.display(),
+                        recorded.method,
+                        recorded.path,
+                        if recorded.query.is_empty() { "" } else { "?" },
+                        recorded.query,
+                    )
+                    .into(),
+                    None,
+                )),
+            });
+        };
+
+        HttpConnectorFuture::new(async move {
+            let mut response = inner.call(request).await?;
+            let body = ByteStream::new(response.take_body())
+                .collect()
+                .await
+                .map_err(|err| ConnectorError::io(Box::new(err)))?
+                .into_bytes();
+            cassette
+                .append(Interaction {
+                    request: recorded,
+                    response: RecordedResponse::new(
+                        response.status().as_u16(),
+                        response.headers(),
+                        &body,
+                    ),
+                })
+                .map_err(|err| ConnectorError::other(Box::new(err), None))?;
+            *response.body_mut() = SdkBody::from(body);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str, query: &str) -> RecordedRequest {
+        RecordedRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    fn response(body: &str) -> RecordedResponse {
+        RecordedResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: Some(body.to_string()),
+            body_base64: None,
+        }
+    }
+
+    fn cassette(interactions: Vec<Interaction>) -> Cassette {
+        let served = vec![false; interactions.len()];
+        Cassette {
+            path: PathBuf::from("test.json"),
+            recording: false,
+            state: Arc::new(Mutex::new(CassetteState {
+                interactions,
+                served,
+            })),
+        }
+    }
+
+    #[test]
+    fn signatures_and_credentials_are_scrubbed_from_queries() {
+        assert_eq!(
+            scrub_query("X-Amz-Algorithm=AWS4-HMAC-SHA256&X-Amz-Credential=AKIA%2F1&X-Amz-Signature=abc"),
+            "X-Amz-Algorithm=AWS4-HMAC-SHA256&X-Amz-Credential=[scrubbed]&X-Amz-Signature=[scrubbed]"
+        );
+        assert_eq!(scrub_query("list-type=2&prefix=a"), "list-type=2&prefix=a");
+    }
+
+    #[test]
+    fn requests_match_on_method_path_and_query_in_any_order() {
+        let cassette = cassette(vec![
+            Interaction {
+                request: request("GET", "/bucket", "list-type=2&prefix=a"),
+                response: response("first"),
+            },
+            Interaction {
+                request: request("GET", "/bucket", "list-type=2&prefix=a"),
+                response: response("second"),
+            },
+        ]);
+
+        assert!(cassette
+            .take(&request("HEAD", "/bucket", "list-type=2&prefix=a"))
+            .is_none());
+        assert!(cassette
+            .take(&request("GET", "/bucket", "list-type=2&prefix=b"))
+            .is_none());
+        let take = || {
+            cassette
+                .take(&request("GET", "/bucket", "prefix=a&list-type=2"))
+                .and_then(|response| response.body)
+        };
+        assert_eq!(take().as_deref(), Some("first"));
+        assert_eq!(take().as_deref(), Some("second"));
+        assert_eq!(take(), None);
+        assert_eq!(cassette.remaining(), 0);
+    }
+
+    #[test]
+    fn binary_bodies_survive_a_round_trip() {
+        let body = [0u8, 159, 146, 150, 255];
+        let recorded = RecordedResponse::new(200, &Headers::new(), &body);
+        assert!(recorded.body.is_none());
+
+        let mut replayed = recorded.to_response().unwrap();
+        let bytes = replayed.take_body();
+        assert_eq!(bytes.bytes(), Some(&body[..]));
+    }
+}
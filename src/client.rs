@@ -7,13 +7,26 @@ use std::time::Duration;
 use aws_sdk_s3::config::http::HttpResponse;
 use aws_sdk_s3::config::retry::RetryConfig;
 use aws_sdk_s3::config::timeout::TimeoutConfig;
-use aws_sdk_s3::config::{Credentials, Region, RequestChecksumCalculation};
+use aws_sdk_s3::config::{
+    Credentials, Region, RequestChecksumCalculation, SharedHttpClient, SharedInterceptor,
+};
 use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
-use aws_sdk_s3::types::{BucketLifecycleConfiguration, CorsConfiguration, CorsRule, LifecycleRule};
+use aws_sdk_s3::types::{BucketLifecycleConfiguration, CorsConfiguration};
 use aws_sdk_s3::Client;
 
+#[cfg(feature = "cassette")]
+use crate::cassette::Cassette;
 use crate::config::{endpoint_for_account, Jurisdiction, R2Config, DEFAULT_REGION};
+use crate::cors::{matching_rule, CorsMethod, CorsRule, MAX_CORS_RULES};
 use crate::error::{from_sdk, Error, Result};
+#[cfg(feature = "faults")]
+use crate::fault::FaultInjection;
+use crate::lifecycle::{LifecycleRule, MAX_LIFECYCLE_RULES};
+use crate::limit::RateLimit;
+use crate::metrics::{MetricsInterceptor, MetricsObserver};
+use crate::retry::RetryPolicy;
+use crate::trace::{record, RecordStatus};
+use crate::transport::HttpOptions;
 use crate::types::BucketSummary;
 
 /// Connect timeout applied unless the caller overrides it.
@@ -78,8 +91,7 @@ pub struct R2ClientBuilder<EndpointState, BucketState, AccessKeyState, SecretKey
     secret_access_key: Option<String>,
     region: Option<String>,
     jurisdiction: Jurisdiction,
-    retry_config: Option<RetryConfig>,
-    timeout_config: Option<TimeoutConfig>,
+    options: ClientOptions,
     _endpoint: PhantomData<EndpointState>,
     _bucket: PhantomData<BucketState>,
     _access_key: PhantomData<AccessKeyState>,
@@ -101,6 +113,10 @@ impl<EndpointState, BucketState, AccessKeyState, SecretKeyState> std::fmt::Debug
             )
             .field("region", &self.region)
             .field("jurisdiction", &self.jurisdiction)
+            .field("http_options", &self.options.http_options)
+            .field("rate_limit", &self.options.rate_limit)
+            .field("retry_policy", &self.options.retry_policy)
+            .field("observers", &self.options.observers.len())
             .finish_non_exhaustive()
     }
 }
@@ -128,8 +144,7 @@ macro_rules! transition {
             secret_access_key: $self.secret_access_key,
             region: $self.region,
             jurisdiction: $self.jurisdiction,
-            retry_config: $self.retry_config,
-            timeout_config: $self.timeout_config,
+            options: $self.options,
             _endpoint: PhantomData,
             _bucket: PhantomData,
             _access_key: PhantomData,
@@ -222,7 +237,20 @@ impl<EndpointState, BucketState, AccessKeyState, SecretKeyState>
     /// when the caller does its own retrying.
     #[must_use]
     pub fn retry_config(mut self, retry_config: RetryConfig) -> Self {
-        self.retry_config = Some(retry_config);
+        self.options.retry_config = Some(retry_config);
+        self
+    }
+
+    /// Retries the failed unit of a multi-request operation — one part, one
+    /// listing page, one batch of deletes — rather than failing the whole
+    /// operation. See [`RetryPolicy`].
+    ///
+    /// This sits on top of [`retry_config`](R2ClientBuilder::retry_config),
+    /// which retries individual requests: a unit is only retried here once the
+    /// SDK has given up on it. Off by default.
+    #[must_use]
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.options.retry_policy = Some(retry_policy);
         self
     }
 
@@ -232,7 +260,119 @@ impl<EndpointState, BucketState, AccessKeyState, SecretKeyState>
     /// a single upload or download can legitimately run for a long time.
     #[must_use]
     pub fn timeout_config(mut self, timeout_config: TimeoutConfig) -> Self {
-        self.timeout_config = Some(timeout_config);
+        self.options.timeout_config = Some(timeout_config);
+        self
+    }
+
+    /// Configures the HTTP transport: proxy, extra root certificates,
+    /// connection pooling, TCP keepalive and HTTP/2. See [`HttpOptions`].
+    ///
+    /// Cannot be combined with [`http_client`](R2ClientBuilder::http_client),
+    /// which replaces the transport outright: `build()` fails if both are set.
+    #[must_use]
+    pub fn http_options(mut self, http_options: HttpOptions) -> Self {
+        self.options.http_options = Some(http_options);
+        self
+    }
+
+    /// Replaces the HTTP client the SDK sends requests through.
+    ///
+    /// This is the escape hatch for transport settings [`HttpOptions`] does
+    /// not cover, and the seam for test doubles. Any `aws-smithy` HTTP client
+    /// works, including one built with `aws-smithy-http-client`'s own builder.
+    /// Cannot be combined with [`http_options`](R2ClientBuilder::http_options).
+    #[must_use]
+    pub fn http_client(mut self, http_client: SharedHttpClient) -> Self {
+        self.options.http_client = Some(http_client);
+        self
+    }
+
+    /// Throttles every request the client makes. See [`RateLimit`].
+    ///
+    /// The limiter is shared by the client's clones and by
+    /// [`with_bucket`](R2Client::with_bucket) children, so one limit covers
+    /// all the tasks that were handed a copy.
+    #[must_use]
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.options.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Reports every operation the client completes to `observer`. See
+    /// [`MetricsObserver`].
+    ///
+    /// May be called more than once; each observer hears about every
+    /// operation. Observers are shared by the client's clones and
+    /// [`with_bucket`](R2Client::with_bucket) children.
+    #[must_use]
+    pub fn metrics(mut self, observer: Arc<dyn MetricsObserver>) -> Self {
+        self.options.observers.push(observer);
+        self
+    }
+
+    /// Injects failures into the client's requests, for testing how code
+    /// copes with R2 misbehaving. See [`FaultInjection`].
+    ///
+    /// The faults sit between the SDK and the transport, so retries, rate
+    /// limiting and metrics all see them as they would real failures. Works
+    /// with [`http_options`](R2ClientBuilder::http_options) and
+    /// [`http_client`](R2ClientBuilder::http_client), wrapping whichever
+    /// transport they configure.
+    #[cfg(feature = "faults")]
+    #[must_use]
+    pub fn fault_injection(mut self, faults: FaultInjection) -> Self {
+        self.options.faults = Some(faults);
+        self
+    }
+
+    /// Records the client's HTTP interactions to a cassette, or replays
+    /// them from one instead of contacting R2. See [`Cassette`].
+    ///
+    /// A recording cassette wraps the transport configured by
+    /// [`http_options`](R2ClientBuilder::http_options) or
+    /// [`http_client`](R2ClientBuilder::http_client); a replaying one
+    /// replaces it. Injected faults are never recorded.
+    #[cfg(feature = "cassette")]
+    #[must_use]
+    pub fn cassette(mut self, cassette: Cassette) -> Self {
+        self.options.cassette = Some(cassette);
+        self
+    }
+
+    /// Decompresses downloads whose `Content-Encoding` is `gzip`, `zstd` or
+    /// `br`, so that [`get_object`](R2Client::get_object),
+    /// [`get_object_stream`](R2Client::get_object_stream) and
+    /// [`download_to`](R2Client::download_to) hand back the original bytes
+    /// of an object uploaded with
+    /// [`PutOptions::compression`](crate::PutOptions::compression). Off by
+    /// default: without it those methods return bodies exactly as stored.
+    ///
+    /// [`get_object_range`](R2Client::get_object_range) is never
+    /// decompressed, since a range of a compressed body cannot be decoded on
+    /// its own.
+    #[cfg(feature = "compression")]
+    #[must_use]
+    pub fn decompress(mut self, decompress: bool) -> Self {
+        self.options.decompress = decompress;
+        self
+    }
+
+    /// Encrypts uploads client-side under data keys wrapped by `provider`,
+    /// and decrypts objects carrying its metadata on
+    /// [`get_object`](R2Client::get_object),
+    /// [`get_object_stream`](R2Client::get_object_stream),
+    /// [`get_object_range`](R2Client::get_object_range) and
+    /// [`download_to`](R2Client::download_to). Objects stored without
+    /// encryption still read back as they are.
+    ///
+    /// Presigned `GET`s hand out the ciphertext, and
+    /// [`create_multipart_upload`](R2Client::create_multipart_upload) is
+    /// refused, since parts uploaded by hand would bypass the cipher; use
+    /// [`upload_file`](R2Client::upload_file) for large objects.
+    #[cfg(feature = "encryption")]
+    #[must_use]
+    pub fn encryption(mut self, provider: Arc<dyn crate::KeyProvider>) -> Self {
+        self.options.encryption = Some(provider);
         self
     }
 }
@@ -264,12 +404,32 @@ impl R2ClientBuilder<HasEndpoint, HasBucket, HasAccessKey, HasSecretKey> {
                 bucket,
                 region: self.region.unwrap_or_else(|| DEFAULT_REGION.to_string()),
             },
-            self.retry_config,
-            self.timeout_config,
+            self.options,
         )
     }
 }
 
+/// Everything besides the endpoint and credentials that shapes how a client
+/// behaves, gathered so the typestate transitions move one field, not many.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClientOptions {
+    pub(crate) retry_config: Option<RetryConfig>,
+    pub(crate) timeout_config: Option<TimeoutConfig>,
+    pub(crate) http_options: Option<HttpOptions>,
+    pub(crate) http_client: Option<SharedHttpClient>,
+    pub(crate) rate_limit: Option<RateLimit>,
+    pub(crate) retry_policy: Option<RetryPolicy>,
+    pub(crate) observers: Vec<Arc<dyn MetricsObserver>>,
+    #[cfg(feature = "faults")]
+    pub(crate) faults: Option<FaultInjection>,
+    #[cfg(feature = "cassette")]
+    pub(crate) cassette: Option<Cassette>,
+    #[cfg(feature = "compression")]
+    pub(crate) decompress: bool,
+    #[cfg(feature = "encryption")]
+    pub(crate) encryption: Option<Arc<dyn crate::KeyProvider>>,
+}
+
 fn require_opt(value: Option<String>) -> Option<String> {
     value
         .map(|value| value.trim().to_string())
@@ -300,6 +460,11 @@ pub struct R2Client {
     pub(crate) client: Arc<Client>,
     pub(crate) bucket: String,
     endpoint: String,
+    pub(crate) retry_policy: RetryPolicy,
+    #[cfg(feature = "compression")]
+    pub(crate) decompress: bool,
+    #[cfg(feature = "encryption")]
+    pub(crate) encryption: Option<Arc<dyn crate::KeyProvider>>,
 }
 
 impl R2Client {
@@ -312,14 +477,10 @@ impl R2Client {
     /// Builds a client from fully resolved configuration, with default retry
     /// and timeout policies.
     pub fn from_config(config: R2Config) -> Result<Self> {
-        Self::build(config, None, None)
+        Self::build(config, ClientOptions::default())
     }
 
-    fn build(
-        config: R2Config,
-        retry_config: Option<RetryConfig>,
-        timeout_config: Option<TimeoutConfig>,
-    ) -> Result<Self> {
+    fn build(config: R2Config, options: ClientOptions) -> Result<Self> {
         let endpoint = config.endpoint.trim().trim_end_matches('/').to_string();
         if endpoint.is_empty() {
             return Err(Error::MissingConfig("endpoint"));
@@ -336,7 +497,27 @@ impl R2Client {
             "cloudflare-r2-rs",
         );
 
-        let s3_config = aws_sdk_s3::config::Builder::new()
+        let http_client = match (options.http_client, options.http_options) {
+            (Some(_), Some(_)) => {
+                return Err(Error::InvalidConfig {
+                    field: "http_options",
+                    message: "http_options configures the transport that http_client replaces; \
+                              set one or the other"
+                        .to_string(),
+                })
+            }
+            (Some(http_client), None) => Some(http_client),
+            (None, Some(http_options)) => Some(http_options.into_http_client()?),
+            (None, None) => None,
+        };
+        let limiter = options
+            .rate_limit
+            .map(RateLimit::into_limiter)
+            .transpose()?;
+        let retry_policy = options.retry_policy.unwrap_or_else(RetryPolicy::disabled);
+        retry_policy.validate()?;
+
+        let mut s3_config = aws_sdk_s3::config::Builder::new()
             .region(Region::new(config.region))
             .endpoint_url(endpoint.clone())
             .credentials_provider(credentials)
@@ -350,20 +531,58 @@ impl R2Client {
             // becomes undecodable to browsers. Required-checksum operations
             // such as DeleteObjects are unaffected.
             .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
-            .retry_config(retry_config.unwrap_or_else(RetryConfig::standard))
-            .timeout_config(timeout_config.unwrap_or_else(|| {
+            .retry_config(options.retry_config.unwrap_or_else(RetryConfig::standard))
+            .timeout_config(options.timeout_config.unwrap_or_else(|| {
                 // No operation timeout: a large upload or download is allowed
                 // to take as long as it takes.
                 TimeoutConfig::builder()
                     .connect_timeout(DEFAULT_CONNECT_TIMEOUT)
                     .build()
-            }))
-            .build();
+            }));
+        #[cfg(feature = "cassette")]
+        let http_client = match options.cassette {
+            Some(cassette) => Some(cassette.install(http_client)?),
+            None => http_client,
+        };
+        #[cfg(feature = "faults")]
+        let http_client = match options.faults {
+            Some(faults) => {
+                faults.validate()?;
+                let inner = match http_client {
+                    Some(http_client) => http_client,
+                    None => HttpOptions::default().into_http_client()?,
+                };
+                let (interceptor, http_client) = faults.install(inner);
+                s3_config.push_interceptor(SharedInterceptor::new(interceptor));
+                Some(http_client)
+            }
+            None => http_client,
+        };
+        // Outermost, so that every attempt waits its turn, retries and
+        // injected faults included.
+        let http_client = match limiter {
+            Some(limiter) => Some(limiter.install(match http_client {
+                Some(http_client) => http_client,
+                None => HttpOptions::default().into_http_client()?,
+            })),
+            None => http_client,
+        };
+        s3_config.set_http_client(http_client);
+        if !options.observers.is_empty() {
+            s3_config.push_interceptor(SharedInterceptor::new(MetricsInterceptor::new(
+                options.observers,
+            )));
+        }
 
         Ok(R2Client {
-            client: Arc::new(Client::from_conf(s3_config)),
+            client: Arc::new(Client::from_conf(s3_config.build())),
             bucket: config.bucket,
             endpoint,
+            retry_policy,
+            #[cfg(feature = "compression")]
+            decompress: options.decompress,
+            #[cfg(feature = "encryption")]
+            encryption: options.encryption,
         })
     }
 
@@ -390,6 +609,11 @@ impl R2Client {
             client: Arc::clone(&self.client),
             bucket: bucket.into(),
             endpoint: self.endpoint.clone(),
+            retry_policy: self.retry_policy,
+            #[cfg(feature = "compression")]
+            decompress: self.decompress,
+            #[cfg(feature = "encryption")]
+            encryption: self.encryption.clone(),
         }
     }
 
@@ -402,7 +626,7 @@ impl R2Client {
     /// Turns a 404 on a bucket operation into [`Error::BucketNotFound`].
     fn map_bucket_error<E>(&self, operation: &'static str, err: SdkError<E, HttpResponse>) -> Error
     where
-        E: std::error::Error + Send + Sync + 'static,
+        E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
     {
         let err = from_sdk(operation, err);
         if err.is_not_found() {
@@ -415,10 +639,20 @@ impl R2Client {
     }
 
     /// Creates this client's bucket.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, status = tracing::field::Empty)
+        )
+    )]
     pub async fn create_bucket(&self) -> Result<()> {
         self.client
             .create_bucket()
             .bucket(&self.bucket)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("create_bucket", err))?;
@@ -426,10 +660,20 @@ impl R2Client {
     }
 
     /// Deletes this client's bucket. The bucket must already be empty.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, status = tracing::field::Empty)
+        )
+    )]
     pub async fn delete_bucket(&self) -> Result<()> {
         self.client
             .delete_bucket()
             .bucket(&self.bucket)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| self.map_bucket_error("delete_bucket", err))?;
@@ -441,8 +685,24 @@ impl R2Client {
     /// A `403` is an error, not `false`: with a bucket-scoped R2 API token,
     /// "exists but this token cannot see it" is a different answer from "does
     /// not exist", and reporting the former as `false` would be a lie.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, status = tracing::field::Empty)
+        )
+    )]
     pub async fn bucket_exists(&self) -> Result<bool> {
-        match self.client.head_bucket().bucket(&self.bucket).send().await {
+        match self
+            .client
+            .head_bucket()
+            .bucket(&self.bucket)
+            .customize()
+            .record_status()
+            .send()
+            .await
+        {
             Ok(_) => Ok(true),
             Err(err) => {
                 let err = from_sdk("head_bucket", err);
@@ -459,15 +719,29 @@ impl R2Client {
     ///
     /// Requires an account-scoped API token; a bucket-scoped token gets a
     /// permission error.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                buckets = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn list_buckets(&self) -> Result<Vec<BucketSummary>> {
         let response = self
             .client
             .list_buckets()
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("list_buckets", err))?;
 
-        Ok(response
+        let buckets: Vec<BucketSummary> = response
             .buckets
             .unwrap_or_default()
             .into_iter()
@@ -477,7 +751,9 @@ impl R2Client {
                     creation_date: bucket.creation_date,
                 })
             })
-            .collect())
+            .collect();
+        record!("buckets", buckets.len());
+        Ok(buckets)
     }
 
     /// Reads the bucket's CORS rules.
@@ -486,21 +762,57 @@ impl R2Client {
     /// configuration, which the API reports as the `NoSuchCORSConfiguration`
     /// error. Any other failure — including a missing bucket — is returned as
     /// an error rather than as "no rules".
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                rules = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn get_cors(&self) -> Result<Vec<CorsRule>> {
         match self
             .client
             .get_bucket_cors()
             .bucket(&self.bucket)
+            .customize()
+            .record_status()
             .send()
             .await
         {
-            Ok(response) => Ok(response.cors_rules.unwrap_or_default()),
+            Ok(response) => {
+                let rules = response
+                    .cors_rules
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(CorsRule::try_from)
+                    .collect::<Result<Vec<_>>>()?;
+                record!("rules", rules.len());
+                Ok(rules)
+            }
             Err(err) if service_code(&err) == Some("NoSuchCORSConfiguration") => Ok(Vec::new()),
             Err(err) => Err(self.map_bucket_error("get_bucket_cors", err)),
         }
     }
 
     /// Replaces the bucket's CORS rules.
+    ///
+    /// Every rule is [validated](CorsRule::validate) first, and at most
+    /// [`MAX_CORS_RULES`](crate::MAX_CORS_RULES) are accepted, so a
+    /// configuration R2 would refuse fails with [`Error::InvalidArgument`]
+    /// before any request is made.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, rules = rules.len(), status = tracing::field::Empty)
+        )
+    )]
     pub async fn put_cors(&self, rules: Vec<CorsRule>) -> Result<()> {
         if rules.is_empty() {
             return Err(Error::invalid_argument(
@@ -508,9 +820,18 @@ impl R2Client {
                 "at least one CORS rule is required; use delete_cors() to clear them",
             ));
         }
+        if rules.len() > MAX_CORS_RULES {
+            return Err(Error::invalid_argument(
+                "rules",
+                format!("a bucket holds at most {MAX_CORS_RULES} CORS rules"),
+            ));
+        }
+        for rule in &rules {
+            rule.validate()?;
+        }
 
         let configuration = CorsConfiguration::builder()
-            .set_cors_rules(Some(rules))
+            .set_cors_rules(Some(rules.into_iter().map(Into::into).collect()))
             .build()
             .map_err(|err| Error::invalid_argument("rules", err.to_string()))?;
 
@@ -518,17 +839,64 @@ impl R2Client {
             .put_bucket_cors()
             .bucket(&self.bucket)
             .cors_configuration(configuration)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| self.map_bucket_error("put_bucket_cors", err))?;
         Ok(())
     }
 
+    /// Reads the bucket's CORS rules and returns the one that would let
+    /// `origin` make a `method` request with `headers`, or `None` if the
+    /// browser would be refused. Rules are tried in order, as R2 does.
+    ///
+    /// ```no_run
+    /// # async fn run(client: cloudflare_r2_rs::R2Client) -> cloudflare_r2_rs::Result<()> {
+    /// use cloudflare_r2_rs::CorsMethod;
+    ///
+    /// let rule = client
+    ///     .check_cors("https://app.example.com", CorsMethod::Put, &["content-type"])
+    ///     .await?;
+    /// if rule.is_none() {
+    ///     eprintln!("browser uploads from the app would be blocked");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, origin = %origin, method = %method)
+        )
+    )]
+    pub async fn check_cors(
+        &self,
+        origin: &str,
+        method: CorsMethod,
+        headers: &[&str],
+    ) -> Result<Option<CorsRule>> {
+        let rules = self.get_cors().await?;
+        Ok(matching_rule(&rules, origin, method, headers).cloned())
+    }
+
     /// Removes the bucket's CORS configuration entirely.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, status = tracing::field::Empty)
+        )
+    )]
     pub async fn delete_cors(&self) -> Result<()> {
         self.client
             .delete_bucket_cors()
             .bucket(&self.bucket)
+            .customize()
+            .record_status()
             .send()
             .await
             // Deliberately not map_bucket_error: a 404 here can mean "no CORS
@@ -544,15 +912,33 @@ impl R2Client {
     /// configuration, which the API reports as the
     /// `NoSuchLifecycleConfiguration` error. Any other failure — including a
     /// missing bucket — is returned as an error rather than as "no rules".
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                rules = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn get_lifecycle(&self) -> Result<Vec<LifecycleRule>> {
         match self
             .client
             .get_bucket_lifecycle_configuration()
             .bucket(&self.bucket)
+            .customize()
+            .record_status()
             .send()
             .await
         {
-            Ok(response) => Ok(response.rules.unwrap_or_default()),
+            Ok(response) => {
+                let rules = response.rules.unwrap_or_default();
+                record!("rules", rules.len());
+                rules.into_iter().map(LifecycleRule::try_from).collect()
+            }
             Err(err) if service_code(&err) == Some("NoSuchLifecycleConfiguration") => {
                 Ok(Vec::new())
             }
@@ -561,6 +947,17 @@ impl R2Client {
     }
 
     /// Replaces the bucket's lifecycle rules.
+    ///
+    /// Every rule is [validated](LifecycleRule::validate) before anything is
+    /// sent, and a bucket holds at most [`MAX_LIFECYCLE_RULES`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, rules = rules.len(), status = tracing::field::Empty)
+        )
+    )]
     pub async fn put_lifecycle(&self, rules: Vec<LifecycleRule>) -> Result<()> {
         if rules.is_empty() {
             return Err(Error::invalid_argument(
@@ -568,9 +965,18 @@ impl R2Client {
                 "at least one lifecycle rule is required; use delete_lifecycle() to clear them",
             ));
         }
+        if rules.len() > MAX_LIFECYCLE_RULES {
+            return Err(Error::invalid_argument(
+                "rules",
+                format!("a bucket holds at most {MAX_LIFECYCLE_RULES} lifecycle rules"),
+            ));
+        }
+        for rule in &rules {
+            rule.validate()?;
+        }
 
         let configuration = BucketLifecycleConfiguration::builder()
-            .set_rules(Some(rules))
+            .set_rules(Some(rules.into_iter().map(Into::into).collect()))
             .build()
             .map_err(|err| Error::invalid_argument("rules", err.to_string()))?;
 
@@ -578,6 +984,8 @@ impl R2Client {
             .put_bucket_lifecycle_configuration()
             .bucket(&self.bucket)
             .lifecycle_configuration(configuration)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| self.map_bucket_error("put_bucket_lifecycle_configuration", err))?;
@@ -585,10 +993,20 @@ impl R2Client {
     }
 
     /// Removes the bucket's lifecycle configuration entirely.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, status = tracing::field::Empty)
+        )
+    )]
     pub async fn delete_lifecycle(&self) -> Result<()> {
         self.client
             .delete_bucket_lifecycle()
             .bucket(&self.bucket)
+            .customize()
+            .record_status()
             .send()
             .await
             // See delete_cors: a 404 is ambiguous here.
@@ -624,6 +1042,29 @@ mod tests {
         assert!(rendered.contains("AKIAPUBLIC"));
     }
 
+    #[test]
+    fn http_options_and_a_replacement_http_client_are_exclusive() {
+        let err = R2Client::builder()
+            .account_id("acct")
+            .bucket("bucket")
+            .access_key_id("key")
+            .secret_access_key("secret")
+            .http_options(HttpOptions::new().tcp_nodelay(false))
+            .http_client(HttpOptions::new().into_http_client().unwrap())
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::InvalidConfig {
+                    field: "http_options",
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
     #[test]
     fn builds_from_account_id() {
         let client = client();
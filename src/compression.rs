@@ -0,0 +1,132 @@
+//! Transparent compression of object bodies, behind the `compression`
+//! feature.
+//!
+//! Uploads compress when [`PutOptions::compression`] is set and record the
+//! codec in `Content-Encoding`. Downloads decompress when the client was built
+//! with [`R2ClientBuilder::decompress`](crate::R2ClientBuilder::decompress)
+//! and the object's `Content-Encoding` names a codec this module knows.
+
+use std::pin::Pin;
+
+use async_compression::tokio::bufread::{
+    BrotliDecoder, BrotliEncoder, GzipDecoder, GzipEncoder, ZstdDecoder, ZstdEncoder,
+};
+use aws_sdk_s3::primitives::ByteStream;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt};
+use tokio_util::io::ReaderStream;
+
+use crate::body::stream_body;
+use crate::error::Result;
+use crate::types::PutOptions;
+
+/// A codec for [`PutOptions::compression`].
+///
+/// ```
+/// use cloudflare_r2_rs::{Compression, PutOptions};
+///
+/// let options = PutOptions::new().compression(Compression::Zstd);
+/// assert_eq!(Compression::Zstd.content_encoding(), "zstd");
+/// assert_eq!(Compression::from_content_encoding("br"), Some(Compression::Brotli));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Compression {
+    /// gzip, which every HTTP client can decode.
+    Gzip,
+    /// Zstandard: faster than gzip at a better ratio.
+    Zstd,
+    /// Brotli: the best ratio for text, and the slowest to compress.
+    Brotli,
+}
+
+/// A boxed reader, `Sync` so that decoders can back a response body.
+pub(crate) type Reader = Pin<Box<dyn AsyncRead + Send + Sync>>;
+
+impl Compression {
+    /// The `Content-Encoding` value for this codec.
+    pub fn content_encoding(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Brotli => "br",
+        }
+    }
+
+    /// The codec a `Content-Encoding` value names, if it is one of these.
+    /// A list of several encodings is not recognized.
+    pub fn from_content_encoding(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            "br" => Some(Compression::Brotli),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn encoder<R>(self, reader: R) -> Reader
+    where
+        R: AsyncBufRead + Send + Sync + 'static,
+    {
+        match self {
+            Compression::Gzip => Box::pin(GzipEncoder::new(reader)),
+            Compression::Zstd => Box::pin(ZstdEncoder::new(reader)),
+            Compression::Brotli => Box::pin(BrotliEncoder::new(reader)),
+        }
+    }
+
+    fn decoder<R>(self, reader: R) -> Reader
+    where
+        R: AsyncBufRead + Send + Sync + 'static,
+    {
+        match self {
+            Compression::Gzip => {
+                let mut decoder = GzipDecoder::new(reader);
+                // Concatenated gzip members are one valid stream.
+                decoder.multiple_members(true);
+                Box::pin(decoder)
+            }
+            Compression::Zstd => {
+                let mut decoder = ZstdDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::pin(decoder)
+            }
+            Compression::Brotli => Box::pin(BrotliDecoder::new(reader)),
+        }
+    }
+
+    /// Compresses a whole body into memory. A single `PUT` must state its
+    /// length up front, which a compressed stream does not know until it
+    /// ends.
+    pub(crate) async fn compress(self, body: ByteStream) -> Result<ByteStream> {
+        let mut compressed = Vec::new();
+        self.encoder(body.into_async_read())
+            .read_to_end(&mut compressed)
+            .await?;
+        Ok(ByteStream::from(compressed))
+    }
+
+    /// Decompresses a response body as it streams.
+    pub(crate) fn decompress(self, body: ByteStream) -> ByteStream {
+        let reader = self.decoder(body.into_async_read());
+        stream_body(ReaderStream::new(reader))
+    }
+}
+
+impl PutOptions {
+    /// Compresses the body with `compression` on upload, and sets
+    /// `Content-Encoding` to match.
+    #[must_use]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Splits the codec off, recording it as the `Content-Encoding`, for
+    /// [`create_multipart_upload`](R2Client::create_multipart_upload), which
+    /// refuses to compress parts it never sees.
+    pub(crate) fn take_compression(mut self) -> Result<(Self, Option<Compression>)> {
+        self.content_encoding = self.resolved_content_encoding()?;
+        let compression = self.compression.take();
+        Ok((self, compression))
+    }
+}
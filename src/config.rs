@@ -115,12 +115,25 @@ impl R2Config {
     /// Optional variables: `R2_JURISDICTION` (`default`, `eu`, `fedramp`) and
     /// `R2_REGION`.
     pub fn from_env() -> Result<Self> {
+        Self::read_env(None)
+    }
+
+    /// Like [`from_env`](R2Config::from_env), but for `bucket`: `R2_BUCKET`
+    /// is neither read nor required.
+    pub fn from_env_with_bucket(bucket: impl Into<String>) -> Result<Self> {
+        Self::read_env(Some(bucket.into()))
+    }
+
+    fn read_env(bucket: Option<String>) -> Result<Self> {
         let access_key_id = env_any(&["R2_ACCESS_KEY_ID", "CLOUDFLARE_CLIENT_ID"])
             .ok_or(Error::MissingConfig("R2_ACCESS_KEY_ID"))?;
         let secret_access_key = env_any(&["R2_SECRET_ACCESS_KEY", "CLOUDFLARE_SECRET_KEY"])
             .ok_or(Error::MissingConfig("R2_SECRET_ACCESS_KEY"))?;
-        let bucket = env_any(&["R2_BUCKET", "CLOUDFLARE_BUCKET_NAME"])
-            .ok_or(Error::MissingConfig("R2_BUCKET"))?;
+        let bucket = match bucket {
+            Some(bucket) => bucket,
+            None => env_any(&["R2_BUCKET", "CLOUDFLARE_BUCKET_NAME"])
+                .ok_or(Error::MissingConfig("R2_BUCKET"))?,
+        };
 
         let jurisdiction = match env_any(&["R2_JURISDICTION"]) {
             Some(value) => Jurisdiction::parse(&value)?,
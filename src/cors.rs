@@ -0,0 +1,480 @@
+//! Bucket CORS rules, checked against R2's constraints before they are sent.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use aws_sdk_s3::types::CorsRule as SdkCorsRule;
+
+use crate::error::{Error, Result};
+
+/// The most rules one bucket's CORS configuration may hold.
+pub const MAX_CORS_RULES: usize = 100;
+
+/// The longest rule ID the API accepts, in characters.
+const MAX_ID_LEN: usize = 255;
+
+/// A method a CORS rule can allow. R2 supports exactly these five.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "UPPERCASE"))]
+pub enum CorsMethod {
+    /// `GET`
+    Get,
+    /// `PUT`
+    Put,
+    /// `POST`
+    Post,
+    /// `DELETE`
+    Delete,
+    /// `HEAD`
+    Head,
+}
+
+impl CorsMethod {
+    /// The method as it appears on the wire, e.g. `"GET"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CorsMethod::Get => "GET",
+            CorsMethod::Put => "PUT",
+            CorsMethod::Post => "POST",
+            CorsMethod::Delete => "DELETE",
+            CorsMethod::Head => "HEAD",
+        }
+    }
+}
+
+impl fmt::Display for CorsMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CorsMethod {
+    type Err = Error;
+
+    /// Parses a method name, ignoring case.
+    fn from_str(method: &str) -> Result<Self> {
+        [
+            CorsMethod::Get,
+            CorsMethod::Put,
+            CorsMethod::Post,
+            CorsMethod::Delete,
+            CorsMethod::Head,
+        ]
+        .into_iter()
+        .find(|candidate| candidate.as_str().eq_ignore_ascii_case(method))
+        .ok_or_else(|| {
+            Error::invalid_argument(
+                "allowed_methods",
+                format!("`{method}` is not a method R2 allows in CORS rules; use GET, PUT, POST, DELETE or HEAD"),
+            )
+        })
+    }
+}
+
+/// One CORS rule for [`put_cors`](crate::R2Client::put_cors).
+///
+/// Built up with chained calls, and checked by [`validate`](CorsRule::validate)
+/// — which `put_cors` runs on every rule — so a configuration R2 would refuse
+/// is caught before it is sent:
+///
+/// - at least one origin, each either `*` or a bare `http`/`https` origin
+///   such as `https://example.com:8443`, with no path and no partial
+///   wildcards;
+/// - at least one method;
+/// - allowed headers that are header names or `*`, and exposed headers that
+///   are header names;
+/// - a max age that fits the API's 32-bit field, and an ID of at most 255
+///   characters.
+///
+/// ```
+/// use std::time::Duration;
+/// use cloudflare_r2_rs::{CorsMethod, CorsRule};
+///
+/// let rule = CorsRule::new()
+///     .allow_origin("https://app.example.com")
+///     .allow_methods([CorsMethod::Get, CorsMethod::Head])
+///     .allow_header("range")
+///     .expose_header("etag")
+///     .max_age(Duration::from_secs(3600));
+/// rule.validate().unwrap();
+/// assert!(rule.allows("https://app.example.com", CorsMethod::Get, &["Range"]));
+/// assert!(!rule.allows("https://evil.example", CorsMethod::Get, &[]));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct CorsRule {
+    /// An optional name for the rule.
+    pub id: Option<String>,
+    /// Origins allowed to make requests: `*`, or `scheme://host[:port]`.
+    pub allowed_origins: Vec<String>,
+    /// Methods those origins may use.
+    pub allowed_methods: Vec<CorsMethod>,
+    /// Request headers a preflight may ask for; `*` allows any.
+    pub allowed_headers: Vec<String>,
+    /// Response headers the browser may expose to scripts.
+    pub expose_headers: Vec<String>,
+    /// How long browsers may cache the preflight response.
+    pub max_age: Option<Duration>,
+}
+
+impl CorsRule {
+    /// Creates an empty rule, which needs at least one origin and method.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names the rule.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Allows an origin, or every origin with `*`.
+    #[must_use]
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    /// Allows a method.
+    #[must_use]
+    pub fn allow_method(mut self, method: CorsMethod) -> Self {
+        self.allowed_methods.push(method);
+        self
+    }
+
+    /// Allows several methods.
+    #[must_use]
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = CorsMethod>) -> Self {
+        self.allowed_methods.extend(methods);
+        self
+    }
+
+    /// Allows a request header, or every header with `*`.
+    #[must_use]
+    pub fn allow_header(mut self, header: impl Into<String>) -> Self {
+        self.allowed_headers.push(header.into());
+        self
+    }
+
+    /// Exposes a response header to scripts.
+    #[must_use]
+    pub fn expose_header(mut self, header: impl Into<String>) -> Self {
+        self.expose_headers.push(header.into());
+        self
+    }
+
+    /// Sets how long browsers may cache the preflight response. Whole
+    /// seconds are sent; anything finer is dropped.
+    #[must_use]
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Checks the rule against R2's constraints, listed on [`CorsRule`].
+    pub fn validate(&self) -> Result<()> {
+        if let Some(id) = &self.id {
+            if id.chars().count() > MAX_ID_LEN {
+                return Err(Error::invalid_argument(
+                    "id",
+                    format!("rule IDs are at most {MAX_ID_LEN} characters"),
+                ));
+            }
+        }
+        if self.allowed_origins.is_empty() {
+            return Err(Error::invalid_argument(
+                "allowed_origins",
+                "a CORS rule needs at least one allowed origin",
+            ));
+        }
+        for origin in &self.allowed_origins {
+            validate_origin(origin)?;
+        }
+        if self.allowed_methods.is_empty() {
+            return Err(Error::invalid_argument(
+                "allowed_methods",
+                "a CORS rule needs at least one allowed method",
+            ));
+        }
+        for header in &self.allowed_headers {
+            if header != "*" && !is_header_name(header) {
+                return Err(Error::invalid_argument(
+                    "allowed_headers",
+                    format!("`{header}` is neither a header name nor `*`"),
+                ));
+            }
+        }
+        for header in &self.expose_headers {
+            if !is_header_name(header) {
+                return Err(Error::invalid_argument(
+                    "expose_headers",
+                    format!("`{header}` is not a header name; exposed headers cannot be wildcards"),
+                ));
+            }
+        }
+        if self
+            .max_age
+            .is_some_and(|max_age| max_age.as_secs() > i32::MAX as u64)
+        {
+            return Err(Error::invalid_argument(
+                "max_age",
+                format!("max age is at most {} seconds", i32::MAX),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether this rule lets `origin` make a `method` request sending
+    /// `headers`, as a browser's preflight would ask. Origins and header
+    /// names compare case-insensitively.
+    pub fn allows(&self, origin: &str, method: CorsMethod, headers: &[&str]) -> bool {
+        let origin_allowed = self
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(origin));
+        let headers_allowed = headers.iter().all(|header| {
+            self.allowed_headers
+                .iter()
+                .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(header))
+        });
+        origin_allowed && self.allowed_methods.contains(&method) && headers_allowed
+    }
+}
+
+/// The first of `rules` that allows the request, as R2 picks one.
+pub(crate) fn matching_rule<'a>(
+    rules: &'a [CorsRule],
+    origin: &str,
+    method: CorsMethod,
+    headers: &[&str],
+) -> Option<&'a CorsRule> {
+    rules
+        .iter()
+        .find(|rule| rule.allows(origin, method, headers))
+}
+
+fn validate_origin(origin: &str) -> Result<()> {
+    if origin == "*" {
+        return Ok(());
+    }
+    let invalid = |reason: &str| {
+        Err(Error::invalid_argument(
+            "allowed_origins",
+            format!("`{origin}` {reason}"),
+        ))
+    };
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return invalid("is not an origin; expected `*` or `scheme://host[:port]`");
+    };
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return invalid("does not use http or https");
+    }
+    if authority.contains('*') {
+        return invalid("has a partial wildcard; R2 only accepts `*` on its own");
+    }
+    if authority.is_empty() || authority.contains(['/', '?', '#', '@', ' ']) {
+        return invalid("must be just a scheme, host and optional port, with no path");
+    }
+    if let Some((_, port)) = authority
+        .rsplit_once(':')
+        .filter(|_| !authority.ends_with(']'))
+    {
+        if port.parse::<u16>().is_err() {
+            return invalid("has an invalid port");
+        }
+    }
+    Ok(())
+}
+
+/// An HTTP token: the characters a header name may use.
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+        && name != "*"
+}
+
+impl From<CorsRule> for SdkCorsRule {
+    fn from(rule: CorsRule) -> Self {
+        SdkCorsRule::builder()
+            .set_id(rule.id)
+            .set_allowed_origins(Some(rule.allowed_origins))
+            .set_allowed_methods(Some(
+                rule.allowed_methods
+                    .iter()
+                    .map(|method| method.as_str().to_string())
+                    .collect(),
+            ))
+            .set_allowed_headers(Some(rule.allowed_headers).filter(|headers| !headers.is_empty()))
+            .set_expose_headers(Some(rule.expose_headers).filter(|headers| !headers.is_empty()))
+            .set_max_age_seconds(
+                rule.max_age
+                    .map(|max_age| i32::try_from(max_age.as_secs()).unwrap_or(i32::MAX)),
+            )
+            .build()
+            .expect("origins and methods are always set")
+    }
+}
+
+impl TryFrom<SdkCorsRule> for CorsRule {
+    type Error = Error;
+
+    /// Fails on a method outside the five R2 supports.
+    fn try_from(rule: SdkCorsRule) -> Result<Self> {
+        Ok(CorsRule {
+            allowed_methods: rule
+                .allowed_methods
+                .iter()
+                .map(|method| method.parse())
+                .collect::<Result<_>>()?,
+            id: rule.id,
+            allowed_origins: rule.allowed_origins,
+            allowed_headers: rule.allowed_headers.unwrap_or_default(),
+            expose_headers: rule.expose_headers.unwrap_or_default(),
+            max_age: rule
+                .max_age_seconds
+                .map(|seconds| Duration::from_secs(seconds.max(0) as u64)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule() -> CorsRule {
+        CorsRule::new()
+            .allow_origin("https://example.com")
+            .allow_method(CorsMethod::Get)
+    }
+
+    #[test]
+    fn origins_are_checked() {
+        for origin in [
+            "*",
+            "https://example.com",
+            "http://localhost:3000",
+            "HTTPS://Example.com",
+            "http://[::1]:8080",
+        ] {
+            rule().allow_origin(origin).validate().unwrap();
+        }
+        for origin in [
+            "example.com",
+            "ftp://example.com",
+            "https://*.example.com",
+            "https://example.com/",
+            "https://example.com/app",
+            "https://",
+            "https://example.com:99999",
+        ] {
+            let err = rule().allow_origin(origin).validate().unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    Error::InvalidArgument {
+                        argument: "allowed_origins",
+                        ..
+                    }
+                ),
+                "{origin}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn incomplete_and_malformed_rules_are_rejected() {
+        let argument = |rule: CorsRule| match rule.validate().unwrap_err() {
+            Error::InvalidArgument { argument, .. } => argument,
+            err => panic!("{err}"),
+        };
+        assert_eq!(argument(CorsRule::new()), "allowed_origins");
+        assert_eq!(
+            argument(CorsRule::new().allow_origin("*")),
+            "allowed_methods"
+        );
+        assert_eq!(
+            argument(rule().allow_header("bad header")),
+            "allowed_headers"
+        );
+        assert_eq!(argument(rule().expose_header("*")), "expose_headers");
+        assert_eq!(
+            argument(rule().max_age(Duration::from_secs(1 << 40))),
+            "max_age"
+        );
+        assert_eq!(argument(rule().id("x".repeat(256))), "id");
+        rule()
+            .allow_header("*")
+            .allow_header("x-amz-meta-owner")
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn sdk_rules_round_trip() {
+        let rule = rule()
+            .id("web")
+            .allow_method(CorsMethod::Put)
+            .allow_header("content-type")
+            .expose_header("etag")
+            .max_age(Duration::from_secs(600));
+        let sdk = SdkCorsRule::from(rule.clone());
+        assert_eq!(sdk.allowed_methods(), ["GET", "PUT"]);
+        assert_eq!(sdk.max_age_seconds(), Some(600));
+        assert_eq!(CorsRule::try_from(sdk).unwrap(), rule);
+
+        let patch = SdkCorsRule::builder()
+            .allowed_origins("*")
+            .allowed_methods("PATCH")
+            .build()
+            .unwrap();
+        assert!(CorsRule::try_from(patch).is_err());
+        assert_eq!("head".parse::<CorsMethod>().unwrap(), CorsMethod::Head);
+    }
+
+    #[test]
+    fn the_first_matching_rule_wins() {
+        let rules = [
+            CorsRule::new()
+                .id("uploads")
+                .allow_origin("https://app.example.com")
+                .allow_methods([CorsMethod::Put, CorsMethod::Post])
+                .allow_header("content-type"),
+            CorsRule::new()
+                .id("reads")
+                .allow_origin("*")
+                .allow_methods([CorsMethod::Get, CorsMethod::Head]),
+        ];
+        let id = |origin, method, headers: &[&str]| {
+            matching_rule(&rules, origin, method, headers).and_then(|rule| rule.id.as_deref())
+        };
+        assert_eq!(
+            id(
+                "https://APP.example.com",
+                CorsMethod::Put,
+                &["Content-Type"]
+            ),
+            Some("uploads")
+        );
+        assert_eq!(
+            id("https://app.example.com", CorsMethod::Put, &["x-custom"]),
+            None
+        );
+        assert_eq!(id("https://other.example", CorsMethod::Put, &[]), None);
+        assert_eq!(
+            id("https://other.example", CorsMethod::Get, &[]),
+            Some("reads")
+        );
+        assert_eq!(
+            id("https://other.example", CorsMethod::Get, &["range"]),
+            None
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! Estimating what a client's R2 usage costs.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::metrics::{MetricsObserver, OperationClass, OperationEvent};
+
+/// R2 bills storage in GB-months, where a GB is 2^30 bytes.
+const GB: f64 = (1u64 << 30) as f64;
+
+/// The month [`CostTracker::monthly_estimate`] projects onto.
+const MONTH: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The storage class R2 reports for Infrequent Access objects.
+const INFREQUENT_ACCESS: &str = "STANDARD_IA";
+
+/// Prices, in US dollars, for one R2 storage class.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoragePricing {
+    /// Per GB-month stored.
+    pub storage_per_gb_month: f64,
+    /// Per million Class A operations.
+    pub class_a_per_million: f64,
+    /// Per million Class B operations.
+    pub class_b_per_million: f64,
+    /// Per GB read back. Only Infrequent Access charges for retrieval.
+    pub retrieval_per_gb: f64,
+}
+
+/// What a [`CostTracker`] charges for R2 usage.
+///
+/// The default is Cloudflare's published pricing at the time of this
+/// release, including the monthly free tier, which covers Standard storage
+/// only. Prices change; override the fields to match your contract.
+///
+/// ```
+/// use cloudflare_r2_rs::Pricing;
+///
+/// let mut pricing = Pricing::new().without_free_tier();
+/// pricing.standard.storage_per_gb_month = 0.012;
+/// assert_eq!(pricing.free_class_a, 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+    /// Prices for Standard storage.
+    pub standard: StoragePricing,
+    /// Prices for Infrequent Access storage.
+    pub infrequent_access: StoragePricing,
+    /// Free Standard GB-months per month.
+    pub free_storage_gb: f64,
+    /// Free Standard Class A operations per month.
+    pub free_class_a: u64,
+    /// Free Standard Class B operations per month.
+    pub free_class_b: u64,
+}
+
+impl Default for Pricing {
+    fn default() -> Self {
+        Pricing {
+            standard: StoragePricing {
+                storage_per_gb_month: 0.015,
+                class_a_per_million: 4.50,
+                class_b_per_million: 0.36,
+                retrieval_per_gb: 0.0,
+            },
+            infrequent_access: StoragePricing {
+                storage_per_gb_month: 0.01,
+                class_a_per_million: 9.00,
+                class_b_per_million: 0.90,
+                retrieval_per_gb: 0.01,
+            },
+            free_storage_gb: 10.0,
+            free_class_a: 1_000_000,
+            free_class_b: 10_000_000,
+        }
+    }
+}
+
+impl Pricing {
+    /// Creates Cloudflare's published pricing.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops the free tier, e.g. when other workloads on the account already
+    /// use it up.
+    #[must_use]
+    pub fn without_free_tier(mut self) -> Self {
+        self.free_storage_gb = 0.0;
+        self.free_class_a = 0;
+        self.free_class_b = 0;
+        self
+    }
+
+    fn estimate(&self, usage: &CostUsage) -> CostEstimate {
+        let per_million = |count: u64, free: u64, price: f64| {
+            count.saturating_sub(free) as f64 / 1_000_000.0 * price
+        };
+        let (standard, ia) = (&self.standard, &self.infrequent_access);
+
+        let class_a = per_million(
+            usage.standard.class_a,
+            self.free_class_a,
+            standard.class_a_per_million,
+        ) + per_million(usage.infrequent_access.class_a, 0, ia.class_a_per_million);
+        let class_b = per_million(
+            usage.standard.class_b,
+            self.free_class_b,
+            standard.class_b_per_million,
+        ) + per_million(usage.infrequent_access.class_b, 0, ia.class_b_per_million);
+        let storage = (usage.standard.stored_bytes as f64 / GB - self.free_storage_gb).max(0.0)
+            * standard.storage_per_gb_month
+            + usage.infrequent_access.stored_bytes as f64 / GB * ia.storage_per_gb_month;
+        let retrieval = usage.standard.retrieved_bytes as f64 / GB * standard.retrieval_per_gb
+            + usage.infrequent_access.retrieved_bytes as f64 / GB * ia.retrieval_per_gb;
+
+        CostEstimate {
+            class_a,
+            class_b,
+            storage,
+            retrieval,
+            total: class_a + class_b + storage + retrieval,
+        }
+    }
+}
+
+/// An estimated bill, in US dollars, broken down by line item.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[non_exhaustive]
+pub struct CostEstimate {
+    /// Class A operations.
+    pub class_a: f64,
+    /// Class B operations.
+    pub class_b: f64,
+    /// Storage, for one month.
+    pub storage: f64,
+    /// Infrequent Access retrieval.
+    pub retrieval: f64,
+    /// The sum of the above.
+    pub total: f64,
+}
+
+/// Usage of one storage class, as counted by a [`CostTracker`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClassUsage {
+    /// Class A requests.
+    pub class_a: u64,
+    /// Class B requests.
+    pub class_b: u64,
+    /// Bytes stored.
+    pub stored_bytes: u64,
+    /// Bytes read back.
+    pub retrieved_bytes: u64,
+}
+
+/// Usage counted by a [`CostTracker`], per storage class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CostUsage {
+    /// Standard storage.
+    pub standard: ClassUsage,
+    /// Infrequent Access storage.
+    pub infrequent_access: ClassUsage,
+}
+
+/// Storage classes R2 bills differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageClass {
+    /// The default class.
+    Standard,
+    /// Infrequent Access (`STANDARD_IA`): cheaper to store, dearer to touch.
+    InfrequentAccess,
+}
+
+impl StorageClass {
+    fn of(event: &OperationEvent) -> Self {
+        match event.storage_class.as_deref() {
+            Some(INFREQUENT_ACCESS) => StorageClass::InfrequentAccess,
+            _ => StorageClass::Standard,
+        }
+    }
+}
+
+/// A [`MetricsObserver`] that turns a client's traffic into an estimated
+/// R2 bill.
+///
+/// Install it with [`R2ClientBuilder::metrics`](crate::R2ClientBuilder::metrics)
+/// and it counts Class A and Class B requests, bytes uploaded and bytes read
+/// back, per storage class, then prices them with a [`Pricing`]:
+///
+/// ```
+/// use std::sync::Arc;
+/// use cloudflare_r2_rs::{CostTracker, Pricing, R2Client};
+///
+/// let costs = Arc::new(CostTracker::new(Pricing::new()));
+/// let client = R2Client::builder()
+///     .account_id("0123456789abcdef")
+///     .bucket("media")
+///     .access_key_id("access-key")
+///     .secret_access_key("secret-key")
+///     .metrics(costs.clone())
+///     .build()
+///     .unwrap();
+///
+/// // ... run the feature being costed ...
+/// let bill = costs.monthly_estimate();
+/// println!("about ${:.2} a month", bill.total);
+/// ```
+///
+/// Stored bytes only grow: R2 does not say how large a deleted or
+/// overwritten object was. For an accurate storage line, measure what the
+/// bucket holds and pass it to [`set_stored_bytes`](CostTracker::set_stored_bytes).
+/// Objects moved to Infrequent Access by a lifecycle rule are recognised once
+/// R2 reports their storage class on a read.
+#[derive(Debug)]
+pub struct CostTracker {
+    pricing: Pricing,
+    state: Mutex<TrackerState>,
+}
+
+#[derive(Debug)]
+struct TrackerState {
+    usage: CostUsage,
+    since: Instant,
+}
+
+impl CostTracker {
+    /// Creates a tracker that has counted nothing yet.
+    #[must_use]
+    pub fn new(pricing: Pricing) -> Self {
+        CostTracker {
+            pricing,
+            state: Mutex::new(TrackerState {
+                usage: CostUsage::default(),
+                since: Instant::now(),
+            }),
+        }
+    }
+
+    /// The pricing estimates are made with.
+    pub fn pricing(&self) -> &Pricing {
+        &self.pricing
+    }
+
+    /// Usage counted since the tracker was created or last reset.
+    pub fn usage(&self) -> CostUsage {
+        self.lock().usage
+    }
+
+    /// Replaces the stored-bytes count of `class` with a measured figure.
+    pub fn set_stored_bytes(&self, class: StorageClass, bytes: u64) {
+        self.lock().usage.class_mut(class).stored_bytes = bytes;
+    }
+
+    /// Prices the usage counted so far as if it were one month's worth.
+    pub fn estimate(&self) -> CostEstimate {
+        self.pricing.estimate(&self.lock().usage)
+    }
+
+    /// Projects the request and retrieval rate seen so far onto a 30-day
+    /// month, and prices it with the current stored bytes.
+    ///
+    /// The projection is only as good as the traffic behind it: a few seconds
+    /// of a bursty workload make a poor basis for a month.
+    pub fn monthly_estimate(&self) -> CostEstimate {
+        let state = self.lock();
+        let elapsed = state.since.elapsed().max(Duration::from_secs(1));
+        let scale = MONTH.as_secs_f64() / elapsed.as_secs_f64();
+
+        let project = |usage: ClassUsage| ClassUsage {
+            class_a: (usage.class_a as f64 * scale) as u64,
+            class_b: (usage.class_b as f64 * scale) as u64,
+            stored_bytes: usage.stored_bytes,
+            retrieved_bytes: (usage.retrieved_bytes as f64 * scale) as u64,
+        };
+        self.pricing.estimate(&CostUsage {
+            standard: project(state.usage.standard),
+            infrequent_access: project(state.usage.infrequent_access),
+        })
+    }
+
+    /// Clears the counts and restarts the clock, stored bytes included.
+    pub fn reset(&self) {
+        let mut state = self.lock();
+        state.usage = CostUsage::default();
+        state.since = Instant::now();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TrackerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl CostUsage {
+    fn class_mut(&mut self, class: StorageClass) -> &mut ClassUsage {
+        match class {
+            StorageClass::Standard => &mut self.standard,
+            StorageClass::InfrequentAccess => &mut self.infrequent_access,
+        }
+    }
+}
+
+impl MetricsObserver for CostTracker {
+    fn on_operation(&self, event: &OperationEvent) {
+        let mut state = self.lock();
+        let usage = state.usage.class_mut(StorageClass::of(event));
+
+        // Every attempt that reached R2 is billed, successful or not.
+        let attempts = u64::from(event.attempts);
+        match event.class {
+            OperationClass::ClassA => usage.class_a += attempts,
+            OperationClass::ClassB => usage.class_b += attempts,
+            OperationClass::Free => {}
+        }
+
+        if event.success {
+            match event.operation.as_str() {
+                // Each attempt sends the whole body, so one attempt's worth
+                // is what ends up stored.
+                "PutObject" | "UploadPart" => {
+                    usage.stored_bytes += event.bytes_sent / attempts.max(1);
+                }
+                "GetObject" => usage.retrieved_bytes += event.bytes_received,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(operation: &str, storage_class: Option<&str>, bytes: u64) -> OperationEvent {
+        OperationEvent {
+            operation: operation.to_string(),
+            class: OperationClass::of(operation),
+            status: Some(200),
+            success: true,
+            latency: Duration::from_millis(5),
+            bytes_sent: if operation == "GetObject" { 0 } else { bytes },
+            bytes_received: if operation == "GetObject" { bytes } else { 0 },
+            attempts: 1,
+            storage_class: storage_class.map(str::to_string),
+        }
+    }
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() < 1e-9
+    }
+
+    #[test]
+    fn counts_requests_and_bytes_per_storage_class() {
+        let tracker = CostTracker::new(Pricing::new());
+        tracker.on_operation(&event("PutObject", None, 100));
+        tracker.on_operation(&event("PutObject", Some("STANDARD_IA"), 50));
+        tracker.on_operation(&event("GetObject", Some("STANDARD_IA"), 50));
+        tracker.on_operation(&event("DeleteObject", None, 0));
+
+        let usage = tracker.usage();
+        assert_eq!(usage.standard.class_a, 1);
+        assert_eq!(usage.standard.stored_bytes, 100);
+        assert_eq!(usage.infrequent_access.class_a, 1);
+        assert_eq!(usage.infrequent_access.class_b, 1);
+        assert_eq!(usage.infrequent_access.stored_bytes, 50);
+        assert_eq!(usage.infrequent_access.retrieved_bytes, 50);
+    }
+
+    #[test]
+    fn the_free_tier_covers_standard_usage_only() {
+        let tracker = CostTracker::new(Pricing::new());
+        tracker.set_stored_bytes(StorageClass::Standard, 5 << 30);
+        tracker.set_stored_bytes(StorageClass::InfrequentAccess, 100 << 30);
+        tracker.on_operation(&event("GetObject", Some("STANDARD_IA"), 10 << 30));
+
+        let bill = tracker.estimate();
+        assert!(close(bill.storage, 100.0 * 0.01), "{bill:?}");
+        assert!(close(bill.retrieval, 10.0 * 0.01), "{bill:?}");
+        assert!(close(bill.class_b, 0.9 / 1_000_000.0), "{bill:?}");
+        assert!(close(bill.total, 1.0 + 0.1 + 0.9 / 1_000_000.0), "{bill:?}");
+    }
+
+    #[test]
+    fn usage_beyond_the_free_tier_is_billed() {
+        let tracker = CostTracker::new(Pricing::new().without_free_tier());
+        for _ in 0..1000 {
+            tracker.on_operation(&event("PutObject", None, 0));
+        }
+        tracker.set_stored_bytes(StorageClass::Standard, 1 << 30);
+
+        let bill = tracker.estimate();
+        assert!(close(bill.class_a, 4.5 / 1000.0), "{bill:?}");
+        assert!(close(bill.storage, 0.015), "{bill:?}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_monthly_estimate_projects_the_request_rate() {
+        let tracker = CostTracker::new(Pricing::new().without_free_tier());
+        tracker.on_operation(&event("PutObject", None, 0));
+        tokio::time::advance(Duration::from_secs(24 * 60 * 60)).await;
+
+        // One request a day is thirty a month.
+        let bill = tracker.monthly_estimate();
+        assert!(close(bill.class_a, 30.0 * 4.5 / 1_000_000.0), "{bill:?}");
+    }
+}
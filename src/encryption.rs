@@ -0,0 +1,567 @@
+//! Client-side envelope encryption, behind the `encryption` feature.
+//!
+//! Every object gets a fresh 256-bit data key. The body is encrypted with it
+//! under AES-256-GCM in frames of 64 KiB, each sealed with its own 16-byte
+//! tag, so bodies stream in both directions and a ranged read only fetches
+//! and decrypts the frames it touches. The data key itself is wrapped by a
+//! [`KeyProvider`] — a KMS, an HSM, or [`LocalKeyProvider`] — and stored
+//! beside the object as user metadata:
+//!
+//! | Metadata                | Value                                          |
+//! |-------------------------|------------------------------------------------|
+//! | `encryption-algorithm`  | `AES-256-GCM-64KiB`                            |
+//! | `encryption-key-id`     | the provider's [`WrappedKey::key_id`]          |
+//! | `encryption-key`        | the wrapped data key, base64-encoded           |
+//!
+//! Frame `n` uses the nonce `n` (big-endian, zero-padded to 96 bits), which
+//! is safe because no data key encrypts more than one object. Its additional
+//! data is a single byte, `1` for the last frame and `0` otherwise, so a body
+//! cut short at a frame boundary fails to decrypt rather than reading as a
+//! shorter object.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use aws_sdk_s3::primitives::ByteStream;
+use bytes::{Bytes, BytesMut};
+use futures::future::BoxFuture;
+
+use crate::body::stream_body;
+use crate::client::R2Client;
+use crate::error::{Error, Result};
+use crate::trace::RecordStatus;
+use crate::types::PutOptions;
+
+/// Plaintext bytes per frame.
+pub(crate) const FRAME_SIZE: u64 = 64 * 1024;
+const TAG_LEN: usize = 16;
+/// Stored bytes per full frame: the plaintext and its tag.
+const SEALED_FRAME: u64 = FRAME_SIZE + TAG_LEN as u64;
+
+const ALGORITHM: &str = "AES-256-GCM-64KiB";
+const ALGORITHM_KEY: &str = "encryption-algorithm";
+const KEY_ID_KEY: &str = "encryption-key-id";
+const WRAPPED_KEY_KEY: &str = "encryption-key";
+
+/// The error a [`KeyProvider`] fails with. The client wraps it in
+/// [`Error::Encryption`] naming the object.
+pub type KeyProviderError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A data key as wrapped by a [`KeyProvider`], ready to store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct WrappedKey {
+    /// Which key-encryption key wrapped it, so that the provider can unwrap
+    /// it after rotating to a new one.
+    pub key_id: String,
+    /// The wrapped key, in whatever form the provider produces.
+    pub ciphertext: Vec<u8>,
+}
+
+impl WrappedKey {
+    /// Pairs a wrapped key with the ID of the key that wrapped it.
+    pub fn new(key_id: impl Into<String>, ciphertext: impl Into<Vec<u8>>) -> Self {
+        WrappedKey {
+            key_id: key_id.into(),
+            ciphertext: ciphertext.into(),
+        }
+    }
+}
+
+/// Wraps and unwraps the per-object data keys of client-side encryption.
+///
+/// Implement this over a KMS or HSM so the key-encryption key never enters
+/// the process; [`LocalKeyProvider`] holds it in memory instead. Methods
+/// return boxed futures so a provider can be shared as a trait object, and
+/// are called once per upload or download, never per frame.
+///
+/// ```
+/// use cloudflare_r2_rs::{KeyProvider, KeyProviderError, WrappedKey};
+/// use futures::future::BoxFuture;
+///
+/// /// Hands data keys to a KMS that does the wrapping.
+/// #[derive(Debug)]
+/// struct Kms {
+///     key_arn: String,
+/// }
+///
+/// impl KeyProvider for Kms {
+///     fn wrap_key<'a>(
+///         &'a self,
+///         data_key: &'a [u8],
+///     ) -> BoxFuture<'a, Result<WrappedKey, KeyProviderError>> {
+///         Box::pin(async move {
+///             let ciphertext = data_key.to_vec(); // call the KMS here
+///             Ok(WrappedKey::new(self.key_arn.clone(), ciphertext))
+///         })
+///     }
+///
+///     fn unwrap_key<'a>(
+///         &'a self,
+///         wrapped: &'a WrappedKey,
+///     ) -> BoxFuture<'a, Result<Vec<u8>, KeyProviderError>> {
+///         Box::pin(async move { Ok(wrapped.ciphertext.clone()) })
+///     }
+/// }
+/// ```
+pub trait KeyProvider: Send + Sync + fmt::Debug {
+    /// Wraps a fresh 32-byte data key for storage beside a new object.
+    fn wrap_key<'a>(
+        &'a self,
+        data_key: &'a [u8],
+    ) -> BoxFuture<'a, std::result::Result<WrappedKey, KeyProviderError>>;
+
+    /// Recovers the data key from what [`wrap_key`](KeyProvider::wrap_key)
+    /// returned.
+    fn unwrap_key<'a>(
+        &'a self,
+        wrapped: &'a WrappedKey,
+    ) -> BoxFuture<'a, std::result::Result<Vec<u8>, KeyProviderError>>;
+}
+
+/// A [`KeyProvider`] holding its key-encryption keys in memory, wrapping data
+/// keys with AES-256-GCM.
+///
+/// New objects are wrapped with the current key. Keys retired by rotation
+/// can be kept with [`with_retired_key`](LocalKeyProvider::with_retired_key)
+/// so that objects written under them still decrypt.
+///
+/// ```
+/// use cloudflare_r2_rs::LocalKeyProvider;
+///
+/// # let (current, previous) = ([7u8; 32], [3u8; 32]);
+/// let provider = LocalKeyProvider::new("2026-10", current).with_retired_key("2026-04", previous);
+/// ```
+pub struct LocalKeyProvider {
+    current: String,
+    keys: HashMap<String, LessSafeKey>,
+}
+
+impl LocalKeyProvider {
+    /// A provider wrapping new data keys with `key`, recorded as `key_id`.
+    pub fn new(key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        let current = key_id.into();
+        let mut keys = HashMap::new();
+        keys.insert(current.clone(), aes_key(&key));
+        LocalKeyProvider { current, keys }
+    }
+
+    /// Keeps a retired key for unwrapping the data keys it wrapped.
+    #[must_use]
+    pub fn with_retired_key(mut self, key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        self.keys
+            .entry(key_id.into())
+            .or_insert_with(|| aes_key(&key));
+        self
+    }
+
+    fn wrap(&self, data_key: &[u8]) -> std::result::Result<WrappedKey, KeyProviderError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        aws_lc_rs::rand::fill(&mut nonce).map_err(|_| "no randomness for a nonce")?;
+        let mut ciphertext = data_key.to_vec();
+        self.keys[&self.current]
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(self.current.as_bytes()),
+                &mut ciphertext,
+            )
+            .map_err(|_| "the data key could not be wrapped")?;
+        ciphertext.splice(0..0, nonce);
+        Ok(WrappedKey::new(self.current.clone(), ciphertext))
+    }
+
+    fn unwrap(&self, wrapped: &WrappedKey) -> std::result::Result<Vec<u8>, KeyProviderError> {
+        let key = self
+            .keys
+            .get(&wrapped.key_id)
+            .ok_or_else(|| format!("no key with ID `{}`", wrapped.key_id))?;
+        if wrapped.ciphertext.len() < NONCE_LEN + TAG_LEN {
+            return Err("the wrapped key is too short".into());
+        }
+        let (nonce, sealed) = wrapped.ciphertext.split_at(NONCE_LEN);
+        let mut sealed = sealed.to_vec();
+        let data_key = key
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(nonce).map_err(|_| "bad nonce")?,
+                Aad::from(wrapped.key_id.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| {
+                format!(
+                    "the wrapped key does not open with key `{}`",
+                    wrapped.key_id
+                )
+            })?;
+        Ok(data_key.to_vec())
+    }
+}
+
+impl fmt::Debug for LocalKeyProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Key IDs only: the keys themselves must not reach logs.
+        f.debug_struct("LocalKeyProvider")
+            .field("current", &self.current)
+            .field("key_ids", &self.keys.keys().collect::<BTreeSet<_>>())
+            .finish()
+    }
+}
+
+impl KeyProvider for LocalKeyProvider {
+    fn wrap_key<'a>(
+        &'a self,
+        data_key: &'a [u8],
+    ) -> BoxFuture<'a, std::result::Result<WrappedKey, KeyProviderError>> {
+        Box::pin(std::future::ready(self.wrap(data_key)))
+    }
+
+    fn unwrap_key<'a>(
+        &'a self,
+        wrapped: &'a WrappedKey,
+    ) -> BoxFuture<'a, std::result::Result<Vec<u8>, KeyProviderError>> {
+        Box::pin(std::future::ready(self.unwrap(wrapped)))
+    }
+}
+
+fn aes_key(key: &[u8; 32]) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("32 bytes is an AES-256 key"))
+}
+
+fn frame_nonce(index: u64) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[NONCE_LEN - 8..].copy_from_slice(&index.to_be_bytes());
+    Nonce::assume_unique_for_key(nonce)
+}
+
+fn frame_aad(last: bool) -> Aad<[u8; 1]> {
+    Aad::from([u8::from(last)])
+}
+
+/// The plaintext length of an object stored as `sealed` bytes, or `None` if
+/// no run of frames is that long: every frame, the last included, carries at
+/// least a tag.
+pub(crate) fn plaintext_len(sealed: u64) -> Option<u64> {
+    let last_frame = sealed % SEALED_FRAME;
+    if last_frame != 0 && last_frame < TAG_LEN as u64 {
+        return None;
+    }
+    sealed.checked_sub(sealed.div_ceil(SEALED_FRAME).max(1) * TAG_LEN as u64)
+}
+
+/// Whether stored metadata marks an object as encrypted by this module.
+pub(crate) fn is_encrypted(metadata: &HashMap<String, String>) -> bool {
+    metadata.contains_key(ALGORITHM_KEY)
+}
+
+/// One object's data key.
+#[derive(Clone)]
+pub(crate) struct DataKey(Arc<LessSafeKey>);
+
+impl fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DataKey(..)")
+    }
+}
+
+impl DataKey {
+    /// Encrypts `plaintext` as frames numbered from `first_frame`. `last`
+    /// says whether the final frame ends the object; an empty plaintext is
+    /// only valid as the whole of one, and still yields a frame.
+    pub(crate) fn seal(&self, plaintext: &[u8], first_frame: u64, last: bool) -> Vec<u8> {
+        let frames = plaintext.len().div_ceil(FRAME_SIZE as usize).max(1);
+        let mut sealed = Vec::with_capacity(plaintext.len() + frames * TAG_LEN);
+        for index in 0..frames {
+            let start = index * FRAME_SIZE as usize;
+            let end = (start + FRAME_SIZE as usize).min(plaintext.len());
+            let offset = sealed.len();
+            sealed.extend_from_slice(&plaintext[start..end]);
+            let tag = self
+                .0
+                .seal_in_place_separate_tag(
+                    frame_nonce(first_frame + index as u64),
+                    frame_aad(last && index == frames - 1),
+                    &mut sealed[offset..],
+                )
+                .expect("a 64 KiB frame is within AES-GCM's limits");
+            sealed.extend_from_slice(tag.as_ref());
+        }
+        sealed
+    }
+
+    /// Decrypts one frame in place, leaving just its plaintext.
+    fn open_frame(&self, frame: &mut BytesMut, index: u64, last: bool) -> io::Result<()> {
+        let opened = self
+            .0
+            .open_in_place(frame_nonce(index), frame_aad(last), frame)
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "encrypted frame {index} failed authentication: the object was altered \
+                         or truncated, or was wrapped with a different key"
+                    ),
+                )
+            })?
+            .len();
+        frame.truncate(opened);
+        Ok(())
+    }
+
+    /// Decrypts a whole body as it streams.
+    pub(crate) fn open_stream(self, body: ByteStream) -> ByteStream {
+        stream_body(Opening {
+            body,
+            key: self,
+            buffer: BytesMut::new(),
+            index: 0,
+            done: false,
+        })
+    }
+}
+
+/// A decrypting body. A frame is only known to be the last once the body
+/// ends behind it, so one full frame is held back until more data arrives.
+struct Opening {
+    body: ByteStream,
+    key: DataKey,
+    buffer: BytesMut,
+    index: u64,
+    done: bool,
+}
+
+impl Opening {
+    fn open_next(&mut self, len: usize, last: bool) -> io::Result<Bytes> {
+        let mut frame = self.buffer.split_to(len);
+        self.key.open_frame(&mut frame, self.index, last)?;
+        self.index += 1;
+        Ok(frame.freeze())
+    }
+}
+
+impl futures::Stream for Opening {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+            if self.buffer.len() as u64 > SEALED_FRAME {
+                return Poll::Ready(Some(self.open_next(SEALED_FRAME as usize, false)));
+            }
+            match ready!(Pin::new(&mut self.body).poll_next(cx)) {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Some(Err(err)) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(io::Error::other(err))));
+                }
+                None => {
+                    self.done = true;
+                    let len = self.buffer.len();
+                    return Poll::Ready(Some(self.open_next(len, true)));
+                }
+            }
+        }
+    }
+}
+
+impl R2Client {
+    /// Generates and wraps a data key for a new object, recording it in the
+    /// options' metadata. `None` when the client does not encrypt.
+    pub(crate) async fn seal_options(
+        &self,
+        key: &str,
+        mut options: PutOptions,
+    ) -> Result<(PutOptions, Option<DataKey>)> {
+        let Some(provider) = &self.encryption else {
+            return Ok((options, None));
+        };
+
+        let mut data_key = [0u8; 32];
+        aws_lc_rs::rand::fill(&mut data_key)
+            .map_err(|_| Error::encryption(key, "no randomness for a data key", None))?;
+        let wrapped = provider.wrap_key(&data_key).await.map_err(|err| {
+            Error::encryption(
+                key,
+                "the key provider could not wrap the data key",
+                Some(err),
+            )
+        })?;
+
+        options
+            .metadata
+            .insert(ALGORITHM_KEY.to_string(), ALGORITHM.to_string());
+        options
+            .metadata
+            .insert(KEY_ID_KEY.to_string(), wrapped.key_id);
+        options.metadata.insert(
+            WRAPPED_KEY_KEY.to_string(),
+            aws_smithy_types::base64::encode(&wrapped.ciphertext),
+        );
+        Ok((options, Some(DataKey(Arc::new(aes_key(&data_key))))))
+    }
+
+    /// Encrypts a whole body in memory for a single `PUT`.
+    pub(crate) async fn seal_body(
+        &self,
+        key: &str,
+        body: ByteStream,
+        options: PutOptions,
+    ) -> Result<(ByteStream, PutOptions)> {
+        let (options, data_key) = self.seal_options(key, options).await?;
+        let Some(data_key) = data_key else {
+            return Ok((body, options));
+        };
+        let plaintext = body
+            .collect()
+            .await
+            .map_err(|err| Error::Body(Box::new(err)))?
+            .into_bytes();
+        Ok((
+            ByteStream::from(data_key.seal(&plaintext, 0, true)),
+            options,
+        ))
+    }
+
+    /// The data key of a stored object, unwrapped. `None` when the client
+    /// does not encrypt or the object was not encrypted.
+    pub(crate) async fn data_key(
+        &self,
+        key: &str,
+        metadata: Option<&HashMap<String, String>>,
+    ) -> Result<Option<DataKey>> {
+        let (Some(provider), Some(metadata)) = (&self.encryption, metadata) else {
+            return Ok(None);
+        };
+        let Some(algorithm) = metadata.get(ALGORITHM_KEY) else {
+            return Ok(None);
+        };
+        if algorithm != ALGORITHM {
+            return Err(Error::encryption(
+                key,
+                format!("the object is encrypted with unsupported algorithm `{algorithm}`"),
+                None,
+            ));
+        }
+
+        let ciphertext = metadata
+            .get(WRAPPED_KEY_KEY)
+            .and_then(|wrapped| aws_smithy_types::base64::decode(wrapped).ok())
+            .ok_or_else(|| {
+                Error::encryption(key, "the object's wrapped data key is missing", None)
+            })?;
+        let wrapped = WrappedKey::new(
+            metadata.get(KEY_ID_KEY).cloned().unwrap_or_default(),
+            ciphertext,
+        );
+        let data_key = provider.unwrap_key(&wrapped).await.map_err(|err| {
+            Error::encryption(
+                key,
+                "the key provider could not unwrap the data key",
+                Some(err),
+            )
+        })?;
+        let data_key: [u8; 32] = data_key.as_slice().try_into().map_err(|_| {
+            Error::encryption(
+                key,
+                format!(
+                    "the key provider returned a {}-byte data key, not 32",
+                    data_key.len()
+                ),
+                None,
+            )
+        })?;
+        Ok(Some(DataKey(Arc::new(aes_key(&data_key)))))
+    }
+
+    /// A ranged read when the client decrypts. One request covers both
+    /// cases: the bytes asked for, should the object be plaintext, and the
+    /// whole frames holding them, should it be encrypted.
+    pub(crate) async fn get_encrypted_range(
+        &self,
+        key: &str,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Vec<u8>> {
+        let first_frame = start / FRAME_SIZE;
+        let sealed_start = first_frame * SEALED_FRAME;
+        let request_start = start.min(sealed_start);
+        let range = match end {
+            Some(end) => format!(
+                "bytes={request_start}-{}",
+                (end / FRAME_SIZE + 1) * SEALED_FRAME - 1
+            ),
+            None => format!("bytes={request_start}-"),
+        };
+
+        let response = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .range(range)
+            .customize()
+            .record_status()
+            .send()
+            .await
+            .map_err(|err| self.map_object_error("get_object", key, err))?;
+
+        let data_key = self.data_key(key, response.metadata.as_ref()).await?;
+        let total = response
+            .content_range
+            .as_deref()
+            .and_then(|range| range.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse::<u64>().ok());
+        let body = response
+            .body
+            .collect()
+            .await
+            .map_err(|err| Error::body(key, err))?
+            .into_bytes();
+        let total = total.unwrap_or(request_start + body.len() as u64);
+
+        let (data, offset) = match data_key {
+            None => (body, start - request_start),
+            Some(data_key) => {
+                let last_frame = total.div_ceil(SEALED_FRAME).saturating_sub(1);
+                let skip = ((sealed_start - request_start) as usize).min(body.len());
+                let mut sealed = BytesMut::from(&body[skip..]);
+                let mut plaintext = BytesMut::with_capacity(sealed.len());
+                let mut index = first_frame;
+                while !sealed.is_empty() {
+                    let len = sealed.len().min(SEALED_FRAME as usize);
+                    let mut frame = sealed.split_to(len);
+                    data_key
+                        .open_frame(&mut frame, index, index == last_frame)
+                        .map_err(|err| {
+                            Error::encryption(
+                                key,
+                                "the object failed authentication",
+                                Some(Box::new(err)),
+                            )
+                        })?;
+                    plaintext.extend_from_slice(&frame);
+                    index += 1;
+                }
+                (plaintext.freeze(), start - first_frame * FRAME_SIZE)
+            }
+        };
+
+        let offset = offset as usize;
+        if offset >= data.len() {
+            return Err(Error::invalid_argument(
+                "start",
+                format!("range start {start} is beyond the end of `{key}`"),
+            ));
+        }
+        let len = match end {
+            Some(end) => ((end - start + 1) as usize).min(data.len() - offset),
+            None => data.len() - offset,
+        };
+        Ok(data[offset..offset + len].to_vec())
+    }
+}
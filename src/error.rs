@@ -3,7 +3,7 @@
 use std::path::{Path, PathBuf};
 
 use aws_sdk_s3::config::http::HttpResponse;
-use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
 
 /// Convenient alias for results returned by this crate.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -55,7 +55,9 @@ pub enum Error {
     /// The `Display` message carries the most specific message the service or
     /// transport produced; the full `SdkError` remains reachable as this
     /// error's [`source`](std::error::Error::source), and can be downcast when
-    /// you need the typed service error.
+    /// you need the typed service error. For the common questions — retry or
+    /// not, throttled or not — [`Error::is_retryable`] and friends answer
+    /// without downcasting.
     #[error("R2 operation `{operation}` failed{}: {message}",
         status.map(|status| format!(" (HTTP {status})")).unwrap_or_default())]
     Api {
@@ -65,6 +67,14 @@ pub enum Error {
         message: String,
         /// HTTP status code, when the failure reached the service.
         status: Option<u16>,
+        /// The service's error code, when the response carried one. `HEAD`
+        /// responses have no body and therefore never do.
+        code: Option<ErrorCode>,
+        /// The IDs R2 and Cloudflare assigned to the request.
+        request_ids: Box<RequestIds>,
+        /// Whether the request failed in transit — a timeout or a connection
+        /// that could not be made or was dropped — rather than being answered.
+        transient: bool,
         /// The underlying SDK error.
         #[source]
         source: Box<dyn std::error::Error + Send + Sync>,
@@ -105,6 +115,47 @@ pub enum Error {
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
     },
 
+    /// An object's body did not match the content hash it is stored under.
+    #[error("object `{key}` failed an integrity check: expected SHA-256 {expected}, got {actual}")]
+    Integrity {
+        /// Key of the object that was read.
+        key: String,
+        /// The hash the key names.
+        expected: String,
+        /// The hash of the body that came back.
+        actual: String,
+    },
+
+    /// A value could not be serialized to JSON, or an object's body could not
+    /// be deserialized from it. Only with the `serde` feature.
+    #[cfg(feature = "serde")]
+    #[error("failed to convert `{key}` to or from JSON: {source}")]
+    Json {
+        /// Key of the object being written or read.
+        key: String,
+        /// The underlying `serde_json` error, which carries the line and
+        /// column of a syntax error.
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// An object could not be encrypted or decrypted because the key
+    /// provider failed, the stored key metadata was unusable, or a ranged
+    /// read failed authentication. A whole body that fails authentication
+    /// surfaces as [`Error::Corrupt`] instead, as it streams. Only with the
+    /// `encryption` feature.
+    #[cfg(feature = "encryption")]
+    #[error("failed to encrypt or decrypt `{key}`: {message}")]
+    Encryption {
+        /// Key of the object being written or read.
+        key: String,
+        /// What went wrong.
+        message: String,
+        /// The key provider's error, when it failed.
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+
     /// Reading or writing a local file failed.
     #[error("i/o error: {0}")]
     Io(#[from] std::io::Error),
@@ -112,6 +163,18 @@ pub enum Error {
     /// Reading a response body from the network failed.
     #[error("failed to read response body: {0}")]
     Body(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// An object, or R2's answer about it, was not what the operation
+    /// expected: a body that arrived whole but would not decode, a document
+    /// in the wrong format, a response missing a header it always carries.
+    /// Reading it again returns the same thing, so this is never retryable.
+    #[error("object `{key}` is corrupt or not what was expected: {message}")]
+    Corrupt {
+        /// Key of the object that was read.
+        key: String,
+        /// What was wrong with it.
+        message: String,
+    },
 }
 
 impl Error {
@@ -139,6 +202,71 @@ impl Error {
         }
     }
 
+    /// The service's error code, when it sent one.
+    pub fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            Error::Api { code, .. } => code.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The `x-amz-request-id` of the failed request, for support tickets.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Error::Api { request_ids, .. } => request_ids.request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The `cf-ray` ID of the failed request, for support tickets.
+    pub fn cf_ray(&self) -> Option<&str> {
+        match self {
+            Error::Api { request_ids, .. } => request_ids.cf_ray.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` when R2 asked the client to slow down: a `429`, or a
+    /// `SlowDown` code (which R2 sends with a `503`).
+    pub fn is_throttled(&self) -> bool {
+        self.status() == Some(429)
+            || matches!(
+                self.code(),
+                Some(ErrorCode::SlowDown | ErrorCode::TooManyRequests)
+            )
+    }
+
+    /// Returns `true` when a conditional request was refused because its
+    /// precondition did not hold (`412 Precondition Failed`).
+    pub fn is_precondition_failed(&self) -> bool {
+        self.status() == Some(412) || self.code() == Some(&ErrorCode::PreconditionFailed)
+    }
+
+    /// Returns `true` when sending the same request again may succeed.
+    ///
+    /// Covers throttling, `5xx` responses, request timeouts, and failures in
+    /// transit: timeouts, refused or dropped connections, and response bodies
+    /// cut off mid-read. Everything else — a missing object, bad credentials,
+    /// a malformed request — will fail the same way every time.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Api {
+                status,
+                code,
+                transient,
+                ..
+            } => {
+                *transient
+                    || self.is_throttled()
+                    || matches!(status, Some(500..=599))
+                    || status == &Some(408)
+                    || code.as_ref().is_some_and(ErrorCode::is_retryable)
+            }
+            Error::Body(_) => true,
+            _ => false,
+        }
+    }
+
     pub(crate) fn invalid_argument(argument: &'static str, message: impl Into<String>) -> Self {
         Error::InvalidArgument {
             argument,
@@ -153,6 +281,59 @@ impl Error {
         }
     }
 
+    #[cfg(feature = "serde")]
+    pub(crate) fn json(key: impl Into<String>, source: serde_json::Error) -> Self {
+        Error::Json {
+            key: key.into(),
+            source,
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    pub(crate) fn encryption(
+        key: impl Into<String>,
+        message: impl Into<String>,
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Error::Encryption {
+            key: key.into(),
+            message: message.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn corrupt(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Error::Corrupt {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Wraps a failure to read `key`'s body. Bytes that arrived but would not
+    /// decode — a frame that fails authentication, a damaged compressed
+    /// stream — fail with [`InvalidData`](std::io::ErrorKind::InvalidData)
+    /// and become [`Error::Corrupt`]; anything else is a transport failure.
+    pub(crate) fn body(
+        key: &str,
+        err: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        let err = err.into();
+        let mut cause: Option<&(dyn std::error::Error + 'static)> = Some(&*err);
+        while let Some(current) = cause {
+            cause = current.source();
+            if let Some(io) = current.downcast_ref::<std::io::Error>() {
+                if io.kind() == std::io::ErrorKind::InvalidData {
+                    return Error::corrupt(key, io.to_string());
+                }
+                // An I/O error's `source` skips the error it wraps.
+                if let Some(inner) = io.get_ref() {
+                    cause = Some(inner);
+                }
+            }
+        }
+        Error::Body(err)
+    }
+
     pub(crate) fn file<E>(path: &Path, message: impl Into<String>, source: Option<E>) -> Self
     where
         E: std::error::Error + Send + Sync + 'static,
@@ -165,22 +346,199 @@ impl Error {
     }
 }
 
+/// The identifiers of a failed request, to quote to Cloudflare support.
+///
+/// Boxed inside [`Error::Api`] so that every `Result` in the crate does not
+/// grow by two strings for the sake of its rarest variant.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestIds {
+    /// The `x-amz-request-id` response header.
+    pub request_id: Option<String>,
+    /// The `cf-ray` response header.
+    pub cf_ray: Option<String>,
+}
+
+/// An S3 error code, as sent in the `<Code>` element of an error response.
+///
+/// Only the codes R2 is known to send are named; anything else is kept
+/// verbatim in [`ErrorCode::Other`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// The credentials do not grant access.
+    AccessDenied,
+    /// The bucket name is taken.
+    BucketAlreadyExists,
+    /// The bucket already exists and belongs to this account.
+    BucketAlreadyOwnedByYou,
+    /// The bucket still holds objects.
+    BucketNotEmpty,
+    /// The object is larger than a single request may upload.
+    EntityTooLarge,
+    /// A multipart part, other than the last, is below the minimum size.
+    EntityTooSmall,
+    /// The service failed internally.
+    InternalError,
+    /// The access key ID is not known.
+    InvalidAccessKeyId,
+    /// A request argument is invalid.
+    InvalidArgument,
+    /// The bucket name is not valid.
+    InvalidBucketName,
+    /// A part listed on completion was not uploaded, or its ETag is wrong.
+    InvalidPart,
+    /// Parts listed on completion are not in ascending order.
+    InvalidPartOrder,
+    /// The requested range lies outside the object.
+    InvalidRange,
+    /// The request is malformed.
+    InvalidRequest,
+    /// The bucket does not exist.
+    NoSuchBucket,
+    /// The bucket has no CORS configuration.
+    NoSuchCorsConfiguration,
+    /// The object does not exist.
+    NoSuchKey,
+    /// The bucket has no lifecycle configuration.
+    NoSuchLifecycleConfiguration,
+    /// The multipart upload does not exist, or was completed or aborted.
+    NoSuchUpload,
+    /// A conditional request's precondition did not hold.
+    PreconditionFailed,
+    /// The request body was not sent in time.
+    RequestTimeout,
+    /// The service is temporarily unavailable.
+    ServiceUnavailable,
+    /// The request signature does not match; usually a wrong secret key.
+    SignatureDoesNotMatch,
+    /// The request rate is too high.
+    SlowDown,
+    /// The request rate is too high.
+    TooManyRequests,
+    /// Any other code, verbatim.
+    Other(String),
+}
+
+impl ErrorCode {
+    const NAMED: &'static [(&'static str, ErrorCode)] = &[
+        ("AccessDenied", ErrorCode::AccessDenied),
+        ("BucketAlreadyExists", ErrorCode::BucketAlreadyExists),
+        (
+            "BucketAlreadyOwnedByYou",
+            ErrorCode::BucketAlreadyOwnedByYou,
+        ),
+        ("BucketNotEmpty", ErrorCode::BucketNotEmpty),
+        ("EntityTooLarge", ErrorCode::EntityTooLarge),
+        ("EntityTooSmall", ErrorCode::EntityTooSmall),
+        ("InternalError", ErrorCode::InternalError),
+        ("InvalidAccessKeyId", ErrorCode::InvalidAccessKeyId),
+        ("InvalidArgument", ErrorCode::InvalidArgument),
+        ("InvalidBucketName", ErrorCode::InvalidBucketName),
+        ("InvalidPart", ErrorCode::InvalidPart),
+        ("InvalidPartOrder", ErrorCode::InvalidPartOrder),
+        ("InvalidRange", ErrorCode::InvalidRange),
+        ("InvalidRequest", ErrorCode::InvalidRequest),
+        ("NoSuchBucket", ErrorCode::NoSuchBucket),
+        (
+            "NoSuchCORSConfiguration",
+            ErrorCode::NoSuchCorsConfiguration,
+        ),
+        ("NoSuchKey", ErrorCode::NoSuchKey),
+        (
+            "NoSuchLifecycleConfiguration",
+            ErrorCode::NoSuchLifecycleConfiguration,
+        ),
+        ("NoSuchUpload", ErrorCode::NoSuchUpload),
+        ("PreconditionFailed", ErrorCode::PreconditionFailed),
+        ("RequestTimeout", ErrorCode::RequestTimeout),
+        ("ServiceUnavailable", ErrorCode::ServiceUnavailable),
+        ("SignatureDoesNotMatch", ErrorCode::SignatureDoesNotMatch),
+        ("SlowDown", ErrorCode::SlowDown),
+        ("TooManyRequests", ErrorCode::TooManyRequests),
+    ];
+
+    /// Parses a code as it appears on the wire. Never fails: unknown codes
+    /// become [`ErrorCode::Other`].
+    pub fn parse(code: &str) -> Self {
+        Self::NAMED
+            .iter()
+            .find(|(name, _)| *name == code)
+            .map_or_else(
+                || ErrorCode::Other(code.to_string()),
+                |(_, known)| known.clone(),
+            )
+    }
+
+    /// Returns `true` for codes that mean "try again later": throttling,
+    /// internal errors, unavailability and request timeouts.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::InternalError
+                | ErrorCode::RequestTimeout
+                | ErrorCode::ServiceUnavailable
+                | ErrorCode::SlowDown
+                | ErrorCode::TooManyRequests
+        )
+    }
+
+    /// The code as it appears on the wire.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorCode::Other(code) => code,
+            known => Self::NAMED
+                .iter()
+                .find(|(_, code)| code == known)
+                .map(|(name, _)| *name)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Converts an `SdkError` into our [`Error`], preserving the HTTP status so
-/// callers can distinguish "missing" from "broken".
+/// callers can distinguish "missing" from "broken", and the request IDs so
+/// they can quote them to support.
 pub(crate) fn from_sdk<E>(operation: &'static str, err: SdkError<E, HttpResponse>) -> Error
 where
-    E: std::error::Error + Send + Sync + 'static,
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
 {
-    let status = match &err {
-        SdkError::ServiceError(service) => Some(service.raw().status().as_u16()),
-        SdkError::ResponseError(response) => Some(response.raw().status().as_u16()),
-        _ => None,
+    let (raw, code) = match &err {
+        SdkError::ServiceError(service) => (Some(service.raw()), service.err().code()),
+        SdkError::ResponseError(response) => (Some(response.raw()), None),
+        _ => (None, None),
+    };
+    let header = |name: &str| {
+        raw.and_then(|raw| raw.headers().get(name))
+            .map(str::to_string)
     };
 
+    let status = raw.map(|raw| raw.status().as_u16());
+
     Error::Api {
         operation,
         message: best_message(&err),
         status,
+        code: code.map(ErrorCode::parse),
+        request_ids: Box::new(RequestIds {
+            request_id: header("x-amz-request-id"),
+            cf_ray: header("cf-ray"),
+        }),
+        transient: match &err {
+            SdkError::TimeoutError(_) => true,
+            // Only a connection that timed out or broke is worth retrying; a
+            // request the connector refused to send fails the same way again.
+            SdkError::DispatchFailure(failure) => failure.is_timeout() || failure.is_io(),
+            // A successful status with a response that could not be read:
+            // the connection dropped mid-body.
+            SdkError::ResponseError(response) => response.raw().status().is_success(),
+            _ => false,
+        },
         source: Box::new(err),
     }
 }
@@ -318,6 +676,9 @@ mod tests {
             operation: "put_object",
             message: "Error".into(),
             status: Some(500),
+            code: None,
+            request_ids: Box::default(),
+            transient: false,
             source: Box::new(Layer {
                 message: "Error",
                 source: None,
@@ -360,6 +721,9 @@ mod tests {
             operation: "head_bucket",
             message: "Forbidden".into(),
             status: Some(403),
+            code: Some(ErrorCode::AccessDenied),
+            request_ids: Box::default(),
+            transient: false,
             source: Box::new(Layer {
                 message: "Forbidden",
                 source: None,
@@ -368,4 +732,122 @@ mod tests {
         assert!(!err.is_not_found());
         assert_eq!(err.status(), Some(403));
     }
+
+    fn api(status: Option<u16>, code: Option<&str>, transient: bool) -> Error {
+        Error::Api {
+            operation: "get_object",
+            message: "failed".into(),
+            status,
+            code: code.map(ErrorCode::parse),
+            request_ids: Box::new(RequestIds {
+                request_id: Some("req-1".into()),
+                cf_ray: Some("8a1b2c3d4e5f-LHR".into()),
+            }),
+            transient,
+            source: Box::new(Layer {
+                message: "failed",
+                source: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn error_codes_round_trip() {
+        for code in ["NoSuchKey", "SlowDown", "NoSuchCORSConfiguration", "Teapot"] {
+            assert_eq!(ErrorCode::parse(code).as_str(), code);
+        }
+        assert_eq!(ErrorCode::parse("InvalidPart"), ErrorCode::InvalidPart);
+        assert_eq!(
+            ErrorCode::parse("Teapot"),
+            ErrorCode::Other("Teapot".into())
+        );
+    }
+
+    #[test]
+    fn throttling_is_retryable() {
+        let slow_down = api(Some(503), Some("SlowDown"), false);
+        assert!(slow_down.is_throttled());
+        assert!(slow_down.is_retryable());
+
+        let too_many = api(Some(429), None, false);
+        assert!(too_many.is_throttled());
+        assert!(too_many.is_retryable());
+    }
+
+    #[test]
+    fn server_errors_and_transport_failures_are_retryable() {
+        assert!(api(Some(500), Some("InternalError"), false).is_retryable());
+        assert!(api(Some(502), None, false).is_retryable());
+        assert!(api(None, None, true).is_retryable());
+        assert!(!api(Some(502), None, false).is_throttled());
+    }
+
+    #[test]
+    fn client_errors_are_not_retryable() {
+        for err in [
+            api(Some(400), Some("EntityTooSmall"), false),
+            api(Some(403), Some("SignatureDoesNotMatch"), false),
+            api(Some(412), Some("PreconditionFailed"), false),
+            // Failed while building the request: resending cannot help.
+            api(None, None, false),
+            Error::ObjectNotFound {
+                bucket: "b".into(),
+                key: "k".into(),
+            },
+        ] {
+            assert!(!err.is_retryable(), "{err:?}");
+        }
+    }
+
+    #[test]
+    fn only_timed_out_or_broken_dispatches_are_retryable() {
+        use aws_sdk_s3::operation::get_object::GetObjectError;
+        use aws_smithy_runtime_api::client::result::ConnectorError;
+
+        let dispatch = |err: ConnectorError| {
+            from_sdk::<GetObjectError>("get_object", SdkError::dispatch_failure(err))
+        };
+        let cause = || {
+            Box::new(Layer {
+                message: "failed",
+                source: None,
+            })
+        };
+
+        assert!(dispatch(ConnectorError::io(cause())).is_retryable());
+        assert!(dispatch(ConnectorError::timeout(cause())).is_retryable());
+        assert!(!dispatch(ConnectorError::user(cause())).is_retryable());
+        assert!(!dispatch(ConnectorError::other(cause(), None)).is_retryable());
+    }
+
+    #[test]
+    fn undecodable_bodies_are_corrupt_and_not_retryable() {
+        let damaged = std::io::Error::new(std::io::ErrorKind::InvalidData, "bad frame");
+        let err = Error::body("k", std::io::Error::other(damaged));
+        assert!(
+            matches!(&err, Error::Corrupt { key, .. } if key == "k"),
+            "{err:?}"
+        );
+        assert!(!err.is_retryable());
+
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        let err = Error::body("k", reset);
+        assert!(matches!(err, Error::Body(_)), "{err:?}");
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn precondition_failures_are_recognized_by_status_or_code() {
+        assert!(api(Some(412), None, false).is_precondition_failed());
+        assert!(api(None, Some("PreconditionFailed"), false).is_precondition_failed());
+        assert!(!api(Some(409), None, false).is_precondition_failed());
+    }
+
+    #[test]
+    fn request_ids_are_exposed() {
+        let err = api(Some(500), None, false);
+        assert_eq!(err.request_id(), Some("req-1"));
+        assert_eq!(err.cf_ray(), Some("8a1b2c3d4e5f-LHR"));
+        assert_eq!(Error::MissingConfig("bucket").request_id(), None);
+    }
 }
@@ -0,0 +1,491 @@
+//! Deliberate failures, injected between the SDK and the transport, for
+//! exercising the code paths a healthy R2 never takes.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use aws_sdk_s3::config::interceptors::BeforeTransmitInterceptorContextMut;
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents, SharedHttpClient};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::{HttpRequest, HttpResponse, Metadata};
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use aws_smithy_runtime_api::http::StatusCode;
+use aws_smithy_types::body::SdkBody;
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+
+use crate::error::{Error, Result};
+
+/// Carries the index of the chosen rule from the interceptor, which knows
+/// the operation, to the connector, which acts on it. Always removed before
+/// the request reaches the wire.
+const FAULT_HEADER: &str = "x-cloudflare-r2-rs-fault";
+
+/// A failure to inject in place of, or on top of, R2's real response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fault {
+    /// `503 SlowDown`, R2's answer when a bucket is being hammered. The
+    /// request is not sent.
+    SlowDown,
+    /// `412 PreconditionFailed`, as a conditional request whose condition
+    /// did not hold would get. The request is not sent.
+    PreconditionFailed,
+    /// Any S3-style error response. The request is not sent.
+    Error {
+        /// HTTP status.
+        status: u16,
+        /// The `<Code>` of the XML error body.
+        code: String,
+    },
+    /// A `200` whose XML body breaks off inside its root element's start tag,
+    /// so parsing it fails. The request is not sent.
+    MalformedXml,
+    /// The connection fails before any response arrives. The request is not
+    /// sent.
+    ConnectionReset,
+    /// The request is sent and its response arrives, but the connection
+    /// drops after `after` bytes of the body, as a reset mid-download would.
+    TruncatedBody {
+        /// Body bytes delivered before the failure.
+        after: usize,
+    },
+    /// The request is sent after a delay.
+    Latency(Duration),
+}
+
+impl Fault {
+    /// An error response with the given status and error code.
+    pub fn error(status: u16, code: impl Into<String>) -> Self {
+        Fault::Error {
+            status,
+            code: code.into(),
+        }
+    }
+}
+
+/// When to inject one [`Fault`]: which operations, how often, how many times.
+///
+/// ```
+/// use cloudflare_r2_rs::{Fault, FaultRule};
+///
+/// // Fail the first two part uploads, then let everything through.
+/// let rule = FaultRule::new(Fault::SlowDown).operation("UploadPart").times(2);
+/// # let _ = rule;
+/// ```
+#[derive(Debug, Clone)]
+pub struct FaultRule {
+    /// The failure to inject.
+    pub fault: Fault,
+    /// S3 operation names the rule applies to, e.g. `GetObject`. Empty means
+    /// every operation.
+    pub operations: Vec<String>,
+    /// Chance, from `0.0` to `1.0`, that a matching request is faulted.
+    pub probability: f64,
+    /// Most faults the rule injects, across the client's lifetime.
+    pub limit: Option<u64>,
+}
+
+impl FaultRule {
+    /// A rule that injects `fault` into every request.
+    pub fn new(fault: Fault) -> Self {
+        FaultRule {
+            fault,
+            operations: Vec::new(),
+            probability: 1.0,
+            limit: None,
+        }
+    }
+
+    /// Restricts the rule to an S3 operation, e.g. `PutObject`. May be
+    /// called more than once.
+    #[must_use]
+    pub fn operation(mut self, name: impl Into<String>) -> Self {
+        self.operations.push(name.into());
+        self
+    }
+
+    /// Faults only this fraction of matching requests, drawn from the
+    /// injector's seeded generator.
+    #[must_use]
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = probability;
+        self
+    }
+
+    /// Stops after injecting `times` faults.
+    #[must_use]
+    pub fn times(mut self, times: u64) -> Self {
+        self.limit = Some(times);
+        self
+    }
+
+    fn matches(&self, operation: &str) -> bool {
+        self.operations.is_empty() || self.operations.iter().any(|name| name == operation)
+    }
+}
+
+/// A set of [`FaultRule`]s to apply to every request a client sends.
+///
+/// Install with
+/// [`R2ClientBuilder::fault_injection`](crate::R2ClientBuilder::fault_injection).
+/// Rules are tried in order and the first that fires wins. Probabilities are
+/// drawn from a generator seeded with [`seed`](FaultInjection::seed), so a
+/// test that sends the same requests in the same order sees the same faults
+/// on every run.
+///
+/// Clones share their counters, so keep one to check afterwards how many
+/// faults were injected.
+///
+/// Faults are injected below the SDK's retry loop: a retryable fault is
+/// retried like the real thing, and each attempt is a fresh chance to be
+/// faulted.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use cloudflare_r2_rs::{Fault, FaultInjection, FaultRule};
+///
+/// let faults = FaultInjection::new()
+///     .seed(7)
+///     .rule(FaultRule::new(Fault::SlowDown).probability(0.2))
+///     .rule(FaultRule::new(Fault::TruncatedBody { after: 1024 }).operation("GetObject").times(1))
+///     .rule(FaultRule::new(Fault::Latency(Duration::from_millis(250))).operation("PutObject"));
+/// assert_eq!(faults.injected(), 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FaultInjection {
+    rules: Vec<FaultRule>,
+    state: Arc<FaultState>,
+}
+
+#[derive(Debug, Default)]
+struct FaultState {
+    random: AtomicU64,
+    injected: AtomicU64,
+    /// Faults injected per rule, for [`FaultRule::limit`].
+    fired: std::sync::Mutex<Vec<u64>>,
+}
+
+impl Default for FaultInjection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FaultInjection {
+    /// An injector with no rules, seeded with `0`.
+    pub fn new() -> Self {
+        FaultInjection {
+            rules: Vec::new(),
+            state: Arc::default(),
+        }
+    }
+
+    /// Seeds the generator probabilities are drawn from.
+    #[must_use]
+    pub fn seed(self, seed: u64) -> Self {
+        self.state.random.store(seed, Ordering::Relaxed);
+        self
+    }
+
+    /// Adds a rule, tried after those already added.
+    #[must_use]
+    pub fn rule(mut self, rule: FaultRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// The rules, in the order they are tried.
+    pub fn rules(&self) -> &[FaultRule] {
+        &self.rules
+    }
+
+    /// Faults injected so far, by this injector and its clones.
+    pub fn injected(&self) -> u64 {
+        self.state.injected.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        for rule in &self.rules {
+            if !(0.0..=1.0).contains(&rule.probability) {
+                return Err(Error::InvalidConfig {
+                    field: "fault_injection",
+                    message: format!(
+                        "probability {} for {:?} is not between 0 and 1",
+                        rule.probability, rule.fault
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks the rule, if any, that faults this request.
+    fn choose(&self, operation: &str) -> Option<usize> {
+        let mut fired = self
+            .state
+            .fired
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        fired.resize(self.rules.len(), 0);
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.matches(operation) || rule.limit.is_some_and(|limit| fired[index] >= limit) {
+                continue;
+            }
+            // Drawn only for rules that could fire, so adding a rule for one
+            // operation does not reshuffle the faults seen by the others.
+            if rule.probability < 1.0 && self.draw() >= rule.probability {
+                continue;
+            }
+            fired[index] += 1;
+            self.state.injected.fetch_add(1, Ordering::Relaxed);
+            return Some(index);
+        }
+        None
+    }
+
+    /// A uniform draw from `[0, 1)`: SplitMix64 over the shared state.
+    fn draw(&self) -> f64 {
+        let mut z = self
+            .state
+            .random
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// The interceptor and the HTTP client wrapping `inner` that together
+    /// inject this injector's faults.
+    pub(crate) fn install(self, inner: SharedHttpClient) -> (FaultInterceptor, SharedHttpClient) {
+        let rules = Arc::new(self.rules.clone());
+        let client = SharedHttpClient::new(FaultClient { inner, rules });
+        (FaultInterceptor { injection: self }, client)
+    }
+}
+
+/// Decides, per attempt, whether to fault the request, and tags it for the
+/// connector.
+#[derive(Debug)]
+pub(crate) struct FaultInterceptor {
+    injection: FaultInjection,
+}
+
+impl Intercept for FaultInterceptor {
+    fn name(&self) -> &'static str {
+        "FaultInterceptor"
+    }
+
+    fn modify_before_transmit(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let operation = cfg.load::<Metadata>().map_or("Unknown", Metadata::name);
+        if let Some(index) = self.injection.choose(operation) {
+            log::debug!("injecting fault {index} into {operation}");
+            context
+                .request_mut()
+                .headers_mut()
+                .insert(FAULT_HEADER, index.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Hands out [`FaultConnector`]s around the real transport's connectors.
+#[derive(Debug)]
+struct FaultClient {
+    inner: SharedHttpClient,
+    rules: Arc<Vec<FaultRule>>,
+}
+
+impl HttpClient for FaultClient {
+    fn http_connector(
+        &self,
+        settings: &HttpConnectorSettings,
+        components: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(FaultConnector {
+            inner: self.inner.http_connector(settings, components),
+            rules: Arc::clone(&self.rules),
+        })
+    }
+}
+
+/// Applies the fault a request was tagged with, or passes it through.
+#[derive(Debug)]
+struct FaultConnector {
+    inner: SharedHttpConnector,
+    rules: Arc<Vec<FaultRule>>,
+}
+
+impl HttpConnector for FaultConnector {
+    fn call(&self, mut request: HttpRequest) -> HttpConnectorFuture {
+        let fault = request
+            .headers_mut()
+            .remove(FAULT_HEADER)
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| self.rules.get(index))
+            .map(|rule| rule.fault.clone());
+
+        let Some(fault) = fault else {
+            return self.inner.call(request);
+        };
+        match fault {
+            Fault::SlowDown => ready(error_response(503, "SlowDown")),
+            Fault::PreconditionFailed => ready(error_response(412, "PreconditionFailed")),
+            Fault::Error { status, code } => ready(error_response(status, &code)),
+            Fault::MalformedXml => ready(response(
+                200,
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult",
+            )),
+            Fault::ConnectionReset => {
+                HttpConnectorFuture::ready(Err(ConnectorError::io(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "connection reset (injected fault)",
+                )))))
+            }
+            Fault::Latency(delay) => {
+                let inner = self.inner.clone();
+                HttpConnectorFuture::new(async move {
+                    tokio::time::sleep(delay).await;
+                    inner.call(request).await
+                })
+            }
+            Fault::TruncatedBody { after } => {
+                let inner = self.inner.clone();
+                HttpConnectorFuture::new(async move {
+                    let mut response = inner.call(request).await?;
+                    let body = ByteStream::new(response.take_body())
+                        .collect()
+                        .await
+                        .map_err(|err| ConnectorError::io(Box::new(err)))?
+                        .into_bytes();
+                    let kept = body.slice(..after.min(body.len()));
+                    *response.body_mut() = SdkBody::from_body_1_x(TruncatedBody(Some(kept)));
+                    Ok(response)
+                })
+            }
+        }
+    }
+}
+
+fn ready(response: HttpResponse) -> HttpConnectorFuture {
+    HttpConnectorFuture::ready(Ok(response))
+}
+
+fn response(status: u16, body: &str) -> HttpResponse {
+    let status = StatusCode::try_from(status)
+        .unwrap_or_else(|_| StatusCode::try_from(500).expect("500 is a valid status code"));
+    let mut response = HttpResponse::new(status, SdkBody::from(body.to_string()));
+    response
+        .headers_mut()
+        .insert("content-type", "application/xml");
+    response
+        .headers_mut()
+        .insert("x-amz-request-id", "injected-fault");
+    response
+}
+
+fn error_response(status: u16, code: &str) -> HttpResponse {
+    response(
+        status,
+        &format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <Error><Code>{code}</Code><Message>Injected fault.</Message></Error>"
+        ),
+    )
+}
+
+/// A body that yields what it holds and then fails, as a connection dropped
+/// mid-transfer does.
+struct TruncatedBody(Option<Bytes>);
+
+impl Body for TruncatedBody {
+    type Data = Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, std::io::Error>>> {
+        Poll::Ready(Some(match self.0.take() {
+            Some(data) if !data.is_empty() => Ok(Frame::data(data)),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "connection reset mid-body (injected fault)",
+            )),
+        }))
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_are_tried_in_order_and_respect_their_limit() {
+        let faults = FaultInjection::new()
+            .rule(
+                FaultRule::new(Fault::SlowDown)
+                    .operation("PutObject")
+                    .times(1),
+            )
+            .rule(FaultRule::new(Fault::ConnectionReset).operation("PutObject"));
+        assert_eq!(faults.choose("PutObject"), Some(0));
+        assert_eq!(faults.choose("PutObject"), Some(1));
+        assert_eq!(faults.choose("GetObject"), None);
+        assert_eq!(faults.injected(), 2);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_faults() {
+        let pick = |seed| {
+            let faults = FaultInjection::new()
+                .seed(seed)
+                .rule(FaultRule::new(Fault::SlowDown).probability(0.5));
+            (0..64)
+                .map(|_| faults.choose("GetObject").is_some())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pick(42), pick(42));
+        assert_ne!(pick(42), pick(43));
+    }
+
+    #[test]
+    fn probabilities_are_roughly_honoured() {
+        let faults = FaultInjection::new()
+            .seed(1)
+            .rule(FaultRule::new(Fault::SlowDown).probability(0.25));
+        let hits = (0..10_000)
+            .filter(|_| faults.choose("GetObject").is_some())
+            .count();
+        assert!((2_000..3_000).contains(&hits), "{hits} of 10000");
+    }
+
+    #[test]
+    fn clones_share_counters() {
+        let faults = FaultInjection::new().rule(FaultRule::new(Fault::SlowDown).times(1));
+        let clone = faults.clone();
+        assert!(clone.choose("GetObject").is_some());
+        assert!(faults.choose("GetObject").is_none());
+        assert_eq!(faults.injected(), 1);
+    }
+}
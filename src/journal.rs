@@ -0,0 +1,653 @@
+//! An append-only journal of records, sealed into numbered segment objects.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+
+use crate::client::R2Client;
+use crate::error::{Error, Result};
+use crate::types::{ListOptions, PutOptions};
+
+/// Starts every segment object, so foreign objects are never read as one.
+const SEGMENT_MAGIC: &[u8; 4] = b"R2J1";
+
+/// Segment size at which records are sealed unless set otherwise: 8 MiB.
+const DEFAULT_SEGMENT_SIZE: usize = 8 * 1024 * 1024;
+
+/// Age of the oldest buffered record at which it is sealed unless set
+/// otherwise.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// When a [`JournalWriter`] seals its buffered records into a segment.
+///
+/// ```
+/// use std::time::Duration;
+/// use cloudflare_r2_rs::JournalOptions;
+///
+/// let options = JournalOptions::new().max_age(Duration::from_secs(5));
+/// assert_eq!(options.segment_size, 8 * 1024 * 1024);
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct JournalOptions {
+    /// Seal once the buffered segment reaches this many bytes.
+    pub segment_size: usize,
+    /// Seal once the oldest buffered record is this old. Checked on each
+    /// append and by [`flush_if_due`](JournalWriter::flush_if_due).
+    pub max_age: Duration,
+}
+
+impl Default for JournalOptions {
+    fn default() -> Self {
+        JournalOptions {
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+}
+
+impl JournalOptions {
+    /// Creates options sealing at 8 MiB or after a minute.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the segment size at which records are sealed.
+    #[must_use]
+    pub fn segment_size(mut self, segment_size: usize) -> Self {
+        self.segment_size = segment_size;
+        self
+    }
+
+    /// Sets the age of the oldest buffered record at which it is sealed.
+    #[must_use]
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+}
+
+/// A record's position in a [`Journal`]: its segment's number and its index
+/// within that segment. Offsets order the same way the records were
+/// appended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct JournalOffset {
+    /// The segment holding the record.
+    pub segment: u64,
+    /// The record's index within the segment.
+    pub record: u64,
+}
+
+impl JournalOffset {
+    /// The offset of the first record ever appended.
+    pub const START: JournalOffset = JournalOffset {
+        segment: 0,
+        record: 0,
+    };
+
+    /// The offset of record `record` of segment `segment`.
+    pub fn new(segment: u64, record: u64) -> Self {
+        JournalOffset { segment, record }
+    }
+
+    /// The offset just past this one, to resume reading after a record that
+    /// has been processed.
+    #[must_use]
+    pub fn next(self) -> Self {
+        JournalOffset::new(self.segment, self.record + 1)
+    }
+}
+
+impl fmt::Display for JournalOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.segment, self.record)
+    }
+}
+
+/// One record read back by [`Journal::read_from`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct JournalRecord {
+    /// Where the record is in the journal.
+    pub offset: JournalOffset,
+    /// The record as appended.
+    pub data: Bytes,
+}
+
+/// An append-only journal under a prefix, for audit events and similar
+/// streams of records.
+///
+/// A [`JournalWriter`] buffers records and seals them into segment objects,
+/// `<prefix>/segments/<number>`, numbered from zero and zero-padded so they
+/// list in order. A manifest object, `<prefix>/manifest`, counts the sealed
+/// segments; a segment is committed once the manifest has been advanced past
+/// it with a conditional put, and readers never look beyond the manifest.
+///
+/// Opening a writer takes the journal over: the manifest is rewritten with
+/// `If-Match`, so any earlier writer's next seal fails with a precondition
+/// failure rather than interleaving with the new one, and segments a failed
+/// writer left uncommitted are deleted. Segments are created with
+/// `If-None-Match`, so not even a delayed write from an earlier writer can
+/// replace a committed one.
+///
+/// ```no_run
+/// # async fn run(client: cloudflare_r2_rs::R2Client) -> cloudflare_r2_rs::Result<()> {
+/// use cloudflare_r2_rs::{Journal, JournalOffset, JournalOptions};
+/// use futures::TryStreamExt;
+///
+/// let journal = Journal::new(client, "audit");
+/// let mut writer = journal.writer(JournalOptions::new()).await?;
+/// writer.append(r#"{"event":"login","user":"ada"}"#).await?;
+/// writer.close().await?;
+///
+/// let mut records = journal.read_from(JournalOffset::START);
+/// while let Some(record) = records.try_next().await? {
+///     println!("{}: {:?}", record.offset, record.data);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Journal {
+    client: R2Client,
+    prefix: String,
+}
+
+impl Journal {
+    /// Keeps a journal under `prefix` in `client`'s bucket. Leading and
+    /// trailing slashes are ignored; an empty prefix uses the bucket root.
+    pub fn new(client: R2Client, prefix: impl Into<String>) -> Self {
+        Journal {
+            client,
+            prefix: prefix.into().trim_matches('/').to_string(),
+        }
+    }
+
+    /// The client the journal is stored with.
+    pub fn client(&self) -> &R2Client {
+        &self.client
+    }
+
+    fn join(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{name}", self.prefix)
+        }
+    }
+
+    /// The object key of segment `number`.
+    pub fn segment_key(&self, number: u64) -> String {
+        self.join(&format!("segments/{number:020}"))
+    }
+
+    fn manifest_key(&self) -> String {
+        self.join("manifest")
+    }
+
+    /// The offset the next committed record will get: everything before it
+    /// has been sealed and committed.
+    pub async fn head(&self) -> Result<JournalOffset> {
+        let segments = match self.read_manifest().await? {
+            Some((manifest, _)) => manifest.segments,
+            None => 0,
+        };
+        Ok(JournalOffset::new(segments, 0))
+    }
+
+    /// Opens a writer, taking the journal over from any earlier one. The
+    /// writer appends after the last committed segment.
+    pub async fn writer(&self, options: JournalOptions) -> Result<JournalWriter> {
+        let manifest_key = self.manifest_key();
+        let (manifest, etag) = loop {
+            let (manifest, preconditions) = match self.read_manifest().await? {
+                Some((current, etag)) => {
+                    let next = Manifest {
+                        segments: current.segments,
+                        epoch: current.epoch + 1,
+                    };
+                    (next, PutOptions::new().if_match(etag))
+                }
+                None => (
+                    Manifest {
+                        segments: 0,
+                        epoch: 1,
+                    },
+                    PutOptions::new().if_none_match("*"),
+                ),
+            };
+            match self
+                .client
+                .put_object_with(&manifest_key, manifest.encode(), preconditions)
+                .await
+            {
+                Ok(outcome) => break (manifest, required_etag(&manifest_key, outcome.etag)?),
+                // Another writer opened at the same time; read its manifest
+                // and take over from it.
+                Err(err) if err.is_precondition_failed() => continue,
+                Err(err) => return Err(err),
+            }
+        };
+        self.remove_uncommitted(manifest.segments).await?;
+        Ok(JournalWriter {
+            journal: self.clone(),
+            options,
+            manifest,
+            manifest_etag: etag,
+            pending: None,
+            buffer: BytesMut::new(),
+            records: 0,
+            oldest: None,
+        })
+    }
+
+    /// Deletes segments numbered `committed` and above, left behind by a
+    /// writer that failed between writing a segment and committing it.
+    async fn remove_uncommitted(&self, committed: u64) -> Result<()> {
+        let mut options = ListOptions::new().prefix(self.join("segments/"));
+        if let Some(last) = committed.checked_sub(1) {
+            options = options.start_after(self.segment_key(last));
+        }
+        let leftovers: Vec<String> = self
+            .client
+            .list_stream(options)
+            .map_ok(|object| object.key)
+            .try_collect()
+            .await?;
+        if leftovers.is_empty() {
+            return Ok(());
+        }
+        log::debug!(
+            "removing {} uncommitted journal segments from `{}`",
+            leftovers.len(),
+            self.prefix
+        );
+        let report = self.client.delete_objects(leftovers).await?;
+        for failure in report.failed {
+            log::warn!(
+                "failed to remove uncommitted journal segment `{}`: {}",
+                failure.key,
+                failure
+                    .message
+                    .as_deref()
+                    .or(failure.code.as_deref())
+                    .unwrap_or("unknown error")
+            );
+        }
+        Ok(())
+    }
+
+    /// Streams committed records from `offset` on, across segments, listing
+    /// segments a page at a time with `start_after`. The stream ends at the
+    /// head as of when it started. Segments that no longer exist, such as
+    /// those expired by a lifecycle rule, are skipped.
+    pub fn read_from(&self, offset: JournalOffset) -> BoxStream<'static, Result<JournalRecord>> {
+        let journal = self.clone();
+        let mut options = ListOptions::new().prefix(self.join("segments/"));
+        if let Some(before) = offset.segment.checked_sub(1) {
+            options = options.start_after(self.segment_key(before));
+        }
+        stream::once(async move { journal.head().await.map(|head| (journal, head)) })
+            .map_ok(move |(journal, head)| {
+                let segments_prefix = journal.join("segments/");
+                journal
+                    .client
+                    .list_stream(options.clone())
+                    .and_then(move |object| {
+                        let number = object
+                            .key
+                            .strip_prefix(&segments_prefix)
+                            .and_then(|number| number.parse::<u64>().ok())
+                            .ok_or_else(|| Error::corrupt(&object.key, "not a journal segment"));
+                        future::ready(number.map(|number| (number, object.key)))
+                    })
+                    .try_take_while(move |(number, _)| future::ready(Ok(*number < head.segment)))
+                    .and_then(move |(number, key)| {
+                        let client = journal.client.clone();
+                        async move {
+                            let body = Bytes::from(client.get_object(&key).await?);
+                            let skip = if number == offset.segment {
+                                offset.record
+                            } else {
+                                0
+                            };
+                            let records = decode_segment(&key, body)?
+                                .into_iter()
+                                .enumerate()
+                                .skip(usize::try_from(skip).unwrap_or(usize::MAX))
+                                .map(move |(index, data)| {
+                                    Ok(JournalRecord {
+                                        offset: JournalOffset::new(number, index as u64),
+                                        data,
+                                    })
+                                });
+                            Ok(stream::iter(records))
+                        }
+                    })
+                    .try_flatten()
+            })
+            .try_flatten()
+            .boxed()
+    }
+
+    async fn read_manifest(&self) -> Result<Option<(Manifest, String)>> {
+        let key = self.manifest_key();
+        let (body, etag) = match self.client.open_object(&key).await {
+            Ok(opened) => opened,
+            Err(err) if err.is_not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let body = body
+            .collect()
+            .await
+            .map_err(|err| Error::body(&key, err))?
+            .into_bytes();
+        let manifest = Manifest::decode(&body)
+            .ok_or_else(|| Error::corrupt(&key, "not a journal manifest"))?;
+        Ok(Some((manifest, required_etag(&key, etag)?)))
+    }
+}
+
+/// Appends records to a [`Journal`], sealing them into segments.
+///
+/// [`append`](JournalWriter::append) buffers a record and returns the offset
+/// it will have once committed. The buffer is sealed into the next segment
+/// when it reaches [`segment_size`](JournalOptions::segment_size) bytes, when
+/// its oldest record reaches [`max_age`](JournalOptions::max_age), or on
+/// [`flush`](JournalWriter::flush). Nothing runs in the background: call
+/// [`flush_if_due`](JournalWriter::flush_if_due) from a timer to honour
+/// `max_age` between appends, and [`close`](JournalWriter::close) when done.
+/// Records still buffered when the writer is dropped are lost.
+///
+/// If a seal fails, the records stay buffered for the next attempt. When the
+/// segment was written but its commit failed, the next attempt commits that
+/// segment rather than writing it again, and appending waits until it has.
+/// A seal failing with a precondition failure means another writer has taken
+/// the journal over; open a new writer to continue, and compare the offsets of
+/// unacknowledged records with [`Journal::head`] to see which were committed.
+#[derive(Debug)]
+pub struct JournalWriter {
+    journal: Journal,
+    options: JournalOptions,
+    manifest: Manifest,
+    manifest_etag: String,
+    /// ETag of the segment written for the buffered records whose commit
+    /// failed, if any.
+    pending: Option<String>,
+    buffer: BytesMut,
+    records: u64,
+    oldest: Option<Instant>,
+}
+
+impl JournalWriter {
+    /// Buffers a record, sealing the segment if a threshold is reached, and
+    /// returns the record's offset. If sealing fails, the record is not
+    /// kept and the error is returned.
+    pub async fn append(&mut self, record: impl Into<Bytes>) -> Result<JournalOffset> {
+        let record = record.into();
+        let len = u32::try_from(record.len())
+            .map_err(|_| Error::invalid_argument("record", "records must be smaller than 4 GiB"))?;
+        // The written segment holds the buffer as it was; commit it before
+        // buffering more.
+        if self.pending.is_some() {
+            self.flush().await?;
+        }
+        let offset = JournalOffset::new(self.manifest.segments, self.records);
+        if self.buffer.is_empty() {
+            self.buffer.put_slice(SEGMENT_MAGIC);
+        }
+        let mark = self.buffer.len();
+        self.buffer.put_u32(len);
+        self.buffer.put_slice(&record);
+        self.records += 1;
+        self.oldest.get_or_insert_with(Instant::now);
+
+        if let Err(err) = self.flush_if_due().await {
+            self.buffer.truncate(mark);
+            self.records -= 1;
+            if self.records == 0 {
+                self.buffer.clear();
+                self.oldest = None;
+            }
+            return Err(err);
+        }
+        Ok(offset)
+    }
+
+    /// Seals the buffered records if they have reached the segment size or
+    /// the oldest has reached the maximum age. Returns whether a segment was
+    /// sealed.
+    pub async fn flush_if_due(&mut self) -> Result<bool> {
+        let due = self.buffer.len() >= self.options.segment_size
+            || self
+                .oldest
+                .is_some_and(|oldest| oldest.elapsed() >= self.options.max_age);
+        if due {
+            self.flush().await?;
+        }
+        Ok(due)
+    }
+
+    /// Seals the buffered records into the next segment and commits it.
+    /// Does nothing when no records are buffered.
+    pub async fn flush(&mut self) -> Result<()> {
+        if self.records == 0 {
+            return Ok(());
+        }
+        let client = &self.journal.client;
+        let segment_key = self.journal.segment_key(self.manifest.segments);
+        // A segment left by an attempt whose commit failed is reused while
+        // it is still there unchanged: writing it again would be refused by
+        // `If-None-Match`.
+        let reused = match self.pending.take() {
+            Some(etag) => match client.head_object(&segment_key).await {
+                Ok(head) if head.etag.as_deref() == Some(etag.as_str()) => Some(etag),
+                Ok(_) => None,
+                Err(err) if err.is_not_found() => None,
+                Err(err) => {
+                    self.pending = Some(etag);
+                    return Err(err);
+                }
+            },
+            None => None,
+        };
+        let segment_etag = match reused {
+            Some(etag) => etag,
+            None => {
+                let written = client
+                    .put_object_with(
+                        &segment_key,
+                        self.buffer.clone().freeze(),
+                        PutOptions::new().if_none_match("*"),
+                    )
+                    .await?;
+                required_etag(&segment_key, written.etag)?
+            }
+        };
+
+        let manifest_key = self.journal.manifest_key();
+        let next = Manifest {
+            segments: self.manifest.segments + 1,
+            ..self.manifest
+        };
+        let committed = client
+            .put_object_with(
+                &manifest_key,
+                next.encode(),
+                PutOptions::new().if_match(&self.manifest_etag),
+            )
+            .await;
+        let manifest_etag = match committed {
+            Ok(outcome) => required_etag(&manifest_key, outcome.etag)?,
+            // An earlier attempt whose answer was lost may have committed
+            // the segment already; only a manifest this writer did not write
+            // means it was taken over.
+            Err(err) if err.is_precondition_failed() => match self.journal.read_manifest().await {
+                Ok(Some((current, etag))) if current == next => etag,
+                Ok(_) => {
+                    // Taken over: the segment will never be committed, so
+                    // remove it rather than leave it to the new writer.
+                    if let Err(cleanup) = client
+                        .delete_object_if_match(&segment_key, &segment_etag)
+                        .await
+                    {
+                        log::warn!(
+                            "failed to remove uncommitted segment `{segment_key}`: {cleanup}"
+                        );
+                    }
+                    return Err(err);
+                }
+                Err(read) => {
+                    self.pending = Some(segment_etag);
+                    return Err(read);
+                }
+            },
+            // Any other failure may have committed it after all, so it stays
+            // for the next attempt to commit.
+            Err(err) => {
+                self.pending = Some(segment_etag);
+                return Err(err);
+            }
+        };
+        self.manifest_etag = manifest_etag;
+        self.manifest = next;
+        self.buffer.clear();
+        self.records = 0;
+        self.oldest = None;
+        Ok(())
+    }
+
+    /// Seals any buffered records and closes the writer.
+    pub async fn close(mut self) -> Result<()> {
+        self.flush().await
+    }
+
+    /// The number of records buffered and not yet sealed.
+    pub fn buffered(&self) -> usize {
+        self.records as usize
+    }
+
+    /// The offset the next appended record will get.
+    pub fn next_offset(&self) -> JournalOffset {
+        JournalOffset::new(self.manifest.segments, self.records)
+    }
+}
+
+impl Drop for JournalWriter {
+    fn drop(&mut self) {
+        if self.records > 0 {
+            log::warn!(
+                "journal writer for `{}` dropped with {} unsealed records",
+                self.journal.prefix,
+                self.records
+            );
+        }
+    }
+}
+
+/// The journal's commit point: how many segments are sealed, and which
+/// writer generation sealed them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Manifest {
+    segments: u64,
+    epoch: u64,
+}
+
+impl Manifest {
+    fn encode(&self) -> String {
+        format!("segments={}\nepoch={}\n", self.segments, self.epoch)
+    }
+
+    fn decode(body: &[u8]) -> Option<Manifest> {
+        let mut segments = None;
+        let mut epoch = None;
+        for line in std::str::from_utf8(body).ok()?.lines() {
+            match line.split_once('=')? {
+                ("segments", value) => segments = Some(value.parse().ok()?),
+                ("epoch", value) => epoch = Some(value.parse().ok()?),
+                _ => {}
+            }
+        }
+        Some(Manifest {
+            segments: segments?,
+            epoch: epoch?,
+        })
+    }
+}
+
+/// Splits a segment into its records, sharing `body`'s buffer.
+fn decode_segment(key: &str, mut body: Bytes) -> Result<Vec<Bytes>> {
+    if !body.starts_with(SEGMENT_MAGIC) {
+        return Err(Error::corrupt(key, "not a journal segment"));
+    }
+    body.advance(SEGMENT_MAGIC.len());
+    let mut records = Vec::new();
+    while body.has_remaining() {
+        if body.remaining() < 4 {
+            return Err(Error::corrupt(
+                key,
+                "the segment ends partway through a record",
+            ));
+        }
+        let len = body.get_u32() as usize;
+        if body.remaining() < len {
+            return Err(Error::corrupt(
+                key,
+                "the segment ends partway through a record",
+            ));
+        }
+        records.push(body.split_to(len));
+    }
+    Ok(records)
+}
+
+fn required_etag(key: &str, etag: Option<String>) -> Result<String> {
+    etag.ok_or_else(|| Error::corrupt(key, "R2 returned no ETag"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_round_trip() {
+        let manifest = Manifest {
+            segments: 12,
+            epoch: 3,
+        };
+        assert_eq!(
+            Manifest::decode(manifest.encode().as_bytes()),
+            Some(manifest)
+        );
+        assert_eq!(Manifest::decode(b"segments=1"), None);
+        assert_eq!(Manifest::decode(b"segments=x\nepoch=1"), None);
+    }
+
+    #[test]
+    fn segments_split_into_their_records() {
+        let mut body = BytesMut::from(&SEGMENT_MAGIC[..]);
+        for record in [&b"one"[..], b"", b"three"] {
+            body.put_u32(record.len() as u32);
+            body.put_slice(record);
+        }
+        let records = decode_segment("s", body.clone().freeze()).unwrap();
+        assert_eq!(records, [&b"one"[..], b"", b"three"]);
+
+        body.truncate(body.len() - 1);
+        assert!(decode_segment("s", body.freeze()).is_err());
+        assert!(decode_segment("s", Bytes::from_static(b"JSON")).is_err());
+    }
+
+    #[test]
+    fn offsets_order_by_segment_then_record() {
+        assert!(JournalOffset::new(0, 9) < JournalOffset::new(1, 0));
+        assert_eq!(JournalOffset::new(2, 4).next(), JournalOffset::new(2, 5));
+        assert_eq!(JournalOffset::new(2, 4).to_string(), "2:4");
+    }
+}
@@ -0,0 +1,154 @@
+//! Typed JSON documents, behind the `serde` feature.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::client::R2Client;
+use crate::error::{Error, Result};
+use crate::types::{PutOptions, PutOutcome};
+
+/// The content type JSON documents are stored with.
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+impl R2Client {
+    /// Serializes `value` to JSON and stores it as `application/json`.
+    ///
+    /// ```no_run
+    /// # async fn run(client: cloudflare_r2_rs::R2Client) -> cloudflare_r2_rs::Result<()> {
+    /// #[derive(serde::Serialize, serde::Deserialize)]
+    /// struct Settings {
+    ///     theme: String,
+    /// }
+    ///
+    /// let settings = Settings { theme: "dark".into() };
+    /// client.put_json("users/42/settings.json", &settings).await?;
+    /// let settings: Settings = client.get_json("users/42/settings.json").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Fails with [`Error::Json`] if `value` cannot be serialized, before any
+    /// request is made.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key)
+        )
+    )]
+    pub async fn put_json<T>(&self, key: &str, value: &T) -> Result<PutOutcome>
+    where
+        T: Serialize + ?Sized,
+    {
+        let (body, options) = json_body(key, value, PutOptions::new())?;
+        self.put_object_with(key, body, options).await
+    }
+
+    /// Like [`put_json`](R2Client::put_json), with headers and user metadata.
+    /// The content type is `application/json` unless `options` sets another.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key)
+        )
+    )]
+    pub async fn put_json_with<T>(
+        &self,
+        key: &str,
+        value: &T,
+        options: PutOptions,
+    ) -> Result<PutOutcome>
+    where
+        T: Serialize + ?Sized,
+    {
+        let (body, options) = json_body(key, value, options)?;
+        self.put_object_with(key, body, options).await
+    }
+
+    /// Fetches an object and deserializes its body as JSON.
+    ///
+    /// Fails with [`Error::ObjectNotFound`] when the key does not exist, and
+    /// with [`Error::Json`] when the body is not valid JSON for `T`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key)
+        )
+    )]
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<T> {
+        let body = self.get_object(key).await?;
+        serde_json::from_slice(&body).map_err(|err| Error::json(key, err))
+    }
+}
+
+/// Serializes `value`, defaulting the content type to JSON.
+fn json_body<T>(key: &str, value: &T, mut options: PutOptions) -> Result<(Vec<u8>, PutOptions)>
+where
+    T: Serialize + ?Sized,
+{
+    let body = serde_json::to_vec(value).map_err(|err| Error::json(key, err))?;
+    options
+        .content_type
+        .get_or_insert_with(|| JSON_CONTENT_TYPE.to_string());
+    Ok((body, options))
+}
+
+/// `Option<DateTime>` as an RFC 3339 string, for the `serde` derives on
+/// public types. `aws-smithy-types` only implements serde behind an
+/// unstable cfg.
+pub(crate) mod date_time {
+    use aws_smithy_types::date_time::Format;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::types::DateTime;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<DateTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(date) => {
+                let text = date
+                    .fmt(Format::DateTime)
+                    .map_err(serde::ser::Error::custom)?;
+                serializer.serialize_some(&text)
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| DateTime::from_str(&text, Format::DateTime).map_err(de::Error::custom))
+            .transpose()
+    }
+}
+
+/// [`DateTime`](crate::DateTime) as an RFC 3339 string, like [`date_time`]
+/// but for values that are always present.
+pub(crate) mod date {
+    use serde::{Deserializer, Serializer};
+
+    use crate::types::DateTime;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &DateTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        super::date_time::serialize(&Some(*value), serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime, D::Error> {
+        super::date_time::deserialize(deserializer)?
+            .ok_or_else(|| serde::de::Error::custom("expected a date, found null"))
+    }
+}
@@ -0,0 +1,327 @@
+//! A key-value store with compare-and-swap, over objects and their ETags.
+
+use std::fmt;
+
+use futures::stream::{BoxStream, StreamExt};
+
+use crate::body::IntoBody;
+use crate::client::R2Client;
+use crate::error::{Error, Result};
+use crate::types::{DateTime, ListOptions, PutOptions};
+
+/// The longest key R2 accepts, in bytes.
+const MAX_KEY_LEN: usize = 1024;
+
+/// An entry's version: the ETag of the object holding it. Opaque, and
+/// changed by every write.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version(String);
+
+impl Version {
+    /// Wraps an ETag, for instance one persisted from an earlier read.
+    pub fn new(etag: impl Into<String>) -> Self {
+        Version(etag.into())
+    }
+
+    /// The ETag.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A value read by [`KvStore::get`], with the version to write it back at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct KvEntry {
+    /// The stored bytes.
+    pub value: Vec<u8>,
+    /// The version they were read at.
+    pub version: Version,
+}
+
+/// One key found by [`KvStore::scan`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct KvItem {
+    /// The key, unescaped.
+    pub key: String,
+    /// Its current version.
+    pub version: Version,
+    /// The value's length in bytes.
+    pub size: i64,
+    /// When the value was last written.
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::json::date_time"))]
+    pub last_modified: Option<DateTime>,
+}
+
+/// A small key-value store for configuration and state, one object per key.
+///
+/// Every write returns the entry's new [`Version`], and the conditional
+/// writes — [`put_if_version`](KvStore::put_if_version),
+/// [`put_if_absent`](KvStore::put_if_absent) and
+/// [`delete_if_version`](KvStore::delete_if_version) — only apply when the
+/// stored entry is still as the caller last saw it, via `If-Match` and
+/// `If-None-Match`. A lost race is reported as `None` or `false`, not an
+/// error.
+///
+/// Keys may be any non-empty string. Bytes other than ASCII letters, digits,
+/// `-`, `_` and `/` are percent-escaped in the object key, so `.`/`..`
+/// segments, spaces and control characters cannot be mangled on the way to
+/// R2. [`scan`](KvStore::scan) lists in the order of the escaped keys.
+///
+/// ```no_run
+/// # async fn run(client: cloudflare_r2_rs::R2Client) -> cloudflare_r2_rs::Result<()> {
+/// use cloudflare_r2_rs::KvStore;
+///
+/// let kv = KvStore::new(client, "state");
+/// kv.put_if_absent("counter", "0").await?;
+/// loop {
+///     let entry = kv.get("counter").await?.expect("created above");
+///     let next: u64 = String::from_utf8_lossy(&entry.value).parse::<u64>().unwrap() + 1;
+///     if kv.put_if_version("counter", next.to_string(), &entry.version).await?.is_some() {
+///         break;
+///     }
+///     // Someone else incremented it first; read again and retry.
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KvStore {
+    client: R2Client,
+    prefix: String,
+}
+
+impl KvStore {
+    /// Stores entries under `prefix` in `client`'s bucket. Leading and
+    /// trailing slashes are ignored; an empty prefix uses the bucket root.
+    pub fn new(client: R2Client, prefix: impl Into<String>) -> Self {
+        KvStore {
+            client,
+            prefix: prefix.into().trim_matches('/').to_string(),
+        }
+    }
+
+    /// The client entries are stored with.
+    pub fn client(&self) -> &R2Client {
+        &self.client
+    }
+
+    /// The object key an entry is stored under.
+    pub fn object_key(&self, key: &str) -> Result<String> {
+        if key.is_empty() {
+            return Err(Error::invalid_argument("key", "key must not be empty"));
+        }
+        let object_key = self.join(&escape(key));
+        if object_key.len() > MAX_KEY_LEN {
+            return Err(Error::invalid_argument(
+                "key",
+                format!("escaped key is longer than {MAX_KEY_LEN} bytes"),
+            ));
+        }
+        Ok(object_key)
+    }
+
+    fn join(&self, escaped: &str) -> String {
+        if self.prefix.is_empty() {
+            escaped.to_string()
+        } else {
+            format!("{}/{escaped}", self.prefix)
+        }
+    }
+
+    /// Reads an entry and its version; `None` when the key is absent.
+    pub async fn get(&self, key: &str) -> Result<Option<KvEntry>> {
+        let object_key = self.object_key(key)?;
+        let (body, etag) = match self.client.open_object(&object_key).await {
+            Ok(opened) => opened,
+            Err(err) if err.is_not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let value = body
+            .collect()
+            .await
+            .map_err(|err| Error::body(&object_key, err))?
+            .into_bytes()
+            .to_vec();
+        Ok(Some(KvEntry {
+            value,
+            version: version(&object_key, etag)?,
+        }))
+    }
+
+    /// Writes an entry unconditionally, returning its new version.
+    pub async fn put(&self, key: &str, value: impl IntoBody) -> Result<Version> {
+        let object_key = self.object_key(key)?;
+        let outcome = self.client.put_object(&object_key, value).await?;
+        version(&object_key, outcome.etag)
+    }
+
+    /// Writes an entry only if it is still at `version`. Returns the new
+    /// version, or `None` if the entry was changed or deleted since.
+    pub async fn put_if_version(
+        &self,
+        key: &str,
+        value: impl IntoBody,
+        version: &Version,
+    ) -> Result<Option<Version>> {
+        let object_key = self.object_key(key)?;
+        let options = PutOptions::new().if_match(version.as_str());
+        self.write(&object_key, value, options).await
+    }
+
+    /// Creates an entry only if the key is absent. Returns its version, or
+    /// `None` if the key already exists.
+    pub async fn put_if_absent(&self, key: &str, value: impl IntoBody) -> Result<Option<Version>> {
+        let object_key = self.object_key(key)?;
+        let options = PutOptions::new().if_none_match("*");
+        self.write(&object_key, value, options).await
+    }
+
+    /// Deletes an entry unconditionally. Deleting an absent key succeeds.
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        self.client.delete_object(&self.object_key(key)?).await
+    }
+
+    /// Deletes an entry only if it is still at `version`. Returns `false` if
+    /// it was changed or deleted since.
+    pub async fn delete_if_version(&self, key: &str, version: &Version) -> Result<bool> {
+        let object_key = self.object_key(key)?;
+        match self
+            .client
+            .delete_object_if_match(&object_key, version.as_str())
+            .await
+        {
+            Ok(()) => Ok(true),
+            Err(err) if err.is_precondition_failed() || err.is_not_found() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Streams every key starting with `prefix`, with its version, a page at
+    /// a time. An empty prefix scans the whole store.
+    pub fn scan(&self, prefix: &str) -> BoxStream<'static, Result<KvItem>> {
+        let store_prefix = self.join("");
+        let options = ListOptions::new().prefix(self.join(&escape(prefix)));
+        self.client
+            .list_stream(options)
+            .map(move |object| {
+                let object = object?;
+                let key = object
+                    .key
+                    .strip_prefix(&store_prefix)
+                    .and_then(unescape)
+                    .ok_or_else(|| {
+                        Error::invalid_argument(
+                            "key",
+                            format!("`{}` is not a key this store wrote", object.key),
+                        )
+                    })?;
+                Ok(KvItem {
+                    version: version(&object.key, object.etag)?,
+                    key,
+                    size: object.size,
+                    last_modified: object.last_modified,
+                })
+            })
+            .boxed()
+    }
+
+    /// Puts, reporting a failed precondition as `None`.
+    async fn write(
+        &self,
+        object_key: &str,
+        value: impl IntoBody,
+        options: PutOptions,
+    ) -> Result<Option<Version>> {
+        match self
+            .client
+            .put_object_with(object_key, value, options)
+            .await
+        {
+            Ok(outcome) => version(object_key, outcome.etag).map(Some),
+            Err(err) if err.is_precondition_failed() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn version(object_key: &str, etag: Option<String>) -> Result<Version> {
+    etag.map(Version).ok_or_else(|| {
+        Error::corrupt(
+            object_key,
+            "R2 returned no ETag, so the entry has no version",
+        )
+    })
+}
+
+/// Percent-escapes every byte but ASCII letters, digits, `-`, `_` and `/`.
+/// Byte-wise, so the escape of a prefix is a prefix of the escaped key.
+fn escape(key: &str) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'/') {
+            escaped.push(byte as char);
+        } else {
+            escaped.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    escaped
+}
+
+fn unescape(escaped: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut rest = escaped.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaping_round_trips_awkward_keys() {
+        for key in [
+            "plain/key",
+            "../etc",
+            "a b\tc",
+            "100%",
+            "ключ",
+            "x.json",
+            "?#&",
+        ] {
+            let escaped = escape(key);
+            assert!(escaped
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_/%".contains(&b)));
+            assert_eq!(unescape(&escaped).as_deref(), Some(key), "{escaped}");
+        }
+        assert_eq!(escape("../a.b"), "%2E%2E/a%2Eb");
+        assert!(escape("config.json").starts_with(&escape("config.")));
+    }
+
+    #[test]
+    fn malformed_escapes_are_rejected() {
+        assert_eq!(unescape("%2"), None);
+        assert_eq!(unescape("%zz"), None);
+        assert_eq!(unescape("%FF"), None);
+    }
+}
@@ -0,0 +1,350 @@
+//! Leases: mutual exclusion for one holder at a time, over a single object.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+use crate::kv::{KvStore, Version};
+
+/// How long a lease lasts unless set otherwise.
+const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+/// How far apart clocks may drift unless set otherwise.
+const DEFAULT_CLOCK_SKEW: Duration = Duration::from_secs(5);
+
+/// How long a [`Lease`] lasts and how much clock skew it tolerates.
+///
+/// ```
+/// use std::time::Duration;
+/// use cloudflare_r2_rs::LeaseOptions;
+///
+/// let options = LeaseOptions::new().ttl(Duration::from_secs(60));
+/// assert_eq!(options.clock_skew, Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct LeaseOptions {
+    /// How long a lease lasts from each acquisition or renewal.
+    pub ttl: Duration,
+    /// The most any two holders' clocks may disagree by. A contender waits
+    /// this long past the recorded expiry before stealing a lease.
+    pub clock_skew: Duration,
+}
+
+impl Default for LeaseOptions {
+    fn default() -> Self {
+        LeaseOptions {
+            ttl: DEFAULT_TTL,
+            clock_skew: DEFAULT_CLOCK_SKEW,
+        }
+    }
+}
+
+impl LeaseOptions {
+    /// Creates options with a 30 second TTL and 5 seconds of clock skew.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how long a lease lasts from each acquisition or renewal.
+    #[must_use]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the clock skew tolerated between holders.
+    #[must_use]
+    pub fn clock_skew(mut self, clock_skew: Duration) -> Self {
+        self.clock_skew = clock_skew;
+        self
+    }
+}
+
+/// Who holds a lease, as recorded in its object. See [`Lease::current`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LeaseHolder {
+    /// The owner the holder acquired it as.
+    pub owner: String,
+    /// When it expires, by the holder's clock.
+    pub expires_at: SystemTime,
+}
+
+/// An exclusive lease on a key of a [`KvStore`], for leader election and
+/// singleton jobs.
+///
+/// [`acquire`](Lease::acquire) creates the lease object with `If-None-Match`,
+/// or takes over one whose expiry has passed with `If-Match` on the ETag it
+/// read, so of several contenders exactly one wins. The object records the
+/// owner and an absolute expiry; [`renew`](Lease::renew) pushes the expiry
+/// out, again with `If-Match`, and reports a lease lost to a contender as
+/// `false`. [`release`](Lease::release) deletes the object if it is still
+/// ours, and dropping a held lease does the same in the background.
+///
+/// # Clocks
+///
+/// Expiry is wall-clock time written by the holder and judged by contenders
+/// with their own clocks, so a contender only steals a lease once
+/// [`clock_skew`](LeaseOptions::clock_skew) has passed beyond the recorded
+/// expiry. The holder measures its own deadline on the monotonic clock from
+/// just before its write, so [`is_held`](Lease::is_held) stays accurate when
+/// the wall clock jumps. Mutual exclusion therefore holds as long as clocks
+/// disagree by less than `clock_skew` and the holder stops working once
+/// `is_held` turns false: renew well within the TTL (a third of it is
+/// typical), and keep each unit of guarded work shorter than the TTL.
+///
+/// ```no_run
+/// # async fn run(kv: cloudflare_r2_rs::KvStore) -> cloudflare_r2_rs::Result<()> {
+/// use std::time::Duration;
+/// use cloudflare_r2_rs::{Lease, LeaseOptions};
+///
+/// let options = LeaseOptions::new().ttl(Duration::from_secs(30));
+/// let Some(mut lease) = Lease::acquire(&kv, "cron/nightly", "pod-a", options).await? else {
+///     return Ok(()); // Another pod is the leader.
+/// };
+/// while lease.is_held() {
+///     // ... a bounded step of the job ...
+///     tokio::time::sleep(Duration::from_secs(10)).await;
+///     if !lease.renew().await? {
+///         break; // Lost to a contender; stop working.
+///     }
+/// }
+/// lease.release().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Lease {
+    kv: KvStore,
+    key: String,
+    owner: String,
+    options: LeaseOptions,
+    version: Version,
+    expires_at: SystemTime,
+    deadline: Instant,
+    held: bool,
+}
+
+impl Lease {
+    /// Acquires the lease on `key` as `owner`, if it is free or has expired.
+    /// Returns `None` if someone else holds it, or won the race to take it.
+    ///
+    /// `owner` identifies the holder to [`current`](Lease::current) and in
+    /// logs; it must be non-empty and on one line. Acquiring a lease held
+    /// under the same owner still waits for it to expire.
+    pub async fn acquire(
+        kv: &KvStore,
+        key: &str,
+        owner: impl Into<String>,
+        options: LeaseOptions,
+    ) -> Result<Option<Lease>> {
+        let owner = owner.into();
+        if owner.is_empty() || owner.contains(['\n', '\r']) {
+            return Err(Error::invalid_argument(
+                "owner",
+                "owner must be non-empty and on one line",
+            ));
+        }
+        if options.ttl.is_zero() {
+            return Err(Error::invalid_argument("ttl", "ttl must not be zero"));
+        }
+
+        let current = kv.get(key).await?;
+        let started = Instant::now();
+        let expires_at = expiry(options.ttl);
+        let body = encode(&owner, expires_at);
+        let version = match current {
+            None => kv.put_if_absent(key, body).await?,
+            Some(entry) => {
+                let holder = decode(key, &entry.value)?;
+                if SystemTime::now() < holder.expires_at + options.clock_skew {
+                    return Ok(None);
+                }
+                log::debug!(
+                    "taking over lease `{key}` from `{}`, expired {:?} ago",
+                    holder.owner,
+                    holder.expires_at.elapsed().unwrap_or_default()
+                );
+                kv.put_if_version(key, body, &entry.version).await?
+            }
+        };
+        Ok(version.map(|version| Lease {
+            kv: kv.clone(),
+            key: key.to_string(),
+            owner,
+            deadline: started + options.ttl,
+            options,
+            version,
+            expires_at,
+            held: true,
+        }))
+    }
+
+    /// Reads who holds the lease on `key`, expired or not; `None` if no one
+    /// has it.
+    pub async fn current(kv: &KvStore, key: &str) -> Result<Option<LeaseHolder>> {
+        match kv.get(key).await? {
+            Some(entry) => decode(key, &entry.value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Extends the lease by another TTL. Returns `false`, and stops holding
+    /// the lease, if a contender took it over since the last write.
+    pub async fn renew(&mut self) -> Result<bool> {
+        if !self.held {
+            return Ok(false);
+        }
+        let started = Instant::now();
+        let expires_at = expiry(self.options.ttl);
+        let body = encode(&self.owner, expires_at);
+        match self
+            .kv
+            .put_if_version(&self.key, body, &self.version)
+            .await?
+        {
+            Some(version) => {
+                self.version = version;
+                self.expires_at = expires_at;
+                self.deadline = started + self.options.ttl;
+                Ok(true)
+            }
+            None => {
+                self.held = false;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Gives the lease up so a contender can take it at once. Returns
+    /// `false` if it had already been lost.
+    pub async fn release(mut self) -> Result<bool> {
+        if !self.held {
+            return Ok(false);
+        }
+        self.held = false;
+        self.kv.delete_if_version(&self.key, &self.version).await
+    }
+
+    /// Whether the lease is still ours: not lost to a contender, and not
+    /// past its deadline on this machine's monotonic clock.
+    pub fn is_held(&self) -> bool {
+        self.held && Instant::now() < self.deadline
+    }
+
+    /// Time left before the lease expires, zero once it has.
+    pub fn remaining(&self) -> Duration {
+        if self.held {
+            self.deadline.saturating_duration_since(Instant::now())
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// The key the lease is on.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The owner it was acquired as.
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// The version of the lease object last written.
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// The expiry recorded in the lease object, by this machine's clock.
+    pub fn expires_at(&self) -> SystemTime {
+        self.expires_at
+    }
+}
+
+impl Drop for Lease {
+    /// Releases a held lease on the current Tokio runtime. Outside a runtime
+    /// the lease is left to expire.
+    fn drop(&mut self) {
+        if !self.held {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let kv = self.kv.clone();
+        let key = std::mem::take(&mut self.key);
+        let version = self.version.clone();
+        runtime.spawn(async move {
+            if let Err(err) = kv.delete_if_version(&key, &version).await {
+                log::warn!("failed to release lease `{key}` on drop: {err}");
+            }
+        });
+    }
+}
+
+/// `ttl` from now, truncated to the milliseconds the lease object records.
+fn expiry(ttl: Duration) -> SystemTime {
+    let since_epoch = (SystemTime::now() + ttl)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    UNIX_EPOCH + Duration::from_millis(since_epoch.as_millis() as u64)
+}
+
+fn encode(owner: &str, expires_at: SystemTime) -> String {
+    let millis = expires_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("owner={owner}\nexpires_at_ms={millis}\n")
+}
+
+fn decode(key: &str, body: &[u8]) -> Result<LeaseHolder> {
+    let parsed = std::str::from_utf8(body).ok().and_then(|text| {
+        let mut owner = None;
+        let mut millis = None;
+        for line in text.lines() {
+            match line.split_once('=')? {
+                ("owner", value) => owner = Some(value),
+                ("expires_at_ms", value) => millis = Some(value.parse::<u64>().ok()?),
+                _ => {}
+            }
+        }
+        Some(LeaseHolder {
+            owner: owner?.to_string(),
+            expires_at: UNIX_EPOCH + Duration::from_millis(millis?),
+        })
+    });
+    parsed.ok_or_else(|| Error::corrupt(key, "the entry does not hold a lease"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holders_round_trip_to_the_millisecond() {
+        let expires_at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let body = encode("pod-a=1", expires_at);
+        assert_eq!(
+            decode("k", body.as_bytes()).unwrap(),
+            LeaseHolder {
+                owner: "pod-a=1".into(),
+                expires_at,
+            }
+        );
+    }
+
+    #[test]
+    fn foreign_bodies_are_not_leases() {
+        for body in [
+            "",
+            "owner=a",
+            "expires_at_ms=1",
+            "owner=a\nexpires_at_ms=soon",
+        ] {
+            assert!(decode("k", body.as_bytes()).is_err(), "{body:?}");
+        }
+    }
+}
@@ -43,7 +43,8 @@
 //!   [`delete_objects`](R2Client::delete_objects),
 //!   [`delete_prefix`](R2Client::delete_prefix).
 //! - **Listing** — [`list_objects`](R2Client::list_objects) for one page,
-//!   [`list_all_objects`](R2Client::list_all_objects) to follow pagination, and
+//!   [`list_all_objects`](R2Client::list_all_objects) to follow pagination,
+//!   [`list_stream`](R2Client::list_stream) to stream it, and
 //!   [`list_prefixes`](R2Client::list_prefixes) for folder-style browsing.
 //! - **Files** — [`upload_file`](R2Client::upload_file) streams from disk and
 //!   switches to multipart automatically;
@@ -56,8 +57,74 @@
 //! - **Presigned URLs** — [`presign_get`](R2Client::presign_get),
 //!   [`presign_put`](R2Client::presign_put),
 //!   [`presign_delete`](R2Client::presign_delete).
-//! - **Buckets** — create, delete, existence checks, listing, plus CORS and
-//!   lifecycle configuration.
+//! - **Buckets** — create, delete, existence checks, listing, plus
+//!   [`LifecycleRule`]s for the expirations, transitions and multipart cleanup
+//!   R2 supports, and CORS rules built and validated as [`CorsRule`]s, with
+//!   [`check_cors`](R2Client::check_cors) to test a browser request against
+//!   them.
+//! - **Many buckets** — [`R2Registry`] hands out clients by logical name,
+//!   sharing connection pools between buckets on the same account.
+//! - **Read-through cache** — [`CachedClient`] keeps hot objects in a
+//!   size-bounded LRU cache on local disk, shared across processes and
+//!   revalidated with `If-None-Match` on every read.
+//! - **Content-addressed blobs** — [`BlobStore`] stores content under its
+//!   SHA-256, uploading each distinct body once, verifies it on read, and
+//!   garbage-collects blobs no longer referenced.
+//! - **Key-value state** — [`KvStore`] keeps small values under escaped
+//!   keys, with versions taken from ETags for compare-and-swap writes and
+//!   deletes, and a streaming [`scan`](KvStore::scan).
+//! - **Leases** — [`Lease`] elects one holder of a key at a time with
+//!   conditional writes, for leader election and singleton jobs, renewing
+//!   and stealing expired leases with `If-Match`.
+//! - **Journals** — [`Journal`] seals appended records into numbered segment
+//!   objects at size and age thresholds, commits them through a manifest
+//!   updated with conditional puts, and streams them back from any
+//!   [`JournalOffset`].
+//! - **Throttling** — [`RateLimit`] caps request rate and concurrency
+//!   client-side, before R2 starts answering `429`.
+//! - **Metrics** — a [`MetricsObserver`] hears about every operation's
+//!   latency, status, bytes and retries; [`UsageCounter`] tallies R2's Class A
+//!   and Class B operations, and [`CostTracker`] prices them into an
+//!   estimated monthly bill.
+//! - **Storage abstraction** — [`ObjectStore`] covers the core object
+//!   operations and is implemented by [`R2Client`], by [`LocalStore`] for a
+//!   directory on disk and by [`MemoryStore`] for tests. With the
+//!   `object_store` feature, `R2ObjectStore` also implements the
+//!   `object_store` crate's trait of the same name, for DataFusion, Polars
+//!   and delta-rs.
+//! - **Compression** — with the `compression` feature, uploads can be
+//!   compressed with gzip, zstd or brotli, and a client built with
+//!   `decompress(true)` decodes them again on download.
+//! - **Encryption** — with the `encryption` feature, a client given a key
+//!   provider encrypts bodies with AES-256-GCM before they leave the
+//!   process, and decrypts them again on download, ranged reads included.
+//! - **JSON documents** — with the `serde` feature, `put_json` and
+//!   `get_json` store and load any serde type as `application/json`, and the
+//!   result types implement `Serialize` and `Deserialize`.
+//! - **Synchronous use** — with the `blocking` feature,
+//!   `blocking::R2Client` mirrors the async API on its own runtime, for code
+//!   that does not run one.
+//! - **Command line** — the `cli` feature builds `r2`, a command-line client
+//!   for copying, listing, deleting and presigning objects and managing
+//!   buckets, their CORS and lifecycle rules, and multipart uploads.
+//!
+//! # Tracing
+//!
+//! With the `tracing` feature enabled, every public [`R2Client`] method opens
+//! a span named after the method, carrying the bucket, key, byte counts, part
+//! numbers and the HTTP status of the response. Fields are named explicitly, so
+//! credentials and request bodies are never recorded.
+//!
+//! # Testing
+//!
+//! With the `mock` feature, `mock::MockServer` runs an in-memory,
+//! S3-compatible server and hands out clients pointed at it, so code built on
+//! this crate can be tested without credentials or network access.
+//! The `faults` feature adds `R2ClientBuilder::fault_injection`, which
+//! injects throttling, error responses, resets and latency by operation, so
+//! retry and cleanup paths can be tested deterministically.
+//! The `cassette` feature records a client's HTTP traffic against R2 and
+//! replays it offline, through `R2ClientBuilder::cassette`.
 //!
 //! # Errors
 //!
@@ -84,23 +151,84 @@
 #![warn(missing_docs)]
 #![warn(clippy::all)]
 
+#[cfg(feature = "object_store")]
+mod adapter;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod body;
+mod cache;
+mod cas;
+#[cfg(feature = "cassette")]
+mod cassette;
 mod client;
+#[cfg(feature = "compression")]
+mod compression;
 mod config;
+mod cors;
+mod cost;
+#[cfg(feature = "encryption")]
+mod encryption;
 mod error;
+#[cfg(feature = "faults")]
+mod fault;
+mod journal;
+#[cfg(feature = "serde")]
+mod json;
+mod kv;
+mod lease;
+mod lifecycle;
+mod limit;
+mod local;
+mod memory;
+mod metrics;
+#[cfg(feature = "mock")]
+pub mod mock;
 mod multipart;
 mod object;
 mod presign;
+mod registry;
+mod retry;
+mod store;
+mod trace;
+mod transport;
 mod types;
 
+#[cfg(feature = "object_store")]
+pub use adapter::R2ObjectStore;
 pub use body::IntoBody;
+pub use cache::CachedClient;
+pub use cas::{BlobHash, BlobStore, GcReport, StoredBlob};
+#[cfg(feature = "cassette")]
+pub use cassette::Cassette;
 pub use client::{
     HasAccessKey, HasBucket, HasEndpoint, HasSecretKey, NoAccessKey, NoBucket, NoEndpoint,
     NoSecretKey, R2Client, R2ClientBuilder,
 };
+#[cfg(feature = "compression")]
+pub use compression::Compression;
 pub use config::{endpoint_for_account, Jurisdiction, R2Config, DEFAULT_REGION};
-pub use error::{Error, Result};
+pub use cors::{CorsMethod, CorsRule, MAX_CORS_RULES};
+pub use cost::{
+    ClassUsage, CostEstimate, CostTracker, CostUsage, Pricing, StorageClass, StoragePricing,
+};
+#[cfg(feature = "encryption")]
+pub use encryption::{KeyProvider, KeyProviderError, LocalKeyProvider, WrappedKey};
+pub use error::{Error, ErrorCode, RequestIds, Result};
+#[cfg(feature = "faults")]
+pub use fault::{Fault, FaultInjection, FaultRule};
+pub use journal::{Journal, JournalOffset, JournalOptions, JournalRecord, JournalWriter};
+pub use kv::{KvEntry, KvItem, KvStore, Version};
+pub use lease::{Lease, LeaseHolder, LeaseOptions};
+pub use lifecycle::{LifecycleRule, LifecycleTrigger, MAX_LIFECYCLE_RULES};
+pub use limit::{Limits, RateLimit};
+pub use local::LocalStore;
+pub use memory::MemoryStore;
+pub use metrics::{MetricsObserver, OperationClass, OperationEvent, Usage, UsageCounter};
 pub use presign::{PresignedRequest, MAX_PRESIGN_EXPIRY};
+pub use registry::R2Registry;
+pub use retry::RetryPolicy;
+pub use store::ObjectStore;
+pub use transport::HttpOptions;
 pub use types::{
     BucketSummary, CompletedPart, DateTime, DeleteFailure, DeleteReport, ListOptions, ListPage,
     MultipartOptions, MultipartUpload, ObjectMetadata, ObjectSummary, PresignOptions, PutOptions,
@@ -110,11 +238,12 @@ pub use types::{
 
 /// Re-exported `aws-sdk-s3` items that appear in this crate's public API.
 ///
-/// CORS and lifecycle rules are deep, rarely-touched structures, so rather than
-/// mirror them this crate takes the SDK's own types directly.
+/// The SDK's CORS and lifecycle rule types are here for converting to and
+/// from the crate's own [`CorsRule`] and [`LifecycleRule`].
 pub mod s3 {
     pub use aws_sdk_s3::config::retry::RetryConfig;
     pub use aws_sdk_s3::config::timeout::TimeoutConfig;
+    pub use aws_sdk_s3::config::{HttpClient, SharedHttpClient};
     pub use aws_sdk_s3::primitives::ByteStream;
     pub use aws_sdk_s3::types::{
         CorsRule, LifecycleExpiration, LifecycleRule, LifecycleRuleFilter,
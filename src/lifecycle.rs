@@ -0,0 +1,511 @@
+//! Bucket lifecycle rules, limited to the actions R2 supports.
+
+use aws_sdk_s3::types::{
+    AbortIncompleteMultipartUpload, ExpirationStatus, LifecycleExpiration,
+    LifecycleRule as SdkLifecycleRule, LifecycleRuleFilter, Transition, TransitionStorageClass,
+};
+
+use crate::error::{Error, Result};
+use crate::types::DateTime;
+
+/// The most rules one bucket's lifecycle configuration may hold.
+pub const MAX_LIFECYCLE_RULES: usize = 1000;
+
+/// The longest rule ID the API accepts, in characters.
+const MAX_ID_LEN: usize = 255;
+
+/// The longest prefix a rule can filter on: the longest key, in bytes.
+const MAX_PREFIX_LEN: usize = 1024;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// When a lifecycle action applies to an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LifecycleTrigger {
+    /// This many days after the object was written.
+    AfterDays(u32),
+    /// From this date on, for every object the rule covers. The API only
+    /// accepts midnight UTC.
+    On(#[cfg_attr(feature = "serde", serde(with = "crate::json::date"))] DateTime),
+}
+
+/// One lifecycle rule for [`put_lifecycle`](crate::R2Client::put_lifecycle).
+///
+/// R2 supports a subset of S3's lifecycle actions, and this type can express
+/// exactly that subset: expiring objects, transitioning them to Infrequent
+/// Access, and aborting incomplete multipart uploads, for the whole bucket or
+/// the keys under a prefix. [`validate`](LifecycleRule::validate), which
+/// `put_lifecycle` runs on every rule, catches the rest of what R2 would
+/// refuse:
+///
+/// - at least one action;
+/// - day counts from 1 up to the API's 32-bit limit, and dates at midnight
+///   UTC;
+/// - a transition that comes before the expiration, when a rule has both
+///   counted the same way;
+/// - an ID of at most 255 characters and a prefix no longer than a key.
+///
+/// Rules built from the SDK's type with [`TryFrom`] are checked for fields
+/// R2 does not support, such as tag filters or noncurrent-version actions.
+///
+/// ```
+/// use cloudflare_r2_rs::{LifecycleRule, LifecycleTrigger};
+///
+/// let rule = LifecycleRule::new()
+///     .id("logs")
+///     .prefix("logs/")
+///     .transition_to_infrequent_access_after(30)
+///     .expire_after(90)
+///     .abort_incomplete_multipart_after(1);
+/// rule.validate().unwrap();
+/// assert_eq!(rule.expiration, Some(LifecycleTrigger::AfterDays(90)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct LifecycleRule {
+    /// An optional name for the rule.
+    pub id: Option<String>,
+    /// Whether the rule is applied. Disabled rules are stored but do nothing.
+    pub enabled: bool,
+    /// The key prefix the rule covers; `None` covers the whole bucket.
+    pub prefix: Option<String>,
+    /// When objects are deleted.
+    pub expiration: Option<LifecycleTrigger>,
+    /// When objects move to the Infrequent Access storage class, the only
+    /// class R2 transitions to.
+    pub transition: Option<LifecycleTrigger>,
+    /// Days after which an incomplete multipart upload is aborted and its
+    /// parts deleted.
+    pub abort_incomplete_multipart_after: Option<u32>,
+}
+
+impl Default for LifecycleRule {
+    fn default() -> Self {
+        LifecycleRule {
+            id: None,
+            enabled: true,
+            prefix: None,
+            expiration: None,
+            transition: None,
+            abort_incomplete_multipart_after: None,
+        }
+    }
+}
+
+impl LifecycleRule {
+    /// Creates an enabled rule for the whole bucket, which needs at least one
+    /// action.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names the rule.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Limits the rule to keys starting with `prefix`.
+    #[must_use]
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets whether the rule is applied.
+    #[must_use]
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Deletes objects `days` after they were written.
+    #[must_use]
+    pub fn expire_after(mut self, days: u32) -> Self {
+        self.expiration = Some(LifecycleTrigger::AfterDays(days));
+        self
+    }
+
+    /// Deletes objects from `date` on.
+    #[must_use]
+    pub fn expire_on(mut self, date: DateTime) -> Self {
+        self.expiration = Some(LifecycleTrigger::On(date));
+        self
+    }
+
+    /// Moves objects to Infrequent Access `days` after they were written.
+    #[must_use]
+    pub fn transition_to_infrequent_access_after(mut self, days: u32) -> Self {
+        self.transition = Some(LifecycleTrigger::AfterDays(days));
+        self
+    }
+
+    /// Moves objects to Infrequent Access from `date` on.
+    #[must_use]
+    pub fn transition_to_infrequent_access_on(mut self, date: DateTime) -> Self {
+        self.transition = Some(LifecycleTrigger::On(date));
+        self
+    }
+
+    /// Aborts multipart uploads still incomplete `days` after they started.
+    #[must_use]
+    pub fn abort_incomplete_multipart_after(mut self, days: u32) -> Self {
+        self.abort_incomplete_multipart_after = Some(days);
+        self
+    }
+
+    /// Checks the rule against R2's constraints, listed on [`LifecycleRule`].
+    pub fn validate(&self) -> Result<()> {
+        if let Some(id) = &self.id {
+            if id.chars().count() > MAX_ID_LEN {
+                return Err(Error::invalid_argument(
+                    "id",
+                    format!("rule IDs are at most {MAX_ID_LEN} characters"),
+                ));
+            }
+        }
+        if self
+            .prefix
+            .as_ref()
+            .is_some_and(|prefix| prefix.len() > MAX_PREFIX_LEN)
+        {
+            return Err(Error::invalid_argument(
+                "prefix",
+                format!("prefixes are at most {MAX_PREFIX_LEN} bytes"),
+            ));
+        }
+        if self.expiration.is_none()
+            && self.transition.is_none()
+            && self.abort_incomplete_multipart_after.is_none()
+        {
+            return Err(Error::invalid_argument(
+                "rule",
+                "a lifecycle rule needs at least one action",
+            ));
+        }
+        if let Some(expiration) = &self.expiration {
+            validate_trigger("expiration", expiration)?;
+        }
+        if let Some(transition) = &self.transition {
+            validate_trigger("transition", transition)?;
+        }
+        if let Some(days) = self.abort_incomplete_multipart_after {
+            validate_days("abort_incomplete_multipart_after", days)?;
+        }
+
+        let transitions_first = match (self.transition, self.expiration) {
+            (Some(LifecycleTrigger::AfterDays(t)), Some(LifecycleTrigger::AfterDays(e))) => t < e,
+            (Some(LifecycleTrigger::On(t)), Some(LifecycleTrigger::On(e))) => t.secs() < e.secs(),
+            _ => true,
+        };
+        if !transitions_first {
+            return Err(Error::invalid_argument(
+                "transition",
+                "objects must transition to Infrequent Access before they expire",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the rule covers `key`.
+    pub fn matches(&self, key: &str) -> bool {
+        self.prefix
+            .as_deref()
+            .is_none_or(|prefix| key.starts_with(prefix))
+    }
+}
+
+fn validate_trigger(argument: &'static str, trigger: &LifecycleTrigger) -> Result<()> {
+    match trigger {
+        LifecycleTrigger::AfterDays(days) => validate_days(argument, *days),
+        LifecycleTrigger::On(date) => {
+            if date.secs() % SECONDS_PER_DAY != 0 || date.subsec_nanos() != 0 {
+                return Err(Error::invalid_argument(
+                    argument,
+                    format!("{date} is not midnight UTC"),
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn validate_days(argument: &'static str, days: u32) -> Result<()> {
+    if days == 0 || days > i32::MAX as u32 {
+        return Err(Error::invalid_argument(
+            argument,
+            format!("days must be between 1 and {}", i32::MAX),
+        ));
+    }
+    Ok(())
+}
+
+impl From<LifecycleRule> for SdkLifecycleRule {
+    fn from(rule: LifecycleRule) -> Self {
+        let (expiration_days, expiration_date) = split(rule.expiration);
+        let (transition_days, transition_date) = split(rule.transition);
+        SdkLifecycleRule::builder()
+            .set_id(rule.id)
+            .status(if rule.enabled {
+                ExpirationStatus::Enabled
+            } else {
+                ExpirationStatus::Disabled
+            })
+            .filter(
+                LifecycleRuleFilter::builder()
+                    .prefix(rule.prefix.unwrap_or_default())
+                    .build(),
+            )
+            .set_expiration(rule.expiration.map(|_| {
+                LifecycleExpiration::builder()
+                    .set_days(expiration_days)
+                    .set_date(expiration_date)
+                    .build()
+            }))
+            .set_transitions(rule.transition.map(|_| {
+                vec![Transition::builder()
+                    .set_days(transition_days)
+                    .set_date(transition_date)
+                    .storage_class(TransitionStorageClass::StandardIa)
+                    .build()]
+            }))
+            .set_abort_incomplete_multipart_upload(rule.abort_incomplete_multipart_after.map(
+                |days| {
+                    AbortIncompleteMultipartUpload::builder()
+                        .days_after_initiation(i32::try_from(days).unwrap_or(i32::MAX))
+                        .build()
+                },
+            ))
+            .build()
+            .expect("status is always set")
+    }
+}
+
+/// A trigger as the API's separate day and date fields.
+fn split(trigger: Option<LifecycleTrigger>) -> (Option<i32>, Option<DateTime>) {
+    match trigger {
+        Some(LifecycleTrigger::AfterDays(days)) => {
+            (Some(i32::try_from(days).unwrap_or(i32::MAX)), None)
+        }
+        Some(LifecycleTrigger::On(date)) => (None, Some(date)),
+        None => (None, None),
+    }
+}
+
+impl TryFrom<SdkLifecycleRule> for LifecycleRule {
+    type Error = Error;
+
+    /// Fails on a field R2 does not support: tag, size or combined filters,
+    /// noncurrent-version actions, expired delete markers, or transitions to
+    /// any class but Infrequent Access.
+    fn try_from(rule: SdkLifecycleRule) -> Result<Self> {
+        let unsupported =
+            |what: &str| Error::invalid_argument("rule", format!("R2 does not support {what}"));
+        if rule.noncurrent_version_expiration.is_some()
+            || rule
+                .noncurrent_version_transitions
+                .as_ref()
+                .is_some_and(|transitions| !transitions.is_empty())
+        {
+            return Err(unsupported("noncurrent-version actions"));
+        }
+
+        let enabled = match &rule.status {
+            ExpirationStatus::Enabled => true,
+            ExpirationStatus::Disabled => false,
+            other => {
+                return Err(Error::invalid_argument(
+                    "rule",
+                    format!("unknown rule status `{}`", other.as_str()),
+                ))
+            }
+        };
+
+        #[allow(deprecated)]
+        let mut prefix = rule.prefix;
+        if let Some(filter) = rule.filter {
+            if filter.tag.is_some() || filter.and.is_some() {
+                return Err(unsupported("tag or combined filters"));
+            }
+            if filter.object_size_greater_than.is_some() || filter.object_size_less_than.is_some() {
+                return Err(unsupported("object size filters"));
+            }
+            prefix = filter.prefix.or(prefix);
+        }
+
+        let expiration = match rule.expiration {
+            Some(expiration) if expiration.expired_object_delete_marker == Some(true) => {
+                return Err(unsupported("expiring delete markers"));
+            }
+            Some(expiration) => trigger("expiration", expiration.days, expiration.date)?,
+            None => None,
+        };
+
+        let mut transitions = rule.transitions.unwrap_or_default().into_iter();
+        let transition = match (transitions.next(), transitions.next()) {
+            (None, _) => None,
+            (Some(_), Some(_)) => return Err(unsupported("more than one transition per rule")),
+            (Some(transition), None) => {
+                if transition.storage_class != Some(TransitionStorageClass::StandardIa) {
+                    return Err(unsupported(
+                        "transitions to storage classes other than STANDARD_IA",
+                    ));
+                }
+                trigger("transition", transition.days, transition.date)?
+            }
+        };
+
+        let abort_incomplete_multipart_after = rule
+            .abort_incomplete_multipart_upload
+            .and_then(|abort| abort.days_after_initiation)
+            .map(|days| days_from("abort_incomplete_multipart_after", days))
+            .transpose()?;
+
+        Ok(LifecycleRule {
+            id: rule.id,
+            enabled,
+            prefix: prefix.filter(|prefix| !prefix.is_empty()),
+            expiration,
+            transition,
+            abort_incomplete_multipart_after,
+        })
+    }
+}
+
+fn trigger(
+    argument: &'static str,
+    days: Option<i32>,
+    date: Option<DateTime>,
+) -> Result<Option<LifecycleTrigger>> {
+    match (days, date) {
+        (Some(_), Some(_)) => Err(Error::invalid_argument(
+            argument,
+            "set either days or a date, not both",
+        )),
+        (Some(days), None) => Ok(Some(LifecycleTrigger::AfterDays(days_from(
+            argument, days,
+        )?))),
+        (None, Some(date)) => Ok(Some(LifecycleTrigger::On(date))),
+        (None, None) => Ok(None),
+    }
+}
+
+fn days_from(argument: &'static str, days: i32) -> Result<u32> {
+    u32::try_from(days).map_err(|_| Error::invalid_argument(argument, "days must not be negative"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argument(rule: LifecycleRule) -> &'static str {
+        match rule.validate().unwrap_err() {
+            Error::InvalidArgument { argument, .. } => argument,
+            err => panic!("{err}"),
+        }
+    }
+
+    #[test]
+    fn rules_are_validated() {
+        assert_eq!(argument(LifecycleRule::new().prefix("tmp/")), "rule");
+        assert_eq!(argument(LifecycleRule::new().expire_after(0)), "expiration");
+        assert_eq!(
+            argument(LifecycleRule::new().expire_on(DateTime::from_secs(1_700_000_001))),
+            "expiration"
+        );
+        assert_eq!(
+            argument(
+                LifecycleRule::new()
+                    .transition_to_infrequent_access_after(30)
+                    .expire_after(30)
+            ),
+            "transition"
+        );
+        assert_eq!(
+            argument(LifecycleRule::new().abort_incomplete_multipart_after(0)),
+            "abort_incomplete_multipart_after"
+        );
+        assert_eq!(
+            argument(LifecycleRule::new().id("x".repeat(256)).expire_after(1)),
+            "id"
+        );
+        LifecycleRule::new()
+            .expire_on(DateTime::from_secs(1_700_006_400))
+            .transition_to_infrequent_access_after(30)
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn rules_round_trip_through_the_sdk_type() {
+        let rule = LifecycleRule::new()
+            .id("uploads")
+            .prefix("uploads/")
+            .enabled(false)
+            .transition_to_infrequent_access_on(DateTime::from_secs(1_700_006_400))
+            .abort_incomplete_multipart_after(2);
+        let sdk = SdkLifecycleRule::from(rule.clone());
+        assert_eq!(
+            sdk.transitions()[0].storage_class(),
+            Some(&TransitionStorageClass::StandardIa)
+        );
+        assert_eq!(LifecycleRule::try_from(sdk).unwrap(), rule);
+
+        let whole_bucket = LifecycleRule::new().expire_after(7);
+        let sdk = SdkLifecycleRule::from(whole_bucket.clone());
+        assert_eq!(LifecycleRule::try_from(sdk).unwrap(), whole_bucket);
+    }
+
+    #[test]
+    fn unsupported_sdk_fields_are_rejected() {
+        let base = || SdkLifecycleRule::builder().status(ExpirationStatus::Enabled);
+        let glacier = base()
+            .transitions(
+                Transition::builder()
+                    .days(30)
+                    .storage_class(TransitionStorageClass::Glacier)
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let tagged = base()
+            .filter(
+                LifecycleRuleFilter::builder()
+                    .tag(
+                        aws_sdk_s3::types::Tag::builder()
+                            .key("tier")
+                            .value("cold")
+                            .build()
+                            .unwrap(),
+                    )
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let noncurrent = base()
+            .noncurrent_version_expiration(
+                aws_sdk_s3::types::NoncurrentVersionExpiration::builder()
+                    .noncurrent_days(1)
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        for rule in [glacier, tagged, noncurrent] {
+            let err = LifecycleRule::try_from(rule).unwrap_err();
+            assert!(err.to_string().contains("R2 does not support"), "{err}");
+        }
+    }
+
+    #[test]
+    fn prefixes_select_keys() {
+        let rule = LifecycleRule::new().prefix("logs/").expire_after(1);
+        assert!(rule.matches("logs/2024/01.gz"));
+        assert!(!rule.matches("data/logs/01.gz"));
+        assert!(LifecycleRule::new().matches("anything"));
+    }
+}
@@ -0,0 +1,483 @@
+//! Client-side request rate and concurrency limits.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use aws_sdk_s3::config::{RuntimeComponents, SharedHttpClient};
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::HttpRequest;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::error::{Error, Result};
+
+/// Caps on how fast and how many requests one class of traffic may make.
+///
+/// Every field defaults to "no limit".
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// Sustained requests per second, enforced as a token bucket.
+    pub requests_per_second: Option<f64>,
+    /// How many requests may go out back to back before the rate applies.
+    /// Defaults to one second's worth of `requests_per_second`.
+    pub burst: Option<u32>,
+    /// Most requests allowed in flight at once.
+    pub max_in_flight: Option<usize>,
+}
+
+impl Limits {
+    /// Creates unlimited limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the sustained request rate.
+    #[must_use]
+    pub fn requests_per_second(mut self, rate: f64) -> Self {
+        self.requests_per_second = Some(rate);
+        self
+    }
+
+    /// Sets how many requests may burst before the rate applies.
+    #[must_use]
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = Some(burst);
+        self
+    }
+
+    /// Sets the most requests allowed in flight at once.
+    #[must_use]
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    fn validate(&self, field: &'static str) -> Result<()> {
+        let invalid = |message: String| Err(Error::InvalidConfig { field, message });
+
+        if let Some(rate) = self.requests_per_second {
+            if !(rate.is_finite() && rate > 0.0) {
+                return invalid(format!(
+                    "requests per second must be a positive number, got {rate}"
+                ));
+            }
+        }
+        if self.burst == Some(0) {
+            return invalid("burst must be at least 1".to_string());
+        }
+        if self.max_in_flight == Some(0) {
+            return invalid("max in flight must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Client-side throttling for every request a client makes.
+///
+/// R2 enforces per-bucket request rates and answers with `429`/`503` when they
+/// are exceeded, so many tasks sharing one client are better held back here
+/// than retried there. Every request passes through the limits of its class,
+/// reads (`GET`, `HEAD` and listings) or writes (everything else), and then
+/// through the shared [`Limits`], so one class waiting on its own cap does not
+/// hold the other back. The limiter sits in front of the
+/// transport, so it counts attempts rather than calls: each retry the SDK
+/// makes waits its turn again, and requests sent through
+/// [`R2Client::inner`](crate::R2Client::inner) are limited too.
+///
+/// The limiter belongs to the client it was configured on, and is shared by
+/// its clones and by [`with_bucket`](crate::R2Client::with_bucket) children.
+///
+/// ```
+/// use cloudflare_r2_rs::{Limits, RateLimit};
+///
+/// // At most 200 requests a second and 64 in flight overall, of which
+/// // writes get 50 a second.
+/// let limit = RateLimit::new()
+///     .requests_per_second(200.0)
+///     .max_in_flight(64)
+///     .writes(Limits::new().requests_per_second(50.0));
+/// assert_eq!(limit.shared.max_in_flight, Some(64));
+/// ```
+///
+/// A request is counted as in flight until its response headers arrive. A body
+/// read through [`get_object_stream`](crate::R2Client::get_object_stream) is
+/// drained on the caller's schedule and does not hold a slot.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimit {
+    /// Limits every request is subject to.
+    pub shared: Limits,
+    /// Additional limits for reads.
+    pub reads: Limits,
+    /// Additional limits for writes.
+    pub writes: Limits,
+}
+
+impl RateLimit {
+    /// Creates a limiter that limits nothing until configured.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the sustained request rate shared by all requests.
+    #[must_use]
+    pub fn requests_per_second(mut self, rate: f64) -> Self {
+        self.shared.requests_per_second = Some(rate);
+        self
+    }
+
+    /// Sets how many requests may burst before the shared rate applies.
+    #[must_use]
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.shared.burst = Some(burst);
+        self
+    }
+
+    /// Sets the most requests allowed in flight at once, across both classes.
+    #[must_use]
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.shared.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    /// Sets limits that apply to reads only.
+    #[must_use]
+    pub fn reads(mut self, limits: Limits) -> Self {
+        self.reads = limits;
+        self
+    }
+
+    /// Sets limits that apply to writes only.
+    #[must_use]
+    pub fn writes(mut self, limits: Limits) -> Self {
+        self.writes = limits;
+        self
+    }
+
+    pub(crate) fn into_limiter(self) -> Result<Limiter> {
+        self.shared.validate("rate_limit")?;
+        self.reads.validate("rate_limit.reads")?;
+        self.writes.validate("rate_limit.writes")?;
+
+        Ok(Limiter {
+            shared: Gate::new(self.shared),
+            reads: Gate::new(self.reads),
+            writes: Gate::new(self.writes),
+        })
+    }
+}
+
+/// Which class of traffic a request belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestClass {
+    Read,
+    Write,
+}
+
+impl RequestClass {
+    fn of(request: &HttpRequest) -> Self {
+        match request.method() {
+            "GET" | "HEAD" => RequestClass::Read,
+            _ => RequestClass::Write,
+        }
+    }
+}
+
+/// The runtime side of a [`RateLimit`].
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    shared: Gate,
+    reads: Gate,
+    writes: Gate,
+}
+
+impl Limiter {
+    /// Waits until a request of `class` may go out. The returned permit holds
+    /// its in-flight slots until dropped.
+    async fn acquire(&self, class: RequestClass) -> Permit {
+        let class_gate = match class {
+            RequestClass::Read => &self.reads,
+            RequestClass::Write => &self.writes,
+        };
+
+        // Slots before tokens: a request that spent a token and then queued
+        // for a slot would waste its share of the rate. Within each, the
+        // class comes first, so a request held back by its class's cap does
+        // not sit on shared capacity the other class could use.
+        let class_slot = class_gate.slot().await;
+        let shared = self.shared.slot().await;
+        class_gate.token().await;
+        self.shared.token().await;
+
+        Permit {
+            _shared: shared,
+            _class: class_slot,
+        }
+    }
+
+    /// Wraps `inner` so that every attempt the SDK makes, retries included,
+    /// waits for this limiter before it is sent.
+    pub(crate) fn install(self, inner: SharedHttpClient) -> SharedHttpClient {
+        SharedHttpClient::new(LimitedClient {
+            inner,
+            limiter: Arc::new(self),
+        })
+    }
+}
+
+/// Hands out [`LimitedConnector`]s around the real transport's connectors.
+#[derive(Debug)]
+struct LimitedClient {
+    inner: SharedHttpClient,
+    limiter: Arc<Limiter>,
+}
+
+impl HttpClient for LimitedClient {
+    fn http_connector(
+        &self,
+        settings: &HttpConnectorSettings,
+        components: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(LimitedConnector {
+            inner: self.inner.http_connector(settings, components),
+            limiter: Arc::clone(&self.limiter),
+        })
+    }
+}
+
+/// Holds each request until the limiter lets it go, and keeps its in-flight
+/// slots until the response headers arrive.
+#[derive(Debug)]
+struct LimitedConnector {
+    inner: SharedHttpConnector,
+    limiter: Arc<Limiter>,
+}
+
+impl HttpConnector for LimitedConnector {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let inner = self.inner.clone();
+        let limiter = Arc::clone(&self.limiter);
+        HttpConnectorFuture::new(async move {
+            let _permit = limiter.acquire(RequestClass::of(&request)).await;
+            inner.call(request).await
+        })
+    }
+}
+
+/// Held for the duration of one request.
+#[derive(Debug)]
+struct Permit {
+    _shared: Option<OwnedSemaphorePermit>,
+    _class: Option<OwnedSemaphorePermit>,
+}
+
+/// One set of [`Limits`], ready to enforce.
+#[derive(Debug)]
+struct Gate {
+    bucket: Option<TokenBucket>,
+    slots: Option<Arc<Semaphore>>,
+}
+
+impl Gate {
+    fn new(limits: Limits) -> Self {
+        Gate {
+            bucket: limits.requests_per_second.map(|rate| {
+                let burst = limits.burst.map_or(rate.ceil().max(1.0), f64::from);
+                TokenBucket::new(rate, burst)
+            }),
+            slots: limits
+                .max_in_flight
+                .map(|max| Arc::new(Semaphore::new(max))),
+        }
+    }
+
+    async fn slot(&self) -> Option<OwnedSemaphorePermit> {
+        match &self.slots {
+            // The semaphore is never closed, so acquiring cannot fail.
+            Some(slots) => Arc::clone(slots).acquire_owned().await.ok(),
+            None => None,
+        }
+    }
+
+    async fn token(&self) {
+        if let Some(bucket) = &self.bucket {
+            bucket.take().await;
+        }
+    }
+}
+
+/// A token bucket that hands out reservations rather than polling.
+///
+/// Taking a token always succeeds immediately, possibly driving the balance
+/// negative; the caller then sleeps off its share of the debt. Callers are
+/// therefore served in the order they arrived, and nobody spins.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, capacity: f64) -> Self {
+        TokenBucket {
+            rate,
+            capacity,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    async fn take(&self) {
+        let wait = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let earned = now.duration_since(state.refilled_at).as_secs_f64() * self.rate;
+            state.tokens = (state.tokens + earned).min(self.capacity) - 1.0;
+            state.refilled_at = now;
+
+            (state.tokens < 0.0).then(|| Duration::from_secs_f64(-state.tokens / self.rate))
+        };
+
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_nonsensical_limits() {
+        for limit in [
+            RateLimit::new().requests_per_second(0.0),
+            RateLimit::new().requests_per_second(f64::NAN),
+            RateLimit::new().burst(0),
+            RateLimit::new().max_in_flight(0),
+            RateLimit::new().writes(Limits::new().max_in_flight(0)),
+        ] {
+            assert!(limit.into_limiter().is_err(), "{limit:?}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_token_bucket_allows_a_burst_then_paces() {
+        let limiter = RateLimit::new()
+            .requests_per_second(10.0)
+            .burst(2)
+            .into_limiter()
+            .unwrap();
+
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire(RequestClass::Read).await;
+        }
+        // Two free, then two more at 100ms apiece.
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn class_limits_only_apply_to_their_class() {
+        let limiter = RateLimit::new()
+            .writes(Limits::new().requests_per_second(1.0).burst(1))
+            .into_limiter()
+            .unwrap();
+
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire(RequestClass::Read).await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        limiter.acquire(RequestClass::Write).await;
+        limiter.acquire(RequestClass::Write).await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn in_flight_slots_are_held_until_the_permit_drops() {
+        let limiter = Arc::new(RateLimit::new().max_in_flight(1).into_limiter().unwrap());
+
+        let held = limiter.acquire(RequestClass::Write).await;
+        let waiting = {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move { limiter.acquire(RequestClass::Read).await })
+        };
+
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished(), "a second request got a slot");
+
+        drop(held);
+        waiting.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reads_get_shared_slots_while_writes_queue_behind_their_cap() {
+        let limiter = Arc::new(
+            RateLimit::new()
+                .max_in_flight(2)
+                .writes(Limits::new().max_in_flight(1))
+                .into_limiter()
+                .unwrap(),
+        );
+
+        let held = limiter.acquire(RequestClass::Write).await;
+        let queued: Vec<_> = (0..2)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                tokio::spawn(async move { limiter.acquire(RequestClass::Write).await })
+            })
+            .collect();
+        tokio::task::yield_now().await;
+
+        for _ in 0..3 {
+            tokio::time::timeout(Duration::from_secs(1), limiter.acquire(RequestClass::Read))
+                .await
+                .expect("a queued write took the shared slot reads need");
+        }
+
+        drop(held);
+        for write in queued {
+            write.await.unwrap();
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn writes_waiting_on_their_rate_spend_none_of_the_shared_rate() {
+        let limiter = Arc::new(
+            RateLimit::new()
+                .requests_per_second(10.0)
+                .burst(1)
+                .writes(Limits::new().requests_per_second(1.0).burst(1))
+                .into_limiter()
+                .unwrap(),
+        );
+
+        let start = Instant::now();
+        limiter.acquire(RequestClass::Write).await;
+        for _ in 0..3 {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move { limiter.acquire(RequestClass::Write).await });
+        }
+        tokio::task::yield_now().await;
+
+        for _ in 0..5 {
+            limiter.acquire(RequestClass::Read).await;
+        }
+        // Only the first write came out of the shared rate.
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+    }
+}
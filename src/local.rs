@@ -0,0 +1,377 @@
+//! An [`ObjectStore`] over a directory on the local filesystem.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use crate::error::{Error, Result};
+use crate::object::{safe_join, temporary_path, validate_key};
+use crate::store::{paginate, resolve_range, ObjectStore};
+use crate::types::{
+    DateTime, ListOptions, ListPage, ObjectMetadata, ObjectSummary, PutOptions, PutOutcome,
+};
+
+/// Prefix of the in-progress files writes land in before being renamed.
+const TEMPORARY_PREFIX: &str = ".r2partial.";
+
+/// An [`ObjectStore`] that keeps each object as a file under a root directory.
+///
+/// A key maps to the path of the same name below the root, with `/` as the
+/// separator, so `photos/2024/cat.png` lives at `<root>/photos/2024/cat.png`.
+/// Keys that would resolve outside the root — absolute keys, `..` segments,
+/// backslashes — are rejected with [`Error::InvalidArgument`], exactly as
+/// [`R2Client::download_file`](crate::R2Client::download_file) rejects them.
+///
+/// Writes are atomic: the body goes to a temporary file beside the
+/// destination and is renamed into place. Only the body is stored; the
+/// content type reported by [`head`](ObjectStore::head) is guessed from the
+/// key's extension, the ETag is derived from the file's size and
+/// modification time, and the other [`PutOptions`] are not kept.
+///
+/// Because keys are paths, a key cannot be both an object and a "directory"
+/// of other objects: with `a` stored, putting `a/b` fails.
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    /// Creates a store rooted at `root`.
+    ///
+    /// The directory is created on the first write; until then the store
+    /// simply lists as empty.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The directory objects are stored under.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        validate_key(key)?;
+        safe_join(&self.root, key, None)
+    }
+
+    fn not_found(&self, key: &str) -> Error {
+        Error::ObjectNotFound {
+            bucket: self.root.display().to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    /// Maps a failed read of `key`'s file, turning "no such file" into
+    /// [`Error::ObjectNotFound`].
+    fn read_error(&self, key: &str, path: &Path, err: std::io::Error) -> Error {
+        match err.kind() {
+            ErrorKind::NotFound | ErrorKind::IsADirectory | ErrorKind::NotADirectory => {
+                self.not_found(key)
+            }
+            _ => Error::file(path, "could not read the object", Some(err)),
+        }
+    }
+
+    /// Runs `write` against a temporary file beside `path`, then renames the
+    /// result into place.
+    async fn write_atomically<F, Fut>(&self, path: &Path, write: F) -> Result<()>
+    where
+        F: FnOnce(PathBuf) -> Fut,
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|err| {
+                Error::file(parent, "could not create the parent directory", Some(err))
+            })?;
+        }
+
+        let temporary = temporary_path(path);
+        if let Err(err) = write(temporary.clone()).await {
+            let _ = tokio::fs::remove_file(&temporary).await;
+            return Err(err);
+        }
+        if let Err(err) = tokio::fs::rename(&temporary, path).await {
+            let _ = tokio::fs::remove_file(&temporary).await;
+            return Err(Error::file(
+                path,
+                "could not move the object into place",
+                Some(err),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Walks the root, collecting every object in key order.
+    async fn summaries(&self) -> Result<Vec<ObjectSummary>> {
+        let mut summaries = Vec::new();
+        let mut pending = vec![(self.root.clone(), String::new())];
+
+        while let Some((directory, prefix)) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&directory).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(Error::file(
+                        &directory,
+                        "could not list the directory",
+                        Some(err),
+                    ))
+                }
+            };
+
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|err| Error::file(&directory, "could not list the directory", Some(err)))?
+            {
+                // Names that are not UTF-8 cannot be keys.
+                let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                    continue;
+                };
+                if name.starts_with(TEMPORARY_PREFIX) {
+                    continue;
+                }
+                let key = format!("{prefix}{name}");
+                let path = entry.path();
+                let metadata = tokio::fs::metadata(&path)
+                    .await
+                    .map_err(|err| Error::file(&path, "could not read the object", Some(err)))?;
+
+                if metadata.is_dir() {
+                    pending.push((path, format!("{key}/")));
+                } else if metadata.is_file() {
+                    summaries.push(ObjectSummary {
+                        key,
+                        size: metadata.len() as i64,
+                        etag: Some(file_etag(&metadata)),
+                        last_modified: metadata.modified().ok().map(DateTime::from),
+                    });
+                }
+            }
+        }
+
+        summaries.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(summaries)
+    }
+}
+
+impl ObjectStore for LocalStore {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        body: Bytes,
+        _options: PutOptions,
+    ) -> BoxFuture<'a, Result<PutOutcome>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            self.write_atomically(&path, |temporary| async move {
+                let mut file = tokio::fs::File::create(&temporary).await.map_err(|err| {
+                    Error::file(&temporary, "could not create the object file", Some(err))
+                })?;
+                file.write_all(&body).await.map_err(|err| {
+                    Error::file(&temporary, "could not write the object", Some(err))
+                })?;
+                file.flush()
+                    .await
+                    .map_err(|err| Error::file(&temporary, "could not flush the object", Some(err)))
+            })
+            .await?;
+
+            let metadata = tokio::fs::metadata(&path)
+                .await
+                .map_err(|err| Error::file(&path, "could not read the object", Some(err)))?;
+            Ok(PutOutcome {
+                key: key.to_string(),
+                etag: Some(file_etag(&metadata)),
+            })
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            tokio::fs::read(&path)
+                .await
+                .map_err(|err| self.read_error(key, &path, err))
+        })
+    }
+
+    fn get_range<'a>(
+        &'a self,
+        key: &'a str,
+        start: u64,
+        end: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            let mut file = tokio::fs::File::open(&path)
+                .await
+                .map_err(|err| self.read_error(key, &path, err))?;
+            let size = file
+                .metadata()
+                .await
+                .map_err(|err| self.read_error(key, &path, err))?
+                .len();
+            let (start, end) = resolve_range(start, end, size)?;
+
+            file.seek(std::io::SeekFrom::Start(start))
+                .await
+                .map_err(|err| self.read_error(key, &path, err))?;
+            let mut bytes = Vec::with_capacity((end - start) as usize);
+            file.take(end - start)
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(|err| self.read_error(key, &path, err))?;
+            Ok(bytes)
+        })
+    }
+
+    fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ObjectMetadata>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            let metadata = tokio::fs::metadata(&path)
+                .await
+                .map_err(|err| self.read_error(key, &path, err))?;
+            if !metadata.is_file() {
+                return Err(self.not_found(key));
+            }
+
+            Ok(ObjectMetadata {
+                content_length: metadata.len() as i64,
+                content_type: Some(PutOptions::new().resolved_content_type(key)),
+                etag: Some(file_etag(&metadata)),
+                last_modified: metadata.modified().ok().map(DateTime::from),
+                cache_control: None,
+                content_disposition: None,
+                content_encoding: None,
+                content_language: None,
+                metadata: Default::default(),
+            })
+        })
+    }
+
+    fn list(&self, options: ListOptions) -> BoxFuture<'_, Result<ListPage>> {
+        Box::pin(async move { Ok(paginate(self.summaries().await?, &options)) })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => Ok(()),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+                Err(err) => Err(Error::file(&path, "could not delete the object", Some(err))),
+            }
+        })
+    }
+
+    fn copy<'a>(
+        &'a self,
+        source_key: &'a str,
+        destination_key: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let source = self.path(source_key)?;
+            let destination = self.path(destination_key)?;
+            if !tokio::fs::metadata(&source)
+                .await
+                .map_err(|err| self.read_error(source_key, &source, err))?
+                .is_file()
+            {
+                return Err(self.not_found(source_key));
+            }
+
+            self.write_atomically(&destination, |temporary| async move {
+                tokio::fs::copy(&source, &temporary)
+                    .await
+                    .map(drop)
+                    .map_err(|err| Error::file(&source, "could not copy the object", Some(err)))
+            })
+            .await
+        })
+    }
+}
+
+/// An ETag for a file, from its size and modification time.
+///
+/// Cheaper than hashing the body and changes whenever the file is rewritten,
+/// which is what callers comparing ETags rely on.
+fn file_etag(metadata: &std::fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos());
+    format!("\"{:x}-{modified:x}\"", metadata.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keys_map_to_paths_under_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path());
+        store
+            .put("a/b/c.txt", Bytes::from_static(b"abc"), PutOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(dir.path().join("a/b/c.txt")).unwrap(), b"abc");
+    }
+
+    #[tokio::test]
+    async fn keys_that_escape_the_root_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path().join("root"));
+        for key in ["../escape.txt", "/etc/passwd", "a\\b"] {
+            let err = store
+                .put(key, Bytes::from_static(b"x"), PutOptions::new())
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidArgument { .. }),
+                "{key}: {err:?}"
+            );
+        }
+        assert!(!dir.path().join("escape.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn temporary_files_are_not_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".r2partial.1.0"), b"partial").unwrap();
+        std::fs::write(dir.path().join("done.txt"), b"done").unwrap();
+
+        let store = LocalStore::new(dir.path());
+        let keys: Vec<_> = store
+            .list_all(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| object.key)
+            .collect();
+        assert_eq!(keys, ["done.txt"]);
+    }
+
+    #[tokio::test]
+    async fn a_missing_root_lists_as_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path().join("not-yet"));
+        assert!(store.list_all(None).await.unwrap().is_empty());
+        assert!(store.get("a.txt").await.unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn directories_are_not_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("folder")).unwrap();
+        let store = LocalStore::new(dir.path());
+        assert!(!store.exists("folder").await.unwrap());
+        assert!(store.get("folder").await.unwrap_err().is_not_found());
+    }
+}
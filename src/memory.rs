@@ -0,0 +1,210 @@
+//! An [`ObjectStore`] held entirely in process memory.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+
+use crate::error::{Error, Result};
+use crate::object::validate_key;
+use crate::store::{content_etag, paginate, resolve_range, ObjectStore};
+use crate::types::{
+    DateTime, ListOptions, ListPage, ObjectMetadata, ObjectSummary, PutOptions, PutOutcome,
+};
+
+/// The bucket name reported in [`Error::ObjectNotFound`] from a [`MemoryStore`].
+const MEMORY_BUCKET: &str = "memory";
+
+/// An [`ObjectStore`] backed by an in-memory map.
+///
+/// Keeps each object's body and the headers and metadata it was put with, so
+/// [`head`](ObjectStore::head) answers the way R2 would. Clones share the
+/// same objects, which makes it easy to hand one store to the code under test
+/// and inspect it afterwards.
+///
+/// Nothing is persisted; the objects go away with the last clone.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    objects: Arc<Mutex<BTreeMap<String, MemoryObject>>>,
+}
+
+#[derive(Debug, Clone)]
+struct MemoryObject {
+    body: Bytes,
+    metadata: ObjectMetadata,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of objects in the store.
+    pub fn len(&self) -> usize {
+        self.objects().len()
+    }
+
+    /// Whether the store holds no objects.
+    pub fn is_empty(&self) -> bool {
+        self.objects().is_empty()
+    }
+
+    /// Removes every object.
+    pub fn clear(&self) {
+        self.objects().clear();
+    }
+
+    fn objects(&self) -> MutexGuard<'_, BTreeMap<String, MemoryObject>> {
+        // The map is never left half-updated, so a panic elsewhere while the
+        // lock was held does not make its contents suspect.
+        self.objects.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn object(&self, key: &str) -> Result<MemoryObject> {
+        validate_key(key)?;
+        self.objects()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| not_found(key))
+    }
+}
+
+impl ObjectStore for MemoryStore {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        body: Bytes,
+        options: PutOptions,
+    ) -> BoxFuture<'a, Result<PutOutcome>> {
+        Box::pin(async move {
+            validate_key(key)?;
+
+            let etag = content_etag(&body);
+            let metadata = ObjectMetadata {
+                content_length: body.len() as i64,
+                content_type: Some(options.resolved_content_type(key)),
+                etag: Some(etag.clone()),
+                last_modified: Some(DateTime::from(SystemTime::now())),
+                cache_control: options.cache_control,
+                content_disposition: options.content_disposition,
+                content_encoding: options.content_encoding,
+                content_language: options.content_language,
+                metadata: options.metadata,
+            };
+            self.objects()
+                .insert(key.to_string(), MemoryObject { body, metadata });
+
+            Ok(PutOutcome {
+                key: key.to_string(),
+                etag: Some(etag),
+            })
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(async move { Ok(self.object(key)?.body.to_vec()) })
+    }
+
+    fn get_range<'a>(
+        &'a self,
+        key: &'a str,
+        start: u64,
+        end: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(async move {
+            let body = self.object(key)?.body;
+            let (start, end) = resolve_range(start, end, body.len() as u64)?;
+            Ok(body[start as usize..end as usize].to_vec())
+        })
+    }
+
+    fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ObjectMetadata>> {
+        Box::pin(async move { Ok(self.object(key)?.metadata) })
+    }
+
+    fn list(&self, options: ListOptions) -> BoxFuture<'_, Result<ListPage>> {
+        Box::pin(async move {
+            let summaries: Vec<ObjectSummary> = self
+                .objects()
+                .iter()
+                .map(|(key, object)| ObjectSummary {
+                    key: key.clone(),
+                    size: object.metadata.content_length,
+                    etag: object.metadata.etag.clone(),
+                    last_modified: object.metadata.last_modified,
+                })
+                .collect();
+            Ok(paginate(summaries, &options))
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            validate_key(key)?;
+            self.objects().remove(key);
+            Ok(())
+        })
+    }
+
+    fn copy<'a>(
+        &'a self,
+        source_key: &'a str,
+        destination_key: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            validate_key(destination_key)?;
+            let mut object = self.object(source_key)?;
+            object.metadata.last_modified = Some(DateTime::from(SystemTime::now()));
+            self.objects().insert(destination_key.to_string(), object);
+            Ok(())
+        })
+    }
+}
+
+fn not_found(key: &str) -> Error {
+    Error::ObjectNotFound {
+        bucket: MEMORY_BUCKET.to_string(),
+        key: key.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clones_share_their_objects() {
+        let store = MemoryStore::new();
+        let clone = store.clone();
+        store
+            .put("a.txt", Bytes::from_static(b"a"), PutOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(clone.get("a.txt").await.unwrap(), b"a");
+        assert_eq!(clone.len(), 1);
+
+        clone.clear();
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn copies_keep_the_source_metadata() {
+        let store = MemoryStore::new();
+        let options = PutOptions::new()
+            .content_type("text/csv")
+            .metadata("owner", "ops");
+        store
+            .put("a.csv", Bytes::from_static(b"x,y"), options)
+            .await
+            .unwrap();
+        store.copy("a.csv", "b.csv").await.unwrap();
+
+        let head = store.head("b.csv").await.unwrap();
+        assert_eq!(head.content_type.as_deref(), Some("text/csv"));
+        assert_eq!(head.metadata.get("owner").map(String::as_str), Some("ops"));
+    }
+}
@@ -0,0 +1,382 @@
+//! Per-operation metrics: counts, latencies, bytes and R2 billing classes.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use aws_sdk_s3::config::interceptors::{
+    BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextRef,
+    FinalizerInterceptorContextRef,
+};
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::orchestrator::Metadata;
+use aws_smithy_runtime_api::client::retries::RequestAttempts;
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+
+/// Receives one [`OperationEvent`] for every S3 operation a client completes.
+///
+/// Install with [`R2ClientBuilder::metrics`](crate::R2ClientBuilder::metrics).
+/// The observer sees every request the client sends, including those made
+/// through [`R2Client::inner`](crate::R2Client::inner), and is called on the
+/// task that made the request, so it should record and return rather than do
+/// anything slow.
+///
+/// ```
+/// use cloudflare_r2_rs::{MetricsObserver, OperationEvent};
+///
+/// #[derive(Debug)]
+/// struct LogLatency;
+///
+/// impl MetricsObserver for LogLatency {
+///     fn on_operation(&self, event: &OperationEvent) {
+///         println!("{} took {:?}", event.operation, event.latency);
+///     }
+/// }
+/// ```
+pub trait MetricsObserver: Send + Sync + std::fmt::Debug {
+    /// Called once an operation has succeeded or failed for good.
+    fn on_operation(&self, event: &OperationEvent);
+}
+
+/// What one S3 operation did, as reported to a [`MetricsObserver`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OperationEvent {
+    /// The S3 operation name, e.g. `PutObject` or `ListObjectsV2`.
+    pub operation: String,
+    /// How R2 bills the operation.
+    pub class: OperationClass,
+    /// HTTP status of the final attempt, when a response arrived.
+    pub status: Option<u16>,
+    /// Whether the operation succeeded.
+    pub success: bool,
+    /// Time from the start of the operation to its outcome, retries and
+    /// backoff included.
+    pub latency: Duration,
+    /// Request body bytes sent, summed over attempts.
+    pub bytes_sent: u64,
+    /// Response body bytes announced by the final response's
+    /// `Content-Length`. For a streamed download this is what R2 sent, not
+    /// necessarily what the caller read.
+    pub bytes_received: u64,
+    /// Requests sent, including the first. `attempts - 1` is the retry count.
+    pub attempts: u32,
+    /// The object's storage class, e.g. `STANDARD_IA`, when the request or
+    /// response named one. R2 omits it for Standard objects.
+    pub storage_class: Option<String>,
+}
+
+impl OperationEvent {
+    /// How many times the SDK retried the operation.
+    pub fn retries(&self) -> u32 {
+        self.attempts.saturating_sub(1)
+    }
+}
+
+/// R2's billing classes for operations.
+///
+/// Class A operations mutate state or list; Class B operations read. Deletes
+/// and aborting a multipart upload are free. The mapping follows Cloudflare's
+/// R2 pricing page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperationClass {
+    /// Writes, listings and bucket configuration changes.
+    ClassA,
+    /// Reads of objects and bucket configuration.
+    ClassB,
+    /// Deletes and multipart aborts.
+    Free,
+}
+
+impl OperationClass {
+    /// The class of the S3 operation called `operation`.
+    ///
+    /// Operations this crate does not know about are counted as Class A, so
+    /// that an unfamiliar operation overstates usage rather than hides it.
+    ///
+    /// ```
+    /// use cloudflare_r2_rs::OperationClass;
+    ///
+    /// assert_eq!(OperationClass::of("PutObject"), OperationClass::ClassA);
+    /// assert_eq!(OperationClass::of("GetObject"), OperationClass::ClassB);
+    /// assert_eq!(OperationClass::of("DeleteObject"), OperationClass::Free);
+    /// ```
+    pub fn of(operation: &str) -> Self {
+        match operation {
+            "HeadBucket"
+            | "HeadObject"
+            | "GetObject"
+            | "GetBucketEncryption"
+            | "GetBucketLocation"
+            | "GetBucketCors"
+            | "GetBucketLifecycleConfiguration" => OperationClass::ClassB,
+            "DeleteObject" | "DeleteObjects" | "DeleteBucket" | "AbortMultipartUpload" => {
+                OperationClass::Free
+            }
+            _ => OperationClass::ClassA,
+        }
+    }
+}
+
+/// A ready-made [`MetricsObserver`] that counts operations per billing class
+/// and bytes in each direction.
+///
+/// Keep an [`Arc`] to it and hand a clone to the builder:
+///
+/// ```
+/// use std::sync::Arc;
+/// use cloudflare_r2_rs::{R2Client, UsageCounter};
+///
+/// let usage = Arc::new(UsageCounter::new());
+/// let client = R2Client::builder()
+///     .account_id("0123456789abcdef")
+///     .bucket("media")
+///     .access_key_id("access-key")
+///     .secret_access_key("secret-key")
+///     .metrics(usage.clone())
+///     .build()
+///     .unwrap();
+/// assert_eq!(usage.snapshot().class_a, 0);
+/// ```
+///
+/// Every attempt counts towards its class, since every request that reaches
+/// R2 is billable whether or not it was later retried.
+#[derive(Debug, Default)]
+pub struct UsageCounter {
+    class_a: AtomicU64,
+    class_b: AtomicU64,
+    free: AtomicU64,
+    failures: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+/// A point-in-time copy of a [`UsageCounter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Usage {
+    /// Class A requests.
+    pub class_a: u64,
+    /// Class B requests.
+    pub class_b: u64,
+    /// Free requests.
+    pub free: u64,
+    /// Operations that failed.
+    pub failures: u64,
+    /// Request body bytes sent.
+    pub bytes_sent: u64,
+    /// Response body bytes received.
+    pub bytes_received: u64,
+}
+
+impl UsageCounter {
+    /// Creates a counter at zero.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The counts so far.
+    pub fn snapshot(&self) -> Usage {
+        Usage {
+            class_a: self.class_a.load(Ordering::Relaxed),
+            class_b: self.class_b.load(Ordering::Relaxed),
+            free: self.free.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MetricsObserver for UsageCounter {
+    fn on_operation(&self, event: &OperationEvent) {
+        let class = match event.class {
+            OperationClass::ClassA => &self.class_a,
+            OperationClass::ClassB => &self.class_b,
+            OperationClass::Free => &self.free,
+        };
+        class.fetch_add(u64::from(event.attempts), Ordering::Relaxed);
+        if !event.success {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes_sent
+            .fetch_add(event.bytes_sent, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(event.bytes_received, Ordering::Relaxed);
+    }
+}
+
+const STORAGE_CLASS: &str = "x-amz-storage-class";
+
+/// When the current operation started. Lives in the operation's config bag.
+#[derive(Debug)]
+struct Started(Instant);
+
+impl Storable for Started {
+    type Storer = StoreReplace<Self>;
+}
+
+/// Request body bytes sent so far by the current operation.
+#[derive(Debug)]
+struct BytesSent(u64);
+
+impl Storable for BytesSent {
+    type Storer = StoreReplace<Self>;
+}
+
+/// The storage class the current operation's request asked for.
+#[derive(Debug)]
+struct RequestedStorageClass(String);
+
+impl Storable for RequestedStorageClass {
+    type Storer = StoreReplace<Self>;
+}
+
+/// The SDK interceptor that turns each operation into an [`OperationEvent`].
+#[derive(Debug)]
+pub(crate) struct MetricsInterceptor {
+    observers: Vec<Arc<dyn MetricsObserver>>,
+}
+
+impl MetricsInterceptor {
+    pub(crate) fn new(observers: Vec<Arc<dyn MetricsObserver>>) -> Self {
+        MetricsInterceptor { observers }
+    }
+}
+
+impl Intercept for MetricsInterceptor {
+    fn name(&self) -> &'static str {
+        "MetricsInterceptor"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        cfg.interceptor_state().store_put(Started(Instant::now()));
+        Ok(())
+    }
+
+    fn read_before_transmit(
+        &self,
+        context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let sent = cfg.load::<BytesSent>().map_or(0, |sent| sent.0);
+        let body = context.request().body().content_length().unwrap_or(0);
+        cfg.interceptor_state().store_put(BytesSent(sent + body));
+        if let Some(class) = context.request().headers().get(STORAGE_CLASS) {
+            cfg.interceptor_state()
+                .store_put(RequestedStorageClass(class.to_string()));
+        }
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        // A presigned request stops before it is sent and has no outcome.
+        let Some(outcome) = context.output_or_error() else {
+            return Ok(());
+        };
+        let operation = cfg
+            .load::<Metadata>()
+            .map_or("Unknown", Metadata::name)
+            .to_string();
+        let response = context.response();
+        // A HEAD response announces the length of a body it does not send.
+        let bytes_received = match response {
+            Some(response) if !operation.starts_with("Head") => response
+                .headers()
+                .get("content-length")
+                .and_then(|length| length.parse().ok())
+                .unwrap_or(0),
+            _ => 0,
+        };
+
+        let event = OperationEvent {
+            class: OperationClass::of(&operation),
+            operation,
+            status: response.map(|response| response.status().as_u16()),
+            success: outcome.is_ok(),
+            latency: cfg
+                .load::<Started>()
+                .map_or(Duration::ZERO, |started| started.0.elapsed()),
+            bytes_sent: cfg.load::<BytesSent>().map_or(0, |sent| sent.0),
+            bytes_received,
+            attempts: cfg
+                .load::<RequestAttempts>()
+                .map_or(1, RequestAttempts::attempts),
+            storage_class: response
+                .and_then(|response| response.headers().get(STORAGE_CLASS))
+                .or_else(|| {
+                    cfg.load::<RequestedStorageClass>()
+                        .map(|class| class.0.as_str())
+                })
+                .map(str::to_string),
+        };
+        for observer in &self.observers {
+            observer.on_operation(&event);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(operation: &str, attempts: u32, success: bool) -> OperationEvent {
+        OperationEvent {
+            operation: operation.to_string(),
+            class: OperationClass::of(operation),
+            status: Some(if success { 200 } else { 500 }),
+            success,
+            latency: Duration::from_millis(5),
+            bytes_sent: 10,
+            bytes_received: 20,
+            attempts,
+            storage_class: None,
+        }
+    }
+
+    #[test]
+    fn classifies_operations_the_way_r2_bills_them() {
+        for (operation, class) in [
+            ("PutObject", OperationClass::ClassA),
+            ("ListObjectsV2", OperationClass::ClassA),
+            ("UploadPart", OperationClass::ClassA),
+            ("PutBucketCors", OperationClass::ClassA),
+            ("GetObject", OperationClass::ClassB),
+            ("HeadObject", OperationClass::ClassB),
+            ("HeadBucket", OperationClass::ClassB),
+            ("DeleteObject", OperationClass::Free),
+            ("AbortMultipartUpload", OperationClass::Free),
+            ("SomethingNew", OperationClass::ClassA),
+        ] {
+            assert_eq!(OperationClass::of(operation), class, "{operation}");
+        }
+    }
+
+    #[test]
+    fn the_usage_counter_counts_every_attempt() {
+        let usage = UsageCounter::new();
+        usage.on_operation(&event("PutObject", 3, true));
+        usage.on_operation(&event("GetObject", 1, false));
+
+        let snapshot = usage.snapshot();
+        assert_eq!(snapshot.class_a, 3);
+        assert_eq!(snapshot.class_b, 1);
+        assert_eq!(snapshot.free, 0);
+        assert_eq!(snapshot.failures, 1);
+        assert_eq!(snapshot.bytes_sent, 20);
+        assert_eq!(snapshot.bytes_received, 40);
+    }
+}
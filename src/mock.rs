@@ -0,0 +1,1173 @@
+//! An in-memory, S3-compatible stand-in for R2, for tests.
+//!
+//! Enabled by the `mock` feature. [`MockServer`] listens on a loopback port and
+//! speaks enough of the S3 protocol for everything [`R2Client`] does — objects,
+//! ranges, conditional requests, copies, batch deletes, paginated listings
+//! with delimiters, multipart uploads, and bucket CORS and lifecycle
+//! configuration — so code built on this crate can be tested end to end
+//! without credentials or network access.
+//!
+//! ```
+//! use cloudflare_r2_rs::mock::MockServer;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> cloudflare_r2_rs::Result<()> {
+//! let server = MockServer::start().await?;
+//! let client = server.client();
+//!
+//! client.put_object("reports/q1.csv", "region,total\n").await?;
+//! assert_eq!(client.get_object("reports/q1.csv").await?, b"region,total\n");
+//! assert_eq!(server.keys(client.bucket()), ["reports/q1.csv"]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests are not authenticated, and a few of R2's limits are deliberately
+//! not enforced: multipart parts may be of any size, so tests can exercise
+//! multipart code paths with small files. ETags are opaque and not MD5s.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+use aws_smithy_types::date_time::{DateTime, Format};
+use bytes::Bytes;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::client::{
+    HasAccessKey, HasBucket, HasEndpoint, HasSecretKey, R2Client, R2ClientBuilder,
+};
+use crate::error::Result;
+use crate::store::{content_etag, paginate};
+use crate::types::{ListOptions, ObjectSummary};
+
+/// The bucket [`MockServer::client`] is bound to. It exists from the start.
+pub const MOCK_BUCKET: &str = "mock-bucket";
+
+/// Object headers that are stored with an object and returned on reads.
+const STORED_HEADERS: &[&str] = &[
+    "content-type",
+    "cache-control",
+    "content-disposition",
+    "content-encoding",
+    "content-language",
+    "expires",
+];
+
+/// An in-memory S3-compatible server on a loopback port.
+///
+/// Every client handed out shares the server's state, so what one writes the
+/// others read. The server stops when this value is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    endpoint: String,
+    state: Arc<Mutex<State>>,
+    accept: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts a server with one empty bucket, [`MOCK_BUCKET`].
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+
+        let mut state = State::default();
+        state.create_bucket(MOCK_BUCKET);
+        let state = Arc::new(Mutex::new(state));
+
+        let shared = Arc::clone(&state);
+        let accept = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, Arc::clone(&shared)));
+            }
+        });
+
+        Ok(MockServer {
+            endpoint,
+            state,
+            accept,
+        })
+    }
+
+    /// The server's base URL, e.g. `http://127.0.0.1:40123`.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// A client bound to [`MOCK_BUCKET`].
+    pub fn client(&self) -> R2Client {
+        self.client_for(MOCK_BUCKET)
+    }
+
+    /// A client bound to `bucket`, which is created if it does not exist yet.
+    ///
+    /// # Panics
+    ///
+    /// If `bucket` is blank, which no client can be built for.
+    pub fn client_for(&self, bucket: &str) -> R2Client {
+        self.lock().create_bucket(bucket);
+        self.builder(bucket)
+            .build()
+            .expect("a client for the mock server is always valid")
+    }
+
+    /// A builder already pointed at the server and bound to `bucket`, for
+    /// clients that need more options — a rate limit, a metrics observer.
+    /// Unlike [`client_for`](MockServer::client_for), this does not create
+    /// the bucket.
+    pub fn builder(
+        &self,
+        bucket: &str,
+    ) -> R2ClientBuilder<HasEndpoint, HasBucket, HasAccessKey, HasSecretKey> {
+        R2Client::builder()
+            .endpoint(&self.endpoint)
+            .bucket(bucket)
+            .access_key_id("mock-access-key")
+            .secret_access_key("mock-secret-key")
+    }
+
+    /// The body stored under `key` in `bucket`, read without going through
+    /// HTTP.
+    pub fn object(&self, bucket: &str, key: &str) -> Option<Bytes> {
+        self.lock()
+            .buckets
+            .get(bucket)?
+            .objects
+            .get(key)
+            .map(|object| object.body.clone())
+    }
+
+    /// Every key in `bucket`, in lexicographic order. Empty if the bucket does
+    /// not exist.
+    pub fn keys(&self, bucket: &str) -> Vec<String> {
+        self.lock()
+            .buckets
+            .get(bucket)
+            .map(|bucket| bucket.objects.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+// --- state -------------------------------------------------------------------
+
+#[derive(Debug, Default)]
+struct State {
+    buckets: BTreeMap<String, Bucket>,
+    uploads: HashMap<String, Upload>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    created: DateTime,
+    objects: BTreeMap<String, Object>,
+    cors: Option<Bytes>,
+    lifecycle: Option<Bytes>,
+}
+
+#[derive(Debug, Clone)]
+struct Object {
+    body: Bytes,
+    etag: String,
+    last_modified: DateTime,
+    /// Content headers and `x-amz-meta-*` user metadata, as sent.
+    headers: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+struct Upload {
+    bucket: String,
+    key: String,
+    initiated: DateTime,
+    headers: Vec<(String, String)>,
+    parts: BTreeMap<i32, (String, Bytes)>,
+}
+
+impl State {
+    fn create_bucket(&mut self, name: &str) -> bool {
+        if self.buckets.contains_key(name) {
+            return false;
+        }
+        self.buckets.insert(
+            name.to_string(),
+            Bucket {
+                created: now(),
+                objects: BTreeMap::new(),
+                cors: None,
+                lifecycle: None,
+            },
+        );
+        true
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn handle(&mut self, request: &Request) -> Response {
+        let (bucket, key) = match request.path.split_once('/') {
+            Some((bucket, key)) => (bucket, key),
+            None => (request.path.as_str(), ""),
+        };
+        let response = if bucket.is_empty() {
+            match request.method.as_str() {
+                "GET" => self.list_buckets(),
+                _ => Response::not_implemented(),
+            }
+        } else if !self.buckets.contains_key(bucket) && !is_create_bucket(request, key) {
+            Response::error(404, "NoSuchBucket", "The specified bucket does not exist.")
+        } else if key.is_empty() {
+            self.bucket_request(bucket, request)
+        } else {
+            self.object_request(bucket, key, request)
+        };
+
+        let id = self.next_id();
+        response.header("x-amz-request-id", format!("mock-{id}"))
+    }
+
+    fn bucket_request(&mut self, name: &str, request: &Request) -> Response {
+        match request.method.as_str() {
+            "PUT" if request.has_query("cors") => {
+                self.bucket(name).cors = Some(request.body.clone());
+                Response::empty(200)
+            }
+            "PUT" if request.has_query("lifecycle") => {
+                self.bucket(name).lifecycle = Some(request.body.clone());
+                Response::empty(200)
+            }
+            "PUT" => {
+                if self.create_bucket(name) {
+                    Response::empty(200)
+                } else {
+                    Response::error(
+                        409,
+                        "BucketAlreadyOwnedByYou",
+                        "The bucket you tried to create already exists, and you own it.",
+                    )
+                }
+            }
+            "HEAD" => Response::empty(200),
+            "DELETE" if request.has_query("cors") => {
+                self.bucket(name).cors = None;
+                Response::empty(204)
+            }
+            "DELETE" if request.has_query("lifecycle") => {
+                self.bucket(name).lifecycle = None;
+                Response::empty(204)
+            }
+            "DELETE" => {
+                if !self.bucket(name).objects.is_empty() {
+                    return Response::error(
+                        409,
+                        "BucketNotEmpty",
+                        "The bucket you tried to delete is not empty.",
+                    );
+                }
+                self.buckets.remove(name);
+                Response::empty(204)
+            }
+            "GET" if request.has_query("cors") => match &self.bucket(name).cors {
+                Some(cors) => Response::xml(200, cors.clone()),
+                None => Response::error(
+                    404,
+                    "NoSuchCORSConfiguration",
+                    "The CORS configuration does not exist.",
+                ),
+            },
+            "GET" if request.has_query("lifecycle") => match &self.bucket(name).lifecycle {
+                Some(lifecycle) => Response::xml(200, lifecycle.clone()),
+                None => Response::error(
+                    404,
+                    "NoSuchLifecycleConfiguration",
+                    "The lifecycle configuration does not exist.",
+                ),
+            },
+            "GET" if request.has_query("uploads") => self.list_uploads(name, request),
+            "GET" if request.query("list-type") == Some("2") => self.list_objects(name, request),
+            "POST" if request.has_query("delete") => self.delete_objects(name, request),
+            _ => Response::not_implemented(),
+        }
+    }
+
+    fn object_request(&mut self, bucket: &str, key: &str, request: &Request) -> Response {
+        let upload_id = request.query("uploadId");
+        match (request.method.as_str(), upload_id) {
+            ("PUT", Some(upload_id)) => self.upload_part(bucket, key, upload_id, request),
+            ("PUT", None) if request.header("x-amz-copy-source").is_some() => {
+                self.copy_object(bucket, key, request)
+            }
+            ("PUT", None) => self.put_object(bucket, key, request),
+            ("GET", None) => self.get_object(bucket, key, request, false),
+            ("HEAD", None) => self.get_object(bucket, key, request, true),
+            ("DELETE", Some(upload_id)) => {
+                if self.upload(bucket, key, upload_id).is_none() {
+                    return no_such_upload();
+                }
+                self.uploads.remove(upload_id);
+                Response::empty(204)
+            }
+            ("DELETE", None) => {
+                if request.header("if-match").is_some() {
+                    let existing = self.bucket(bucket).objects.get(key);
+                    if let Some(failed) = check_write_preconditions(request, existing) {
+                        return failed;
+                    }
+                }
+                self.bucket(bucket).objects.remove(key);
+                Response::empty(204)
+            }
+            ("POST", None) if request.has_query("uploads") => {
+                self.create_upload(bucket, key, request)
+            }
+            ("POST", Some(upload_id)) => self.complete_upload(bucket, key, upload_id, request),
+            _ => Response::not_implemented(),
+        }
+    }
+
+    /// The bucket called `name`, which the caller has checked exists.
+    fn bucket(&mut self, name: &str) -> &mut Bucket {
+        self.buckets
+            .get_mut(name)
+            .expect("checked before dispatching")
+    }
+
+    fn upload(&self, bucket: &str, key: &str, upload_id: &str) -> Option<&Upload> {
+        self.uploads
+            .get(upload_id)
+            .filter(|upload| upload.bucket == bucket && upload.key == key)
+    }
+
+    // --- objects ---------------------------------------------------------
+
+    fn put_object(&mut self, bucket: &str, key: &str, request: &Request) -> Response {
+        let existing = self.bucket(bucket).objects.get(key);
+        if let Some(failed) = check_write_preconditions(request, existing) {
+            return failed;
+        }
+
+        let object = Object {
+            etag: content_etag(&request.body),
+            body: request.body.clone(),
+            last_modified: now(),
+            headers: stored_headers(request),
+        };
+        let response = Response::empty(200).header("etag", object.etag.clone());
+        self.bucket(bucket).objects.insert(key.to_string(), object);
+        response
+    }
+
+    fn copy_object(&mut self, bucket: &str, key: &str, request: &Request) -> Response {
+        let source = request.header("x-amz-copy-source").unwrap_or_default();
+        let source = percent_decode(source.trim_start_matches('/'));
+        let Some((source_bucket, source_key)) = source.split_once('/') else {
+            return Response::error(400, "InvalidArgument", "Invalid copy source.");
+        };
+        let Some(source_bucket) = self.buckets.get(source_bucket) else {
+            return Response::error(404, "NoSuchBucket", "The specified bucket does not exist.");
+        };
+        let Some(source) = source_bucket.objects.get(source_key).cloned() else {
+            return Response::error(404, "NoSuchKey", "The specified key does not exist.");
+        };
+
+        let headers = if request.header("x-amz-metadata-directive") == Some("REPLACE") {
+            stored_headers(request)
+        } else {
+            source.headers
+        };
+        let object = Object {
+            body: source.body,
+            etag: source.etag,
+            last_modified: now(),
+            headers,
+        };
+        let body = format!(
+            "<CopyObjectResult><LastModified>{}</LastModified><ETag>{}</ETag></CopyObjectResult>",
+            iso8601(&object.last_modified),
+            escape(&object.etag),
+        );
+        self.bucket(bucket).objects.insert(key.to_string(), object);
+        Response::xml(200, body)
+    }
+
+    fn get_object(&mut self, bucket: &str, key: &str, request: &Request, head: bool) -> Response {
+        let Some(object) = self.bucket(bucket).objects.get(key) else {
+            return Response::error(404, "NoSuchKey", "The specified key does not exist.");
+        };
+
+        if let Some(expected) = request.header("if-match") {
+            if !etag_matches(expected, &object.etag) {
+                return Response::error(
+                    412,
+                    "PreconditionFailed",
+                    "At least one of the preconditions you specified did not hold.",
+                );
+            }
+        }
+        if let Some(unexpected) = request.header("if-none-match") {
+            if etag_matches(unexpected, &object.etag) {
+                return Response::empty(304).header("etag", object.etag.clone());
+            }
+        }
+
+        let size = object.body.len() as u64;
+        let mut response = Response::empty(200)
+            .header("etag", object.etag.clone())
+            .header("last-modified", http_date(&object.last_modified))
+            .header("accept-ranges", "bytes");
+        if !object
+            .headers
+            .iter()
+            .any(|(name, _)| name == "content-type")
+        {
+            response = response.header("content-type", "application/octet-stream");
+        }
+        for (name, value) in &object.headers {
+            response = response.header(name.clone(), value.clone());
+        }
+
+        let (body, range) = match request.header("range") {
+            Some(range) if !head => match parse_range(range, size) {
+                Some((start, end)) => (
+                    object.body.slice(start as usize..=end as usize),
+                    Some(format!("bytes {start}-{end}/{size}")),
+                ),
+                None => {
+                    return Response::error(
+                        416,
+                        "InvalidRange",
+                        "The requested range is not satisfiable.",
+                    )
+                }
+            },
+            _ => (object.body.clone(), None),
+        };
+
+        if head {
+            // No body, but the length of the one a GET would return.
+            return response.header("content-length", size.to_string());
+        }
+        response.body = body;
+        match range {
+            Some(range) => {
+                response.status = 206;
+                response.header("content-range", range)
+            }
+            None => response,
+        }
+    }
+
+    fn delete_objects(&mut self, bucket: &str, request: &Request) -> Response {
+        let body = String::from_utf8_lossy(&request.body);
+        let quiet = elements(&body, "Quiet").first().map(|quiet| quiet.trim()) == Some("true");
+
+        let mut deleted = String::new();
+        for object in elements(&body, "Object") {
+            let Some(key) = elements(object, "Key").first().map(|key| unescape(key)) else {
+                return Response::error(400, "MalformedXML", "An object has no key.");
+            };
+            self.bucket(bucket).objects.remove(&key);
+            if !quiet {
+                deleted.push_str(&format!("<Deleted><Key>{}</Key></Deleted>", escape(&key)));
+            }
+        }
+        Response::xml(200, format!("<DeleteResult>{deleted}</DeleteResult>"))
+    }
+
+    fn list_objects(&mut self, bucket: &str, request: &Request) -> Response {
+        let max_keys = request
+            .query("max-keys")
+            .and_then(|max| max.parse::<i32>().ok());
+        let options = ListOptions {
+            prefix: request.query("prefix").map(str::to_string),
+            delimiter: request.query("delimiter").map(str::to_string),
+            max_keys,
+            start_after: request.query("start-after").map(str::to_string),
+            continuation_token: request.query("continuation-token").map(str::to_string),
+        };
+        let summaries = self
+            .bucket(bucket)
+            .objects
+            .iter()
+            .map(|(key, object)| ObjectSummary {
+                key: key.clone(),
+                size: object.body.len() as i64,
+                etag: Some(object.etag.clone()),
+                last_modified: Some(object.last_modified),
+            });
+        let page = paginate(summaries, &options);
+
+        let mut contents = String::new();
+        for object in &page.objects {
+            contents.push_str(&format!(
+                "<Contents><Key>{}</Key><LastModified>{}</LastModified>\
+                 <ETag>{}</ETag><Size>{}</Size><StorageClass>STANDARD</StorageClass>\
+                 </Contents>",
+                escape(&object.key),
+                object
+                    .last_modified
+                    .as_ref()
+                    .map(iso8601)
+                    .unwrap_or_default(),
+                escape(object.etag.as_deref().unwrap_or_default()),
+                object.size,
+            ));
+        }
+        let mut prefixes = String::new();
+        for common in &page.common_prefixes {
+            prefixes.push_str(&format!(
+                "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+                escape(common)
+            ));
+        }
+        let count = page.objects.len() + page.common_prefixes.len();
+        let token = page
+            .next_continuation_token
+            .map(|token| {
+                format!(
+                    "<NextContinuationToken>{}</NextContinuationToken>",
+                    escape(&token)
+                )
+            })
+            .unwrap_or_default();
+        Response::xml(
+            200,
+            format!(
+                "<ListBucketResult><Name>{}</Name><Prefix>{}</Prefix>{}\
+                 <MaxKeys>{}</MaxKeys><KeyCount>{count}</KeyCount>\
+                 <IsTruncated>{}</IsTruncated>{token}{contents}{prefixes}\
+                 </ListBucketResult>",
+                escape(bucket),
+                escape(options.prefix.as_deref().unwrap_or_default()),
+                options
+                    .delimiter
+                    .as_deref()
+                    .filter(|delimiter| !delimiter.is_empty())
+                    .map(|delimiter| format!("<Delimiter>{}</Delimiter>", escape(delimiter)))
+                    .unwrap_or_default(),
+                max_keys.map_or(1000, |max| max.clamp(0, 1000)),
+                page.is_truncated,
+            ),
+        )
+    }
+
+    // --- multipart -------------------------------------------------------
+
+    fn create_upload(&mut self, bucket: &str, key: &str, request: &Request) -> Response {
+        let upload_id = format!("mock-upload-{}", self.next_id());
+        self.uploads.insert(
+            upload_id.clone(),
+            Upload {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                initiated: now(),
+                headers: stored_headers(request),
+                parts: BTreeMap::new(),
+            },
+        );
+        Response::xml(
+            200,
+            format!(
+                "<InitiateMultipartUploadResult><Bucket>{}</Bucket><Key>{}</Key>\
+                 <UploadId>{upload_id}</UploadId></InitiateMultipartUploadResult>",
+                escape(bucket),
+                escape(key),
+            ),
+        )
+    }
+
+    fn upload_part(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        request: &Request,
+    ) -> Response {
+        let part_number = request
+            .query("partNumber")
+            .and_then(|number| number.parse::<i32>().ok())
+            .filter(|number| (1..=10_000).contains(number));
+        let Some(part_number) = part_number else {
+            return Response::error(
+                400,
+                "InvalidArgument",
+                "Part number must be an integer between 1 and 10000, inclusive.",
+            );
+        };
+        if self.upload(bucket, key, upload_id).is_none() {
+            return no_such_upload();
+        }
+
+        let etag = content_etag(&request.body);
+        if let Some(upload) = self.uploads.get_mut(upload_id) {
+            upload
+                .parts
+                .insert(part_number, (etag.clone(), request.body.clone()));
+        }
+        Response::empty(200).header("etag", etag)
+    }
+
+    fn complete_upload(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        request: &Request,
+    ) -> Response {
+        let Some(upload) = self.upload(bucket, key, upload_id) else {
+            return no_such_upload();
+        };
+        if let Some(failed) =
+            check_write_preconditions(request, self.buckets[bucket].objects.get(key))
+        {
+            return failed;
+        }
+
+        let xml = String::from_utf8_lossy(&request.body);
+        let mut body = Vec::new();
+        let mut etags = String::new();
+        let mut previous = 0;
+        let listed = elements(&xml, "Part");
+        if listed.is_empty() {
+            return Response::error(400, "MalformedXML", "No parts were listed.");
+        }
+        for part in &listed {
+            let number = elements(part, "PartNumber")
+                .first()
+                .and_then(|number| number.trim().parse::<i32>().ok())
+                .unwrap_or_default();
+            if number <= previous {
+                return Response::error(
+                    400,
+                    "InvalidPartOrder",
+                    "The list of parts was not in ascending order.",
+                );
+            }
+            previous = number;
+
+            let listed_etag = elements(part, "ETag").first().map(|etag| unescape(etag));
+            match upload.parts.get(&number) {
+                Some((etag, data)) if listed_etag.as_deref().is_none_or(|e| e == etag) => {
+                    body.extend_from_slice(data);
+                    etags.push_str(etag);
+                }
+                _ => {
+                    return Response::error(
+                        400,
+                        "InvalidPart",
+                        "One or more of the specified parts could not be found.",
+                    )
+                }
+            }
+        }
+
+        let hash = content_etag(etags.as_bytes());
+        let object = Object {
+            etag: format!("{}-{}\"", hash.trim_end_matches('"'), listed.len()),
+            body: Bytes::from(body),
+            last_modified: now(),
+            headers: upload.headers.clone(),
+        };
+        let response = Response::xml(
+            200,
+            format!(
+                "<CompleteMultipartUploadResult><Bucket>{}</Bucket><Key>{}</Key>\
+                 <ETag>{}</ETag></CompleteMultipartUploadResult>",
+                escape(bucket),
+                escape(key),
+                escape(&object.etag),
+            ),
+        );
+        self.uploads.remove(upload_id);
+        self.bucket(bucket).objects.insert(key.to_string(), object);
+        response
+    }
+
+    fn list_uploads(&mut self, bucket: &str, request: &Request) -> Response {
+        let prefix = request.query("prefix").unwrap_or_default();
+        let mut uploads: Vec<(&String, &Upload)> = self
+            .uploads
+            .iter()
+            .filter(|(_, upload)| upload.bucket == bucket && upload.key.starts_with(prefix))
+            .collect();
+        uploads.sort_by(|a, b| (&a.1.key, a.0).cmp(&(&b.1.key, b.0)));
+
+        let listed: String = uploads
+            .into_iter()
+            .map(|(upload_id, upload)| {
+                format!(
+                    "<Upload><Key>{}</Key><UploadId>{upload_id}</UploadId>\
+                     <Initiated>{}</Initiated></Upload>",
+                    escape(&upload.key),
+                    iso8601(&upload.initiated),
+                )
+            })
+            .collect();
+        Response::xml(
+            200,
+            format!(
+                "<ListMultipartUploadsResult><Bucket>{}</Bucket>\
+                 <IsTruncated>false</IsTruncated>{listed}</ListMultipartUploadsResult>",
+                escape(bucket),
+            ),
+        )
+    }
+
+    // --- buckets ---------------------------------------------------------
+
+    fn list_buckets(&self) -> Response {
+        let buckets: String = self
+            .buckets
+            .iter()
+            .map(|(name, bucket)| {
+                format!(
+                    "<Bucket><Name>{}</Name><CreationDate>{}</CreationDate></Bucket>",
+                    escape(name),
+                    iso8601(&bucket.created),
+                )
+            })
+            .collect();
+        Response::xml(
+            200,
+            format!(
+                "<ListAllMyBucketsResult><Buckets>{buckets}</Buckets></ListAllMyBucketsResult>"
+            ),
+        )
+    }
+}
+
+fn is_create_bucket(request: &Request, key: &str) -> bool {
+    request.method == "PUT"
+        && key.is_empty()
+        && !request.has_query("cors")
+        && !request.has_query("lifecycle")
+}
+
+fn no_such_upload() -> Response {
+    Response::error(
+        404,
+        "NoSuchUpload",
+        "The specified multipart upload does not exist.",
+    )
+}
+
+/// Applies `If-Match` and `If-None-Match` to a write.
+fn check_write_preconditions(request: &Request, existing: Option<&Object>) -> Option<Response> {
+    let holds = match (request.header("if-match"), request.header("if-none-match")) {
+        (Some(expected), _) => existing.is_some_and(|object| etag_matches(expected, &object.etag)),
+        (None, Some(unexpected)) => {
+            !existing.is_some_and(|object| etag_matches(unexpected, &object.etag))
+        }
+        (None, None) => true,
+    };
+    (!holds).then(|| {
+        Response::error(
+            412,
+            "PreconditionFailed",
+            "At least one of the preconditions you specified did not hold.",
+        )
+    })
+}
+
+/// Whether a conditional header's ETag list matches `etag`. `*` matches any.
+fn etag_matches(condition: &str, etag: &str) -> bool {
+    condition
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_matches('"') == etag.trim_matches('"'))
+}
+
+fn stored_headers(request: &Request) -> Vec<(String, String)> {
+    request
+        .headers
+        .iter()
+        .filter(|(name, _)| {
+            STORED_HEADERS.contains(&name.as_str()) || name.starts_with("x-amz-meta-")
+        })
+        .cloned()
+        .collect()
+}
+
+/// Resolves a `Range` header against an object of `size` bytes, to an
+/// inclusive byte range.
+fn parse_range(range: &str, size: u64) -> Option<(u64, u64)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (size.checked_sub(suffix.min(size))?, size.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, size.checked_sub(1)?),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.min(size.checked_sub(1)?),
+        ),
+    };
+    (start <= end && start < size).then_some((start, end))
+}
+
+fn now() -> DateTime {
+    DateTime::from(SystemTime::now())
+}
+
+fn iso8601(time: &DateTime) -> String {
+    time.fmt(Format::DateTime).unwrap_or_default()
+}
+
+fn http_date(time: &DateTime) -> String {
+    time.fmt(Format::HttpDate).unwrap_or_default()
+}
+
+// --- XML -----------------------------------------------------------------------
+
+/// The contents of every `<tag>…</tag>` in `xml`. Enough for the flat request
+/// bodies S3 clients send; not a general XML parser.
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else { break };
+        found.push(&rest[..end]);
+        rest = &rest[end + close.len()..];
+    }
+    found
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#34;", "\"")
+        .replace("&amp;", "&")
+}
+
+// --- HTTP ----------------------------------------------------------------------
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    /// The percent-decoded path, without its leading slash.
+    path: String,
+    query: Vec<(String, String)>,
+    /// Lowercased names.
+    headers: Vec<(String, String)>,
+    body: Bytes,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn has_query(&self, name: &str) -> bool {
+        self.query(name).is_some()
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Bytes,
+}
+
+impl Response {
+    fn empty(status: u16) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: Bytes::new(),
+        }
+    }
+
+    fn xml(status: u16, body: impl Into<Bytes>) -> Self {
+        let mut response = Response::empty(status).header("content-type", "application/xml");
+        response.body = body.into();
+        response
+    }
+
+    fn error(status: u16, code: &str, message: &str) -> Self {
+        Response::xml(
+            status,
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <Error><Code>{code}</Code><Message>{}</Message></Error>",
+                escape(message)
+            ),
+        )
+    }
+
+    fn not_implemented() -> Self {
+        Response::error(
+            501,
+            "NotImplemented",
+            "The mock server does not implement this operation.",
+        )
+    }
+
+    fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// Serves requests on one connection until the client closes it.
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let mut stream = BufReader::new(stream);
+    while let Ok(Some(request)) = read_request(&mut stream).await {
+        let response = state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .handle(&request);
+        let head = request.method == "HEAD";
+        if write_response(stream.get_mut(), &response, head)
+            .await
+            .is_err()
+            || request.header("connection") == Some("close")
+        {
+            return;
+        }
+    }
+}
+
+/// Reads one request, or `None` once the client has hung up.
+async fn read_request(stream: &mut BufReader<TcpStream>) -> std::io::Result<Option<Request>> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let method = method.to_string();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = percent_decode(path.trim_start_matches('/'));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        stream.read_line(&mut line).await?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value): &(String, String)| value.as_str())
+    };
+
+    if header("expect").is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue")) {
+        stream
+            .get_mut()
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .await?;
+    }
+    let body = if header("transfer-encoding").is_some_and(|te| te.contains("chunked")) {
+        read_chunked(stream).await?
+    } else {
+        let length = header("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await?;
+        body
+    };
+
+    Ok(Some(Request {
+        method,
+        path,
+        query,
+        headers,
+        body: Bytes::from(body),
+    }))
+}
+
+async fn read_chunked(stream: &mut BufReader<TcpStream>) -> std::io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        stream.read_line(&mut line).await?;
+        let size = line.trim_end().split(';').next().unwrap_or_default();
+        let size =
+            usize::from_str_radix(size.trim(), 16).map_err(|_| invalid("malformed chunk size"))?;
+        if size == 0 {
+            // Trailers, up to the blank line that ends the body.
+            loop {
+                line.clear();
+                if stream.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        stream.read_exact(&mut body[start..]).await?;
+        line.clear();
+        stream.read_line(&mut line).await?;
+    }
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    response: &Response,
+    head: bool,
+) -> std::io::Result<()> {
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        reason(response.status)
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{name}: {value}\r\n"));
+    }
+    if !response
+        .headers
+        .iter()
+        .any(|(name, _)| name == "content-length")
+    {
+        out.push_str(&format!("content-length: {}\r\n", response.body.len()));
+    }
+    out.push_str("\r\n");
+
+    stream.write_all(out.as_bytes()).await?;
+    if !head {
+        stream.write_all(&response.body).await?;
+    }
+    stream.flush().await
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        206 => "Partial Content",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        404 => "Not Found",
+        409 => "Conflict",
+        412 => "Precondition Failed",
+        416 => "Range Not Satisfiable",
+        501 => "Not Implemented",
+        _ => "",
+    }
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_resolve_like_s3() {
+        assert_eq!(parse_range("bytes=0-4", 10), Some((0, 4)));
+        assert_eq!(parse_range("bytes=5-", 10), Some((5, 9)));
+        assert_eq!(parse_range("bytes=-3", 10), Some((7, 9)));
+        assert_eq!(parse_range("bytes=8-100", 10), Some((8, 9)));
+        assert_eq!(parse_range("bytes=10-12", 10), None);
+        assert_eq!(parse_range("bytes=0-0", 0), None);
+    }
+
+    #[test]
+    fn request_xml_is_read_element_by_element() {
+        let xml = "<Delete><Object><Key>a&amp;b</Key></Object>\
+                   <Object><Key>c</Key></Object><Quiet>true</Quiet></Delete>";
+        let keys: Vec<_> = elements(xml, "Object")
+            .into_iter()
+            .flat_map(|object| elements(object, "Key"))
+            .map(unescape)
+            .collect();
+        assert_eq!(keys, ["a&b", "c"]);
+        assert_eq!(elements(xml, "Quiet"), ["true"]);
+    }
+
+    #[test]
+    fn percent_decoding_leaves_stray_percents_alone() {
+        assert_eq!(percent_decode("caf%C3%A9%20x"), "caf\u{e9} x");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
@@ -4,12 +4,15 @@ use std::path::Path;
 
 use aws_sdk_s3::primitives::{ByteStream, Length};
 use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart as SdkCompletedPart};
-use futures::stream::StreamExt;
+use bytes::Bytes;
+use futures::stream::{StreamExt, TryStreamExt};
+use tokio::io::AsyncReadExt;
 
 use crate::body::IntoBody;
 use crate::client::R2Client;
 use crate::error::{from_sdk, Error, Result};
 use crate::object::validate_key;
+use crate::trace::{record, RecordStatus};
 use crate::types::{
     CompletedPart, MultipartOptions, MultipartUpload, PutOptions, PutOutcome, MAX_PARTS,
     MIN_PART_SIZE,
@@ -22,12 +25,34 @@ impl R2Client {
     /// are sent in one request; larger ones are split into concurrent parts.
     /// Either way the body is streamed from disk rather than buffered in
     /// memory.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, path = %path.display())
+        )
+    )]
     pub async fn upload_file(&self, key: &str, path: &Path) -> Result<PutOutcome> {
-        self.upload_file_with(key, path, MultipartOptions::new())
-            .await
+        // Boxed for the same reason as the multipart branch of
+        // `upload_file_with`.
+        Box::pin(self.upload_file_with(key, path, MultipartOptions::new())).await
     }
 
     /// Uploads a local file with explicit part size, concurrency and metadata.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                path = %path.display(),
+                bytes = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn upload_file_with(
         &self,
         key: &str,
@@ -37,6 +62,7 @@ impl R2Client {
         validate_key(key)?;
 
         let size = file_size(path).await?;
+        record!("bytes", size);
         if size < options.threshold {
             let body = ByteStream::from_path(path).await.map_err(|err| {
                 Error::file(path, "could not open the file for upload", Some(err))
@@ -44,7 +70,11 @@ impl R2Client {
             return self.put_object_with(key, body, options.put_options).await;
         }
 
-        self.multipart_upload_file(key, path, options).await
+        // Boxed to keep this future's type shallow: the multipart future nests
+        // parts, retries and (with the `tracing` feature) spans deeply enough
+        // that wrapping it once more overflows rustc's layout query depth in
+        // callers' crates.
+        Box::pin(self.multipart_upload_file(key, path, options)).await
     }
 
     /// Uploads a local file using multipart regardless of its size.
@@ -57,6 +87,21 @@ impl R2Client {
     /// die mid-upload — the incomplete upload keeps holding storage until it is
     /// cleaned up; [`list_multipart_uploads`](R2Client::list_multipart_uploads)
     /// finds those, and a lifecycle rule can expire them automatically.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                path = %path.display(),
+                bytes = tracing::field::Empty,
+                parts = tracing::field::Empty,
+                upload_id = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn multipart_upload_file(
         &self,
         key: &str,
@@ -80,15 +125,20 @@ impl R2Client {
             ));
         }
 
-        let part_size = effective_part_size(size, options.part_size)?;
+        let (put_options, pipeline) = self.pipeline(key, options.put_options).await?;
+        let part_size = pipeline.part_size(effective_part_size(size, options.part_size)?);
         let part_count = size.div_ceil(part_size);
+        record!("bytes", size);
 
-        let upload = self
-            .create_multipart_upload(key, options.put_options)
-            .await?;
+        let preconditions = (
+            put_options.if_match.clone(),
+            put_options.if_none_match.clone(),
+        );
+        let upload = self.start_multipart_upload(key, put_options).await?;
+        record!("upload_id", upload.upload_id.as_str());
 
-        let result = self
-            .upload_all_parts(
+        let result = if pipeline.is_passthrough() {
+            self.upload_all_parts(
                 key,
                 &upload.upload_id,
                 path,
@@ -97,7 +147,21 @@ impl R2Client {
                 part_count,
                 options.concurrency,
             )
-            .await;
+            .await
+        } else {
+            self.upload_piped_parts(
+                key,
+                &upload.upload_id,
+                path,
+                &pipeline,
+                part_size,
+                options.concurrency,
+            )
+            .await
+        };
+        if let Ok(parts) = &result {
+            record!("parts", parts.len());
+        }
 
         let parts = match result {
             Ok(parts) => parts,
@@ -114,12 +178,27 @@ impl R2Client {
             }
         };
 
-        self.complete_multipart_upload(key, &upload.upload_id, parts)
-            .await
+        let result = self
+            .finish_multipart_upload(key, &upload.upload_id, parts, preconditions)
+            .await;
+        if let Err(err) = &result {
+            // A refused precondition leaves the parts stored, yet the upload
+            // can never complete.
+            if err.is_precondition_failed() {
+                if let Err(abort_err) = self.abort_multipart_upload(key, &upload.upload_id).await {
+                    log::warn!(
+                        "failed to abort multipart upload {} for {key}: {abort_err}",
+                        upload.upload_id
+                    );
+                }
+            }
+        }
+        result
     }
 
     /// Uploads every part, at most `concurrency` in flight, and returns them
-    /// ordered by part number.
+    /// ordered by part number. Each part is retried on its own under the
+    /// client's [`RetryPolicy`](crate::RetryPolicy).
     #[allow(clippy::too_many_arguments)]
     async fn upload_all_parts(
         &self,
@@ -139,7 +218,9 @@ impl R2Client {
                 let length = part_size.min(size - offset);
                 let part_number = (index + 1) as i32;
 
-                async move {
+                // A retried part is read from disk afresh: a body stream
+                // cannot be rewound once the SDK has given up on it.
+                self.retry_policy.run("upload_part", move || async move {
                     let body = ByteStream::read_from()
                         .path(path)
                         .offset(offset)
@@ -151,7 +232,7 @@ impl R2Client {
                         })?;
 
                     self.upload_part(key, upload_id, part_number, body).await
-                }
+                })
             })
             .buffer_unordered(concurrency)
             .collect::<Vec<_>>()
@@ -163,14 +244,149 @@ impl R2Client {
         Ok(parts)
     }
 
+    /// Splits the codec and cipher a file upload passes through off its
+    /// options, wrapping a data key when the client encrypts.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    async fn pipeline(&self, key: &str, options: PutOptions) -> Result<(PutOptions, Pipeline)> {
+        #[allow(unused_mut)]
+        let mut pipeline = Pipeline::default();
+        #[cfg(feature = "compression")]
+        let options = {
+            let (options, compression) = options.take_compression()?;
+            pipeline.compression = compression;
+            options
+        };
+        #[cfg(feature = "encryption")]
+        let options = {
+            let (options, data_key) = self.seal_options(key, options).await?;
+            pipeline.data_key = data_key;
+            options
+        };
+        Ok((options, pipeline))
+    }
+
+    /// Uploads a file through its pipeline, part by part as the bytes come
+    /// out, so neither the file nor its transformed form is ever held whole:
+    /// at most `concurrency` parts are in memory at once. Every part but the
+    /// last is exactly `part_size` bytes, as R2 requires.
+    async fn upload_piped_parts(
+        &self,
+        key: &str,
+        upload_id: &str,
+        path: &Path,
+        pipeline: &Pipeline,
+        part_size: u64,
+        concurrency: usize,
+    ) -> Result<Vec<CompletedPart>> {
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|err| Error::file(path, "could not open the file for upload", Some(err)))?;
+        let reader = pipeline.reader(file);
+
+        // One part is read ahead, since a cipher must know which part is the
+        // last before it seals it.
+        let chunks = futures::stream::try_unfold(
+            Some((reader, 1, None)),
+            move |state: Option<(PipeReader, i32, Option<Vec<u8>>)>| async move {
+                let Some((mut reader, part_number, current)) = state else {
+                    return Ok(None);
+                };
+                let current = match current {
+                    Some(current) => current,
+                    None => read_part(&mut reader, part_size, path).await?,
+                };
+                let next = read_part(&mut reader, part_size, path).await?;
+                if part_number as u64 > MAX_PARTS {
+                    return Err(Error::invalid_argument(
+                        "part_size",
+                        format!("the transformed file needs more than {MAX_PARTS} parts"),
+                    ));
+                }
+                let last = next.is_empty();
+                let state = (!last).then_some((reader, part_number + 1, Some(next)));
+                Ok(Some(((part_number, current, last), state)))
+            },
+        );
+
+        let mut parts: Vec<CompletedPart> = chunks
+            .map_ok(|(part_number, data, last)| {
+                let data = pipeline.seal(data, part_number, part_size, last);
+                // Retries resend the part from memory: there is no file
+                // offset to re-read it from, and re-sealing a changed file
+                // would reuse nonces.
+                self.retry_policy.run("upload_part", move || {
+                    let data = data.clone();
+                    async move { self.upload_part(key, upload_id, part_number, data).await }
+                })
+            })
+            .try_buffer_unordered(concurrency)
+            .try_collect()
+            .await?;
+
+        parts.sort_by_key(|part| part.part_number);
+        Ok(parts)
+    }
+
     /// Starts a multipart upload and returns its handle.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                upload_id = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn create_multipart_upload(
         &self,
         key: &str,
         options: PutOptions,
     ) -> Result<MultipartUpload> {
         validate_key(key)?;
+        #[cfg(feature = "compression")]
+        if options.compression.is_some() {
+            return Err(Error::invalid_argument(
+                "compression",
+                "parts uploaded by hand are sent as given; compress them yourself and set \
+                 content_encoding, or use multipart_upload_file",
+            ));
+        }
+        let precondition = if options.if_match.is_some() {
+            Some("if_match")
+        } else if options.if_none_match.is_some() {
+            Some("if_none_match")
+        } else {
+            None
+        };
+        if let Some(argument) = precondition {
+            return Err(Error::invalid_argument(
+                argument,
+                "preconditions apply on completion, which this handle cannot carry; use \
+                 multipart_upload_file",
+            ));
+        }
+        #[cfg(feature = "encryption")]
+        if self.encryption.is_some() {
+            return Err(Error::invalid_argument(
+                "encryption",
+                "parts uploaded by hand would bypass client-side encryption; use \
+                 multipart_upload_file",
+            ));
+        }
+        self.start_multipart_upload(key, options).await
+    }
 
+    /// [`create_multipart_upload`](R2Client::create_multipart_upload) without
+    /// its guards, for uploads whose parts this crate seals itself.
+    async fn start_multipart_upload(
+        &self,
+        key: &str,
+        options: PutOptions,
+    ) -> Result<MultipartUpload> {
         let mut request = self
             .client
             .create_multipart_upload()
@@ -187,6 +403,8 @@ impl R2Client {
         }
 
         let response = request
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("create_multipart_upload", err))?;
@@ -194,6 +412,7 @@ impl R2Client {
         let upload_id = response
             .upload_id
             .ok_or_else(|| Error::multipart(key, "R2 did not return an upload ID"))?;
+        record!("upload_id", upload_id.as_str());
 
         Ok(MultipartUpload {
             key: key.to_string(),
@@ -209,6 +428,21 @@ impl R2Client {
     /// except the last must also be *exactly the same size*, so pick one part
     /// size and use it throughout. The higher-level
     /// [`multipart_upload_file`](R2Client::multipart_upload_file) already does.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                upload_id = %upload_id,
+                part_number = part_number,
+                bytes = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn upload_part(
         &self,
         key: &str,
@@ -223,6 +457,9 @@ impl R2Client {
             ));
         }
 
+        let body = body.into_body();
+        record!("bytes", body.size_hint().1);
+
         let response = self
             .client
             .upload_part()
@@ -230,7 +467,9 @@ impl R2Client {
             .key(key)
             .upload_id(upload_id)
             .part_number(part_number)
-            .body(body.into_body())
+            .body(body)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("upload_part", err))?;
@@ -248,6 +487,35 @@ impl R2Client {
         key: &str,
         upload_id: &str,
         parts: Vec<CompletedPart>,
+    ) -> Result<PutOutcome> {
+        self.finish_multipart_upload(key, upload_id, parts, (None, None))
+            .await
+    }
+
+    /// [`complete_multipart_upload`](R2Client::complete_multipart_upload)
+    /// with the `If-Match` and `If-None-Match` preconditions of the upload's
+    /// options.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "complete_multipart_upload",
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                upload_id = %upload_id,
+                parts = parts.len(),
+                status = tracing::field::Empty,
+            )
+        )
+    )]
+    async fn finish_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+        (if_match, if_none_match): (Option<String>, Option<String>),
     ) -> Result<PutOutcome> {
         if parts.is_empty() {
             return Err(Error::multipart(
@@ -280,6 +548,10 @@ impl R2Client {
             .key(key)
             .upload_id(upload_id)
             .multipart_upload(completed)
+            .set_if_match(if_match)
+            .set_if_none_match(if_none_match)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("complete_multipart_upload", err))?;
@@ -291,12 +563,27 @@ impl R2Client {
     }
 
     /// Cancels a multipart upload and releases the parts already stored.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                upload_id = %upload_id,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
         self.client
             .abort_multipart_upload()
             .bucket(&self.bucket)
             .key(key)
             .upload_id(upload_id)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("abort_multipart_upload", err))?;
@@ -309,6 +596,18 @@ impl R2Client {
     /// Useful for cleaning up storage held by interrupted uploads. Note this
     /// returns uploads started by anyone with access to the bucket, not just
     /// this process — check the keys before aborting any of them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                uploads = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn list_multipart_uploads(&self) -> Result<Vec<MultipartUpload>> {
         let mut uploads = Vec::new();
         let mut markers: Option<(String, Option<String>)> = None;
@@ -322,6 +621,8 @@ impl R2Client {
             }
 
             let response = request
+                .customize()
+                .record_status()
                 .send()
                 .await
                 .map_err(|err| from_sdk("list_multipart_uploads", err))?;
@@ -350,10 +651,81 @@ impl R2Client {
             }
         }
 
+        record!("uploads", uploads.len());
         Ok(uploads)
     }
 }
 
+/// A file's bytes as they become part bodies, read in order.
+type PipeReader = std::pin::Pin<Box<dyn tokio::io::AsyncRead + Send + Sync>>;
+
+/// What a file passes through on its way into parts: nothing, a codec, a
+/// cipher, or both, compressed first.
+#[derive(Debug, Default)]
+struct Pipeline {
+    #[cfg(feature = "compression")]
+    compression: Option<crate::Compression>,
+    #[cfg(feature = "encryption")]
+    data_key: Option<crate::encryption::DataKey>,
+}
+
+impl Pipeline {
+    /// Whether parts can be read straight from their file offsets.
+    fn is_passthrough(&self) -> bool {
+        #[cfg(feature = "compression")]
+        if self.compression.is_some() {
+            return false;
+        }
+        #[cfg(feature = "encryption")]
+        if self.data_key.is_some() {
+            return false;
+        }
+        true
+    }
+
+    /// Rounds a part size up to whole frames, so that each part seals on
+    /// its own.
+    fn part_size(&self, part_size: u64) -> u64 {
+        #[cfg(feature = "encryption")]
+        if self.data_key.is_some() {
+            use crate::encryption::FRAME_SIZE;
+            return part_size.div_ceil(FRAME_SIZE) * FRAME_SIZE;
+        }
+        part_size
+    }
+
+    fn reader(&self, file: tokio::fs::File) -> PipeReader {
+        let file = tokio::io::BufReader::new(file);
+        #[cfg(feature = "compression")]
+        if let Some(compression) = self.compression {
+            return compression.encoder(file);
+        }
+        Box::pin(file)
+    }
+
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn seal(&self, data: Vec<u8>, part_number: i32, part_size: u64, last: bool) -> Bytes {
+        #[cfg(feature = "encryption")]
+        if let Some(data_key) = &self.data_key {
+            let first_frame =
+                (part_number as u64 - 1) * (part_size / crate::encryption::FRAME_SIZE);
+            return Bytes::from(data_key.seal(&data, first_frame, last));
+        }
+        Bytes::from(data)
+    }
+}
+
+/// Reads up to one part from a pipeline; empty once it has ended.
+async fn read_part(reader: &mut PipeReader, part_size: u64, path: &Path) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader
+        .take(part_size)
+        .read_to_end(&mut data)
+        .await
+        .map_err(|err| Error::file(path, "could not read the file for upload", Some(err)))?;
+    Ok(data)
+}
+
 /// Reads a local file's size, reporting a missing or unreadable file as
 /// [`Error::File`] rather than a bare I/O error with no path in it.
 async fn file_size(path: &Path) -> Result<u64> {
@@ -3,18 +3,32 @@
 use std::path::{Component, Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 
+use aws_sdk_s3::operation::get_object::GetObjectOutput;
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use tokio::io::AsyncWriteExt;
 
 use crate::body::IntoBody;
 use crate::client::R2Client;
-use crate::error::{from_sdk, Error, Result};
+use crate::error::{from_sdk, Error, ErrorCode, Result};
+use crate::trace::{record, RecordStatus};
 use crate::types::{
-    DeleteFailure, DeleteReport, ListOptions, ListPage, ObjectMetadata, ObjectSummary, PutOptions,
-    PutOutcome, MAX_DELETE_BATCH,
+    DateTime, DeleteFailure, DeleteReport, ListOptions, ListPage, ObjectMetadata, ObjectSummary,
+    PutOptions, PutOutcome, MAX_DELETE_BATCH,
 };
 
+/// The preconditions of a read, as the `object_store` adapter passes them
+/// through.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReadConditions {
+    pub(crate) if_match: Option<String>,
+    pub(crate) if_none_match: Option<String>,
+    pub(crate) if_modified_since: Option<DateTime>,
+    pub(crate) if_unmodified_since: Option<DateTime>,
+}
+
 impl R2Client {
     /// Stores an object, guessing its content type from the key's extension.
     ///
@@ -28,11 +42,42 @@ impl R2Client {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key)
+        )
+    )]
     pub async fn put_object(&self, key: &str, body: impl IntoBody) -> Result<PutOutcome> {
         self.put_object_with(key, body, PutOptions::new()).await
     }
 
     /// Stores an object with explicit headers and user metadata.
+    ///
+    /// With the `compression` feature, `PutOptions::compression` compresses
+    /// the body first. The compressed body is held in memory, since a single
+    /// request must state its length up front; for large files,
+    /// [`upload_file_with`](R2Client::upload_file_with) compresses part by
+    /// part instead.
+    ///
+    /// With the `encryption` feature and a client built with a key provider,
+    /// the body is then encrypted, again in memory, and the wrapped data key
+    /// is added to the user metadata.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                bytes = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn put_object_with(
         &self,
         key: &str,
@@ -40,24 +85,39 @@ impl R2Client {
         options: PutOptions,
     ) -> Result<PutOutcome> {
         validate_key(key)?;
+        let content_encoding = options.resolved_content_encoding()?;
+
+        let body = body.into_body();
+        #[cfg(feature = "compression")]
+        let body = match options.compression {
+            Some(compression) => compression.compress(body).await?,
+            None => body,
+        };
+        #[cfg(feature = "encryption")]
+        let (body, options) = self.seal_body(key, body, options).await?;
+        record!("bytes", body.size_hint().1);
 
         let mut request = self
             .client
             .put_object()
             .bucket(&self.bucket)
             .key(key)
-            .body(body.into_body())
+            .body(body)
             .content_type(options.resolved_content_type(key))
             .set_cache_control(options.cache_control.clone())
             .set_content_disposition(options.content_disposition.clone())
-            .set_content_encoding(options.content_encoding.clone())
-            .set_content_language(options.content_language.clone());
+            .set_content_encoding(content_encoding)
+            .set_content_language(options.content_language.clone())
+            .set_if_match(options.if_match.clone())
+            .set_if_none_match(options.if_none_match.clone());
 
         for (name, value) in &options.metadata {
             request = request.metadata(name, value);
         }
 
         let response = request
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("put_object", err))?;
@@ -71,18 +131,48 @@ impl R2Client {
     /// Fetches an object's full body into memory.
     ///
     /// Fails with [`Error::ObjectNotFound`] when the key does not exist.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, bytes = tracing::field::Empty)
+        )
+    )]
     pub async fn get_object(&self, key: &str) -> Result<Vec<u8>> {
         let stream = self.get_object_stream(key).await?;
         let bytes = stream
             .collect()
             .await
-            .map_err(|err| Error::Body(Box::new(err)))?;
-        Ok(bytes.into_bytes().to_vec())
+            .map_err(|err| Error::body(key, err))?
+            .into_bytes()
+            .to_vec();
+        record!("bytes", bytes.len());
+        Ok(bytes)
     }
 
     /// Fetches a byte range of an object, `start` and `end` both inclusive.
     ///
     /// Passing `None` for `end` reads to the end of the object.
+    ///
+    /// With the `encryption` feature and a client built with a key provider,
+    /// only the encrypted frames holding the range are fetched and decrypted,
+    /// and `start` and `end` count plaintext bytes.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                start = start,
+                end = ?end,
+                bytes = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn get_object_range(
         &self,
         key: &str,
@@ -100,6 +190,13 @@ impl R2Client {
             }
         }
 
+        #[cfg(feature = "encryption")]
+        if self.encryption.is_some() {
+            let bytes = self.get_encrypted_range(key, start, end).await?;
+            record!("bytes", bytes.len());
+            return Ok(bytes);
+        }
+
         let range = match end {
             Some(end) => format!("bytes={start}-{end}"),
             None => format!("bytes={start}-"),
@@ -111,6 +208,8 @@ impl R2Client {
             .bucket(&self.bucket)
             .key(key)
             .range(range)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| self.map_object_error("get_object", key, err))?;
@@ -119,39 +218,42 @@ impl R2Client {
             .body
             .collect()
             .await
-            .map_err(|err| Error::Body(Box::new(err)))?;
-        Ok(bytes.into_bytes().to_vec())
+            .map_err(|err| Error::body(key, err))?
+            .into_bytes()
+            .to_vec();
+        record!("bytes", bytes.len());
+        Ok(bytes)
     }
 
     /// Opens an object's body as a stream, without buffering it in memory.
+    ///
+    /// With the `compression` feature and a client built with
+    /// `decompress(true)`, a compressed body is decompressed as it streams.
+    /// With the `encryption` feature and a client built with a key provider,
+    /// an encrypted body is decrypted as it streams, and a body that was
+    /// altered or cut short fails with [`Error::Body`] when the stream reaches
+    /// the damage.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, status = tracing::field::Empty)
+        )
+    )]
     pub async fn get_object_stream(&self, key: &str) -> Result<ByteStream> {
-        validate_key(key)?;
-
-        let response = self
-            .client
-            .get_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await
-            .map_err(|err| self.map_object_error("get_object", key, err))?;
-
-        Ok(response.body)
+        let (body, _etag) = self.open_object(key).await?;
+        Ok(body)
     }
 
     /// Reads an object's metadata without transferring its body.
+    ///
+    /// On a client that decrypts, the content length of an encrypted object
+    /// is its plaintext length.
     pub async fn head_object(&self, key: &str) -> Result<ObjectMetadata> {
-        validate_key(key)?;
-
         let response = self
-            .client
-            .head_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await
-            .map_err(|err| self.map_object_error("head_object", key, err))?;
-
+            .head_object_output(key, ReadConditions::default())
+            .await?;
         Ok(ObjectMetadata {
             content_length: response.content_length.unwrap_or_default(),
             content_type: response.content_type,
@@ -168,6 +270,14 @@ impl R2Client {
     /// Reports whether an object exists.
     ///
     /// A permission failure is an error, not `false`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key)
+        )
+    )]
     pub async fn object_exists(&self, key: &str) -> Result<bool> {
         match self.head_object(key).await {
             Ok(_) => Ok(true),
@@ -177,12 +287,38 @@ impl R2Client {
     }
 
     /// Copies an object within this bucket.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                source_key = %source_key,
+                destination_key = %destination_key,
+            )
+        )
+    )]
     pub async fn copy_object(&self, source_key: &str, destination_key: &str) -> Result<()> {
         self.copy_object_from(&self.bucket.clone(), source_key, destination_key)
             .await
     }
 
     /// Copies an object from another bucket on the same account into this one.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                source_bucket = %source_bucket,
+                source_key = %source_key,
+                destination_key = %destination_key,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn copy_object_from(
         &self,
         source_bucket: &str,
@@ -206,6 +342,8 @@ impl R2Client {
             .bucket(&self.bucket)
             .key(destination_key)
             .copy_source(copy_source)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| {
@@ -227,6 +365,14 @@ impl R2Client {
     /// Deletes a single object.
     ///
     /// Deleting a key that does not exist succeeds, matching S3 semantics.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, status = tracing::field::Empty)
+        )
+    )]
     pub async fn delete_object(&self, key: &str) -> Result<()> {
         validate_key(key)?;
 
@@ -234,6 +380,37 @@ impl R2Client {
             .delete_object()
             .bucket(&self.bucket)
             .key(key)
+            .customize()
+            .record_status()
+            .send()
+            .await
+            .map_err(|err| from_sdk("delete_object", err))?;
+        Ok(())
+    }
+
+    /// Deletes an object only if its ETag is still `etag`.
+    ///
+    /// Fails with `412 Precondition Failed` (see
+    /// [`Error::is_precondition_failed`]) when the object has changed or no
+    /// longer exists.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, status = tracing::field::Empty)
+        )
+    )]
+    pub async fn delete_object_if_match(&self, key: &str, etag: &str) -> Result<()> {
+        validate_key(key)?;
+
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .if_match(etag)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("delete_object", err))?;
@@ -247,13 +424,34 @@ impl R2Client {
     /// key never leaves a partially-applied delete behind. Per-key failures
     /// reported by the service are collected in the returned [`DeleteReport`]
     /// rather than aborting the remaining batches; check
-    /// [`all_succeeded`](DeleteReport::all_succeeded).
+    /// [`all_succeeded`](DeleteReport::all_succeeded). So is every key of a
+    /// batch whose request failed outright, with the request's error code and
+    /// message.
+    ///
+    /// Under a [`RetryPolicy`](crate::RetryPolicy), keys that failed with a
+    /// retryable code such as `SlowDown` are re-submitted on their own, and
+    /// only land in [`DeleteReport::failed`] once the policy gives up.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                keys = tracing::field::Empty,
+                deleted = tracing::field::Empty,
+                failed = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn delete_objects<I, K>(&self, keys: I) -> Result<DeleteReport>
     where
         I: IntoIterator<Item = K>,
         K: Into<String>,
     {
         let keys: Vec<String> = keys.into_iter().map(Into::into).collect();
+        record!("keys", keys.len());
 
         // Validate everything up front: a bad key in batch three must not
         // leave batches one and two irreversibly deleted.
@@ -262,59 +460,128 @@ impl R2Client {
         }
 
         let mut report = DeleteReport::default();
+        let mut pending = keys;
+        let mut attempt = 1;
 
-        for batch in keys.chunks(MAX_DELETE_BATCH) {
-            let mut identifiers = Vec::with_capacity(batch.len());
-            for key in batch {
-                identifiers.push(
-                    ObjectIdentifier::builder()
-                        .key(key)
-                        .build()
-                        .map_err(|err| Error::invalid_argument("keys", err.to_string()))?,
-                );
+        loop {
+            let mut retry = Vec::new();
+
+            for batch in pending.chunks(MAX_DELETE_BATCH) {
+                let result = self
+                    .retry_policy
+                    .run("delete_objects", || self.delete_batch(batch))
+                    .await;
+                let (deleted, failed) = match result {
+                    Ok(outcome) => outcome,
+                    // Earlier batches are already gone, so a request that
+                    // failed outright is reported against its keys rather
+                    // than returned, which would lose track of them.
+                    Err(err) => {
+                        let code = err.code().map(|code| code.as_str().to_string());
+                        let message = err.to_string();
+                        report.failed.extend(batch.iter().map(|key| DeleteFailure {
+                            key: key.clone(),
+                            code: code.clone(),
+                            message: Some(message.clone()),
+                        }));
+                        continue;
+                    }
+                };
+
+                report.deleted.extend(deleted);
+                for failure in failed {
+                    // A key the service could not delete *this time* is
+                    // re-submitted on its own; the rest of the batch is done.
+                    let transient = failure
+                        .code
+                        .as_deref()
+                        .is_some_and(|code| ErrorCode::parse(code).is_retryable());
+                    if transient && self.retry_policy.allows_retry(attempt) {
+                        retry.push(failure.key);
+                    } else {
+                        report.failed.push(failure);
+                    }
+                }
             }
 
-            let delete = Delete::builder()
-                .set_objects(Some(identifiers))
-                .build()
-                .map_err(|err| Error::invalid_argument("keys", err.to_string()))?;
-
-            let response = self
-                .client
-                .delete_objects()
-                .bucket(&self.bucket)
-                .delete(delete)
-                .send()
-                .await
-                .map_err(|err| from_sdk("delete_objects", err))?;
-
-            report.deleted.extend(
-                response
-                    .deleted
-                    .unwrap_or_default()
-                    .into_iter()
-                    .filter_map(|deleted| deleted.key),
-            );
-            report.failed.extend(
-                response
-                    .errors
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|error| DeleteFailure {
-                        key: error.key.unwrap_or_default(),
-                        code: error.code,
-                        message: error.message,
-                    }),
+            if retry.is_empty() {
+                break;
+            }
+            log::debug!(
+                "{} key(s) failed to delete on attempt {attempt}, retrying",
+                retry.len()
             );
+            self.retry_policy.back_off(attempt).await;
+            pending = retry;
+            attempt += 1;
         }
 
+        record!("deleted", report.deleted.len());
+        record!("failed", report.failed.len());
         Ok(report)
     }
 
+    /// Sends one `DeleteObjects` request, returning the deleted keys and the
+    /// per-key failures.
+    async fn delete_batch(&self, batch: &[String]) -> Result<(Vec<String>, Vec<DeleteFailure>)> {
+        let mut identifiers = Vec::with_capacity(batch.len());
+        for key in batch {
+            identifiers.push(
+                ObjectIdentifier::builder()
+                    .key(key)
+                    .build()
+                    .map_err(|err| Error::invalid_argument("keys", err.to_string()))?,
+            );
+        }
+
+        let delete = Delete::builder()
+            .set_objects(Some(identifiers))
+            .build()
+            .map_err(|err| Error::invalid_argument("keys", err.to_string()))?;
+
+        let response = self
+            .client
+            .delete_objects()
+            .bucket(&self.bucket)
+            .delete(delete)
+            .customize()
+            .record_status()
+            .send()
+            .await
+            .map_err(|err| from_sdk("delete_objects", err))?;
+
+        let deleted = response
+            .deleted
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|deleted| deleted.key)
+            .collect();
+        let failed = response
+            .errors
+            .unwrap_or_default()
+            .into_iter()
+            .map(|error| DeleteFailure {
+                key: error.key.unwrap_or_default(),
+                code: error.code,
+                message: error.message,
+            })
+            .collect();
+
+        Ok((deleted, failed))
+    }
+
     /// Deletes every object under a prefix.
     ///
     /// Refuses an empty prefix — use [`delete_objects`](R2Client::delete_objects)
     /// with an explicit list if you really mean to empty the bucket.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, prefix = %prefix)
+        )
+    )]
     pub async fn delete_prefix(&self, prefix: &str) -> Result<DeleteReport> {
         if prefix.is_empty() {
             return Err(Error::invalid_argument(
@@ -341,6 +608,19 @@ impl R2Client {
     ///
     /// Follow [`ListPage::next_continuation_token`] to page through the rest,
     /// or use [`list_all_objects`](R2Client::list_all_objects).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                prefix = ?options.prefix,
+                objects = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn list_objects(&self, options: ListOptions) -> Result<ListPage> {
         let response = self
             .client
@@ -351,11 +631,13 @@ impl R2Client {
             .set_max_keys(options.max_keys)
             .set_start_after(options.start_after)
             .set_continuation_token(options.continuation_token)
+            .customize()
+            .record_status()
             .send()
             .await
             .map_err(|err| from_sdk("list_objects_v2", err))?;
 
-        let objects = response
+        let objects: Vec<ObjectSummary> = response
             .contents
             .unwrap_or_default()
             .into_iter()
@@ -376,6 +658,7 @@ impl R2Client {
             .filter_map(|prefix| prefix.prefix)
             .collect();
 
+        record!("objects", objects.len());
         Ok(ListPage {
             objects,
             common_prefixes,
@@ -385,6 +668,14 @@ impl R2Client {
     }
 
     /// Lists every object under an optional prefix, following pagination.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, prefix = ?prefix, objects = tracing::field::Empty)
+        )
+    )]
     pub async fn list_all_objects(&self, prefix: Option<&str>) -> Result<Vec<ObjectSummary>> {
         let mut objects = Vec::new();
         let mut continuation_token = None;
@@ -396,7 +687,10 @@ impl R2Client {
             }
             options.continuation_token = continuation_token;
 
-            let page = self.list_objects(options).await?;
+            let page = self
+                .retry_policy
+                .run("list_objects_v2", || self.list_objects(options.clone()))
+                .await?;
             objects.extend(page.objects);
 
             match page.next_continuation_token {
@@ -407,10 +701,73 @@ impl R2Client {
             }
         }
 
+        record!("objects", objects.len());
         Ok(objects)
     }
 
+    /// Streams every object of a listing, fetching the next page only as the
+    /// stream reaches it, so memory stays at one page however many objects
+    /// match. Each page is retried under the client's
+    /// [`RetryPolicy`](crate::RetryPolicy).
+    ///
+    /// ```no_run
+    /// # async fn run(client: cloudflare_r2_rs::R2Client) -> cloudflare_r2_rs::Result<()> {
+    /// use cloudflare_r2_rs::ListOptions;
+    /// use futures::TryStreamExt;
+    ///
+    /// let mut objects = client.list_stream(ListOptions::new().prefix("logs/"));
+    /// while let Some(object) = objects.try_next().await? {
+    ///     println!("{} ({} bytes)", object.key, object.size);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_stream(&self, options: ListOptions) -> BoxStream<'static, Result<ObjectSummary>> {
+        let client = self.clone();
+        stream::try_unfold(Some(options), move |options| {
+            let client = client.clone();
+            async move {
+                let Some(options) = options else {
+                    return Ok(None);
+                };
+                let fetch = client
+                    .retry_policy
+                    .run("list_objects_v2", || client.list_objects(options.clone()));
+                // The method returns before any request is sent, so each page
+                // fetch gets a span of its own instead.
+                #[cfg(feature = "tracing")]
+                let fetch = tracing::Instrument::instrument(
+                    fetch,
+                    tracing::info_span!(
+                        "list_stream",
+                        bucket = %client.bucket,
+                        prefix = ?options.prefix,
+                    ),
+                );
+                let page = fetch.await?;
+                let next = match page.next_continuation_token {
+                    Some(token) if page.is_truncated => Some(ListOptions {
+                        continuation_token: Some(token),
+                        ..options
+                    }),
+                    _ => None,
+                };
+                Ok::<_, Error>(Some((stream::iter(page.objects.into_iter().map(Ok)), next)))
+            }
+        })
+        .try_flatten()
+        .boxed()
+    }
+
     /// Lists every key in the bucket.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket)
+        )
+    )]
     pub async fn list_keys(&self) -> Result<Vec<String>> {
         Ok(self
             .list_all_objects(None)
@@ -421,6 +778,14 @@ impl R2Client {
     }
 
     /// Lists the immediate "folders" under a prefix, using `/` as the delimiter.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, prefix = %prefix)
+        )
+    )]
     pub async fn list_prefixes(&self, prefix: &str) -> Result<Vec<String>> {
         let mut prefixes = Vec::new();
         let mut continuation_token = None;
@@ -429,7 +794,10 @@ impl R2Client {
             let mut options = ListOptions::new().prefix(prefix).delimiter("/");
             options.continuation_token = continuation_token;
 
-            let page = self.list_objects(options).await?;
+            let page = self
+                .retry_policy
+                .run("list_objects_v2", || self.list_objects(options.clone()))
+                .await?;
             prefixes.extend(page.common_prefixes);
 
             match page.next_continuation_token {
@@ -452,6 +820,14 @@ impl R2Client {
     /// [`Error::InvalidArgument`]. Such keys are legal in R2, so use
     /// [`download_to`](R2Client::download_to) with an explicit destination if
     /// you need to fetch one.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, directory = %directory.display())
+        )
+    )]
     pub async fn download_file(&self, key: &str, directory: &Path) -> Result<PathBuf> {
         validate_key(key)?;
 
@@ -462,7 +838,11 @@ impl R2Client {
             ));
         }
 
-        let destination = safe_join(directory, key)?;
+        let destination = safe_join(
+            directory,
+            key,
+            Some("Use download_to() with an explicit destination path instead."),
+        )?;
         self.download_to(key, &destination).await?;
         Ok(destination)
     }
@@ -477,6 +857,19 @@ impl R2Client {
     ///
     /// The destination is used exactly as given; it is the caller's path, not
     /// the object's, so no key-derived traversal is possible here.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                path = %destination.display(),
+                bytes = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn download_to(&self, key: &str, destination: &Path) -> Result<u64> {
         validate_key(key)?;
 
@@ -503,7 +896,7 @@ impl R2Client {
         let temporary = temporary_path(destination);
         let mut stream = self.get_object_stream(key).await?;
 
-        let written = match stream_to_file(&mut stream, &temporary).await {
+        let written = match stream_to_file(key, &mut stream, &temporary).await {
             Ok(written) => written,
             Err(err) => {
                 // Best effort: leaving the partial file behind would be worse
@@ -522,9 +915,227 @@ impl R2Client {
             ));
         }
 
+        record!("bytes", written);
         Ok(written)
     }
 
+    /// Opens an object's body, decoded as
+    /// [`get_object_stream`](R2Client::get_object_stream) describes, along
+    /// with its ETag.
+    pub(crate) async fn open_object(&self, key: &str) -> Result<(ByteStream, Option<String>)> {
+        validate_key(key)?;
+
+        let response = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .customize()
+            .record_status()
+            .send()
+            .await
+            .map_err(|err| self.map_object_error("get_object", key, err))?;
+        self.decode_object(key, response).await
+    }
+
+    /// Like [`open_object`](R2Client::open_object), but sends
+    /// `If-None-Match: etag` and returns `None` when R2 answers
+    /// `304 Not Modified`.
+    pub(crate) async fn open_object_if_none_match(
+        &self,
+        key: &str,
+        etag: &str,
+    ) -> Result<Option<(ByteStream, Option<String>)>> {
+        validate_key(key)?;
+
+        let response = match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .if_none_match(etag)
+            .customize()
+            .record_status()
+            .send()
+            .await
+            .map_err(|err| self.map_object_error("get_object", key, err))
+        {
+            Ok(response) => response,
+            Err(err) if err.status() == Some(304) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        self.decode_object(key, response).await.map(Some)
+    }
+
+    /// A `HeadObject` response under `conditions`.
+    /// On a client that decrypts, the content length of an encrypted object
+    /// is its plaintext length.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "head_object",
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                bytes = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
+    pub(crate) async fn head_object_output(
+        &self,
+        key: &str,
+        conditions: ReadConditions,
+    ) -> Result<HeadObjectOutput> {
+        validate_key(key)?;
+
+        #[allow(unused_mut)]
+        let mut response = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_if_match(conditions.if_match)
+            .set_if_none_match(conditions.if_none_match)
+            .set_if_modified_since(conditions.if_modified_since)
+            .set_if_unmodified_since(conditions.if_unmodified_since)
+            .customize()
+            .record_status()
+            .send()
+            .await
+            .map_err(|err| self.map_object_error("head_object", key, err))?;
+
+        record!("bytes", response.content_length);
+        #[cfg(feature = "encryption")]
+        if self.decrypts(response.metadata.as_ref()) {
+            let sealed = response.content_length.unwrap_or_default();
+            let plaintext = crate::encryption::plaintext_len(sealed as u64).ok_or_else(|| {
+                Error::encryption(
+                    key,
+                    format!("{sealed} bytes cannot hold encrypted frames"),
+                    None,
+                )
+            })?;
+            response.content_length = Some(plaintext as i64);
+        }
+        Ok(response)
+    }
+
+    /// A `GetObject` response for `range`, a `Range` header value such as
+    /// `bytes=0-99`, under `conditions`, with its body decrypted and
+    /// decompressed as [`open_object`](R2Client::open_object) would. A ranged
+    /// read is returned as stored, as
+    /// [`get_object_range`](R2Client::get_object_range) does without
+    /// encryption; a ranged read of an object this client would decrypt is
+    /// refused.
+    #[cfg(feature = "object_store")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "get_object",
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                range = ?range,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
+    pub(crate) async fn get_object_output(
+        &self,
+        key: &str,
+        range: Option<String>,
+        conditions: ReadConditions,
+    ) -> Result<GetObjectOutput> {
+        validate_key(key)?;
+
+        let response = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_range(range.clone())
+            .set_if_match(conditions.if_match)
+            .set_if_none_match(conditions.if_none_match)
+            .set_if_modified_since(conditions.if_modified_since)
+            .set_if_unmodified_since(conditions.if_unmodified_since)
+            .customize()
+            .record_status()
+            .send()
+            .await
+            .map_err(|err| self.map_object_error("get_object", key, err))?;
+
+        if range.is_none() {
+            return self.decode_response(key, response).await;
+        }
+        #[cfg(feature = "encryption")]
+        if self.decrypts(response.metadata.as_ref()) {
+            return Err(Error::invalid_argument(
+                "range",
+                format!("`{key}` is encrypted; read ranges of it with get_object_range"),
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Decrypts and decompresses a GET response's body as the client is
+    /// configured to.
+    async fn decode_object(
+        &self,
+        key: &str,
+        response: GetObjectOutput,
+    ) -> Result<(ByteStream, Option<String>)> {
+        let response = self.decode_response(key, response).await?;
+        Ok((response.body, response.e_tag))
+    }
+
+    /// Like [`decode_object`](R2Client::decode_object), keeping the rest of
+    /// the response. The content length of a decrypted body is its plaintext
+    /// length; that of a decompressed one is unknown, and left as stored.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    async fn decode_response(
+        &self,
+        key: &str,
+        response: GetObjectOutput,
+    ) -> Result<GetObjectOutput> {
+        #[cfg(feature = "encryption")]
+        let response = match self.data_key(key, response.metadata.as_ref()).await? {
+            Some(data_key) => {
+                let mut response = response;
+                let sealed = response.content_length.unwrap_or_default();
+                response.content_length = crate::encryption::plaintext_len(sealed as u64)
+                    .map(|plaintext| plaintext as i64);
+                response.body = data_key.open_stream(response.body);
+                response
+            }
+            None => response,
+        };
+
+        #[cfg(feature = "compression")]
+        if self.decompress {
+            if let Some(compression) = response
+                .content_encoding
+                .as_deref()
+                .and_then(crate::Compression::from_content_encoding)
+            {
+                let mut response = response;
+                response.body = compression.decompress(response.body);
+                return Ok(response);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Whether this client decrypts objects stored with `metadata`.
+    #[cfg(feature = "encryption")]
+    fn decrypts(&self, metadata: Option<&std::collections::HashMap<String, String>>) -> bool {
+        self.encryption.is_some() && metadata.is_some_and(crate::encryption::is_encrypted)
+    }
+
     /// Turns a 404 on a keyed operation into [`Error::ObjectNotFound`], so
     /// callers can match on "missing" without inspecting status codes.
     pub(crate) fn map_object_error<E>(
@@ -534,7 +1145,7 @@ impl R2Client {
         err: aws_sdk_s3::error::SdkError<E, aws_sdk_s3::config::http::HttpResponse>,
     ) -> Error
     where
-        E: std::error::Error + Send + Sync + 'static,
+        E: aws_sdk_s3::error::ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
     {
         let err = from_sdk(operation, err);
         if err.is_not_found() {
@@ -549,7 +1160,7 @@ impl R2Client {
 }
 
 /// Streams a response body into a file, returning the byte count.
-async fn stream_to_file(stream: &mut ByteStream, path: &Path) -> Result<u64> {
+async fn stream_to_file(key: &str, stream: &mut ByteStream, path: &Path) -> Result<u64> {
     let mut file = tokio::fs::File::create(path)
         .await
         .map_err(|err| Error::file(path, "could not create the download file", Some(err)))?;
@@ -558,7 +1169,7 @@ async fn stream_to_file(stream: &mut ByteStream, path: &Path) -> Result<u64> {
     while let Some(chunk) = stream
         .try_next()
         .await
-        .map_err(|err| Error::Body(Box::new(err)))?
+        .map_err(|err| Error::body(key, err))?
     {
         file.write_all(&chunk)
             .await
@@ -581,7 +1192,7 @@ async fn stream_to_file(stream: &mut ByteStream, path: &Path) -> Result<u64> {
 /// destination's file name: most filesystems cap a single name at 255 bytes,
 /// and appending a suffix to an already-long key segment would fail with
 /// `ENAMETOOLONG` on keys that are otherwise perfectly valid.
-fn temporary_path(destination: &Path) -> PathBuf {
+pub(crate) fn temporary_path(destination: &Path) -> PathBuf {
     static COUNTER: AtomicU64 = AtomicU64::new(0);
     let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
     let name = format!(".r2partial.{}.{unique}", std::process::id());
@@ -615,15 +1226,17 @@ pub(crate) fn validate_key(key: &str) -> Result<()> {
 }
 
 /// Joins an untrusted key onto a directory, refusing anything that escapes it.
-pub(crate) fn safe_join(directory: &Path, key: &str) -> Result<PathBuf> {
+///
+/// `remedy` is appended to the rejection message, for callers that have a
+/// safer alternative to point at.
+pub(crate) fn safe_join(directory: &Path, key: &str, remedy: Option<&str>) -> Result<PathBuf> {
     let reject = |reason: &str| {
-        Err(Error::invalid_argument(
-            "key",
-            format!(
-                "key `{key}` cannot be written under a directory: {reason}. \
-                 Use download_to() with an explicit destination path instead."
-            ),
-        ))
+        let mut message = format!("key `{key}` cannot be written under a directory: {reason}");
+        if let Some(remedy) = remedy {
+            message.push_str(". ");
+            message.push_str(remedy);
+        }
+        Err(Error::invalid_argument("key", message))
     };
 
     // A backslash is a path separator on Windows, so a key containing one
@@ -697,40 +1310,40 @@ mod tests {
     fn safe_join_accepts_ordinary_keys() {
         let base = Path::new("/tmp/dl");
         assert_eq!(
-            safe_join(base, "photos/2024/cat.png").unwrap(),
+            safe_join(base, "photos/2024/cat.png", None).unwrap(),
             base.join("photos/2024/cat.png")
         );
     }
 
     #[test]
     fn safe_join_rejects_parent_segments() {
-        assert!(safe_join(Path::new("/tmp/dl"), "../escape.txt").is_err());
-        assert!(safe_join(Path::new("/tmp/dl"), "a/../../escape.txt").is_err());
+        assert!(safe_join(Path::new("/tmp/dl"), "../escape.txt", None).is_err());
+        assert!(safe_join(Path::new("/tmp/dl"), "a/../../escape.txt", None).is_err());
     }
 
     #[test]
     fn safe_join_rejects_absolute_keys() {
         // Path::join would silently discard the base directory here.
-        assert!(safe_join(Path::new("/tmp/dl"), "/etc/passwd").is_err());
+        assert!(safe_join(Path::new("/tmp/dl"), "/etc/passwd", None).is_err());
     }
 
     #[test]
     fn safe_join_rejects_backslashes_on_every_platform() {
-        assert!(safe_join(Path::new("/tmp/dl"), "..\\escape.txt").is_err());
-        assert!(safe_join(Path::new("/tmp/dl"), "C:\\Windows\\system32").is_err());
+        assert!(safe_join(Path::new("/tmp/dl"), "..\\escape.txt", None).is_err());
+        assert!(safe_join(Path::new("/tmp/dl"), "C:\\Windows\\system32", None).is_err());
     }
 
     #[test]
     fn safe_join_rejects_keys_that_name_no_file() {
-        assert!(safe_join(Path::new("/tmp/dl"), ".").is_err());
-        assert!(safe_join(Path::new("/tmp/dl"), "./").is_err());
+        assert!(safe_join(Path::new("/tmp/dl"), ".", None).is_err());
+        assert!(safe_join(Path::new("/tmp/dl"), "./", None).is_err());
     }
 
     #[test]
     fn safe_join_keeps_the_result_under_the_directory() {
         let base = Path::new("/tmp/dl");
         for key in ["a.txt", "a/b/c.txt", "./a.txt"] {
-            let joined = safe_join(base, key).unwrap();
+            let joined = safe_join(base, key, None).unwrap();
             assert!(joined.starts_with(base), "{key} escaped to {joined:?}");
         }
     }
@@ -19,6 +19,7 @@ pub const MAX_PRESIGN_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
 /// listed here were part of the signature and the uploading client must send
 /// them verbatim or the request is rejected.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PresignedRequest {
     /// The fully signed URL.
     pub url: String,
@@ -72,6 +73,14 @@ impl R2Client {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, expires_in_secs = expires_in.as_secs())
+        )
+    )]
     pub async fn presign_get(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
         self.presign_get_with(key, PresignOptions::new().expires_in(expires_in))
             .await
@@ -79,6 +88,18 @@ impl R2Client {
 
     /// Creates a download URL, optionally overriding the response headers so
     /// the browser sees a different content type or filename.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                expires_in_secs = options.expires_in.as_secs(),
+            )
+        )
+    )]
     pub async fn presign_get_with(
         &self,
         key: &str,
@@ -100,6 +121,14 @@ impl R2Client {
     }
 
     /// Creates a URL a client can `PUT` an object to, valid for `expires_in`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, expires_in_secs = expires_in.as_secs())
+        )
+    )]
     pub async fn presign_put(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
         self.presign_put_with(key, PresignOptions::new().expires_in(expires_in))
             .await
@@ -107,6 +136,18 @@ impl R2Client {
 
     /// Creates an upload URL, optionally pinning the `Content-Type` the client
     /// must send.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(
+                bucket = %self.bucket,
+                key = %key,
+                expires_in_secs = options.expires_in.as_secs(),
+            )
+        )
+    )]
     pub async fn presign_put_with(
         &self,
         key: &str,
@@ -127,6 +168,14 @@ impl R2Client {
     }
 
     /// Creates a URL that deletes an object, valid for `expires_in`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, expires_in_secs = expires_in.as_secs())
+        )
+    )]
     pub async fn presign_delete(
         &self,
         key: &str,
@@ -146,6 +195,14 @@ impl R2Client {
     }
 
     /// Creates a URL that reads an object's metadata, valid for `expires_in`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            err,
+            fields(bucket = %self.bucket, key = %key, expires_in_secs = expires_in.as_secs())
+        )
+    )]
     pub async fn presign_head(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
         validate_key(key)?;
         let config = presigning_config("head_object", key, expires_in)?;
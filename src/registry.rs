@@ -0,0 +1,291 @@
+//! A registry of clients addressed by logical name.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+use crate::client::R2Client;
+use crate::config::R2Config;
+use crate::error::{Error, Result};
+
+/// Clients for many buckets, looked up by a logical name such as `"uploads"`
+/// or `"archive-eu"` rather than by bucket name.
+///
+/// Each name maps to an [`R2Config`]. Clients are built on first use and
+/// cached, and every name that resolves to the same endpoint, credentials and
+/// region shares one connection pool — the second bucket on an account is a
+/// [`with_bucket`](R2Client::with_bucket) child of the first, not a fresh
+/// client.
+///
+/// ```
+/// use cloudflare_r2_rs::{endpoint_for_account, Jurisdiction, R2Config, R2Registry};
+///
+/// let account = |bucket: &str, jurisdiction| R2Config {
+///     endpoint: endpoint_for_account("0123456789abcdef", jurisdiction),
+///     access_key_id: "access-key".into(),
+///     secret_access_key: "secret-key".into(),
+///     bucket: bucket.into(),
+///     region: "auto".into(),
+/// };
+///
+/// let registry = R2Registry::new()
+///     .register("uploads", account("prod-uploads", Jurisdiction::Default))
+///     .register("archive-eu", account("archive", Jurisdiction::Eu));
+///
+/// let archive = registry.get("archive-eu").unwrap();
+/// assert_eq!(archive.bucket(), "archive");
+/// assert!(registry.get("nope").is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct R2Registry {
+    configs: HashMap<String, R2Config>,
+    clients: Mutex<HashMap<String, R2Client>>,
+    pools: Mutex<HashMap<PoolKey, R2Client>>,
+}
+
+/// What two configurations must agree on to share a connection pool.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct PoolKey {
+    endpoint: String,
+    access_key_id: String,
+    secret_access_key: String,
+    region: String,
+}
+
+impl PoolKey {
+    fn new(config: &R2Config) -> Self {
+        PoolKey {
+            // Normalized the same way the client normalizes it, so a trailing
+            // slash does not split one account into two pools.
+            endpoint: config.endpoint.trim().trim_end_matches('/').to_string(),
+            access_key_id: config.access_key_id.clone(),
+            secret_access_key: config.secret_access_key.clone(),
+            region: config.region.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for PoolKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolKey")
+            .field("endpoint", &self.endpoint)
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"<redacted>")
+            .field("region", &self.region)
+            .finish()
+    }
+}
+
+impl R2Registry {
+    /// Creates an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `config` under `name`, replacing any earlier entry.
+    ///
+    /// Nothing is validated or built until [`get`](R2Registry::get) is first
+    /// called for the name.
+    #[must_use]
+    pub fn register(mut self, name: impl Into<String>, config: R2Config) -> Self {
+        self.insert(name, config);
+        self
+    }
+
+    /// Registers `config` under `name` in place, replacing any earlier entry
+    /// and discarding a client already built for it.
+    pub fn insert(&mut self, name: impl Into<String>, config: R2Config) {
+        let name = name.into();
+        self.clients
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&name);
+        self.configs.insert(name, config);
+    }
+
+    /// Returns the client for a logical name, building it on first use.
+    ///
+    /// Fails with [`Error::InvalidArgument`] for a name that was never
+    /// registered, and with whatever [`R2Client::from_config`] reports for an
+    /// unusable configuration.
+    pub fn get(&self, name: &str) -> Result<R2Client> {
+        let config = self.configs.get(name).ok_or_else(|| {
+            Error::invalid_argument("name", format!("no bucket is registered as `{name}`"))
+        })?;
+
+        // Held across the build so two threads asking for the same name at
+        // once end up with one client rather than racing to insert two.
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(client) = clients.get(name) {
+            return Ok(client.clone());
+        }
+
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let client = match pools.get(&PoolKey::new(config)) {
+            Some(pooled) => pooled.with_bucket(&config.bucket),
+            None => {
+                let client = R2Client::from_config(config.clone())?;
+                pools.insert(PoolKey::new(config), client.clone());
+                client
+            }
+        };
+
+        clients.insert(name.to_string(), client.clone());
+        Ok(client)
+    }
+
+    /// Reports whether `name` has been registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.configs.contains_key(name)
+    }
+
+    /// The registered logical names, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.configs.keys().map(String::as_str)
+    }
+}
+
+impl<N: Into<String>> FromIterator<(N, R2Config)> for R2Registry {
+    fn from_iter<I: IntoIterator<Item = (N, R2Config)>>(iter: I) -> Self {
+        let mut registry = R2Registry::new();
+        for (name, config) in iter {
+            registry.insert(name, config);
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(endpoint: &str, access_key_id: &str, bucket: &str) -> R2Config {
+        R2Config {
+            endpoint: endpoint.into(),
+            access_key_id: access_key_id.into(),
+            secret_access_key: "secret".into(),
+            bucket: bucket.into(),
+            region: "auto".into(),
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        let err = R2Registry::new().get("uploads").unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::InvalidArgument {
+                    argument: "name",
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn clients_are_built_once_and_cached() {
+        let registry = R2Registry::new().register(
+            "uploads",
+            config("https://a.r2.cloudflarestorage.com", "key", "prod-uploads"),
+        );
+        let first = registry.get("uploads").unwrap();
+        let second = registry.get("uploads").unwrap();
+        assert_eq!(first.bucket(), "prod-uploads");
+        assert!(std::ptr::eq(first.inner(), second.inner()));
+    }
+
+    #[test]
+    fn buckets_on_one_account_share_a_connection_pool() {
+        let registry = R2Registry::new()
+            .register(
+                "uploads",
+                config("https://a.r2.cloudflarestorage.com", "key", "uploads"),
+            )
+            // Trailing slash: still the same endpoint.
+            .register(
+                "thumbnails",
+                config("https://a.r2.cloudflarestorage.com/", "key", "thumbs"),
+            );
+        let uploads = registry.get("uploads").unwrap();
+        let thumbnails = registry.get("thumbnails").unwrap();
+        assert_eq!(thumbnails.bucket(), "thumbs");
+        assert!(std::ptr::eq(uploads.inner(), thumbnails.inner()));
+    }
+
+    #[test]
+    fn different_endpoints_or_credentials_do_not_share_a_pool() {
+        let registry = R2Registry::new()
+            .register(
+                "uploads",
+                config("https://a.r2.cloudflarestorage.com", "key", "uploads"),
+            )
+            .register(
+                "archive-eu",
+                config("https://a.eu.r2.cloudflarestorage.com", "key", "archive"),
+            )
+            .register(
+                "other-key",
+                config("https://a.r2.cloudflarestorage.com", "key-2", "uploads"),
+            );
+        let uploads = registry.get("uploads").unwrap();
+        let archive = registry.get("archive-eu").unwrap();
+        let other = registry.get("other-key").unwrap();
+        assert!(!std::ptr::eq(uploads.inner(), archive.inner()));
+        assert!(!std::ptr::eq(uploads.inner(), other.inner()));
+    }
+
+    #[test]
+    fn a_bad_configuration_fails_only_when_requested() {
+        let registry = R2Registry::new()
+            .register("broken", config("", "key", "bucket"))
+            .register(
+                "fine",
+                config("https://a.r2.cloudflarestorage.com", "key", "bucket"),
+            );
+        assert!(registry.get("fine").is_ok());
+        assert!(matches!(
+            registry.get("broken").unwrap_err(),
+            Error::MissingConfig("endpoint")
+        ));
+    }
+
+    #[test]
+    fn reregistering_a_name_replaces_its_client() {
+        let mut registry = R2Registry::new().register(
+            "uploads",
+            config("https://a.r2.cloudflarestorage.com", "key", "old"),
+        );
+        assert_eq!(registry.get("uploads").unwrap().bucket(), "old");
+
+        registry.insert(
+            "uploads",
+            config("https://a.r2.cloudflarestorage.com", "key", "new"),
+        );
+        assert_eq!(registry.get("uploads").unwrap().bucket(), "new");
+    }
+
+    #[test]
+    fn collects_from_name_config_pairs() {
+        let registry: R2Registry = [(
+            "uploads",
+            config("https://a.r2.cloudflarestorage.com", "key", "uploads"),
+        )]
+        .into_iter()
+        .collect();
+        assert!(registry.contains("uploads"));
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["uploads"]);
+    }
+
+    #[test]
+    fn debug_output_redacts_secrets() {
+        let mut config = config("https://a.r2.cloudflarestorage.com", "key", "uploads");
+        config.secret_access_key = "do-not-print-me".into();
+        let registry = R2Registry::new().register("uploads", config);
+        registry.get("uploads").unwrap();
+        let rendered = format!("{registry:?}");
+        assert!(rendered.contains("PoolKey"), "{rendered}");
+        assert!(!rendered.contains("do-not-print-me"), "{rendered}");
+    }
+}
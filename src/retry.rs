@@ -0,0 +1,219 @@
+//! Retrying the units of composite operations.
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+
+/// How the crate retries one unit of a multi-request operation — a part of a
+/// multipart upload, a page of a listing, a batch of deletes — once the SDK's
+/// own per-request retries have given up.
+///
+/// Without it, one part that exhausts its retries fails the whole upload and
+/// throws away every part already sent. With it, only that part is sent again,
+/// after an exponential backoff with jitter. Only failures for which
+/// [`Error::is_retryable`] holds are retried.
+///
+/// Off unless set with
+/// [`R2ClientBuilder::retry_policy`](crate::R2ClientBuilder::retry_policy).
+///
+/// ```
+/// use std::time::Duration;
+/// use cloudflare_r2_rs::RetryPolicy;
+///
+/// let policy = RetryPolicy::new()
+///     .max_attempts(5)
+///     .initial_backoff(Duration::from_millis(500));
+/// assert_eq!(policy.max_attempts, 5);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per unit, including the first. `1` disables retrying.
+    pub max_attempts: u32,
+    /// Backoff before the first retry. Doubles with every further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound on a single backoff.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates the default policy: three attempts, backing off from 200ms.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that never retries.
+    #[must_use]
+    pub fn disabled() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the attempts per unit, including the first.
+    #[must_use]
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the backoff before the first retry.
+    #[must_use]
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the upper bound on a single backoff.
+    #[must_use]
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.max_attempts == 0 {
+            return Err(Error::InvalidConfig {
+                field: "retry_policy",
+                message: "max attempts must be at least 1".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether a unit that just failed its `attempt`-th try gets another.
+    pub(crate) fn allows_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// Sleeps off the backoff that follows the `attempt`-th failed try.
+    pub(crate) async fn back_off(&self, attempt: u32) {
+        tokio::time::sleep(self.backoff(attempt)).await;
+    }
+
+    /// Exponential backoff with "equal jitter": somewhere between half and
+    /// all of the exponential delay, so that parts failing together do not
+    /// come back together.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        let half = exponential / 2;
+        half + half.mul_f64(jitter())
+    }
+
+    /// Runs `unit` until it succeeds, fails for good, or runs out of attempts.
+    pub(crate) async fn run<T, F, Fut>(&self, operation: &str, mut unit: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match unit().await {
+                Err(err) if err.is_retryable() && self.allows_retry(attempt) => {
+                    log::debug!("{operation} failed on attempt {attempt}, retrying: {err}");
+                    self.back_off(attempt).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A number in `[0, 1)`. Jitter needs to differ between callers, not to be
+/// unpredictable, so the standard library's randomly keyed hasher will do.
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn retryable() -> Error {
+        Error::Body("connection reset".into())
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_cap() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300));
+        for (attempt, full) in [(1, 100), (2, 200), (3, 300), (10, 300)] {
+            let backoff = policy.backoff(attempt);
+            let full = Duration::from_millis(full);
+            assert!(
+                backoff >= full / 2 && backoff <= full,
+                "{attempt}: {backoff:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn zero_attempts_are_rejected() {
+        assert!(RetryPolicy::new().max_attempts(0).validate().is_err());
+        assert!(RetryPolicy::disabled().validate().is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_retryable_failures_until_success() {
+        let calls = AtomicU32::new(0);
+        let result = RetryPolicy::new()
+            .run("test", || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(retryable()),
+                    _ => Ok("done"),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = RetryPolicy::new()
+            .max_attempts(2)
+            .run("test", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(retryable())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_permanent_failures() {
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = RetryPolicy::new()
+            .run("test", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(Error::MissingConfig("bucket"))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,374 @@
+//! A storage-agnostic interface over the core object operations.
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+
+use crate::client::R2Client;
+use crate::error::{Error, Result};
+use crate::types::{ListOptions, ListPage, ObjectMetadata, ObjectSummary, PutOptions, PutOutcome};
+
+/// Largest page a listing returns, matching R2's own cap.
+const MAX_LIST_KEYS: usize = 1000;
+
+/// The object operations code needs from a bucket, independent of where the
+/// bucket lives.
+///
+/// Implemented by [`R2Client`] for the real service, by
+/// [`LocalStore`](crate::LocalStore) for a directory on disk and by
+/// [`MemoryStore`](crate::MemoryStore) for a process-local map. Code written
+/// against `&dyn ObjectStore` (or a generic `S: ObjectStore`) can run on R2
+/// in production, on a directory during development and in memory in tests.
+///
+/// The methods mirror their [`R2Client`] counterparts: keys are validated the
+/// same way, a missing key fails with [`Error::ObjectNotFound`], ranges are
+/// inclusive at both ends and listings paginate with the same
+/// [`ListOptions`]. Methods return boxed futures so the trait stays usable as
+/// a trait object.
+///
+/// ```
+/// use cloudflare_r2_rs::{MemoryStore, ObjectStore, PutOptions, Result};
+///
+/// async fn archive(store: &dyn ObjectStore, key: &str) -> Result<()> {
+///     let body = store.get(key).await?;
+///     store
+///         .put(&format!("archive/{key}"), body.into(), PutOptions::new())
+///         .await?;
+///     store.delete(key).await
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<()> {
+/// let store = MemoryStore::new();
+/// store.put("report.csv", "a,b\n".into(), PutOptions::new()).await?;
+/// archive(&store, "report.csv").await?;
+/// assert!(store.exists("archive/report.csv").await?);
+/// assert!(!store.exists("report.csv").await?);
+/// # Ok(())
+/// # }
+/// ```
+pub trait ObjectStore: Send + Sync + std::fmt::Debug {
+    /// Stores `body` under `key`, replacing any existing object.
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        body: Bytes,
+        options: PutOptions,
+    ) -> BoxFuture<'a, Result<PutOutcome>>;
+
+    /// Fetches an object's full body.
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>>;
+
+    /// Fetches a byte range of an object, `start` and `end` both inclusive.
+    ///
+    /// Passing `None` for `end` reads to the end of the object.
+    fn get_range<'a>(
+        &'a self,
+        key: &'a str,
+        start: u64,
+        end: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<u8>>>;
+
+    /// Fetches an object's metadata without its body.
+    fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ObjectMetadata>>;
+
+    /// Lists one page of objects.
+    fn list(&self, options: ListOptions) -> BoxFuture<'_, Result<ListPage>>;
+
+    /// Deletes an object. Deleting a missing key succeeds.
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// Copies an object to another key in the same store.
+    fn copy<'a>(
+        &'a self,
+        source_key: &'a str,
+        destination_key: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Whether an object exists.
+    fn exists<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(async move {
+            match self.head(key).await {
+                Ok(_) => Ok(true),
+                Err(err) if err.is_not_found() => Ok(false),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    /// Lists every object under an optional prefix, following pagination.
+    fn list_all<'a>(
+        &'a self,
+        prefix: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<ObjectSummary>>> {
+        Box::pin(async move {
+            let mut objects = Vec::new();
+            let mut continuation_token = None;
+            loop {
+                let mut options = ListOptions::new();
+                options.prefix = prefix.map(str::to_string);
+                options.continuation_token = continuation_token;
+                let page = self.list(options).await?;
+                objects.extend(page.objects);
+                match page.next_continuation_token {
+                    Some(token) if page.is_truncated => continuation_token = Some(token),
+                    _ => return Ok(objects),
+                }
+            }
+        })
+    }
+}
+
+impl ObjectStore for R2Client {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        body: Bytes,
+        options: PutOptions,
+    ) -> BoxFuture<'a, Result<PutOutcome>> {
+        Box::pin(self.put_object_with(key, body, options))
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(self.get_object(key))
+    }
+
+    fn get_range<'a>(
+        &'a self,
+        key: &'a str,
+        start: u64,
+        end: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(self.get_object_range(key, start, end))
+    }
+
+    fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ObjectMetadata>> {
+        Box::pin(self.head_object(key))
+    }
+
+    fn list(&self, options: ListOptions) -> BoxFuture<'_, Result<ListPage>> {
+        Box::pin(self.list_objects(options))
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.delete_object(key))
+    }
+
+    fn copy<'a>(
+        &'a self,
+        source_key: &'a str,
+        destination_key: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.copy_object(source_key, destination_key))
+    }
+
+    fn exists<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(self.object_exists(key))
+    }
+
+    fn list_all<'a>(
+        &'a self,
+        prefix: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<ObjectSummary>>> {
+        Box::pin(self.list_all_objects(prefix))
+    }
+}
+
+/// Pages a key-ordered listing the way `ListObjectsV2` does, for the stores
+/// that hold every object locally.
+///
+/// The continuation token is the last key or common prefix returned, so a
+/// listing resumes correctly even if objects change between pages.
+pub(crate) fn paginate<I>(objects: I, options: &ListOptions) -> ListPage
+where
+    I: IntoIterator<Item = ObjectSummary>,
+{
+    let prefix = options.prefix.as_deref().unwrap_or_default();
+    let delimiter = options.delimiter.as_deref().filter(|d| !d.is_empty());
+    let max_keys = options
+        .max_keys
+        .map_or(MAX_LIST_KEYS, |max| usize::try_from(max).unwrap_or(0))
+        .min(MAX_LIST_KEYS);
+    let after = options
+        .continuation_token
+        .as_deref()
+        .or(options.start_after.as_deref())
+        .unwrap_or_default();
+
+    let mut page = ListPage {
+        objects: Vec::new(),
+        common_prefixes: Vec::new(),
+        next_continuation_token: None,
+        is_truncated: false,
+    };
+    let mut last: Option<String> = None;
+
+    for object in objects {
+        if object.key.as_str() <= after || !object.key.starts_with(prefix) {
+            continue;
+        }
+        let common = delimiter.and_then(|delimiter| {
+            object.key[prefix.len()..]
+                .find(delimiter)
+                .map(|at| object.key[..prefix.len() + at + delimiter.len()].to_string())
+        });
+        // Everything under a common prefix already listed, on this page or —
+        // through the token — on an earlier one.
+        if let Some(common) = &common {
+            if common == after || page.common_prefixes.last() == Some(common) {
+                continue;
+            }
+        }
+        if page.objects.len() + page.common_prefixes.len() == max_keys {
+            page.is_truncated = max_keys > 0;
+            break;
+        }
+        match common {
+            Some(common) => {
+                last = Some(common.clone());
+                page.common_prefixes.push(common);
+            }
+            None => {
+                last = Some(object.key.clone());
+                page.objects.push(object);
+            }
+        }
+    }
+
+    if page.is_truncated {
+        page.next_continuation_token = last;
+    }
+    page
+}
+
+/// Resolves an inclusive range against an object of `size` bytes, to the
+/// half-open span to slice.
+pub(crate) fn resolve_range(start: u64, end: Option<u64>, size: u64) -> Result<(u64, u64)> {
+    if let Some(end) = end {
+        if end < start {
+            return Err(Error::invalid_argument(
+                "end",
+                format!("range end {end} is before range start {start}"),
+            ));
+        }
+    }
+    if start >= size {
+        return Err(Error::invalid_argument(
+            "start",
+            format!("range start {start} is beyond the end of the {size}-byte object"),
+        ));
+    }
+    let end = end.map_or(size, |end| end.saturating_add(1).min(size));
+    Ok((start, end))
+}
+
+/// A quoted, content-derived ETag for the stores that compute their own.
+///
+/// FNV-1a over the body plus its length: cheap, stable across processes and
+/// enough to tell two versions of an object apart, which is all an ETag is
+/// used for outside R2.
+pub(crate) fn content_etag(data: &[u8]) -> String {
+    let hash = data.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("\"{hash:016x}{:016x}\"", data.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summaries(keys: &[&str]) -> Vec<ObjectSummary> {
+        keys.iter()
+            .map(|key| ObjectSummary {
+                key: key.to_string(),
+                size: 1,
+                etag: None,
+                last_modified: None,
+            })
+            .collect()
+    }
+
+    fn keys(page: &ListPage) -> Vec<&str> {
+        page.objects
+            .iter()
+            .map(|object| object.key.as_str())
+            .collect()
+    }
+
+    const KEYS: &[&str] = &[
+        "logs/2026/01.txt",
+        "logs/2026/02.txt",
+        "logs/2027/01.txt",
+        "logs/readme.txt",
+        "other.txt",
+    ];
+
+    #[test]
+    fn pages_resume_after_the_token() {
+        let options = ListOptions::new().prefix("logs/").max_keys(2);
+        let first = paginate(summaries(KEYS), &options);
+        assert_eq!(keys(&first), ["logs/2026/01.txt", "logs/2026/02.txt"]);
+        assert!(first.is_truncated);
+
+        let options = options.continuation_token(first.next_continuation_token.unwrap());
+        let second = paginate(summaries(KEYS), &options);
+        assert_eq!(keys(&second), ["logs/2027/01.txt", "logs/readme.txt"]);
+        assert!(!second.is_truncated);
+        assert_eq!(second.next_continuation_token, None);
+    }
+
+    #[test]
+    fn delimiters_group_into_common_prefixes() {
+        let options = ListOptions::new().prefix("logs/").delimiter("/");
+        let page = paginate(summaries(KEYS), &options);
+        assert_eq!(page.common_prefixes, ["logs/2026/", "logs/2027/"]);
+        assert_eq!(keys(&page), ["logs/readme.txt"]);
+    }
+
+    #[test]
+    fn a_common_prefix_is_not_repeated_on_the_next_page() {
+        let options = ListOptions::new()
+            .prefix("logs/")
+            .delimiter("/")
+            .max_keys(1);
+        let first = paginate(summaries(KEYS), &options);
+        assert_eq!(first.common_prefixes, ["logs/2026/"]);
+
+        let options = options.continuation_token(first.next_continuation_token.unwrap());
+        let second = paginate(summaries(KEYS), &options);
+        assert_eq!(second.common_prefixes, ["logs/2027/"]);
+    }
+
+    #[test]
+    fn start_after_skips_earlier_keys() {
+        let options = ListOptions::new().start_after("logs/readme.txt");
+        assert_eq!(keys(&paginate(summaries(KEYS), &options)), ["other.txt"]);
+    }
+
+    #[test]
+    fn zero_max_keys_returns_an_empty_untruncated_page() {
+        let page = paginate(summaries(KEYS), &ListOptions::new().max_keys(0));
+        assert!(page.objects.is_empty());
+        assert!(!page.is_truncated);
+    }
+
+    #[test]
+    fn ranges_are_inclusive_and_clamped() {
+        assert_eq!(resolve_range(0, Some(4), 10).unwrap(), (0, 5));
+        assert_eq!(resolve_range(3, None, 10).unwrap(), (3, 10));
+        assert_eq!(resolve_range(8, Some(100), 10).unwrap(), (8, 10));
+    }
+
+    #[test]
+    fn ranges_outside_the_object_are_rejected() {
+        assert!(resolve_range(10, None, 10).is_err());
+        assert!(resolve_range(0, None, 0).is_err());
+        assert!(resolve_range(5, Some(4), 10).is_err());
+    }
+
+    #[test]
+    fn etags_follow_content() {
+        assert_eq!(content_etag(b"a"), content_etag(b"a"));
+        assert_ne!(content_etag(b"a"), content_etag(b"b"));
+    }
+}
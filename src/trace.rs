@@ -0,0 +1,70 @@
+//! Plumbing for the optional `tracing` feature.
+//!
+//! Every public [`R2Client`](crate::R2Client) method carries a
+//! `cfg_attr(feature = "tracing", tracing::instrument(...))` attribute that
+//! opens a span named after the method. The exception is
+//! [`list_stream`](crate::R2Client::list_stream), which returns before any
+//! request is sent and instead opens a `list_stream` span around each page
+//! it fetches. Spans always use `skip_all` and name
+//! their fields explicitly: arguments are never captured wholesale, so
+//! credentials, request bodies and presigned URLs cannot leak into a span by
+//! accident. Fields only known once a response arrives — byte counts, the
+//! HTTP status, an upload ID — are declared empty and filled in with
+//! [`record!`], or for the status, by [`RecordStatus`].
+
+use aws_sdk_s3::client::customize::CustomizableOperation;
+
+/// Records `value` under `field` on the current span. Compiles to nothing but
+/// the evaluation of `value` when the `tracing` feature is off.
+macro_rules! record {
+    ($field:literal, $value:expr) => {{
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record($field, $value);
+        #[cfg(not(feature = "tracing"))]
+        let _ = $value;
+    }};
+}
+
+pub(crate) use record;
+
+/// Records the HTTP status of an operation's final response, success or
+/// failure, on the span of the method that sent it.
+pub(crate) trait RecordStatus {
+    /// Must be called inside the method's span: the span is captured here.
+    fn record_status(self) -> Self;
+}
+
+impl<T, E, B> RecordStatus for CustomizableOperation<T, E, B> {
+    fn record_status(self) -> Self {
+        #[cfg(feature = "tracing")]
+        return self.interceptor(StatusInterceptor(tracing::Span::current()));
+        #[cfg(not(feature = "tracing"))]
+        self
+    }
+}
+
+/// The SDK enters spans of its own around each attempt, so an interceptor
+/// cannot reach the calling method's span through `Span::current()`; this one
+/// carries it from where the request was built.
+#[cfg(feature = "tracing")]
+#[derive(Debug)]
+struct StatusInterceptor(tracing::Span);
+
+#[cfg(feature = "tracing")]
+impl aws_sdk_s3::config::Intercept for StatusInterceptor {
+    fn name(&self) -> &'static str {
+        "StatusInterceptor"
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &aws_sdk_s3::config::interceptors::FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &aws_sdk_s3::config::RuntimeComponents,
+        _cfg: &mut aws_sdk_s3::config::ConfigBag,
+    ) -> Result<(), aws_smithy_runtime_api::box_error::BoxError> {
+        if let Some(response) = context.response() {
+            self.0.record("status", response.status().as_u16());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,828 @@
+//! HTTP transport settings: proxies, extra trust roots and connection pooling.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use aws_sdk_s3::config::{RuntimeComponents, SharedHttpClient};
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::connection::{CaptureSmithyConnection, ConnectionMetadata};
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::{HttpRequest, HttpResponse};
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use aws_smithy_types::body::SdkBody;
+use aws_smithy_types::retry::ErrorKind;
+use http::{HeaderValue, Uri};
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder, MaybeHttpsStream};
+use hyper_util::client::legacy::connect::proxy::Tunnel;
+use hyper_util::client::legacy::connect::{
+    capture_connection, CaptureConnection, Connected, Connection, HttpConnector as TcpConnector,
+    HttpInfo,
+};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::proxy::matcher::Matcher;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use rustls::{ClientConfig, RootCertStore};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::CertificateDer;
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+use crate::error::{Error, Result};
+
+/// How the client's HTTP connections are made.
+///
+/// Leaving every field at its default gives the same transport the SDK would
+/// pick on its own: hyper over rustls, HTTP/2 offered over TLS, and pooled
+/// connections closed after 90 idle seconds.
+///
+/// The pool caps idle connections, not open ones: every request goes to the
+/// one R2 endpoint, so
+/// [`RateLimit::max_in_flight`](crate::RateLimit::max_in_flight) bounds the
+/// open connections. Through a proxy, `https://` endpoints are reached with a
+/// `CONNECT` tunnel and `http://` ones by forwarding. For anything else, build
+/// an HTTP client yourself and hand it to
+/// [`R2ClientBuilder::http_client`](crate::R2ClientBuilder::http_client).
+///
+/// ```
+/// use std::time::Duration;
+/// use cloudflare_r2_rs::HttpOptions;
+///
+/// let options = HttpOptions::new()
+///     .proxy("http://egress.internal:3128")
+///     .proxy_auth("svc-r2", "hunter2")
+///     .no_proxy("localhost,.internal")
+///     .pool_idle_timeout(Duration::from_secs(30))
+///     .pool_max_idle_per_host(8)
+///     .tcp_keepalive(Duration::from_secs(60))
+///     .http2(false);
+/// assert_eq!(options.proxy.as_deref(), Some("http://egress.internal:3128"));
+/// ```
+///
+/// `Debug` redacts the proxy password, for the same reason
+/// [`R2Config`](crate::R2Config) redacts the secret key.
+#[derive(Clone)]
+pub struct HttpOptions {
+    /// Proxy URL used for both `http://` and `https://` endpoints.
+    pub proxy: Option<String>,
+    /// Username and password for the proxy's basic authentication.
+    pub proxy_auth: Option<(String, String)>,
+    /// Comma-separated hosts that bypass the proxy, in `NO_PROXY` syntax.
+    pub no_proxy: Option<String>,
+    /// Extra PEM-encoded root certificates to trust, e.g. an internal CA.
+    pub root_certificates: Vec<Vec<u8>>,
+    /// Whether the platform's own root certificates are trusted as well.
+    /// Defaults to `true`.
+    pub native_roots: bool,
+    /// How long an idle pooled connection is kept open. `None` keeps the
+    /// transport's default of 90 seconds.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Most idle connections the pool keeps open per host. `None` keeps the
+    /// transport's default of no limit.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Sets `TCP_NODELAY` on every socket when given. The transport sets it
+    /// by default.
+    pub tcp_nodelay: Option<bool>,
+    /// Idle time before TCP keepalive probes are sent on a socket. `None`
+    /// leaves keepalive off.
+    pub tcp_keepalive: Option<Duration>,
+    /// Whether HTTP/2 is offered when negotiating TLS. Defaults to `true`;
+    /// turn it off to use HTTP/1.1 only.
+    pub http2: bool,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        HttpOptions {
+            proxy: None,
+            proxy_auth: None,
+            no_proxy: None,
+            root_certificates: Vec::new(),
+            native_roots: true,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            tcp_nodelay: None,
+            tcp_keepalive: None,
+            http2: true,
+        }
+    }
+}
+
+impl std::fmt::Debug for HttpOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpOptions")
+            .field("proxy", &self.proxy)
+            .field(
+                "proxy_auth",
+                &self
+                    .proxy_auth
+                    .as_ref()
+                    .map(|(username, _)| (username, "<redacted>")),
+            )
+            .field("no_proxy", &self.no_proxy)
+            .field("root_certificates", &self.root_certificates.len())
+            .field("native_roots", &self.native_roots)
+            .field("pool_idle_timeout", &self.pool_idle_timeout)
+            .field("pool_max_idle_per_host", &self.pool_max_idle_per_host)
+            .field("tcp_nodelay", &self.tcp_nodelay)
+            .field("tcp_keepalive", &self.tcp_keepalive)
+            .field("http2", &self.http2)
+            .finish()
+    }
+}
+
+impl HttpOptions {
+    /// Creates options matching the SDK's default transport.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes every request through the proxy at `url`.
+    #[must_use]
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Authenticates to the proxy with HTTP basic authentication.
+    #[must_use]
+    pub fn proxy_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.proxy_auth = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Lists hosts that bypass the proxy, e.g. `localhost,.internal`.
+    #[must_use]
+    pub fn no_proxy(mut self, rules: impl Into<String>) -> Self {
+        self.no_proxy = Some(rules.into());
+        self
+    }
+
+    /// Trusts the PEM-encoded certificate(s) in `pem` as additional roots.
+    #[must_use]
+    pub fn root_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(pem.into());
+        self
+    }
+
+    /// Chooses whether the platform's root certificates are trusted. Turn this
+    /// off to trust only the certificates added with
+    /// [`root_certificate`](HttpOptions::root_certificate).
+    #[must_use]
+    pub fn native_roots(mut self, enabled: bool) -> Self {
+        self.native_roots = enabled;
+        self
+    }
+
+    /// Sets how long an idle pooled connection is kept open.
+    #[must_use]
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the most idle connections the pool keeps open per host.
+    #[must_use]
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// Sets `TCP_NODELAY` on every socket.
+    #[must_use]
+    pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.tcp_nodelay = Some(enabled);
+        self
+    }
+
+    /// Turns on TCP keepalive, probing a socket after it has been idle for
+    /// `idle`.
+    #[must_use]
+    pub fn tcp_keepalive(mut self, idle: Duration) -> Self {
+        self.tcp_keepalive = Some(idle);
+        self
+    }
+
+    /// Chooses whether HTTP/2 is offered when negotiating TLS. R2 answers
+    /// over HTTP/1.1 when it is not.
+    #[must_use]
+    pub fn http2(mut self, enabled: bool) -> Self {
+        self.http2 = enabled;
+        self
+    }
+
+    /// Validates the options and turns them into an HTTP client for the SDK.
+    ///
+    /// Everything that could fail is checked here, at build time: the
+    /// transport itself panics on a malformed certificate when the first
+    /// connection is made, which is far too late to report a typo.
+    pub(crate) fn into_http_client(self) -> Result<SharedHttpClient> {
+        let proxy = match &self.proxy {
+            None => {
+                if self.proxy_auth.is_some() {
+                    return Err(Error::InvalidConfig {
+                        field: "proxy_auth",
+                        message: "proxy credentials were given without a proxy URL".to_string(),
+                    });
+                }
+                None
+            }
+            Some(url) => Some(Arc::new(proxy_matcher(
+                url,
+                self.proxy_auth.as_ref(),
+                self.no_proxy.as_deref(),
+            )?)),
+        };
+
+        let mut roots = RootCertStore::empty();
+        if self.native_roots {
+            roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+        }
+        for (index, pem) in self.root_certificates.iter().enumerate() {
+            for certificate in validate_pem(index, pem)? {
+                roots.add(certificate).map_err(|err| Error::InvalidConfig {
+                    field: "root_certificates",
+                    message: format!("certificate #{index}: {err}"),
+                })?;
+            }
+        }
+        if !self.native_roots && self.root_certificates.is_empty() {
+            return Err(Error::InvalidConfig {
+                field: "native_roots",
+                message: "native roots are disabled and no root certificate was added, \
+                          so no TLS connection could ever be trusted"
+                    .to_string(),
+            });
+        }
+
+        // The same provider the SDK's default client uses.
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let tls = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|err| Error::InvalidConfig {
+                field: "root_certificates",
+                message: err.to_string(),
+            })?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        Ok(SharedHttpClient::new(Transport {
+            options: self,
+            proxy,
+            tls,
+            connectors: RwLock::new(HashMap::new()),
+        }))
+    }
+}
+
+/// Builds the proxy rules, embedding the credentials in the proxy URL, where
+/// the matcher picks them up for `Proxy-Authorization`.
+fn proxy_matcher(
+    url: &str,
+    auth: Option<&(String, String)>,
+    no_proxy: Option<&str>,
+) -> Result<Matcher> {
+    let invalid = |message: &str| Error::InvalidConfig {
+        field: "proxy",
+        message: format!("`{url}` {message}"),
+    };
+    let uri = url
+        .parse::<Uri>()
+        .map_err(|err| invalid(&format!("is not a URL: {err}")))?;
+    let scheme = match uri.scheme_str() {
+        Some(scheme @ ("http" | "https")) => scheme,
+        _ => return Err(invalid("is not an http:// or https:// URL")),
+    };
+    let authority = uri
+        .authority()
+        .ok_or_else(|| invalid("has no host"))?
+        .as_str();
+
+    let url = match auth {
+        Some((username, password)) => {
+            let host = authority
+                .rsplit_once('@')
+                .map_or(authority, |(_, host)| host);
+            format!(
+                "{scheme}://{}:{}@{host}",
+                escape_userinfo(username),
+                escape_userinfo(password)
+            )
+        }
+        None => format!("{scheme}://{authority}"),
+    };
+    let mut matcher = Matcher::builder().all(url);
+    if let Some(rules) = no_proxy {
+        matcher = matcher.no(rules.to_string());
+    }
+    Ok(matcher.build())
+}
+
+/// Percent-escapes every byte but the URL's unreserved characters.
+fn escape_userinfo(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            escaped.push(char::from(byte));
+        } else {
+            escaped.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    escaped
+}
+
+/// Parses a PEM blob, which must hold at least one well-formed certificate.
+fn validate_pem(index: usize, pem: &[u8]) -> Result<Vec<CertificateDer<'static>>> {
+    let invalid = |message: String| Error::InvalidConfig {
+        field: "root_certificates",
+        message: format!("certificate #{index}: {message}"),
+    };
+
+    let certificates = CertificateDer::pem_slice_iter(pem)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|err| invalid(err.to_string()))?;
+    if certificates.is_empty() {
+        return Err(invalid("no PEM certificate found".to_string()));
+    }
+    Ok(certificates)
+}
+
+/// Connector settings the SDK may vary per request; one connector is cached
+/// for each combination it asks for.
+type ConnectorKey = (Option<Duration>, Option<Duration>);
+
+/// The [`HttpClient`] built from [`HttpOptions`].
+#[derive(Debug)]
+struct Transport {
+    options: HttpOptions,
+    proxy: Option<Arc<Matcher>>,
+    tls: ClientConfig,
+    connectors: RwLock<HashMap<ConnectorKey, SharedHttpConnector>>,
+}
+
+impl HttpClient for Transport {
+    fn http_connector(
+        &self,
+        settings: &HttpConnectorSettings,
+        _components: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        let key = (settings.connect_timeout(), settings.read_timeout());
+        if let Some(connector) = self
+            .connectors
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+        {
+            return connector.clone();
+        }
+
+        let mut connectors = self
+            .connectors
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        connectors
+            .entry(key)
+            .or_insert_with(|| SharedHttpConnector::new(self.connector(settings)))
+            .clone()
+    }
+}
+
+impl Transport {
+    fn connector(&self, settings: &HttpConnectorSettings) -> Connector {
+        let options = &self.options;
+        let mut tcp = TcpConnector::new();
+        // TLS is layered on top, so `https://` destinations are fine here.
+        tcp.enforce_http(false);
+        tcp.set_nodelay(options.tcp_nodelay.unwrap_or(true));
+        tcp.set_keepalive(options.tcp_keepalive);
+
+        let proxy = self.proxy.as_ref().map(|matcher| Proxy {
+            matcher: Arc::clone(matcher),
+            connector: HttpsConnectorBuilder::new()
+                .with_tls_config(self.tls.clone())
+                .https_or_http()
+                .enable_http1()
+                .wrap_connector(tcp.clone()),
+        });
+        let route = Route { tcp, proxy };
+        let https = HttpsConnectorBuilder::new()
+            .with_tls_config(self.tls.clone())
+            .https_or_http()
+            .enable_http1();
+        let https = if options.http2 {
+            https.enable_http2().wrap_connector(route)
+        } else {
+            https.wrap_connector(route)
+        };
+
+        let mut builder = Client::builder(TokioExecutor::new());
+        // Idle connections are only ever closed with a timer to do it.
+        builder.pool_timer(TokioTimer::new());
+        if let Some(timeout) = options.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max_idle) = options.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max_idle);
+        }
+        Connector {
+            client: builder.build(Connect {
+                https,
+                timeout: settings.connect_timeout(),
+            }),
+            proxy: self.proxy.clone(),
+            read_timeout: settings.read_timeout(),
+        }
+    }
+}
+
+/// Sends the SDK's requests through a hyper client.
+#[derive(Debug)]
+struct Connector {
+    client: Client<Connect, SdkBody>,
+    proxy: Option<Arc<Matcher>>,
+    /// How long to wait for the response headers.
+    read_timeout: Option<Duration>,
+}
+
+impl HttpConnector for Connector {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let mut request = match request.try_into_http1x() {
+            Ok(request) => request,
+            Err(err) => return HttpConnectorFuture::ready(Err(ConnectorError::user(err.into()))),
+        };
+        // A forwarding proxy sees the request itself, so it carries the
+        // credentials a tunnel would send with `CONNECT`.
+        if request.uri().scheme_str() == Some("http") {
+            let auth = self
+                .proxy
+                .as_ref()
+                .and_then(|matcher| matcher.intercept(request.uri()))
+                .and_then(|intercept| intercept.basic_auth().cloned());
+            if let Some(auth) = auth {
+                request
+                    .headers_mut()
+                    .insert(http::header::PROXY_AUTHORIZATION, auth);
+            }
+        }
+        // Lets the SDK close a connection that failed, so a retry gets a
+        // fresh one.
+        let captured = capture_connection(&mut request);
+        if let Some(capture) = request.extensions().get::<CaptureSmithyConnection>() {
+            capture.set_connection_retriever(move || connection_metadata(&captured));
+        }
+
+        let responding = self.client.request(request);
+        let read_timeout = self.read_timeout;
+        HttpConnectorFuture::new(async move {
+            let response = match read_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, responding)
+                    .await
+                    .map_err(|elapsed| ConnectorError::timeout(elapsed.into()))?,
+                None => responding.await,
+            }
+            .map_err(connector_error)?;
+            HttpResponse::try_from(response.map(SdkBody::from_body_1_x))
+                .map_err(|err| ConnectorError::other(err.into(), None))
+        })
+    }
+}
+
+fn connection_metadata(captured: &CaptureConnection) -> Option<ConnectionMetadata> {
+    let connected = captured.connection_metadata();
+    let connected = connected.as_ref()?;
+    let mut extras = http::Extensions::new();
+    connected.get_extras(&mut extras);
+    let info = extras.get::<HttpInfo>();
+
+    let poisoner = captured.clone();
+    let mut metadata = ConnectionMetadata::builder()
+        .proxied(connected.is_proxied())
+        .poison_fn(move || {
+            if let Some(connected) = poisoner.connection_metadata().as_ref() {
+                connected.poison();
+            }
+        });
+    metadata
+        .set_local_addr(info.map(HttpInfo::local_addr))
+        .set_remote_addr(info.map(HttpInfo::remote_addr));
+    Some(metadata.build())
+}
+
+/// Classifies a hyper failure the way the SDK's own transport does, so the
+/// retry strategy treats both alike.
+fn connector_error(err: hyper_util::client::legacy::Error) -> ConnectorError {
+    let connect = err.is_connect();
+    let err = BoxError::from(err);
+    if find_source::<tokio::time::error::Elapsed>(err.as_ref()).is_some()
+        || find_source::<io::Error>(err.as_ref())
+            .is_some_and(|io| io.kind() == io::ErrorKind::TimedOut)
+    {
+        return ConnectorError::timeout(err);
+    }
+    if let Some(hyper) = find_source::<hyper::Error>(err.as_ref()) {
+        if hyper.is_timeout() {
+            return ConnectorError::timeout(err);
+        }
+        if hyper.is_user() {
+            return ConnectorError::user(err);
+        }
+        if hyper.is_closed() || hyper.is_canceled() || find_source::<io::Error>(hyper).is_some() {
+            return ConnectorError::io(err);
+        }
+        // R2, like S3, sometimes closes a connection mid-response.
+        if hyper.is_incomplete_message() {
+            return ConnectorError::other(err, Some(ErrorKind::TransientError));
+        }
+        if let Some(h2) = find_source::<h2::Error>(hyper) {
+            if h2.is_go_away() || (h2.is_reset() && h2.reason() == Some(h2::Reason::REFUSED_STREAM))
+            {
+                return ConnectorError::io(err);
+            }
+        }
+    }
+    if connect || find_source::<io::Error>(err.as_ref()).is_some() {
+        return ConnectorError::io(err);
+    }
+    ConnectorError::other(err, None)
+}
+
+fn find_source<'a, E: std::error::Error + 'static>(
+    err: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a E> {
+    let mut next = Some(err);
+    while let Some(err) = next {
+        if let Some(found) = err.downcast_ref::<E>() {
+            return Some(found);
+        }
+        next = err.source();
+    }
+    None
+}
+
+type Connecting<T> = Pin<Box<dyn Future<Output = std::result::Result<T, BoxError>> + Send>>;
+
+/// Opens connections for the hyper client, giving up after the connect
+/// timeout. The timeout covers the TLS handshake and any proxy.
+#[derive(Debug, Clone)]
+struct Connect {
+    https: HttpsConnector<Route>,
+    timeout: Option<Duration>,
+}
+
+impl Service<Uri> for Connect {
+    type Response = MaybeHttpsStream<Stream>;
+    type Error = BoxError;
+    type Future = Connecting<Self::Response>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), BoxError>> {
+        self.https.poll_ready(cx)
+    }
+
+    fn call(&mut self, destination: Uri) -> Self::Future {
+        let connecting = self.https.call(destination);
+        let timeout = self.timeout;
+        Box::pin(async move {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, connecting).await?,
+                None => connecting.await,
+            }
+        })
+    }
+}
+
+/// The proxy rules and how to reach the proxy itself.
+#[derive(Debug, Clone)]
+struct Proxy {
+    matcher: Arc<Matcher>,
+    connector: HttpsConnector<TcpConnector>,
+}
+
+/// Opens the connection TLS is layered on: straight to the destination, a
+/// `CONNECT` tunnel through the proxy for `https://`, or the proxy itself for
+/// `http://`, which the proxy forwards.
+#[derive(Debug, Clone)]
+struct Route {
+    tcp: TcpConnector,
+    proxy: Option<Proxy>,
+}
+
+impl Service<Uri> for Route {
+    type Response = Stream;
+    type Error = BoxError;
+    type Future = Connecting<Stream>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<std::result::Result<(), BoxError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, destination: Uri) -> Self::Future {
+        let intercept = self
+            .proxy
+            .as_ref()
+            .and_then(|proxy| Some((proxy, proxy.matcher.intercept(&destination)?)));
+        match intercept {
+            None => {
+                let connecting = self.tcp.call(destination);
+                Box::pin(async move {
+                    Ok(Stream {
+                        io: MaybeHttpsStream::Http(connecting.await?),
+                        forwarding: false,
+                    })
+                })
+            }
+            Some((proxy, intercept)) if destination.scheme_str() == Some("http") => {
+                let connecting = proxy.connector.clone().call(intercept.uri().clone());
+                Box::pin(async move {
+                    Ok(Stream {
+                        io: connecting.await?,
+                        forwarding: true,
+                    })
+                })
+            }
+            Some((proxy, intercept)) => {
+                let mut tunnel = Tunnel::new(intercept.uri().clone(), proxy.connector.clone());
+                if let Some(auth) = intercept.basic_auth() {
+                    tunnel = tunnel.with_auth(HeaderValue::clone(auth));
+                }
+                let connecting = tunnel.call(destination);
+                Box::pin(async move {
+                    Ok(Stream {
+                        io: connecting.await?,
+                        forwarding: false,
+                    })
+                })
+            }
+        }
+    }
+}
+
+/// A connection opened by [`Route`]. `forwarding` marks a connection to a
+/// forwarding proxy, so hyper sends it absolute URLs.
+#[derive(Debug)]
+struct Stream {
+    io: MaybeHttpsStream<TokioIo<TcpStream>>,
+    forwarding: bool,
+}
+
+impl Connection for Stream {
+    fn connected(&self) -> Connected {
+        self.io.connected().proxy(self.forwarding)
+    }
+}
+
+impl Read for Stream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl Write for Stream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_build() {
+        assert!(HttpOptions::new().into_http_client().is_ok());
+    }
+
+    #[test]
+    fn debug_redacts_the_proxy_password() {
+        let options = HttpOptions::new()
+            .proxy("http://proxy:3128")
+            .proxy_auth("svc", "super-secret-value");
+        let rendered = format!("{options:?}");
+        assert!(!rendered.contains("super-secret-value"), "{rendered}");
+        assert!(rendered.contains("svc"), "{rendered}");
+    }
+
+    #[test]
+    fn rejects_an_unparseable_proxy_url() {
+        for url in ["not a url", "socks5://proxy:1080", "/relative"] {
+            let err = HttpOptions::new()
+                .proxy(url)
+                .into_http_client()
+                .unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidConfig { field: "proxy", .. }),
+                "{url}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn proxy_credentials_are_escaped_into_the_url() {
+        let matcher = proxy_matcher(
+            "http://proxy:3128",
+            Some(&("svc".to_string(), "p@ss:w/rd".to_string())),
+            Some("localhost"),
+        )
+        .unwrap();
+        let intercept = matcher
+            .intercept(&Uri::from_static("https://acct.r2.cloudflarestorage.com/"))
+            .unwrap();
+        assert_eq!(intercept.uri(), "http://proxy:3128/");
+        // base64("svc:p@ss:w/rd")
+        assert_eq!(
+            intercept.basic_auth().unwrap(),
+            "Basic c3ZjOnBAc3M6dy9yZA=="
+        );
+        assert!(matcher
+            .intercept(&Uri::from_static("http://localhost:9000/"))
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_proxy_credentials_without_a_proxy() {
+        let err = HttpOptions::new()
+            .proxy_auth("svc", "pw")
+            .into_http_client()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidConfig {
+                field: "proxy_auth",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_a_malformed_certificate_up_front() {
+        for pem in [
+            b"not a certificate".to_vec(),
+            b"-----BEGIN CERTIFICATE-----\n!!!\n-----END CERTIFICATE-----\n".to_vec(),
+        ] {
+            let err = HttpOptions::new()
+                .root_certificate(pem)
+                .into_http_client()
+                .unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    Error::InvalidConfig {
+                        field: "root_certificates",
+                        ..
+                    }
+                ),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_an_empty_trust_store() {
+        let err = HttpOptions::new()
+            .native_roots(false)
+            .into_http_client()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidConfig {
+                field: "native_roots",
+                ..
+            }
+        ));
+    }
+}
@@ -5,6 +5,8 @@ use std::time::Duration;
 
 pub use aws_sdk_s3::primitives::DateTime;
 
+use crate::error::Result;
+
 /// Default part size for multipart uploads (8 MiB).
 pub const DEFAULT_PART_SIZE: u64 = 8 * 1024 * 1024;
 
@@ -31,7 +33,12 @@ pub const MAX_DELETE_BATCH: usize = 1000;
 ///     .metadata("uploaded-by", "worker-7");
 /// assert_eq!(options.content_type.as_deref(), Some("application/json"));
 /// ```
+///
+/// Build it with [`new`](PutOptions::new) and the setters: fields depend on
+/// crate features, so a struct literal would not compile for every feature
+/// set.
 #[derive(Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct PutOptions {
     /// MIME type. When unset it is guessed from the key's extension.
     pub content_type: Option<String>,
@@ -45,6 +52,19 @@ pub struct PutOptions {
     pub content_language: Option<String>,
     /// Arbitrary user metadata, stored as `x-amz-meta-*` headers.
     pub metadata: HashMap<String, String>,
+    /// Compresses the body on upload, and sets `Content-Encoding` to match.
+    /// Honoured by `put_object_with`, `upload_file_with` and
+    /// `multipart_upload_file`; `create_multipart_upload` rejects it.
+    #[cfg(feature = "compression")]
+    pub compression: Option<crate::Compression>,
+    /// `If-Match` precondition: stores the object only if the current one
+    /// has this ETag. Honoured like `if_none_match`.
+    pub if_match: Option<String>,
+    /// `If-None-Match` precondition: `*` stores the object only if the key is
+    /// free. Honoured by `put_object_with`, `upload_file_with` and
+    /// `multipart_upload_file` (on completion); `create_multipart_upload`
+    /// rejects it.
+    pub if_none_match: Option<String>,
 }
 
 impl PutOptions {
@@ -96,6 +116,25 @@ impl PutOptions {
         self
     }
 
+    /// Sets the `If-Match` precondition: the write fails with `412
+    /// Precondition Failed` unless the stored object's ETag is `etag`, so a
+    /// read-modify-write cannot overwrite a change made in between.
+    #[must_use]
+    pub fn if_match(mut self, etag: impl Into<String>) -> Self {
+        self.if_match = Some(etag.into());
+        self
+    }
+
+    /// Sets the `If-None-Match` precondition. With `"*"`, the write fails
+    /// with `412 Precondition Failed` (see
+    /// [`Error::is_precondition_failed`](crate::Error::is_precondition_failed))
+    /// when the key already exists, instead of replacing the object.
+    #[must_use]
+    pub fn if_none_match(mut self, value: impl Into<String>) -> Self {
+        self.if_none_match = Some(value.into());
+        self
+    }
+
     /// Replaces all user metadata at once.
     #[must_use]
     pub fn metadata_map(mut self, metadata: HashMap<String, String>) -> Self {
@@ -103,6 +142,25 @@ impl PutOptions {
         self
     }
 
+    /// Resolves `Content-Encoding`: the codec's name when compressing, which
+    /// an explicit encoding must agree with.
+    pub(crate) fn resolved_content_encoding(&self) -> Result<Option<String>> {
+        #[cfg(feature = "compression")]
+        if let Some(compression) = self.compression {
+            let encoding = compression.content_encoding();
+            return match &self.content_encoding {
+                Some(explicit) if !explicit.eq_ignore_ascii_case(encoding) => {
+                    Err(crate::Error::invalid_argument(
+                        "content_encoding",
+                        format!("`{explicit}` contradicts compressing the body as `{encoding}`"),
+                    ))
+                }
+                _ => Ok(Some(encoding.to_string())),
+            };
+        }
+        Ok(self.content_encoding.clone())
+    }
+
     /// Resolves the content type, falling back to a guess from `key`.
     pub(crate) fn resolved_content_type(&self, key: &str) -> String {
         self.content_type.clone().unwrap_or_else(|| {
@@ -181,6 +239,7 @@ impl ListOptions {
 
 /// One object as returned by a listing.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct ObjectSummary {
     /// The object's key.
@@ -190,11 +249,13 @@ pub struct ObjectSummary {
     /// Entity tag, quoted as the service returned it.
     pub etag: Option<String>,
     /// When the object was last written.
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::json::date_time"))]
     pub last_modified: Option<DateTime>,
 }
 
 /// A single page of a listing.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct ListPage {
     /// Objects on this page.
@@ -209,6 +270,7 @@ pub struct ListPage {
 
 /// Full metadata for a single object, as returned by `HEAD`.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct ObjectMetadata {
     /// Size in bytes.
@@ -218,6 +280,7 @@ pub struct ObjectMetadata {
     /// Entity tag.
     pub etag: Option<String>,
     /// When the object was last written.
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::json::date_time"))]
     pub last_modified: Option<DateTime>,
     /// Stored `Cache-Control` header.
     pub cache_control: Option<String>,
@@ -233,6 +296,7 @@ pub struct ObjectMetadata {
 
 /// Result of storing an object.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct PutOutcome {
     /// The key that was written.
@@ -243,6 +307,7 @@ pub struct PutOutcome {
 
 /// One key that could not be deleted in a batch delete.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct DeleteFailure {
     /// The key that survived.
@@ -256,6 +321,7 @@ pub struct DeleteFailure {
 /// Outcome of a batch delete. R2 reports per-key failures rather than failing
 /// the whole request, so both lists can be non-empty at once.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct DeleteReport {
     /// Keys that were removed.
@@ -273,11 +339,13 @@ impl DeleteReport {
 
 /// A bucket owned by the account.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct BucketSummary {
     /// Bucket name.
     pub name: String,
     /// When the bucket was created.
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::json::date_time"))]
     pub creation_date: Option<DateTime>,
 }
 
@@ -353,6 +421,7 @@ impl MultipartOptions {
 
 /// An in-progress multipart upload.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct MultipartUpload {
     /// Key being uploaded to.
@@ -360,6 +429,7 @@ pub struct MultipartUpload {
     /// Upload ID, needed to add parts, complete, or abort.
     pub upload_id: String,
     /// When the upload was started, for listings.
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::json::date_time"))]
     pub initiated: Option<DateTime>,
 }
 
@@ -372,6 +442,7 @@ pub struct MultipartUpload {
 /// ETag persisted elsewhere, which is what resuming an upload across processes
 /// requires.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct CompletedPart {
     /// 1-based part number.
@@ -0,0 +1,102 @@
+//! The synchronous client against the mock server, from plain `#[test]`s.
+
+#![cfg(all(feature = "blocking", feature = "mock"))]
+
+use std::time::Duration;
+
+use cloudflare_r2_rs::blocking::R2Client;
+use cloudflare_r2_rs::mock::MockServer;
+use cloudflare_r2_rs::{ListOptions, MultipartOptions, PutOptions};
+
+/// A mock server on its own runtime, which must outlive the client's
+/// requests, and a blocking client for it.
+fn client() -> (tokio::runtime::Runtime, MockServer, R2Client) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(MockServer::start()).unwrap();
+    let client = R2Client::new(server.client()).unwrap();
+    (runtime, server, client)
+}
+
+#[test]
+fn objects_round_trip_without_a_runtime() {
+    let (_runtime, _server, client) = client();
+
+    client
+        .put_object_with(
+            "docs/a.txt",
+            "hello world",
+            PutOptions::new().content_type("text/plain"),
+        )
+        .unwrap();
+    assert_eq!(client.get_object("docs/a.txt").unwrap(), b"hello world");
+    assert_eq!(
+        client.get_object_range("docs/a.txt", 6, None).unwrap(),
+        b"world"
+    );
+    let head = client.head_object("docs/a.txt").unwrap();
+    assert_eq!(head.content_length, 11);
+    assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+
+    client.copy_object("docs/a.txt", "docs/b.txt").unwrap();
+    let page = client
+        .list_objects(ListOptions::new().prefix("docs/"))
+        .unwrap();
+    assert_eq!(page.objects.len(), 2);
+
+    client.delete_object("docs/a.txt").unwrap();
+    assert!(!client.object_exists("docs/a.txt").unwrap());
+    assert!(client.get_object("docs/a.txt").unwrap_err().is_not_found());
+    assert!(client.delete_prefix("docs/").unwrap().all_succeeded());
+    assert!(client.list_all_objects(None).unwrap().is_empty());
+}
+
+#[test]
+fn files_upload_and_download() {
+    let (_runtime, _server, client) = client();
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source.bin");
+    let data: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&source, &data).unwrap();
+
+    client
+        .upload_file_with(
+            "big.bin",
+            &source,
+            MultipartOptions::new()
+                .part_size(16 * 1024)
+                .threshold(16 * 1024),
+        )
+        .unwrap();
+    let destination = dir.path().join("out/big.bin");
+    assert_eq!(
+        client.download_to("big.bin", &destination).unwrap(),
+        data.len() as u64
+    );
+    assert_eq!(std::fs::read(&destination).unwrap(), data);
+}
+
+#[test]
+fn clones_and_other_buckets_share_the_runtime() {
+    let (_runtime, _server, client) = client();
+    let other = client.with_bucket("elsewhere");
+    assert_eq!(other.bucket(), "elsewhere");
+    assert!(!other.bucket_exists().unwrap());
+
+    let clone = client.clone();
+    let handle = std::thread::spawn(move || clone.put_object("from-thread", "x").unwrap());
+    handle.join().unwrap();
+    assert!(client.object_exists("from-thread").unwrap());
+
+    let url = client
+        .presign_get("from-thread", Duration::from_secs(60))
+        .unwrap();
+    assert!(url.as_str().contains("X-Amz-Signature="));
+}
+
+#[tokio::test]
+#[should_panic(expected = "within an async runtime")]
+async fn using_it_inside_a_runtime_panics() {
+    let server = MockServer::start().await.unwrap();
+    let client = R2Client::new(server.client()).unwrap();
+    let _ = client.object_exists("a.txt");
+}
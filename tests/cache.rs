@@ -0,0 +1,145 @@
+//! The on-disk read-through cache against the mock server.
+
+#![cfg(feature = "mock")]
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{CachedClient, MetricsObserver, OperationEvent};
+use tokio::io::AsyncReadExt;
+
+/// Keeps the status of every GET.
+#[derive(Debug, Default)]
+struct Statuses(Mutex<Vec<Option<u16>>>);
+
+impl MetricsObserver for Statuses {
+    fn on_operation(&self, event: &OperationEvent) {
+        if event.operation == "GetObject" {
+            self.0.lock().unwrap().push(event.status);
+        }
+    }
+}
+
+fn entries(dir: &std::path::Path) -> usize {
+    std::fs::read_dir(dir).map_or(0, |entries| entries.count())
+}
+
+#[tokio::test]
+async fn reads_revalidate_and_follow_changes() {
+    let server = MockServer::start().await.unwrap();
+    let statuses = Arc::new(Statuses::default());
+    let client = server
+        .builder(MOCK_BUCKET)
+        .metrics(statuses.clone())
+        .build()
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let cached = CachedClient::new(client.clone(), dir.path().join("cache"), 1 << 20);
+
+    client.put_object("config.json", "v1").await.unwrap();
+    assert_eq!(cached.get_object("config.json").await.unwrap(), b"v1");
+    assert_eq!(cached.get_object("config.json").await.unwrap(), b"v1");
+    assert_eq!(*statuses.0.lock().unwrap(), [Some(200), Some(304)]);
+    assert_eq!(entries(&dir.path().join("cache")), 1);
+
+    client.put_object("config.json", "v2").await.unwrap();
+    assert_eq!(cached.get_object("config.json").await.unwrap(), b"v2");
+    let mut file = cached.open("config.json").await.unwrap();
+    let mut body = String::new();
+    file.read_to_string(&mut body).await.unwrap();
+    assert_eq!(body, "v2");
+    assert_eq!(statuses.0.lock().unwrap()[2..], [Some(200), Some(304)]);
+
+    client.delete_object("config.json").await.unwrap();
+    assert!(cached
+        .get_object("config.json")
+        .await
+        .unwrap_err()
+        .is_not_found());
+    assert_eq!(entries(&dir.path().join("cache")), 0);
+    // The file opened earlier is still readable after its entry is gone.
+    drop(file);
+
+    client.put_object("config.json", "v3").await.unwrap();
+    cached.get_object("config.json").await.unwrap();
+    cached.invalidate("config.json").await.unwrap();
+    cached.invalidate("config.json").await.unwrap();
+    assert_eq!(entries(&dir.path().join("cache")), 0);
+}
+
+#[tokio::test]
+async fn least_recently_used_entries_are_evicted() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let dir = tempfile::tempdir().unwrap();
+    // Room for three 1 KiB bodies and their headers, but not four.
+    let cached = CachedClient::new(client.clone(), dir.path(), 3 * 1024 + 200);
+    for key in ["a", "b", "c", "d"] {
+        client.put_object(key, vec![b'x'; 1024]).await.unwrap();
+    }
+
+    for key in ["a", "b", "c"] {
+        cached.get_object(key).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    // Reading `a` again makes `b` the least recently used.
+    cached.get_object("a").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    cached.get_object("d").await.unwrap();
+    assert_eq!(entries(dir.path()), 3);
+
+    let statuses = Arc::new(Statuses::default());
+    let observed = CachedClient::new(
+        server
+            .builder(MOCK_BUCKET)
+            .metrics(statuses.clone())
+            .build()
+            .unwrap(),
+        dir.path(),
+        3 * 1024 + 200,
+    );
+    for key in ["a", "c", "d"] {
+        observed.get_object(key).await.unwrap();
+    }
+    assert_eq!(*statuses.0.lock().unwrap(), [Some(304); 3]);
+    observed.get_object("b").await.unwrap();
+    assert_eq!(statuses.0.lock().unwrap()[3], Some(200));
+
+    // An object bigger than the whole cache is served but not kept.
+    client
+        .put_object("huge", vec![b'y'; 8 * 1024])
+        .await
+        .unwrap();
+    assert_eq!(cached.get_object("huge").await.unwrap().len(), 8 * 1024);
+    assert!(entries(dir.path()) <= 3);
+}
+
+#[tokio::test]
+async fn concurrent_readers_share_one_entry() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let dir = tempfile::tempdir().unwrap();
+    let body: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+    client.put_object("model.bin", body.clone()).await.unwrap();
+
+    // Separate instances stand in for separate processes.
+    let readers: Vec<_> = (0..8)
+        .map(|_| {
+            let cached = CachedClient::new(client.clone(), dir.path(), 1 << 20);
+            tokio::spawn(async move { cached.get_object("model.bin").await.unwrap() })
+        })
+        .collect();
+    for reader in readers {
+        assert_eq!(reader.await.unwrap(), body);
+    }
+    // One entry, and no temporary files left behind.
+    assert_eq!(entries(dir.path()), 1);
+
+    // Keys are hashed into file names, so none can escape the directory.
+    let cached = CachedClient::new(client.clone(), dir.path(), 1 << 20);
+    client.put_object("../../escape", "x").await.unwrap();
+    cached.get_object("../../escape").await.unwrap();
+    assert_eq!(entries(dir.path()), 2);
+    assert!(!dir.path().join("../../escape").exists());
+}
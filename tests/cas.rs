@@ -0,0 +1,173 @@
+//! Content-addressed blobs against the mock server.
+
+#![cfg(feature = "mock")]
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{BlobHash, BlobStore, Error, MultipartOptions, PutOptions};
+
+#[tokio::test]
+async fn identical_content_is_stored_once() {
+    let server = MockServer::start().await.unwrap();
+    let blobs = BlobStore::new(server.client(), "artifacts");
+
+    let first = blobs.put("hello").await.unwrap();
+    assert!(first.uploaded);
+    assert_eq!(first.hash, BlobHash::of("hello"));
+    assert_eq!(first.key, format!("artifacts/2c/{}", first.hash));
+    assert_eq!(first.size, 5);
+
+    let again = blobs.put("hello").await.unwrap();
+    assert!(!again.uploaded);
+    assert_eq!(again.key, first.key);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("hello.txt");
+    std::fs::write(&path, "hello").unwrap();
+    let from_file = blobs.put_file(&path).await.unwrap();
+    assert!(!from_file.uploaded);
+    assert_eq!(from_file.hash, first.hash);
+
+    assert_eq!(server.keys(MOCK_BUCKET), [first.key.as_str()]);
+    assert!(blobs.contains(&first.hash).await.unwrap());
+    assert_eq!(blobs.get(&first.hash).await.unwrap(), b"hello");
+}
+
+#[tokio::test]
+async fn large_files_stream_up_in_parts() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let blobs = BlobStore::new(client.clone(), "");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("big.bin");
+    let data: Vec<u8> = (0..11 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &data).unwrap();
+
+    let stored = blobs.put_file(&path).await.unwrap();
+    assert!(stored.uploaded);
+    assert_eq!(stored.hash, BlobHash::of(&data));
+    assert_eq!(stored.size, data.len() as u64);
+
+    let destination = dir.path().join("out/big.bin");
+    std::fs::create_dir_all(destination.parent().unwrap()).unwrap();
+    let written = blobs.download_to(&stored.hash, &destination).await.unwrap();
+    assert_eq!(written, data.len() as u64);
+    assert_eq!(std::fs::read(&destination).unwrap(), data);
+
+    // A multipart upload onto an existing key fails on completion, and the
+    // upload is aborted rather than left holding its parts.
+    let err = client
+        .upload_file_with(
+            &stored.key,
+            &path,
+            MultipartOptions::new()
+                .threshold(1024)
+                .put_options(PutOptions::new().if_none_match("*")),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.is_precondition_failed(), "{err}");
+    assert!(client.list_multipart_uploads().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn corrupted_blobs_fail_verification() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let blobs = BlobStore::new(client.clone(), "cas");
+    let stored = blobs.put("original").await.unwrap();
+    client.put_object(&stored.key, "tampered").await.unwrap();
+
+    let err = blobs.get(&stored.hash).await.unwrap_err();
+    assert!(
+        matches!(&err, Error::Integrity { key, expected, .. }
+            if *key == stored.key && *expected == stored.hash.to_string()),
+        "{err}"
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let destination = dir.path().join("blob");
+    let err = blobs
+        .download_to(&stored.hash, &destination)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Integrity { .. }), "{err}");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    let missing = BlobHash::of("never stored");
+    assert!(blobs.get(&missing).await.unwrap_err().is_not_found());
+}
+
+#[tokio::test]
+async fn garbage_collection_spares_live_and_young_blobs() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let blobs = BlobStore::new(client.clone(), "cas");
+    let live = blobs.put("live").await.unwrap();
+    let dead = blobs.put("dead").await.unwrap();
+    client.put_object("cas/README", "not a blob").await.unwrap();
+    client.put_object("elsewhere/x", "outside").await.unwrap();
+    let live_set = HashSet::from([live.hash]);
+
+    // Everything was just written, so an hour's grace keeps it all.
+    let report = blobs
+        .collect_garbage(&live_set, Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert_eq!(report.kept, 2);
+    assert!(report.deleted.is_empty());
+
+    // The mock stamps objects to the second; step past it.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let report = blobs
+        .collect_garbage(&live_set, Duration::ZERO)
+        .await
+        .unwrap();
+    assert_eq!(report.kept, 1);
+    assert_eq!(report.deleted, [dead.key.as_str()]);
+    assert!(report.failed.is_empty());
+
+    assert_eq!(
+        server.keys(MOCK_BUCKET),
+        [live.key, "cas/README".to_string(), "elsewhere/x".into()]
+    );
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn compressed_blobs_need_a_decompressing_client() {
+    use cloudflare_r2_rs::Compression;
+
+    let server = MockServer::start().await.unwrap();
+    let options = PutOptions::new().compression(Compression::Gzip);
+
+    let blobs = BlobStore::new(server.client(), "cas").put_options(options.clone());
+    let err = blobs.put("hello").await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::InvalidArgument {
+                argument: "compression",
+                ..
+            }
+        ),
+        "{err:?}"
+    );
+    assert!(server.keys(MOCK_BUCKET).is_empty());
+
+    let client = server
+        .builder(MOCK_BUCKET)
+        .decompress(true)
+        .build()
+        .unwrap();
+    let blobs = BlobStore::new(client, "cas").put_options(options);
+    let stored = blobs.put("hello").await.unwrap();
+    assert_eq!(blobs.get(&stored.hash).await.unwrap(), b"hello");
+
+    let dir = tempfile::tempdir().unwrap();
+    let destination = dir.path().join("blob");
+    blobs.download_to(&stored.hash, &destination).await.unwrap();
+    assert_eq!(std::fs::read(&destination).unwrap(), b"hello");
+}
@@ -0,0 +1,155 @@
+//! Interactions recorded against the mock server, then replayed without it.
+
+#![cfg(all(feature = "cassette", feature = "mock"))]
+
+use std::path::Path;
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{Cassette, ListOptions, MultipartOptions, PutOptions, R2Client};
+
+/// The requests under test, run once live and once from the cassette.
+async fn exercise(client: &R2Client) {
+    client
+        .put_object_with(
+            "docs/a.txt",
+            "first",
+            PutOptions::new().content_type("text/plain"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(client.get_object("docs/a.txt").await.unwrap(), b"first");
+    client.put_object("docs/a.txt", "second").await.unwrap();
+    assert_eq!(client.get_object("docs/a.txt").await.unwrap(), b"second");
+    client
+        .put_object("docs/b.bin", vec![0u8, 159, 146, 150, 255])
+        .await
+        .unwrap();
+    assert_eq!(
+        client.get_object("docs/b.bin").await.unwrap(),
+        [0u8, 159, 146, 150, 255]
+    );
+
+    let page = client
+        .list_objects(ListOptions::new().prefix("docs/"))
+        .await
+        .unwrap();
+    let keys: Vec<_> = page
+        .objects
+        .iter()
+        .map(|object| object.key.as_str())
+        .collect();
+    assert_eq!(keys, ["docs/a.txt", "docs/b.bin"]);
+
+    let head = client.head_object("docs/a.txt").await.unwrap();
+    assert_eq!(head.content_length, 6);
+    assert!(client
+        .head_object("missing")
+        .await
+        .unwrap_err()
+        .is_not_found());
+
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("big.bin");
+    std::fs::write(&source, vec![3u8; 6 * 1024 * 1024]).unwrap();
+    client
+        .upload_file_with(
+            "big.bin",
+            &source,
+            MultipartOptions::new().part_size(5 * 1024 * 1024),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        client.head_object("big.bin").await.unwrap().content_length,
+        6 * 1024 * 1024
+    );
+}
+
+fn replaying_client(path: &Path) -> (R2Client, Cassette) {
+    let cassette = Cassette::replay(path).unwrap();
+    let client = R2Client::builder()
+        // Nothing listens here; every response must come from the cassette.
+        .endpoint("http://127.0.0.1:9")
+        .bucket(MOCK_BUCKET)
+        .access_key_id("replay")
+        .secret_access_key("replay")
+        .cassette(cassette.clone())
+        .build()
+        .unwrap();
+    (client, cassette)
+}
+
+#[tokio::test]
+async fn recorded_interactions_replay_offline() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cassettes/session.json");
+
+    let server = MockServer::start().await.unwrap();
+    server.client();
+    let recorder = Cassette::record(&path);
+    let client = server
+        .builder(MOCK_BUCKET)
+        .cassette(recorder.clone())
+        .build()
+        .unwrap();
+    exercise(&client).await;
+    drop(client);
+    drop(server);
+    assert!(!recorder.is_empty());
+
+    let (client, cassette) = replaying_client(&path);
+    assert_eq!(cassette.len(), recorder.len());
+    exercise(&client).await;
+    assert_eq!(cassette.remaining(), 0);
+}
+
+#[tokio::test]
+async fn recordings_are_scrubbed_of_credentials() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.json");
+
+    let server = MockServer::start().await.unwrap();
+    server.client();
+    let client = server
+        .builder(MOCK_BUCKET)
+        .cassette(Cassette::record(&path))
+        .build()
+        .unwrap();
+    client.put_object("a.txt", "hello").await.unwrap();
+
+    let recorded = std::fs::read_to_string(&path).unwrap();
+    assert!(recorded.contains("[scrubbed]"));
+    assert!(!recorded.contains("mock-access-key"));
+    assert!(!recorded.contains("Signature="));
+}
+
+#[tokio::test]
+async fn unrecorded_requests_fail_with_the_request_named() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.json");
+    std::fs::write(&path, r#"{ "version": 1, "interactions": [] }"#).unwrap();
+
+    let (client, _cassette) = replaying_client(&path);
+    let err = client.get_object("a.txt").await.unwrap_err();
+    assert!(
+        format!("{err:?}").contains(&format!("GET /{MOCK_BUCKET}/a.txt")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn unreadable_cassettes_are_file_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing.json");
+    assert!(matches!(
+        Cassette::replay(&missing),
+        Err(cloudflare_r2_rs::Error::File { .. })
+    ));
+
+    let invalid = dir.path().join("invalid.json");
+    std::fs::write(&invalid, "not json").unwrap();
+    assert!(matches!(
+        Cassette::replay(&invalid),
+        Err(cloudflare_r2_rs::Error::File { .. })
+    ));
+}
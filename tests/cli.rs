@@ -0,0 +1,268 @@
+//! The `r2` binary against the mock server, configured through the
+//! environment as a user would configure it.
+
+#![cfg(all(feature = "cli", feature = "mock"))]
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::PutOptions;
+use serde_json::Value;
+
+/// A mock server on its own runtime, which keeps serving while the binary
+/// runs.
+fn server() -> (tokio::runtime::Runtime, MockServer) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(MockServer::start()).unwrap();
+    (runtime, server)
+}
+
+/// `r2` with credentials for the mock server, but no default bucket.
+fn command(server: &MockServer, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_r2"));
+    command
+        .args(args)
+        .env_clear()
+        .env("R2_ENDPOINT", server.endpoint())
+        .env("R2_ACCESS_KEY_ID", "mock-access-key")
+        .env("R2_SECRET_ACCESS_KEY", "mock-secret-key");
+    command
+}
+
+fn r2(server: &MockServer, args: &[&str]) -> Output {
+    command(server, args)
+        .env("R2_BUCKET", MOCK_BUCKET)
+        .output()
+        .unwrap()
+}
+
+/// Runs `r2`, asserting it succeeds, and returns its standard output.
+fn ok(server: &MockServer, args: &[&str]) -> String {
+    let output = r2(server, args);
+    assert!(
+        output.status.success(),
+        "r2 {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn json(server: &MockServer, args: &[&str]) -> Value {
+    let mut args = args.to_vec();
+    args.push("--json");
+    serde_json::from_str(&ok(server, &args)).unwrap()
+}
+
+fn remote(key: &str) -> String {
+    format!("r2://{MOCK_BUCKET}/{key}")
+}
+
+#[test]
+fn objects_copy_list_and_delete() {
+    let (_runtime, server) = server();
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("a.txt");
+    std::fs::write(&source, "hello world").unwrap();
+    let source = source.to_str().unwrap();
+
+    ok(&server, &["cp", source, &remote("docs/")]);
+    assert_eq!(
+        server.object(MOCK_BUCKET, "docs/a.txt").unwrap(),
+        "hello world"
+    );
+    ok(
+        &server,
+        &["cp", &remote("docs/a.txt"), &remote("docs/b.txt")],
+    );
+    ok(
+        &server,
+        &["mv", &remote("docs/b.txt"), &remote("old/b.txt")],
+    );
+    assert!(server.object(MOCK_BUCKET, "docs/b.txt").is_none());
+
+    assert_eq!(ok(&server, &["cat", &remote("docs/a.txt")]), "hello world");
+    let head = json(&server, &["head", &remote("docs/a.txt")]);
+    assert_eq!(head["content_length"], 11);
+
+    let listing = json(&server, &["ls", &remote("")]);
+    assert_eq!(listing["prefixes"], serde_json::json!(["docs/", "old/"]));
+    assert_eq!(listing["objects"], serde_json::json!([]));
+    let listing = json(&server, &["ls", "--recursive", &remote("")]);
+    assert_eq!(listing["objects"].as_array().unwrap().len(), 2);
+    assert!(ok(&server, &["ls", &remote("docs/")]).contains("docs/a.txt"));
+
+    let downloaded = dir.path().join("down");
+    std::fs::create_dir(&downloaded).unwrap();
+    ok(
+        &server,
+        &["cp", &remote("old/b.txt"), downloaded.to_str().unwrap()],
+    );
+    assert_eq!(
+        std::fs::read_to_string(downloaded.join("b.txt")).unwrap(),
+        "hello world"
+    );
+
+    let removed = json(&server, &["rm", "--recursive", &remote("docs/")]);
+    assert_eq!(removed["deleted"], serde_json::json!(["docs/a.txt"]));
+    ok(&server, &["rm", &remote("old/b.txt")]);
+    assert!(server.keys(MOCK_BUCKET).is_empty());
+}
+
+#[test]
+fn presigning_and_buckets() {
+    let (_runtime, server) = server();
+
+    let url = ok(&server, &["presign", "--expires", "60", &remote("a.txt")]);
+    assert!(url.contains("X-Amz-Expires=60"), "{url}");
+    let put = json(&server, &["presign", "--method", "put", &remote("a.txt")]);
+    assert_eq!(put["method"], "PUT");
+
+    ok(&server, &["mb", "r2://scratch"]);
+    let buckets = json(&server, &["ls"]);
+    let names: Vec<_> = buckets
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| bucket["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"scratch"), "{names:?}");
+    ok(&server, &["rb", "scratch"]);
+    assert_eq!(json(&server, &["ls"]).as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn rules_round_trip_through_files() {
+    let (_runtime, server) = server();
+    let dir = tempfile::tempdir().unwrap();
+
+    let cors = dir.path().join("cors.json");
+    std::fs::write(
+        &cors,
+        r#"{ "CORSRules": [{ "AllowedOrigins": ["*"], "AllowedMethods": ["GET"] }] }"#,
+    )
+    .unwrap();
+    ok(&server, &["cors", "put", cors.to_str().unwrap()]);
+    let got: Value = serde_json::from_str(&ok(&server, &["cors", "get"])).unwrap();
+    assert_eq!(
+        got["CORSRules"][0]["AllowedMethods"],
+        serde_json::json!(["GET"])
+    );
+
+    let lifecycle = dir.path().join("lifecycle.json");
+    std::fs::write(
+        &lifecycle,
+        r#"{ "Rules": [{ "ID": "logs", "Status": "Enabled",
+             "Filter": { "Prefix": "logs/" }, "Expiration": { "Days": 7 } }] }"#,
+    )
+    .unwrap();
+    ok(&server, &["lifecycle", "put", lifecycle.to_str().unwrap()]);
+    let got: Value = serde_json::from_str(&ok(&server, &["lifecycle", "get"])).unwrap();
+    assert_eq!(got["Rules"][0]["Expiration"]["Days"], 7);
+
+    std::fs::write(&lifecycle, r#"{ "Rules": [{ "Status": "On" }] }"#).unwrap();
+    let output = r2(&server, &["lifecycle", "put", lifecycle.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Status"));
+}
+
+#[test]
+fn multipart_uploads_list_and_abort() {
+    let (runtime, server) = server();
+    let upload = runtime
+        .block_on(
+            server
+                .client()
+                .create_multipart_upload("big.bin", PutOptions::new()),
+        )
+        .unwrap();
+    let upload_id = upload.upload_id;
+
+    let uploads = json(&server, &["multipart", "ls"]);
+    assert_eq!(uploads[0]["key"], "big.bin");
+    assert_eq!(uploads[0]["upload_id"], upload_id.as_str());
+
+    ok(
+        &server,
+        &["multipart", "abort", &remote("big.bin"), &upload_id],
+    );
+    assert_eq!(json(&server, &["multipart", "ls"]), serde_json::json!([]));
+}
+
+#[test]
+fn errors_go_to_stderr_with_a_failing_status() {
+    let (_runtime, server) = server();
+    let output = r2(&server, &["cat", &remote("missing")]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("r2: "));
+
+    let output = r2(&server, &["cp", "a.txt", "b.txt"]);
+    assert!(!output.status.success());
+    assert!(!Path::new("b.txt").exists());
+}
+
+#[test]
+fn buckets_can_be_named_without_r2_bucket() {
+    let (runtime, server) = server();
+    runtime
+        .block_on(server.client().put_object("a.txt", "a"))
+        .unwrap();
+    let run = |args: &[&str]| command(&server, args).output().unwrap();
+
+    let output = run(&["ls", &remote("")]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("a.txt"));
+
+    let output = run(&["--bucket", MOCK_BUCKET, "multipart", "ls"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run(&["cors", "get"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--bucket"));
+}
+
+#[test]
+fn moving_an_object_onto_itself_keeps_it() {
+    let (runtime, server) = server();
+    runtime
+        .block_on(server.client().put_object("dir/a.txt", "a"))
+        .unwrap();
+
+    for destination in [remote("dir/a.txt"), remote("dir/")] {
+        let output = r2(&server, &["mv", &remote("dir/a.txt"), &destination]);
+        assert!(!output.status.success(), "mv to {destination} succeeded");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("same object"));
+        assert_eq!(server.object(MOCK_BUCKET, "dir/a.txt").unwrap(), "a");
+    }
+}
+
+#[test]
+fn removing_a_bucket_location_needs_recursive_and_empties_it() {
+    let (runtime, server) = server();
+    for key in ["a.txt", "dir/b.txt"] {
+        runtime
+            .block_on(server.client().put_object(key, "x"))
+            .unwrap();
+    }
+
+    let output = r2(&server, &["rm", &remote("")]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--recursive"));
+    assert_eq!(server.keys(MOCK_BUCKET).len(), 2);
+
+    let removed = json(&server, &["rm", "--recursive", &remote("")]);
+    assert_eq!(
+        removed["deleted"],
+        serde_json::json!(["a.txt", "dir/b.txt"])
+    );
+    assert!(server.keys(MOCK_BUCKET).is_empty());
+}
@@ -0,0 +1,189 @@
+//! Transparent compression against the mock server.
+
+#![cfg(all(feature = "compression", feature = "mock"))]
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{Compression, Error, MultipartOptions, PutOptions, R2Client};
+
+fn text() -> Vec<u8> {
+    "the quick brown fox jumps over the lazy dog\n"
+        .repeat(2_000)
+        .into_bytes()
+}
+
+/// Bytes no codec can shrink, so that the compressed file still spans
+/// several parts.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+fn decompressing(server: &MockServer) -> R2Client {
+    server.client();
+    server
+        .builder(MOCK_BUCKET)
+        .decompress(true)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn every_codec_round_trips() {
+    let server = MockServer::start().await.unwrap();
+    let client = decompressing(&server);
+
+    for compression in [Compression::Gzip, Compression::Zstd, Compression::Brotli] {
+        let key = format!("notes.{}.txt", compression.content_encoding());
+        client
+            .put_object_with(&key, text(), PutOptions::new().compression(compression))
+            .await
+            .unwrap();
+
+        let stored = server.object(MOCK_BUCKET, &key).unwrap();
+        assert!(stored.len() < text().len() / 10, "{compression:?}");
+        let head = client.head_object(&key).await.unwrap();
+        assert_eq!(
+            head.content_encoding.as_deref(),
+            Some(compression.content_encoding())
+        );
+        assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+
+        assert_eq!(client.get_object(&key).await.unwrap(), text());
+    }
+}
+
+#[tokio::test]
+async fn bodies_come_back_as_stored_unless_asked() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    client
+        .put_object_with(
+            "a.txt",
+            text(),
+            PutOptions::new().compression(Compression::Gzip),
+        )
+        .await
+        .unwrap();
+    client.put_object("plain.txt", text()).await.unwrap();
+
+    let stored = client.get_object("a.txt").await.unwrap();
+    assert_eq!(stored, server.object(MOCK_BUCKET, "a.txt").unwrap());
+    assert_eq!(&stored[..2], [0x1f, 0x8b]);
+
+    let client = decompressing(&server);
+    let range = client.get_object_range("a.txt", 0, Some(1)).await.unwrap();
+    assert_eq!(range, [0x1f, 0x8b]);
+    // Objects without a Content-Encoding are untouched.
+    assert_eq!(client.get_object("plain.txt").await.unwrap(), text());
+    let streamed = client
+        .get_object_stream("a.txt")
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap()
+        .into_bytes();
+    assert_eq!(streamed, text());
+}
+
+#[tokio::test]
+async fn large_files_compress_part_by_part() {
+    let server = MockServer::start().await.unwrap();
+    let client = decompressing(&server);
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("noise.bin");
+    let data = noise(11 * 1024 * 1024);
+    std::fs::write(&source, &data).unwrap();
+
+    client
+        .upload_file_with(
+            "noise.bin",
+            &source,
+            MultipartOptions::new()
+                .threshold(1024)
+                .part_size(5 * 1024 * 1024)
+                .concurrency(2)
+                .put_options(PutOptions::new().compression(Compression::Zstd)),
+        )
+        .await
+        .unwrap();
+    let head = client.head_object("noise.bin").await.unwrap();
+    assert_eq!(head.content_encoding.as_deref(), Some("zstd"));
+    assert!(head.content_length > 10 * 1024 * 1024);
+
+    let destination = dir.path().join("out/noise.bin");
+    let written = client.download_to("noise.bin", &destination).await.unwrap();
+    assert_eq!(written, data.len() as u64);
+    assert_eq!(std::fs::read(&destination).unwrap(), data);
+
+    // Below the threshold the file is compressed in one request.
+    let small = dir.path().join("small.txt");
+    std::fs::write(&small, text()).unwrap();
+    client
+        .upload_file_with(
+            "small.txt",
+            &small,
+            MultipartOptions::new().put_options(PutOptions::new().compression(Compression::Brotli)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(client.get_object("small.txt").await.unwrap(), text());
+}
+
+#[tokio::test]
+async fn contradictions_are_rejected_before_sending() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+
+    let err = client
+        .put_object_with(
+            "a.txt",
+            "hello",
+            PutOptions::new()
+                .content_encoding("br")
+                .compression(Compression::Gzip),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidArgument {
+            argument: "content_encoding",
+            ..
+        }
+    ));
+
+    let err = client
+        .create_multipart_upload("a.txt", PutOptions::new().compression(Compression::Gzip))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidArgument {
+            argument: "compression",
+            ..
+        }
+    ));
+    assert!(server.keys(MOCK_BUCKET).is_empty());
+}
+
+#[test]
+fn content_encodings_name_codecs() {
+    assert_eq!(
+        Compression::from_content_encoding(" GZIP "),
+        Some(Compression::Gzip)
+    );
+    assert_eq!(
+        Compression::from_content_encoding("x-gzip"),
+        Some(Compression::Gzip)
+    );
+    assert_eq!(Compression::from_content_encoding("gzip, br"), None);
+    assert_eq!(Compression::from_content_encoding("identity"), None);
+}
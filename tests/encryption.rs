@@ -0,0 +1,307 @@
+//! Client-side encryption against the mock server.
+
+#![cfg(all(feature = "encryption", feature = "mock"))]
+
+use std::sync::Arc;
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{Error, LocalKeyProvider, MultipartOptions, PutOptions, R2Client};
+
+const FRAME: usize = 64 * 1024;
+
+/// Bytes that differ at every offset, so a misplaced range shows.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// An error and its sources, since body errors arrive wrapped.
+fn chain(err: &dyn std::error::Error) -> String {
+    let mut text = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        text = format!("{text}: {err}");
+        source = err.source();
+    }
+    text
+}
+
+fn encrypting(server: &MockServer, provider: LocalKeyProvider) -> R2Client {
+    server.client();
+    server
+        .builder(MOCK_BUCKET)
+        .encryption(Arc::new(provider))
+        .build()
+        .unwrap()
+}
+
+fn provider() -> LocalKeyProvider {
+    LocalKeyProvider::new("primary", [7; 32])
+}
+
+#[tokio::test]
+async fn bodies_round_trip_and_are_stored_encrypted() {
+    let server = MockServer::start().await.unwrap();
+    let client = encrypting(&server, provider());
+
+    for len in [0, 1, FRAME - 1, FRAME, 3 * FRAME + 17] {
+        let key = format!("blob-{len}");
+        let data = noise(len);
+        client.put_object(&key, data.clone()).await.unwrap();
+
+        let stored = server.object(MOCK_BUCKET, &key).unwrap();
+        let frames = len.div_ceil(FRAME).max(1);
+        assert_eq!(stored.len(), len + 16 * frames, "{len}");
+        assert!(len == 0 || stored[..len.min(64)] != data[..len.min(64)]);
+
+        let head = client.head_object(&key).await.unwrap();
+        assert_eq!(head.content_length, len as i64);
+        assert_eq!(head.metadata["encryption-algorithm"], "AES-256-GCM-64KiB");
+        assert_eq!(head.metadata["encryption-key-id"], "primary");
+        assert!(head.metadata.contains_key("encryption-key"));
+
+        assert_eq!(client.get_object(&key).await.unwrap(), data, "{len}");
+    }
+
+    // A client without a key provider sees the object as stored.
+    let raw = server.client().get_object("blob-1").await.unwrap();
+    assert_eq!(raw.len(), 17);
+}
+
+#[tokio::test]
+async fn ranges_decrypt_only_the_frames_they_touch() {
+    let server = MockServer::start().await.unwrap();
+    let client = encrypting(&server, provider());
+    let data = noise(3 * FRAME + 100);
+    client.put_object("blob", data.clone()).await.unwrap();
+    server
+        .client()
+        .put_object("unencrypted", data.clone())
+        .await
+        .unwrap();
+
+    let cases = [
+        (0, Some(0)),
+        (5, Some(FRAME as u64 - 1)),
+        (FRAME as u64 - 3, Some(FRAME as u64 + 3)),
+        (FRAME as u64, Some(2 * FRAME as u64)),
+        (100, Some(10 * FRAME as u64)),
+        (2 * FRAME as u64 + 9, None),
+        (3 * FRAME as u64 + 99, None),
+    ];
+    for key in ["blob", "unencrypted"] {
+        for (start, end) in cases {
+            let range = client.get_object_range(key, start, end).await.unwrap();
+            let end = end.map_or(data.len(), |end| (end as usize + 1).min(data.len()));
+            assert_eq!(range, data[start as usize..end], "{key} {start}..{end}");
+        }
+    }
+
+    let err = client
+        .get_object_range("blob", data.len() as u64, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidArgument {
+            argument: "start",
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn altered_or_truncated_bodies_fail_to_decrypt() {
+    let server = MockServer::start().await.unwrap();
+    let client = encrypting(&server, provider());
+    let raw = server.client();
+    client
+        .put_object("blob", noise(2 * FRAME + 5))
+        .await
+        .unwrap();
+    let stored = server.object(MOCK_BUCKET, "blob").unwrap();
+    let metadata = raw.head_object("blob").await.unwrap().metadata;
+    let rewrite = |key: &'static str, body: Vec<u8>| {
+        let raw = raw.clone();
+        let metadata = metadata.clone();
+        async move {
+            let mut options = PutOptions::new();
+            options.metadata = metadata;
+            raw.put_object_with(key, body, options).await.unwrap();
+        }
+    };
+
+    let mut flipped = stored.to_vec();
+    flipped[FRAME + 40] ^= 1;
+    rewrite("flipped", flipped).await;
+    let err = client.get_object("flipped").await.unwrap_err();
+    assert!(matches!(err, Error::Corrupt { .. }), "{err}");
+    assert!(!err.is_retryable());
+    assert!(chain(&err).contains("frame 1"), "{}", chain(&err));
+    // Frames before the damage still read.
+    assert!(client
+        .get_object_range("flipped", 0, Some(10))
+        .await
+        .is_ok());
+
+    // Dropping whole frames from the end is caught by the final-frame flag.
+    rewrite("truncated", stored[..2 * (FRAME + 16)].to_vec()).await;
+    let err = client.get_object("truncated").await.unwrap_err();
+    assert!(matches!(err, Error::Corrupt { .. }), "{err}");
+    assert!(!err.is_retryable());
+    let err = client
+        .get_object_range("truncated", FRAME as u64, None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, Error::Encryption { key, .. } if key == "truncated"),
+        "{err}"
+    );
+    assert!(!err.is_retryable());
+
+    // A body too short to hold even one tag has no plaintext length.
+    rewrite("stub", stored[..5].to_vec()).await;
+    let err = client.head_object("stub").await.unwrap_err();
+    assert!(
+        matches!(&err, Error::Encryption { key, .. } if key == "stub"),
+        "{err}"
+    );
+
+    // The wrong key-encryption key cannot unwrap the data key.
+    let other = encrypting(&server, LocalKeyProvider::new("primary", [8; 32]));
+    let err = other.get_object("blob").await.unwrap_err();
+    assert!(
+        matches!(&err, Error::Encryption { key, .. } if key == "blob"),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn retired_keys_still_unwrap_after_rotation() {
+    let server = MockServer::start().await.unwrap();
+    let old = encrypting(&server, LocalKeyProvider::new("2026-04", [3; 32]));
+    old.put_object("old", "written before rotation")
+        .await
+        .unwrap();
+
+    let rotated = encrypting(
+        &server,
+        LocalKeyProvider::new("2026-10", [4; 32]).with_retired_key("2026-04", [3; 32]),
+    );
+    rotated.put_object("new", "written after").await.unwrap();
+    assert_eq!(
+        rotated.get_object("old").await.unwrap(),
+        b"written before rotation"
+    );
+    let head = rotated.head_object("new").await.unwrap();
+    assert_eq!(head.metadata["encryption-key-id"], "2026-10");
+
+    let err = old.get_object("new").await.unwrap_err();
+    assert!(matches!(err, Error::Encryption { .. }), "{err}");
+    assert!(chain(&err).contains("2026-10"), "{}", chain(&err));
+
+    let debug = format!("{:?}", LocalKeyProvider::new("k", [9; 32]));
+    assert!(!debug.contains('9'), "{debug}");
+}
+
+#[tokio::test]
+async fn large_files_encrypt_part_by_part() {
+    let server = MockServer::start().await.unwrap();
+    let client = encrypting(&server, provider());
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("noise.bin");
+    let data = noise(11 * 1024 * 1024 + 3);
+    std::fs::write(&source, &data).unwrap();
+
+    client
+        .upload_file_with(
+            "noise.bin",
+            &source,
+            MultipartOptions::new()
+                .threshold(1024)
+                // Not a whole number of frames: it is rounded up.
+                .part_size(5 * 1024 * 1024 + 1)
+                .concurrency(2),
+        )
+        .await
+        .unwrap();
+    let head = client.head_object("noise.bin").await.unwrap();
+    assert_eq!(head.content_length, data.len() as i64);
+
+    let destination = dir.path().join("out/noise.bin");
+    let written = client.download_to("noise.bin", &destination).await.unwrap();
+    assert_eq!(written, data.len() as u64);
+    assert_eq!(std::fs::read(&destination).unwrap(), data);
+    let middle = 5 * 1024 * 1024 - 10;
+    assert_eq!(
+        client
+            .get_object_range("noise.bin", middle, Some(middle + 200))
+            .await
+            .unwrap(),
+        data[middle as usize..=middle as usize + 200]
+    );
+
+    let err = client
+        .create_multipart_upload("by-hand", PutOptions::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidArgument {
+            argument: "encryption",
+            ..
+        }
+    ));
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn compression_happens_before_encryption() {
+    use cloudflare_r2_rs::Compression;
+
+    let server = MockServer::start().await.unwrap();
+    server.client();
+    let client = server
+        .builder(MOCK_BUCKET)
+        .encryption(Arc::new(provider()))
+        .decompress(true)
+        .build()
+        .unwrap();
+    let text = "the quick brown fox jumps over the lazy dog\n"
+        .repeat(200_000)
+        .into_bytes();
+
+    client
+        .put_object_with(
+            "notes.txt",
+            text.clone(),
+            PutOptions::new().compression(Compression::Zstd),
+        )
+        .await
+        .unwrap();
+    assert!(server.object(MOCK_BUCKET, "notes.txt").unwrap().len() < text.len() / 10);
+    assert_eq!(client.get_object("notes.txt").await.unwrap(), text);
+
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("noise.bin");
+    let data = noise(11 * 1024 * 1024);
+    std::fs::write(&source, &data).unwrap();
+    client
+        .upload_file_with(
+            "noise.bin",
+            &source,
+            MultipartOptions::new()
+                .threshold(1024)
+                .put_options(PutOptions::new().compression(Compression::Gzip)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(client.get_object("noise.bin").await.unwrap(), data);
+}
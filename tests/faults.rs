@@ -0,0 +1,192 @@
+//! Injected failures against the mock server: retries, aborts and atomic
+//! downloads under the conditions R2 produces in production.
+
+#![cfg(all(feature = "faults", feature = "mock"))]
+
+use std::time::{Duration, Instant};
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::s3::RetryConfig;
+use cloudflare_r2_rs::{
+    Error, Fault, FaultInjection, FaultRule, Journal, JournalOffset, JournalOptions, ListOptions,
+    R2Client,
+};
+use futures::TryStreamExt;
+
+async fn client(faults: FaultInjection) -> (MockServer, R2Client) {
+    let server = MockServer::start().await.unwrap();
+    // Creates the bucket; the faulty client below shares it.
+    server.client();
+    let client = server
+        .builder(MOCK_BUCKET)
+        .retry_config(RetryConfig::standard().with_initial_backoff(Duration::from_millis(1)))
+        .fault_injection(faults)
+        .build()
+        .unwrap();
+    (server, client)
+}
+
+#[tokio::test]
+async fn slow_downs_are_retried_until_they_stop() {
+    let faults = FaultInjection::new().rule(
+        FaultRule::new(Fault::SlowDown)
+            .operation("PutObject")
+            .times(2),
+    );
+    let (server, client) = client(faults.clone()).await;
+
+    client.put_object("a.txt", "hello").await.unwrap();
+    assert_eq!(faults.injected(), 2);
+    assert_eq!(
+        server.object(MOCK_BUCKET, "a.txt").as_deref(),
+        Some(&b"hello"[..])
+    );
+}
+
+#[tokio::test]
+async fn persistent_slow_downs_surface_as_throttling() {
+    let faults = FaultInjection::new().rule(FaultRule::new(Fault::SlowDown).operation("PutObject"));
+    let (server, client) = client(faults.clone()).await;
+
+    let err = client.put_object("a.txt", "hello").await.unwrap_err();
+    assert!(err.is_throttled(), "{err:?}");
+    assert_eq!(err.status(), Some(503));
+    assert_eq!(faults.injected(), 3);
+    assert!(server.object(MOCK_BUCKET, "a.txt").is_none());
+}
+
+#[tokio::test]
+async fn faults_only_hit_the_operations_they_name() {
+    let faults = FaultInjection::new()
+        .rule(FaultRule::new(Fault::error(500, "InternalError")).operation("GetObject"));
+    let (_server, client) = client(faults.clone()).await;
+
+    client.put_object("a.txt", "hello").await.unwrap();
+    assert_eq!(faults.injected(), 0);
+    assert_eq!(
+        client.get_object("a.txt").await.unwrap_err().status(),
+        Some(500)
+    );
+}
+
+#[tokio::test]
+async fn connection_resets_are_retried() {
+    let faults = FaultInjection::new().rule(
+        FaultRule::new(Fault::ConnectionReset)
+            .operation("GetObject")
+            .times(1),
+    );
+    let (_server, client) = client(faults.clone()).await;
+
+    client.put_object("a.txt", "hello").await.unwrap();
+    assert_eq!(client.get_object("a.txt").await.unwrap(), b"hello");
+    assert_eq!(faults.injected(), 1);
+}
+
+#[tokio::test]
+async fn truncated_downloads_leave_nothing_behind() {
+    let faults = FaultInjection::new()
+        .rule(FaultRule::new(Fault::TruncatedBody { after: 4 }).operation("GetObject"));
+    let (_server, client) = client(faults).await;
+    client
+        .put_object("big.bin", vec![7u8; 64 * 1024])
+        .await
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let destination = dir.path().join("big.bin");
+    assert!(client.download_to("big.bin", &destination).await.is_err());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn precondition_failures_are_reported_as_such() {
+    let faults = FaultInjection::new()
+        .rule(FaultRule::new(Fault::PreconditionFailed).operation("PutObject"));
+    let (_server, client) = client(faults).await;
+
+    let err = client.put_object("a.txt", "hello").await.unwrap_err();
+    assert!(err.is_precondition_failed(), "{err:?}");
+}
+
+#[tokio::test]
+async fn malformed_responses_fail_to_parse() {
+    let faults =
+        FaultInjection::new().rule(FaultRule::new(Fault::MalformedXml).operation("ListObjectsV2"));
+    let (_server, client) = client(faults.clone()).await;
+
+    let err = client.list_objects(ListOptions::new()).await.unwrap_err();
+    assert!(!matches!(err, Error::ObjectNotFound { .. }), "{err:?}");
+    assert!(faults.injected() >= 1);
+}
+
+#[tokio::test]
+async fn latency_delays_but_does_not_fail() {
+    let faults = FaultInjection::new()
+        .rule(FaultRule::new(Fault::Latency(Duration::from_millis(50))).operation("HeadObject"));
+    let (_server, client) = client(faults).await;
+    client.put_object("a.txt", "hello").await.unwrap();
+
+    let started = Instant::now();
+    assert_eq!(client.head_object("a.txt").await.unwrap().content_length, 5);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn a_journal_commit_that_fails_is_retried_without_rewriting_the_segment() {
+    // The first two puts, the writer's manifest and the segment, go through;
+    // every attempt at the commit that follows fails.
+    let faults = FaultInjection::new()
+        .rule(
+            FaultRule::new(Fault::Latency(Duration::ZERO))
+                .operation("PutObject")
+                .times(2),
+        )
+        .rule(
+            FaultRule::new(Fault::error(500, "InternalError"))
+                .operation("PutObject")
+                .times(3),
+        );
+    let (server, client) = client(faults.clone()).await;
+    let journal = Journal::new(client, "audit");
+    let mut writer = journal.writer(JournalOptions::new()).await.unwrap();
+
+    writer.append("first").await.unwrap();
+    let err = writer.flush().await.unwrap_err();
+    assert_eq!(err.status(), Some(500), "{err:?}");
+    assert_eq!(faults.injected(), 5);
+    assert!(server
+        .object(MOCK_BUCKET, &journal.segment_key(0))
+        .is_some());
+    assert_eq!(journal.head().await.unwrap(), JournalOffset::START);
+
+    // Appending first commits the segment already written.
+    let offset = writer.append("second").await.unwrap();
+    assert_eq!(offset, JournalOffset::new(1, 0));
+    assert_eq!(journal.head().await.unwrap(), JournalOffset::new(1, 0));
+    writer.close().await.unwrap();
+
+    let records: Vec<String> = journal
+        .read_from(JournalOffset::START)
+        .map_ok(|record| String::from_utf8(record.data.to_vec()).unwrap())
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(records, ["first", "second"]);
+}
+
+#[tokio::test]
+async fn invalid_probabilities_are_rejected_at_build_time() {
+    let server = MockServer::start().await.unwrap();
+    let err = server
+        .builder(MOCK_BUCKET)
+        .fault_injection(
+            FaultInjection::new().rule(FaultRule::new(Fault::SlowDown).probability(1.5)),
+        )
+        .build()
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::InvalidConfig { field, .. } if field == "fault_injection"),
+        "{err:?}"
+    );
+}
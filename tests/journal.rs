@@ -0,0 +1,174 @@
+//! The segmented journal against the mock server.
+
+#![cfg(feature = "mock")]
+
+use std::time::Duration;
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{Journal, JournalOffset, JournalOptions};
+use futures::TryStreamExt;
+
+async fn read(journal: &Journal, from: JournalOffset) -> Vec<(JournalOffset, String)> {
+    journal
+        .read_from(from)
+        .map_ok(|record| {
+            let data = String::from_utf8(record.data.to_vec()).unwrap();
+            (record.offset, data)
+        })
+        .try_collect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn records_are_sealed_into_segments_and_read_back_in_order() {
+    let server = MockServer::start().await.unwrap();
+    let journal = Journal::new(server.client(), "audit");
+    // Four 11-byte records, with their framing, fill a 64-byte segment.
+    let mut writer = journal
+        .writer(JournalOptions::new().segment_size(64))
+        .await
+        .unwrap();
+
+    let mut offsets = Vec::new();
+    for i in 0..10 {
+        offsets.push(writer.append(format!("event-{i:05}")).await.unwrap());
+    }
+    assert_eq!(offsets[0], JournalOffset::START);
+    assert_eq!(offsets[3], JournalOffset::new(0, 3));
+    assert_eq!(offsets[4], JournalOffset::new(1, 0));
+    assert_eq!(writer.buffered(), 2);
+    assert_eq!(journal.head().await.unwrap(), JournalOffset::new(2, 0));
+    writer.close().await.unwrap();
+    assert_eq!(journal.head().await.unwrap(), JournalOffset::new(3, 0));
+    assert_eq!(
+        server.keys(MOCK_BUCKET),
+        [
+            "audit/manifest".to_string(),
+            journal.segment_key(0),
+            journal.segment_key(1),
+            journal.segment_key(2),
+        ]
+    );
+
+    let all = read(&journal, JournalOffset::START).await;
+    let expected: Vec<_> = (0..10).map(|i| format!("event-{i:05}")).collect();
+    assert_eq!(
+        all.iter().map(|(_, data)| data).collect::<Vec<_>>(),
+        expected.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        all.iter().map(|(offset, _)| *offset).collect::<Vec<_>>(),
+        offsets
+    );
+
+    // Resuming after a record, including the last of a segment.
+    let tail = read(&journal, offsets[5].next()).await;
+    assert_eq!(tail.first().unwrap().1, "event-00006");
+    assert_eq!(tail.len(), 4);
+    let tail = read(&journal, offsets[3].next()).await;
+    assert_eq!(tail.first().unwrap().0, JournalOffset::new(1, 0));
+    assert!(read(&journal, JournalOffset::new(3, 0)).await.is_empty());
+
+    // A new writer carries on after the committed segments.
+    let mut writer = journal.writer(JournalOptions::new()).await.unwrap();
+    assert_eq!(writer.next_offset(), JournalOffset::new(3, 0));
+    writer.append("later").await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(read(&journal, JournalOffset::new(3, 0)).await.len(), 1);
+}
+
+#[tokio::test]
+async fn old_records_are_sealed_after_the_maximum_age() {
+    let server = MockServer::start().await.unwrap();
+    let journal = Journal::new(server.client(), "audit");
+    let mut writer = journal
+        .writer(JournalOptions::new().max_age(Duration::from_millis(100)))
+        .await
+        .unwrap();
+
+    writer.append("first").await.unwrap();
+    assert!(!writer.flush_if_due().await.unwrap());
+    assert!(read(&journal, JournalOffset::START).await.is_empty());
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(writer.flush_if_due().await.unwrap());
+    assert_eq!(writer.buffered(), 0);
+    assert_eq!(read(&journal, JournalOffset::START).await.len(), 1);
+
+    // The age is also checked as records are appended.
+    writer.append("second").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    writer.append("third").await.unwrap();
+    assert_eq!(writer.buffered(), 0);
+    assert_eq!(journal.head().await.unwrap(), JournalOffset::new(2, 0));
+}
+
+#[tokio::test]
+async fn a_new_writer_fences_out_the_old_one() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let journal = Journal::new(client.clone(), "audit");
+
+    let mut old = journal.writer(JournalOptions::new()).await.unwrap();
+    old.append("committed").await.unwrap();
+    old.flush().await.unwrap();
+    old.append("stale").await.unwrap();
+
+    let mut new = journal.writer(JournalOptions::new()).await.unwrap();
+    let err = old.flush().await.unwrap_err();
+    assert!(err.is_precondition_failed(), "{err}");
+    // The segment the old writer wrote before losing is not left behind.
+    assert!(!client.object_exists(&journal.segment_key(1)).await.unwrap());
+    assert_eq!(old.buffered(), 1);
+
+    new.append("fresh").await.unwrap();
+    new.close().await.unwrap();
+    let records = read(&journal, JournalOffset::START).await;
+    assert_eq!(
+        records
+            .iter()
+            .map(|(_, data)| data.as_str())
+            .collect::<Vec<_>>(),
+        ["committed", "fresh"]
+    );
+    drop(old);
+}
+
+#[tokio::test]
+async fn uncommitted_segments_are_ignored_and_cleaned_up() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let journal = Journal::new(client.clone(), "");
+    let mut writer = journal.writer(JournalOptions::new()).await.unwrap();
+    writer.append("one").await.unwrap();
+    writer.close().await.unwrap();
+
+    // A writer that died between writing segment 1 and committing it.
+    let mut orphan = b"R2J1".to_vec();
+    orphan.extend_from_slice(&[0, 0, 0, 6]);
+    orphan.extend_from_slice(b"orphan");
+    client
+        .put_object(&journal.segment_key(1), orphan)
+        .await
+        .unwrap();
+    assert_eq!(read(&journal, JournalOffset::START).await.len(), 1);
+
+    let mut writer = journal.writer(JournalOptions::new()).await.unwrap();
+    assert!(!client.object_exists(&journal.segment_key(1)).await.unwrap());
+    writer.append("two").await.unwrap();
+    writer.close().await.unwrap();
+    let records = read(&journal, JournalOffset::START).await;
+    assert_eq!(records[1], (JournalOffset::new(1, 0), "two".to_string()));
+
+    client
+        .put_object("manifest", "not a manifest")
+        .await
+        .unwrap();
+    assert!(journal.head().await.is_err());
+    assert!(journal
+        .read_from(JournalOffset::START)
+        .try_collect::<Vec<_>>()
+        .await
+        .is_err());
+}
@@ -0,0 +1,111 @@
+//! Typed JSON documents and the serde derives, against the mock server.
+
+#![cfg(all(feature = "serde", feature = "mock"))]
+
+use std::collections::BTreeMap;
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{CompletedPart, Error, ObjectMetadata, ObjectSummary, PutOptions};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    theme: String,
+    shortcuts: BTreeMap<String, String>,
+}
+
+fn settings() -> Settings {
+    Settings {
+        theme: "dark".into(),
+        shortcuts: [("save".to_string(), "ctrl+s".to_string())].into(),
+    }
+}
+
+#[tokio::test]
+async fn documents_round_trip_as_application_json() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+
+    client
+        .put_json("users/42/settings", &settings())
+        .await
+        .unwrap();
+    assert_eq!(
+        client
+            .get_json::<Settings>("users/42/settings")
+            .await
+            .unwrap(),
+        settings()
+    );
+    let head = client.head_object("users/42/settings").await.unwrap();
+    assert_eq!(head.content_type.as_deref(), Some("application/json"));
+
+    client
+        .put_json_with(
+            "users/42/settings",
+            &settings(),
+            PutOptions::new().cache_control("no-cache"),
+        )
+        .await
+        .unwrap();
+    let head = client.head_object("users/42/settings").await.unwrap();
+    assert_eq!(head.content_type.as_deref(), Some("application/json"));
+    assert_eq!(head.cache_control.as_deref(), Some("no-cache"));
+}
+
+#[tokio::test]
+async fn bodies_that_do_not_parse_name_the_key() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    client.put_object("notes.txt", "not json").await.unwrap();
+    client.put_json("list.json", &[1, 2, 3]).await.unwrap();
+
+    let err = client.get_json::<Settings>("notes.txt").await.unwrap_err();
+    assert!(matches!(&err, Error::Json { key, .. } if key == "notes.txt"));
+    assert!(err.to_string().contains("notes.txt"), "{err}");
+
+    let err = client.get_json::<Settings>("list.json").await.unwrap_err();
+    assert!(matches!(err, Error::Json { .. }));
+    assert!(client
+        .get_json::<Settings>("missing.json")
+        .await
+        .unwrap_err()
+        .is_not_found());
+}
+
+#[tokio::test]
+async fn unserializable_values_fail_before_sending() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    // JSON object keys must be strings.
+    let value: BTreeMap<(u8, u8), u8> = [((1, 2), 3)].into();
+
+    let err = client.put_json("bad.json", &value).await.unwrap_err();
+    assert!(matches!(err, Error::Json { .. }));
+    assert!(server.keys(MOCK_BUCKET).is_empty());
+}
+
+#[tokio::test]
+async fn result_types_serialize_with_rfc3339_dates() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    client.put_json("a.json", &settings()).await.unwrap();
+
+    let listed = client.list_all_objects(None).await.unwrap();
+    let json = serde_json::to_value(&listed).unwrap();
+    let last_modified = json[0]["last_modified"].as_str().unwrap();
+    assert!(last_modified.ends_with('Z'), "{last_modified}");
+    let back: Vec<ObjectSummary> = serde_json::from_value(json).unwrap();
+    assert_eq!(back[0].key, "a.json");
+    assert_eq!(back[0].last_modified, listed[0].last_modified);
+
+    let head = client.head_object("a.json").await.unwrap();
+    let back: ObjectMetadata =
+        serde_json::from_str(&serde_json::to_string(&head).unwrap()).unwrap();
+    assert_eq!(back.content_length, head.content_length);
+    assert_eq!(back.last_modified, head.last_modified);
+
+    let part = CompletedPart::new(3, "\"etag\"");
+    let back: CompletedPart = serde_json::from_str(&serde_json::to_string(&part).unwrap()).unwrap();
+    assert_eq!(back, part);
+}
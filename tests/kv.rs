@@ -0,0 +1,123 @@
+//! The key-value store against the mock server.
+
+#![cfg(feature = "mock")]
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{Error, KvStore, ListOptions, Version};
+use futures::TryStreamExt;
+
+#[tokio::test]
+async fn conditional_writes_detect_lost_races() {
+    let server = MockServer::start().await.unwrap();
+    let kv = KvStore::new(server.client(), "state");
+
+    let created = kv.put_if_absent("leader", "node-1").await.unwrap().unwrap();
+    assert_eq!(kv.put_if_absent("leader", "node-2").await.unwrap(), None);
+
+    let entry = kv.get("leader").await.unwrap().unwrap();
+    assert_eq!(entry.value, b"node-1");
+    assert_eq!(entry.version, created);
+
+    let updated = kv
+        .put_if_version("leader", "node-3", &entry.version)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_ne!(updated, created);
+    // The version read before the update is stale now.
+    assert_eq!(
+        kv.put_if_version("leader", "node-4", &created)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(kv.get("leader").await.unwrap().unwrap().value, b"node-3");
+
+    assert!(!kv.delete_if_version("leader", &created).await.unwrap());
+    assert!(kv.delete_if_version("leader", &updated).await.unwrap());
+    assert_eq!(kv.get("leader").await.unwrap(), None);
+    assert!(!kv.delete_if_version("leader", &updated).await.unwrap());
+    assert_eq!(
+        kv.put_if_version("leader", "x", &Version::new("\"gone\""))
+            .await
+            .unwrap(),
+        None
+    );
+
+    let version = kv.put("leader", "node-5").await.unwrap();
+    assert_eq!(kv.get("leader").await.unwrap().unwrap().version, version);
+    kv.delete("leader").await.unwrap();
+    kv.delete("leader").await.unwrap();
+}
+
+#[tokio::test]
+async fn arbitrary_strings_are_safe_keys() {
+    let server = MockServer::start().await.unwrap();
+    let kv = KvStore::new(server.client(), "/cfg/");
+
+    let keys = [
+        "../../etc/passwd",
+        "with space",
+        "ключ",
+        "a/./b",
+        "100%",
+        "x.json",
+    ];
+    for key in keys {
+        kv.put(key, key).await.unwrap();
+    }
+    for key in keys {
+        assert_eq!(kv.get(key).await.unwrap().unwrap().value, key.as_bytes());
+    }
+    assert_eq!(
+        kv.object_key("../../etc/passwd").unwrap(),
+        "cfg/%2E%2E/%2E%2E/etc/passwd"
+    );
+    for object in server.keys(MOCK_BUCKET) {
+        assert!(object.starts_with("cfg/"), "{object}");
+    }
+
+    let err = kv.put("", "empty").await.unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidArgument {
+            argument: "key",
+            ..
+        }
+    ));
+    assert!(kv.object_key(&"é".repeat(200)).is_err());
+}
+
+#[tokio::test]
+async fn scans_stream_keys_with_their_versions() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let kv = KvStore::new(client.clone(), "kv");
+    let other = KvStore::new(client.clone(), "kv2");
+    other.put("users/1", "elsewhere").await.unwrap();
+
+    let mut written = Vec::new();
+    for i in 0..25 {
+        let key = format!("users/{i:02}.json");
+        let version = kv.put(&key, format!("{{\"id\":{i}}}")).await.unwrap();
+        written.push((key, version));
+    }
+    kv.put("teams/a", "t").await.unwrap();
+
+    let scanned: Vec<_> = kv.scan("users/").try_collect().await.unwrap();
+    assert_eq!(scanned.len(), 25);
+    for (item, (key, version)) in scanned.iter().zip(&written) {
+        assert_eq!(&item.key, key);
+        assert_eq!(&item.version, version);
+        assert!(item.size > 0);
+    }
+    assert_eq!(kv.scan("").try_collect::<Vec<_>>().await.unwrap().len(), 26);
+
+    // The listing underneath follows pagination as the stream is polled.
+    let paged: Vec<_> = client
+        .list_stream(ListOptions::new().prefix("kv/").max_keys(4))
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(paged.len(), 26);
+}
@@ -0,0 +1,147 @@
+//! Leases against the mock server.
+
+#![cfg(feature = "mock")]
+
+use std::time::Duration;
+
+use cloudflare_r2_rs::mock::MockServer;
+use cloudflare_r2_rs::{Error, KvStore, Lease, LeaseOptions};
+
+fn short() -> LeaseOptions {
+    LeaseOptions::new()
+        .ttl(Duration::from_millis(300))
+        .clock_skew(Duration::from_millis(100))
+}
+
+#[tokio::test]
+async fn one_contender_holds_the_lease_at_a_time() {
+    let server = MockServer::start().await.unwrap();
+    let kv = KvStore::new(server.client(), "locks");
+
+    let mut lease = Lease::acquire(&kv, "nightly", "pod-a", LeaseOptions::new())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(lease.is_held());
+    assert!(lease.remaining() > Duration::from_secs(25));
+    assert!(Lease::acquire(&kv, "nightly", "pod-b", LeaseOptions::new())
+        .await
+        .unwrap()
+        .is_none());
+    // The same owner has no special claim either.
+    assert!(Lease::acquire(&kv, "nightly", "pod-a", LeaseOptions::new())
+        .await
+        .unwrap()
+        .is_none());
+
+    let holder = Lease::current(&kv, "nightly").await.unwrap().unwrap();
+    assert_eq!(holder.owner, "pod-a");
+    assert_eq!(holder.expires_at, lease.expires_at());
+
+    let before = lease.version().clone();
+    assert!(lease.renew().await.unwrap());
+    assert_ne!(lease.version(), &before);
+    assert!(lease.expires_at() >= holder.expires_at);
+
+    assert!(lease.release().await.unwrap());
+    assert_eq!(Lease::current(&kv, "nightly").await.unwrap(), None);
+    let next = Lease::acquire(&kv, "nightly", "pod-b", LeaseOptions::new())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(next.owner(), "pod-b");
+}
+
+#[tokio::test]
+async fn expired_leases_are_taken_over_after_the_skew() {
+    let server = MockServer::start().await.unwrap();
+    let kv = KvStore::new(server.client(), "locks");
+
+    let mut stale = Lease::acquire(&kv, "job", "pod-a", short())
+        .await
+        .unwrap()
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(320)).await;
+    assert!(!stale.is_held());
+    assert_eq!(stale.remaining(), Duration::ZERO);
+    // Past the TTL but within the skew tolerance: not yet.
+    assert!(Lease::acquire(&kv, "job", "pod-b", short())
+        .await
+        .unwrap()
+        .is_none());
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let fresh = Lease::acquire(&kv, "job", "pod-b", short())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(fresh.is_held());
+
+    // The old holder finds out when it next renews, and releasing or
+    // dropping it leaves the new holder alone.
+    assert!(!stale.renew().await.unwrap());
+    assert!(!stale.is_held());
+    assert!(!stale.release().await.unwrap());
+    let holder = Lease::current(&kv, "job").await.unwrap().unwrap();
+    assert_eq!(holder.owner, "pod-b");
+    assert_eq!(holder.expires_at, fresh.expires_at());
+    fresh.release().await.unwrap();
+}
+
+#[tokio::test]
+async fn dropping_a_held_lease_releases_it() {
+    let server = MockServer::start().await.unwrap();
+    let kv = KvStore::new(server.client(), "locks");
+
+    let lease = Lease::acquire(&kv, "drop", "pod-a", LeaseOptions::new())
+        .await
+        .unwrap()
+        .unwrap();
+    drop(lease);
+    for _ in 0..50 {
+        if Lease::current(&kv, "drop").await.unwrap().is_none() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("the lease was not released on drop");
+}
+
+#[tokio::test]
+async fn malformed_arguments_and_objects_are_rejected() {
+    let server = MockServer::start().await.unwrap();
+    let kv = KvStore::new(server.client(), "locks");
+
+    for owner in ["", "two\nlines"] {
+        let err = Lease::acquire(&kv, "x", owner, LeaseOptions::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidArgument {
+                argument: "owner",
+                ..
+            }
+        ));
+    }
+    let err = Lease::acquire(&kv, "x", "pod-a", LeaseOptions::new().ttl(Duration::ZERO))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidArgument {
+            argument: "ttl",
+            ..
+        }
+    ));
+
+    kv.put("not-a-lease", "hello").await.unwrap();
+    let err = Lease::acquire(&kv, "not-a-lease", "pod-a", LeaseOptions::new())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, Error::Corrupt { key, .. } if key == "not-a-lease"),
+        "{err}"
+    );
+    assert!(!err.is_retryable());
+}
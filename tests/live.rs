@@ -9,28 +9,88 @@
 //! ```
 //!
 //! Every test namespaces its keys and cleans up after itself.
+//!
+//! With the `cassette` feature, `R2_CASSETTE=record` also writes each test's
+//! traffic, scrubbed of credentials, to `tests/cassettes/<test>.json`, and
+//! `R2_CASSETTE=replay` serves it back from there with no credentials or
+//! network access. Replay only needs `R2_BUCKET`, naming the bucket that was
+//! recorded:
+//!
+//! ```sh
+//! R2_CASSETTE=record cargo test --features cassette --test live -- --ignored --test-threads=1
+//! R2_BUCKET=<bucket> R2_CASSETTE=replay cargo test --features cassette --test live -- --ignored
+//! ```
 
 use std::time::Duration;
 
 use cloudflare_r2_rs::{ListOptions, MultipartOptions, PutOptions, R2Client, Result};
 
 /// Builds a client from the environment, or explains what is missing.
-fn client() -> R2Client {
+#[cfg_attr(not(feature = "cassette"), allow(unused_variables))]
+fn client(test: &str) -> R2Client {
     let _ = dotenvy::dotenv();
+    #[cfg(feature = "cassette")]
+    if let Some(client) = cassette_client(test) {
+        return client;
+    }
     R2Client::from_env().expect(
         "live tests need R2_ACCOUNT_ID, R2_ACCESS_KEY_ID, R2_SECRET_ACCESS_KEY and R2_BUCKET",
     )
 }
 
+/// The recording or replaying client `R2_CASSETTE` asks for, if any.
+#[cfg(feature = "cassette")]
+fn cassette_client(test: &str) -> Option<R2Client> {
+    use cloudflare_r2_rs::{Cassette, R2Config};
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cassettes")
+        .join(format!("{test}.json"));
+    let client = match std::env::var("R2_CASSETTE").ok()?.as_str() {
+        "record" => {
+            let config = R2Config::from_env().expect(
+                "recording needs R2_ACCOUNT_ID, R2_ACCESS_KEY_ID, R2_SECRET_ACCESS_KEY and R2_BUCKET",
+            );
+            R2Client::builder()
+                .endpoint(&config.endpoint)
+                .bucket(&config.bucket)
+                .access_key_id(&config.access_key_id)
+                .secret_access_key(&config.secret_access_key)
+                .region(&config.region)
+                .cassette(Cassette::record(path))
+                .build()
+        }
+        "replay" => R2Client::builder()
+            .endpoint("https://replay.invalid")
+            .bucket(std::env::var("R2_BUCKET").expect("replay needs R2_BUCKET"))
+            .access_key_id("replay")
+            .secret_access_key("replay")
+            .cassette(Cassette::replay(path).expect("no cassette recorded for this test"))
+            .build(),
+        other => panic!("R2_CASSETTE must be `record` or `replay`, not `{other}`"),
+    };
+    Some(client.unwrap())
+}
+
+/// Whether responses come from a cassette rather than R2.
+fn replaying() -> bool {
+    std::env::var("R2_CASSETTE").is_ok_and(|mode| mode == "replay")
+}
+
 /// A key prefix unique to one test *and* one run.
 ///
 /// A fixed prefix would let a failed run leave objects behind that break every
 /// later run's exact-count assertions, so the process ID and a monotonic
-/// counter are folded in.
+/// counter are folded in. Recording and replaying are the exception: the keys
+/// must not change between the two, so a failed recording has to be cleaned
+/// up by hand.
 fn prefix(test: &str) -> String {
     use std::sync::atomic::{AtomicU32, Ordering};
     static COUNTER: AtomicU32 = AtomicU32::new(0);
 
+    if std::env::var_os("R2_CASSETTE").is_some() {
+        return format!("cloudflare-r2-rs-tests/cassette/{test}/");
+    }
     let run = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|elapsed| elapsed.as_millis())
@@ -45,7 +105,7 @@ fn prefix(test: &str) -> String {
 #[tokio::test]
 #[ignore = "requires live R2 credentials"]
 async fn round_trips_an_object() -> Result<()> {
-    let client = client();
+    let client = client("round_trips_an_object");
     let key = format!("{}hello.txt", prefix("round_trip"));
 
     client
@@ -84,7 +144,7 @@ async fn round_trips_an_object() -> Result<()> {
 #[tokio::test]
 #[ignore = "requires live R2 credentials"]
 async fn reports_missing_objects_as_not_found() -> Result<()> {
-    let client = client();
+    let client = client("reports_missing_objects_as_not_found");
     let err = client
         .get_object(&format!("{}nope.txt", prefix("missing")))
         .await
@@ -96,7 +156,7 @@ async fn reports_missing_objects_as_not_found() -> Result<()> {
 #[tokio::test]
 #[ignore = "requires live R2 credentials"]
 async fn lists_copies_and_deletes_by_prefix() -> Result<()> {
-    let client = client();
+    let client = client("lists_copies_and_deletes_by_prefix");
     let base = prefix("listing");
 
     for index in 0..5 {
@@ -137,7 +197,7 @@ async fn lists_copies_and_deletes_by_prefix() -> Result<()> {
 #[tokio::test]
 #[ignore = "requires live R2 credentials"]
 async fn uploads_and_downloads_files() -> Result<()> {
-    let client = client();
+    let client = client("uploads_and_downloads_files");
     let base = prefix("files");
     let key = format!("{base}payload.bin");
 
@@ -163,7 +223,7 @@ async fn uploads_and_downloads_files() -> Result<()> {
 #[tokio::test]
 #[ignore = "requires live R2 credentials"]
 async fn uploads_a_large_file_via_multipart() -> Result<()> {
-    let client = client();
+    let client = client("uploads_a_large_file_via_multipart");
     let base = prefix("multipart");
     let key = format!("{base}large.bin");
 
@@ -198,7 +258,7 @@ async fn uploads_a_large_file_via_multipart() -> Result<()> {
 #[tokio::test]
 #[ignore = "requires live R2 credentials"]
 async fn a_presigned_url_is_accepted_by_r2() -> Result<()> {
-    let client = client();
+    let client = client("a_presigned_url_is_accepted_by_r2");
     let base = prefix("presigned");
     let key = format!("{base}signed.txt");
 
@@ -209,6 +269,11 @@ async fn a_presigned_url_is_accepted_by_r2() -> Result<()> {
         .await?
         .into_url();
 
+    if replaying() {
+        // The URL is signed for R2, which a replay never contacts.
+        client.delete_prefix(&base).await?;
+        return Ok(());
+    }
     match std::process::Command::new("curl")
         .args(["--silent", "--show-error", "--fail", &url])
         .output()
@@ -233,7 +298,7 @@ async fn a_presigned_url_is_accepted_by_r2() -> Result<()> {
 #[tokio::test]
 #[ignore = "requires live R2 credentials"]
 async fn bucket_existence_is_reported_accurately() -> Result<()> {
-    let client = client();
+    let client = client("bucket_existence_is_reported_accurately");
     assert!(client.bucket_exists().await?);
 
     // A bucket-scoped API token gets 403 rather than 404 for a foreign bucket,
@@ -0,0 +1,330 @@
+//! The `mock` feature's in-memory server, driven through the real client.
+
+#![cfg(feature = "mock")]
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{
+    CorsMethod, CorsRule, Error, LifecycleRule, ListOptions, MultipartOptions, PutOptions,
+    MIN_PART_SIZE,
+};
+
+#[tokio::test]
+async fn objects_round_trip_with_their_metadata() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+
+    let options = PutOptions::new()
+        .content_type("text/csv")
+        .cache_control("max-age=60")
+        .metadata("owner", "finance");
+    let put = client
+        .put_object_with("reports/q1.csv", "region,total\n", options)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.get_object("reports/q1.csv").await.unwrap(),
+        b"region,total\n"
+    );
+    assert_eq!(
+        client
+            .get_object_range("reports/q1.csv", 0, Some(5))
+            .await
+            .unwrap(),
+        b"region"
+    );
+
+    let head = client.head_object("reports/q1.csv").await.unwrap();
+    assert_eq!(head.content_length, 13);
+    assert_eq!(head.content_type.as_deref(), Some("text/csv"));
+    assert_eq!(head.cache_control.as_deref(), Some("max-age=60"));
+    assert_eq!(head.etag, put.etag);
+    assert_eq!(
+        head.metadata.get("owner").map(String::as_str),
+        Some("finance")
+    );
+
+    assert_eq!(
+        server.object(MOCK_BUCKET, "reports/q1.csv").as_deref(),
+        Some(&b"region,total\n"[..])
+    );
+}
+
+#[tokio::test]
+async fn missing_objects_and_buckets_map_to_typed_errors() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+
+    let err = client.get_object("nope.txt").await.unwrap_err();
+    assert!(matches!(err, Error::ObjectNotFound { .. }), "{err:?}");
+    assert!(!client.object_exists("nope.txt").await.unwrap());
+
+    let unknown = client.with_bucket("no-such-bucket");
+    assert!(!unknown.bucket_exists().await.unwrap());
+    assert!(unknown
+        .get_object("a.txt")
+        .await
+        .unwrap_err()
+        .is_not_found());
+}
+
+#[tokio::test]
+async fn copies_and_deletes() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let archive = server.client_for("archive");
+
+    client.put_object("a b+c.txt", "hello").await.unwrap();
+    client.copy_object("a b+c.txt", "copy.txt").await.unwrap();
+    archive
+        .copy_object_from(MOCK_BUCKET, "a b+c.txt", "moved.txt")
+        .await
+        .unwrap();
+    assert_eq!(client.get_object("copy.txt").await.unwrap(), b"hello");
+    assert_eq!(archive.get_object("moved.txt").await.unwrap(), b"hello");
+
+    client.delete_object("copy.txt").await.unwrap();
+    let report = client
+        .delete_objects(["a b+c.txt", "never-existed.txt"])
+        .await
+        .unwrap();
+    assert!(report.all_succeeded());
+    assert!(server.keys(MOCK_BUCKET).is_empty());
+}
+
+#[tokio::test]
+async fn listings_paginate_and_group_by_delimiter() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    for key in [
+        "logs/2026/01.txt",
+        "logs/2026/02.txt",
+        "logs/2027/01.txt",
+        "logs/readme.txt",
+        "other.txt",
+    ] {
+        client.put_object(key, "x").await.unwrap();
+    }
+
+    let page = client
+        .list_objects(ListOptions::new().prefix("logs/").max_keys(2))
+        .await
+        .unwrap();
+    assert!(page.is_truncated);
+    assert_eq!(page.objects.len(), 2);
+    let rest = client
+        .list_objects(
+            ListOptions::new()
+                .prefix("logs/")
+                .continuation_token(page.next_continuation_token.unwrap()),
+        )
+        .await
+        .unwrap();
+    assert!(!rest.is_truncated);
+    assert_eq!(rest.objects.len(), 2);
+
+    assert_eq!(
+        client.list_prefixes("logs/").await.unwrap(),
+        ["logs/2026/", "logs/2027/"]
+    );
+
+    // One common prefix per page still pages correctly.
+    let first = client
+        .list_objects(
+            ListOptions::new()
+                .prefix("logs/")
+                .delimiter("/")
+                .max_keys(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(first.common_prefixes, ["logs/2026/"]);
+    let second = client
+        .list_objects(
+            ListOptions::new()
+                .prefix("logs/")
+                .delimiter("/")
+                .max_keys(1)
+                .continuation_token(first.next_continuation_token.unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(second.common_prefixes, ["logs/2027/"]);
+
+    assert_eq!(client.list_all_objects(None).await.unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn multipart_uploads_assemble_their_parts() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("big.bin");
+    let data: Vec<u8> = (0..MIN_PART_SIZE + 1234).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &data).unwrap();
+
+    client
+        .multipart_upload_file("big.bin", &path, MultipartOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        server.object(MOCK_BUCKET, "big.bin").as_deref(),
+        Some(&data[..])
+    );
+    assert!(client.list_multipart_uploads().await.unwrap().is_empty());
+
+    let upload = client
+        .create_multipart_upload("abandoned.bin", PutOptions::new())
+        .await
+        .unwrap();
+    let pending = client.list_multipart_uploads().await.unwrap();
+    assert_eq!(pending.len(), 1);
+    client
+        .abort_multipart_upload("abandoned.bin", &upload.upload_id)
+        .await
+        .unwrap();
+    assert!(client.list_multipart_uploads().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn hand_driven_uploads_name_the_precondition_they_refuse() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+
+    for (options, expected) in [
+        (PutOptions::new().if_match("\"abc\""), "if_match"),
+        (PutOptions::new().if_none_match("*"), "if_none_match"),
+    ] {
+        let err = client
+            .create_multipart_upload("a.bin", options)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidArgument { argument, .. } if argument == expected),
+            "{err:?}"
+        );
+    }
+    assert!(client.list_multipart_uploads().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn buckets_and_their_configuration() {
+    let server = MockServer::start().await.unwrap();
+    let scratch = server.client().with_bucket("scratch");
+
+    scratch.create_bucket().await.unwrap();
+    let names: Vec<_> = scratch
+        .list_buckets()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|bucket| bucket.name)
+        .collect();
+    assert_eq!(names, [MOCK_BUCKET, "scratch"]);
+
+    assert!(scratch.get_cors().await.unwrap().is_empty());
+    let rule = CorsRule::new()
+        .allow_method(CorsMethod::Get)
+        .allow_origin("https://example.com");
+    scratch.put_cors(vec![rule.clone()]).await.unwrap();
+    assert_eq!(scratch.get_cors().await.unwrap(), [rule]);
+
+    assert!(scratch.get_lifecycle().await.unwrap().is_empty());
+    let rule = LifecycleRule::new()
+        .id("expire-tmp")
+        .prefix("tmp/")
+        .expire_after(7);
+    scratch.put_lifecycle(vec![rule.clone()]).await.unwrap();
+    assert_eq!(scratch.get_lifecycle().await.unwrap(), [rule]);
+
+    scratch.put_object("x.txt", "x").await.unwrap();
+    assert!(scratch.delete_bucket().await.is_err());
+    scratch.delete_object("x.txt").await.unwrap();
+    scratch.delete_bucket().await.unwrap();
+    assert!(!scratch.bucket_exists().await.unwrap());
+}
+
+#[tokio::test]
+async fn cors_rules_are_validated_and_checked() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+
+    let err = client
+        .put_cors(vec![CorsRule::new()
+            .allow_origin("https://example.com/app")
+            .allow_method(CorsMethod::Get)])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument { .. }), "{err:?}");
+    assert!(client.get_cors().await.unwrap().is_empty());
+
+    let uploads = CorsRule::new()
+        .id("uploads")
+        .allow_origin("https://app.example.com")
+        .allow_methods([CorsMethod::Put, CorsMethod::Post])
+        .allow_header("content-type");
+    let reads = CorsRule::new()
+        .allow_origin("*")
+        .allow_method(CorsMethod::Get);
+    client
+        .put_cors(vec![uploads.clone(), reads.clone()])
+        .await
+        .unwrap();
+
+    let check = |origin: &'static str, method, headers: &'static [&'static str]| {
+        client.check_cors(origin, method, headers)
+    };
+    assert_eq!(
+        check(
+            "https://app.example.com",
+            CorsMethod::Put,
+            &["Content-Type"]
+        )
+        .await
+        .unwrap(),
+        Some(uploads)
+    );
+    assert_eq!(
+        check("https://other.example", CorsMethod::Get, &[])
+            .await
+            .unwrap(),
+        Some(reads)
+    );
+    for (origin, method, headers) in [
+        ("https://other.example", CorsMethod::Put, &[][..]),
+        (
+            "https://app.example.com",
+            CorsMethod::Put,
+            &["x-secret"][..],
+        ),
+    ] {
+        assert_eq!(check(origin, method, headers).await.unwrap(), None);
+    }
+}
+
+#[tokio::test]
+async fn lifecycle_rules_are_validated_before_sending() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+
+    let backwards = LifecycleRule::new()
+        .transition_to_infrequent_access_after(60)
+        .expire_after(30);
+    let err = client.put_lifecycle(vec![backwards]).await.unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument { .. }), "{err:?}");
+    assert!(client.get_lifecycle().await.unwrap().is_empty());
+
+    let rules = vec![
+        LifecycleRule::new()
+            .id("logs")
+            .prefix("logs/")
+            .transition_to_infrequent_access_after(30)
+            .expire_after(365),
+        LifecycleRule::new()
+            .id("uploads")
+            .abort_incomplete_multipart_after(1),
+    ];
+    client.put_lifecycle(rules.clone()).await.unwrap();
+    assert_eq!(client.get_lifecycle().await.unwrap(), rules);
+}
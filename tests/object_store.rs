@@ -0,0 +1,271 @@
+//! `R2ObjectStore` driven through the `object_store` API against the mock server.
+
+#![cfg(all(feature = "object_store", feature = "mock"))]
+
+use std::sync::Arc;
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use cloudflare_r2_rs::{R2ObjectStore, MIN_PART_SIZE};
+use futures::TryStreamExt;
+use object_store::path::Path;
+use object_store::{
+    Attribute, Attributes, GetOptions, GetRange, ObjectStore, ObjectStoreExt, PutMode, PutOptions,
+    PutPayload,
+};
+
+async fn store() -> (MockServer, Arc<dyn ObjectStore>) {
+    let server = MockServer::start().await.unwrap();
+    let store = Arc::new(R2ObjectStore::new(server.client()));
+    (server, store)
+}
+
+#[tokio::test]
+async fn objects_round_trip_with_ranges_and_attributes() {
+    let (_server, store) = store().await;
+    let location = Path::from("data/part-0.parquet");
+
+    let mut attributes = Attributes::new();
+    attributes.insert(
+        Attribute::ContentType,
+        "application/vnd.apache.parquet".into(),
+    );
+    attributes.insert(Attribute::Metadata("writer".into()), "datafusion".into());
+    let put = store
+        .put_opts(
+            &location,
+            PutPayload::from_static(b"PAR1 hello PAR1"),
+            PutOptions::from(attributes),
+        )
+        .await
+        .unwrap();
+
+    let result = store.get(&location).await.unwrap();
+    assert_eq!(result.meta.size, 15);
+    assert_eq!(result.meta.e_tag, put.e_tag);
+    assert_eq!(
+        result
+            .attributes
+            .get(&Attribute::ContentType)
+            .map(|value| value.as_ref()),
+        Some("application/vnd.apache.parquet")
+    );
+    assert_eq!(
+        result
+            .attributes
+            .get(&Attribute::Metadata("writer".into()))
+            .map(|value| value.as_ref()),
+        Some("datafusion")
+    );
+    assert_eq!(result.bytes().await.unwrap().as_ref(), b"PAR1 hello PAR1");
+
+    assert_eq!(
+        store.get_range(&location, 5..10).await.unwrap().as_ref(),
+        b"hello"
+    );
+    let suffix = store
+        .get_opts(
+            &location,
+            GetOptions {
+                range: Some(GetRange::Suffix(4)),
+                ..GetOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(suffix.range, 11..15);
+    assert_eq!(suffix.meta.size, 15);
+    assert_eq!(suffix.bytes().await.unwrap().as_ref(), b"PAR1");
+
+    let head = store.head(&location).await.unwrap();
+    assert_eq!(head.size, 15);
+    assert_eq!(head.location, location);
+}
+
+#[tokio::test]
+async fn missing_objects_are_not_found() {
+    let (_server, store) = store().await;
+    let missing = Path::from("nope");
+
+    for err in [
+        store.get(&missing).await.unwrap_err(),
+        store.head(&missing).await.unwrap_err(),
+    ] {
+        assert!(
+            matches!(err, object_store::Error::NotFound { .. }),
+            "{err:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn listings_follow_directory_semantics() {
+    let (_server, store) = store().await;
+    for key in [
+        "table/_delta_log/0.json",
+        "table/part-0.parquet",
+        "table/part-1.parquet",
+        "tablespace.txt",
+    ] {
+        store
+            .put(&Path::from(key), PutPayload::from_static(b"x"))
+            .await
+            .unwrap();
+    }
+
+    let listed: Vec<_> = store
+        .list(Some(&Path::from("table")))
+        .map_ok(|meta| meta.location.to_string())
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(
+        listed,
+        [
+            "table/_delta_log/0.json",
+            "table/part-0.parquet",
+            "table/part-1.parquet",
+        ]
+    );
+
+    let offset: Vec<_> = store
+        .list_with_offset(
+            Some(&Path::from("table")),
+            &Path::from("table/part-0.parquet"),
+        )
+        .map_ok(|meta| meta.location.to_string())
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(offset, ["table/part-1.parquet"]);
+
+    let grouped = store
+        .list_with_delimiter(Some(&Path::from("table")))
+        .await
+        .unwrap();
+    assert_eq!(grouped.common_prefixes, [Path::from("table/_delta_log")]);
+    assert_eq!(grouped.objects.len(), 2);
+
+    let root = store.list_with_delimiter(None).await.unwrap();
+    assert_eq!(root.common_prefixes, [Path::from("table")]);
+    assert_eq!(root.objects[0].location, Path::from("tablespace.txt"));
+}
+
+#[tokio::test]
+async fn copies_and_deletes() {
+    let (server, store) = store().await;
+    let source = Path::from("a.txt");
+    store
+        .put(&source, PutPayload::from_static(b"hello"))
+        .await
+        .unwrap();
+
+    store.copy(&source, &Path::from("b.txt")).await.unwrap();
+    assert_eq!(
+        server.object(MOCK_BUCKET, "b.txt").as_deref(),
+        Some(&b"hello"[..])
+    );
+
+    store.delete(&source).await.unwrap();
+    let deleted: Vec<_> = store
+        .delete_stream(Box::pin(futures::stream::iter([
+            Ok(Path::from("b.txt")),
+            Ok(Path::from("never-existed.txt")),
+        ])))
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(deleted.len(), 2);
+    assert!(server.keys(MOCK_BUCKET).is_empty());
+}
+
+#[tokio::test]
+async fn multipart_writes_assemble_in_order() {
+    let (server, store) = store().await;
+    let location = Path::from("big.bin");
+    let data: Vec<u8> = (0..MIN_PART_SIZE * 2 + 17)
+        .map(|i| (i % 251) as u8)
+        .collect();
+
+    // Parts are uploaded concurrently and may finish out of order.
+    let mut upload = store.put_multipart(&location).await.unwrap();
+    let parts: Vec<_> = data
+        .chunks(MIN_PART_SIZE as usize)
+        .map(|chunk| upload.put_part(PutPayload::from(chunk.to_vec())))
+        .collect();
+    futures::future::try_join_all(parts.into_iter().rev())
+        .await
+        .unwrap();
+    upload.complete().await.unwrap();
+
+    assert_eq!(
+        server.object(MOCK_BUCKET, "big.bin").as_deref(),
+        Some(&data[..])
+    );
+}
+
+#[tokio::test]
+async fn conditional_puts_map_to_preconditions() {
+    let (_server, store) = store().await;
+    let location = Path::from("a.txt");
+    let put = |mode: PutMode, body: &'static [u8]| {
+        store.put_opts(
+            &location,
+            PutPayload::from_static(body),
+            PutOptions::from(mode),
+        )
+    };
+
+    let created = put(PutMode::Create, b"one").await.unwrap();
+    let err = put(PutMode::Create, b"two").await.unwrap_err();
+    assert!(
+        matches!(err, object_store::Error::AlreadyExists { .. }),
+        "{err:?}"
+    );
+
+    let updated = put(PutMode::Update(created.clone().into()), b"three")
+        .await
+        .unwrap();
+    let err = put(PutMode::Update(created.into()), b"four")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, object_store::Error::Precondition { .. }),
+        "{err:?}"
+    );
+    assert_ne!(updated.e_tag, None);
+    let body = store.get(&location).await.unwrap().bytes().await.unwrap();
+    assert_eq!(&body[..], b"three");
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn encrypted_objects_round_trip_as_plaintext() {
+    let server = MockServer::start().await.unwrap();
+    let client = server
+        .builder(MOCK_BUCKET)
+        .encryption(Arc::new(cloudflare_r2_rs::LocalKeyProvider::new(
+            "primary", [7; 32],
+        )))
+        .build()
+        .unwrap();
+    let store = R2ObjectStore::new(client);
+    let location = Path::from("secret.csv");
+    let body = b"id,name\n1,ada\n".repeat(10_000);
+
+    store
+        .put(&location, PutPayload::from(body.clone()))
+        .await
+        .unwrap();
+    assert_ne!(server.object(MOCK_BUCKET, "secret.csv").unwrap(), body);
+
+    let got = store.get(&location).await.unwrap();
+    assert_eq!(got.meta.size, body.len() as u64);
+    assert_eq!(got.bytes().await.unwrap(), body);
+    assert_eq!(store.head(&location).await.unwrap().size, body.len() as u64);
+
+    let ranged = GetOptions {
+        range: Some(GetRange::Bounded(0..7)),
+        ..GetOptions::default()
+    };
+    assert!(store.get_opts(&location, ranged).await.is_err());
+}
@@ -45,6 +45,8 @@ struct Canned {
     /// actually written before the socket closes — the shape of a connection
     /// dropped mid-transfer.
     declared_length: Option<usize>,
+    /// Extra response headers.
+    headers: Vec<(&'static str, &'static str)>,
 }
 
 impl Canned {
@@ -53,6 +55,7 @@ impl Canned {
             status: 200,
             body: body.into(),
             declared_length: None,
+            headers: Vec::new(),
         }
     }
 
@@ -62,6 +65,7 @@ impl Canned {
             status: 200,
             body: body.into(),
             declared_length: Some(declared),
+            headers: Vec::new(),
         }
     }
 
@@ -73,8 +77,14 @@ impl Canned {
 <Error><Code>{code}</Code><Message>{message}</Message></Error>"#
             ),
             declared_length: None,
+            headers: Vec::new(),
         }
     }
+
+    fn header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
 }
 
 /// A minimal S3-shaped HTTP server that records what it is sent.
@@ -135,6 +145,23 @@ impl MockR2 {
             .unwrap()
     }
 
+    /// A client that retries failed units of composite operations, with
+    /// backoffs short enough not to slow the suite down.
+    fn retrying_client(&self) -> R2Client {
+        R2Client::builder()
+            .endpoint(&self.endpoint)
+            .bucket("test-bucket")
+            .access_key_id("test-key")
+            .secret_access_key("test-secret")
+            .retry_config(cloudflare_r2_rs::s3::RetryConfig::disabled())
+            .retry_policy(
+                cloudflare_r2_rs::RetryPolicy::new()
+                    .initial_backoff(std::time::Duration::from_millis(1)),
+            )
+            .build()
+            .unwrap()
+    }
+
     fn requests(&self) -> Vec<Recorded> {
         self.recorded.lock().unwrap().clone()
     }
@@ -270,8 +297,13 @@ async fn decode_chunked(stream: &TcpStream, initial: Vec<u8>) -> Vec<u8> {
 }
 
 async fn write_response(stream: &TcpStream, canned: &Canned) -> std::io::Result<()> {
+    let headers: String = canned
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect();
     let response = format!(
-        "HTTP/1.1 {} OK\r\nContent-Length: {}\r\nContent-Type: application/xml\r\nConnection: close\r\n\r\n{}",
+        "HTTP/1.1 {} OK\r\nContent-Length: {}\r\nContent-Type: application/xml\r\nConnection: close\r\n{headers}\r\n{}",
         canned.status,
         canned.declared_length.unwrap_or(canned.body.len()),
         canned.body
@@ -581,6 +613,27 @@ async fn delete_objects_reports_per_key_failures() {
     assert_eq!(report.failed[0].code.as_deref(), Some("AccessDenied"));
 }
 
+#[tokio::test]
+async fn a_failed_batch_is_reported_without_losing_the_others() {
+    let keys: Vec<String> = (0..1001).map(|index| format!("key-{index}")).collect();
+    let mock = MockR2::start(vec![
+        Canned::ok(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<DeleteResult><Deleted><Key>key-0</Key></Deleted></DeleteResult>"#,
+        ),
+        Canned::error(403, "AccessDenied", "nope"),
+    ])
+    .await;
+
+    let report = mock.client().delete_objects(keys).await.unwrap();
+
+    assert_eq!(report.deleted, ["key-0"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].key, "key-1000");
+    assert_eq!(report.failed[0].code.as_deref(), Some("AccessDenied"));
+    assert!(report.failed[0].message.is_some());
+}
+
 #[tokio::test]
 async fn an_invalid_key_aborts_the_batch_before_anything_is_deleted() {
     let mock = MockR2::start(vec![Canned::ok("")]).await;
@@ -696,6 +749,69 @@ async fn a_missing_lifecycle_configuration_reads_as_no_rules() {
     assert!(mock.client().get_lifecycle().await.unwrap().is_empty());
 }
 
+#[tokio::test]
+async fn api_errors_are_classified_and_carry_request_ids() {
+    let mock = MockR2::start(vec![Canned::error(
+        503,
+        "SlowDown",
+        "Reduce your request rate.",
+    )
+    .header("x-amz-request-id", "4442587FB7D0A2F9")
+    .header("cf-ray", "8a1b2c3d4e5f6789-LHR")])
+    .await;
+    let err = mock.client().put_object("a.txt", "x").await.unwrap_err();
+
+    assert_eq!(err.code(), Some(&cloudflare_r2_rs::ErrorCode::SlowDown));
+    assert!(err.is_throttled());
+    assert!(err.is_retryable());
+    assert!(!err.is_precondition_failed());
+    assert_eq!(err.request_id(), Some("4442587FB7D0A2F9"));
+    assert_eq!(err.cf_ray(), Some("8a1b2c3d4e5f6789-LHR"));
+}
+
+#[tokio::test]
+async fn a_rejected_part_is_not_retryable() {
+    let mock = MockR2::start(vec![Canned::error(
+        400,
+        "EntityTooSmall",
+        "Your proposed upload is smaller than the minimum allowed object size.",
+    )])
+    .await;
+    let err = mock
+        .client()
+        .upload_part("a.bin", "upload-1", 1, "tiny")
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.code(),
+        Some(&cloudflare_r2_rs::ErrorCode::EntityTooSmall)
+    );
+    assert!(!err.is_retryable());
+}
+
+#[tokio::test]
+async fn a_refused_connection_is_retryable() {
+    // Bind and drop a listener to find a port nothing is listening on.
+    let address = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let client = R2Client::builder()
+        .endpoint(format!("http://{address}"))
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .retry_config(cloudflare_r2_rs::s3::RetryConfig::disabled())
+        .build()
+        .unwrap();
+
+    let err = client.delete_object("a.txt").await.unwrap_err();
+    assert_eq!(err.status(), None);
+    assert!(err.is_retryable(), "{err:?}");
+}
+
 // --- upload paths ----------------------------------------------------------
 
 #[tokio::test]
@@ -782,6 +898,474 @@ async fn a_failed_part_aborts_the_upload() {
     assert!(aborted, "no abort was sent: {requests:#?}");
 }
 
+// --- transport -------------------------------------------------------------
+
+#[tokio::test]
+async fn requests_are_routed_through_a_configured_proxy() {
+    // The mock plays the proxy. The endpoint's host does not resolve, so the
+    // request can only arrive if the transport really went via the proxy.
+    let proxy = MockR2::start(vec![Canned::ok("")]).await;
+    let client = R2Client::builder()
+        .endpoint("http://r2.invalid")
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .retry_config(cloudflare_r2_rs::s3::RetryConfig::disabled())
+        .http_options(
+            cloudflare_r2_rs::HttpOptions::new()
+                .proxy(&proxy.endpoint)
+                .proxy_auth("svc", "pw"),
+        )
+        .build()
+        .unwrap();
+
+    client.put_object("a.txt", "x").await.unwrap();
+
+    let request = proxy.first();
+    // A forward proxy receives the absolute URL, not just the path.
+    assert!(
+        request
+            .target
+            .starts_with("http://r2.invalid/test-bucket/a.txt"),
+        "{}",
+        request.target
+    );
+    // base64("svc:pw")
+    assert_eq!(
+        request.header("proxy-authorization"),
+        Some("Basic c3ZjOnB3")
+    );
+}
+
+#[tokio::test]
+async fn https_endpoints_are_tunnelled_through_the_proxy() {
+    // The mock accepts the tunnel and hangs up, so the TLS handshake that
+    // follows fails; what matters is the `CONNECT` it was asked for.
+    let proxy = MockR2::start(vec![Canned::ok("")]).await;
+    let client = R2Client::builder()
+        .endpoint("https://r2.invalid")
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .retry_config(cloudflare_r2_rs::s3::RetryConfig::disabled())
+        .http_options(
+            cloudflare_r2_rs::HttpOptions::new()
+                .proxy(&proxy.endpoint)
+                .proxy_auth("svc", "p@ss:word"),
+        )
+        .build()
+        .unwrap();
+
+    client.put_object("a.txt", "x").await.unwrap_err();
+
+    let request = proxy.first();
+    assert_eq!(request.method, "CONNECT");
+    assert_eq!(request.target, "r2.invalid:443");
+    // base64("svc:p@ss:word"): the credentials survive being put in a URL.
+    assert_eq!(
+        request.header("proxy-authorization"),
+        Some("Basic c3ZjOnBAc3M6d29yZA==")
+    );
+}
+
+#[tokio::test]
+async fn pool_keepalive_and_protocol_settings_reach_the_endpoint() {
+    let mock = MockR2::start(vec![Canned::ok("one"), Canned::ok("two")]).await;
+    let client = R2Client::builder()
+        .endpoint(&mock.endpoint)
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .http_options(
+            cloudflare_r2_rs::HttpOptions::new()
+                .pool_max_idle_per_host(1)
+                .pool_idle_timeout(std::time::Duration::from_secs(5))
+                .tcp_keepalive(std::time::Duration::from_secs(30))
+                .http2(false),
+        )
+        .build()
+        .unwrap();
+
+    assert_eq!(client.get_object("a.txt").await.unwrap(), b"one");
+    assert_eq!(client.get_object("b.txt").await.unwrap(), b"two");
+    assert_eq!(mock.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn default_http_options_reach_the_endpoint_directly() {
+    let mock = MockR2::start(vec![Canned::ok("hello")]).await;
+    let client = R2Client::builder()
+        .endpoint(&mock.endpoint)
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .http_options(cloudflare_r2_rs::HttpOptions::new().tcp_nodelay(true))
+        .build()
+        .unwrap();
+
+    assert_eq!(client.get_object("a.txt").await.unwrap(), b"hello");
+    assert!(mock.first().target.starts_with("/test-bucket/a.txt"));
+}
+
+// --- rate limiting ---------------------------------------------------------
+
+#[tokio::test]
+async fn the_rate_limit_is_shared_by_with_bucket_children() {
+    let mock = MockR2::start(vec![Canned::ok(""), Canned::ok(""), Canned::ok("")]).await;
+    let client = R2Client::builder()
+        .endpoint(&mock.endpoint)
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .retry_config(cloudflare_r2_rs::s3::RetryConfig::disabled())
+        .rate_limit(
+            cloudflare_r2_rs::RateLimit::new()
+                .requests_per_second(10.0)
+                .burst(1),
+        )
+        .build()
+        .unwrap();
+    let other = client.with_bucket("other-bucket");
+
+    let start = std::time::Instant::now();
+    client.delete_object("a.txt").await.unwrap();
+    other.delete_object("b.txt").await.unwrap();
+    client.clone().delete_object("c.txt").await.unwrap();
+
+    // One token up front, then 100ms for each of the other two: had the child
+    // or the clone got a limiter of its own, this would take no time at all.
+    assert!(start.elapsed() >= std::time::Duration::from_millis(190));
+    assert_eq!(mock.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn the_rate_limit_counts_every_attempt() {
+    let mock = MockR2::start(vec![
+        Canned::error(503, "SlowDown", "Reduce your request rate."),
+        Canned::ok(""),
+    ])
+    .await;
+    let client = R2Client::builder()
+        .endpoint(&mock.endpoint)
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .retry_config(
+            cloudflare_r2_rs::s3::RetryConfig::standard()
+                .with_max_attempts(2)
+                .with_initial_backoff(std::time::Duration::from_millis(1)),
+        )
+        .rate_limit(
+            cloudflare_r2_rs::RateLimit::new()
+                .requests_per_second(10.0)
+                .burst(1),
+        )
+        .build()
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    client.delete_object("a.txt").await.unwrap();
+
+    // The backoff is a millisecond at most; the retry waited for a token.
+    assert!(start.elapsed() >= std::time::Duration::from_millis(90));
+    assert_eq!(mock.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn invalid_rate_limits_are_rejected_at_build_time() {
+    let err = R2Client::builder()
+        .endpoint("http://localhost:9000")
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .rate_limit(cloudflare_r2_rs::RateLimit::new().max_in_flight(0))
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidConfig { .. }), "{err:?}");
+}
+
+// --- application-level retry ---------------------------------------------
+
+#[tokio::test]
+async fn only_the_failed_part_is_retried() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("big.bin");
+    tokio::fs::write(&path, vec![b'x'; 6 * 1024 * 1024])
+        .await
+        .unwrap();
+
+    let mock = MockR2::start(vec![
+        Canned::ok(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<InitiateMultipartUploadResult><UploadId>upload-1</UploadId></InitiateMultipartUploadResult>"#,
+        ),
+        Canned::ok("").header("ETag", "\"etag-1\""),
+        Canned::error(503, "SlowDown", "Reduce your request rate."),
+        Canned::ok("").header("ETag", "\"etag-2\""),
+        Canned::ok(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<CompleteMultipartUploadResult><ETag>&quot;final&quot;</ETag></CompleteMultipartUploadResult>"#,
+        ),
+    ])
+    .await;
+
+    mock.retrying_client()
+        .multipart_upload_file(
+            "big.bin",
+            &path,
+            MultipartOptions::new()
+                .part_size(5 * 1024 * 1024)
+                .concurrency(1),
+        )
+        .await
+        .unwrap();
+
+    let requests = mock.requests();
+    let part_numbers: Vec<&str> = requests
+        .iter()
+        .filter(|request| request.method == "PUT")
+        .map(|request| {
+            request
+                .target
+                .split(['?', '&'])
+                .find_map(|param| param.strip_prefix("partNumber="))
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(part_numbers, ["1", "2", "2"]);
+    // The retried part was re-read from disk in full.
+    let sizes: Vec<usize> = requests
+        .iter()
+        .filter(|request| request.method == "PUT")
+        .map(|request| request.body.len())
+        .collect();
+    assert_eq!(sizes[1], sizes[2]);
+    assert!(requests.iter().all(|request| request.method != "DELETE"));
+}
+
+#[tokio::test]
+async fn only_the_failed_page_is_retried() {
+    let mock = MockR2::start(vec![
+        Canned::ok(list_page(&["a.txt"], Some("TOKEN-2"))),
+        Canned::error(500, "InternalError", "try again"),
+        Canned::ok(list_page(&["b.txt"], None)),
+    ])
+    .await;
+
+    let objects = mock.retrying_client().list_all_objects(None).await.unwrap();
+    assert_eq!(objects.len(), 2);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(!requests[0].target.contains("continuation-token"));
+    assert!(requests[1].target.contains("continuation-token=TOKEN-2"));
+    assert!(requests[2].target.contains("continuation-token=TOKEN-2"));
+}
+
+#[tokio::test]
+async fn only_keys_that_failed_transiently_are_resubmitted() {
+    let mock = MockR2::start(vec![
+        Canned::ok(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<DeleteResult><Deleted><Key>a.txt</Key></Deleted>
+<Error><Key>b.txt</Key><Code>InternalError</Code><Message>try again</Message></Error>
+<Error><Key>c.txt</Key><Code>AccessDenied</Code><Message>nope</Message></Error></DeleteResult>"#,
+        ),
+        Canned::ok(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<DeleteResult><Deleted><Key>b.txt</Key></Deleted></DeleteResult>"#,
+        ),
+    ])
+    .await;
+
+    let report = mock
+        .retrying_client()
+        .delete_objects(vec!["a.txt", "b.txt", "c.txt"])
+        .await
+        .unwrap();
+
+    assert_eq!(report.deleted, ["a.txt", "b.txt"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].key, "c.txt");
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    let resubmitted = String::from_utf8_lossy(&requests[1].body);
+    assert!(resubmitted.contains("<Key>b.txt</Key>"), "{resubmitted}");
+    assert_eq!(resubmitted.matches("<Key>").count(), 1, "{resubmitted}");
+}
+
+#[tokio::test]
+async fn keys_still_failing_after_the_last_attempt_are_reported() {
+    let still_failing = r#"<?xml version="1.0" encoding="UTF-8"?>
+<DeleteResult><Error><Key>b.txt</Key><Code>SlowDown</Code><Message>slow down</Message></Error></DeleteResult>"#;
+    let mock = MockR2::start(vec![
+        Canned::ok(still_failing),
+        Canned::ok(still_failing),
+        Canned::ok(still_failing),
+    ])
+    .await;
+
+    let report = mock
+        .retrying_client()
+        .delete_objects(vec!["b.txt"])
+        .await
+        .unwrap();
+
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].code.as_deref(), Some("SlowDown"));
+    assert_eq!(mock.requests().len(), 3);
+}
+
+// --- metrics ---------------------------------------------------------------
+
+/// A `MetricsObserver` that keeps every event.
+#[derive(Debug, Default)]
+struct Events(Mutex<Vec<cloudflare_r2_rs::OperationEvent>>);
+
+impl cloudflare_r2_rs::MetricsObserver for Events {
+    fn on_operation(&self, event: &cloudflare_r2_rs::OperationEvent) {
+        self.0.lock().unwrap().push(event.clone());
+    }
+}
+
+#[tokio::test]
+async fn every_operation_is_reported_with_its_class_status_and_bytes() {
+    use cloudflare_r2_rs::OperationClass;
+
+    let mock = MockR2::start(vec![
+        Canned::ok(""),
+        Canned::ok("world!"),
+        Canned::error(404, "NoSuchKey", "The specified key does not exist."),
+    ])
+    .await;
+    let events = Arc::new(Events::default());
+    let client = R2Client::builder()
+        .endpoint(&mock.endpoint)
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .retry_config(cloudflare_r2_rs::s3::RetryConfig::disabled())
+        .metrics(events.clone())
+        .build()
+        .unwrap();
+
+    client.put_object("a.txt", "hello").await.unwrap();
+    client.get_object("a.txt").await.unwrap();
+    client.head_object("gone.txt").await.unwrap_err();
+    // Presigning sends nothing, so there is nothing to report.
+    client
+        .presign_get("a.txt", std::time::Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    let events = events.0.lock().unwrap().clone();
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| {
+            (
+                event.operation.as_str(),
+                event.class,
+                event.status,
+                event.success,
+                event.bytes_sent,
+                event.bytes_received,
+                event.attempts,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (
+                "PutObject",
+                OperationClass::ClassA,
+                Some(200),
+                true,
+                5,
+                0,
+                1
+            ),
+            (
+                "GetObject",
+                OperationClass::ClassB,
+                Some(200),
+                true,
+                0,
+                6,
+                1
+            ),
+            (
+                "HeadObject",
+                OperationClass::ClassB,
+                Some(404),
+                false,
+                0,
+                0,
+                1
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sdk_retries_are_counted_as_attempts() {
+    let mock = MockR2::start(vec![
+        Canned::error(500, "InternalError", "We encountered an internal error."),
+        Canned::ok(""),
+    ])
+    .await;
+    let usage = Arc::new(cloudflare_r2_rs::UsageCounter::new());
+    let client = R2Client::builder()
+        .endpoint(&mock.endpoint)
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .retry_config(
+            cloudflare_r2_rs::s3::RetryConfig::standard()
+                .with_max_attempts(2)
+                .with_initial_backoff(std::time::Duration::from_millis(1)),
+        )
+        .metrics(usage.clone())
+        .build()
+        .unwrap();
+
+    client.put_object("a.txt", "hello").await.unwrap();
+
+    let usage = usage.snapshot();
+    assert_eq!(usage.class_a, 2, "both attempts reached R2");
+    assert_eq!(usage.failures, 0);
+    assert_eq!(usage.bytes_sent, 10);
+}
+
+#[tokio::test]
+async fn reads_of_infrequent_access_objects_are_costed_as_such() {
+    let mock = MockR2::start(vec![
+        Canned::ok("archived").header("x-amz-storage-class", "STANDARD_IA")
+    ])
+    .await;
+    let costs = Arc::new(cloudflare_r2_rs::CostTracker::new(
+        cloudflare_r2_rs::Pricing::new(),
+    ));
+    let client = R2Client::builder()
+        .endpoint(&mock.endpoint)
+        .bucket("test-bucket")
+        .access_key_id("test-key")
+        .secret_access_key("test-secret")
+        .retry_config(cloudflare_r2_rs::s3::RetryConfig::disabled())
+        .metrics(costs.clone())
+        .build()
+        .unwrap();
+
+    client.get_object("old.log").await.unwrap();
+
+    let usage = costs.usage();
+    assert_eq!(usage.standard.class_b, 0);
+    assert_eq!(usage.infrequent_access.class_b, 1);
+    assert_eq!(usage.infrequent_access.retrieved_bytes, 8);
+    assert!(costs.estimate().retrieval > 0.0);
+}
+
 // --- downloads -------------------------------------------------------------
 
 #[tokio::test]
@@ -929,6 +1513,7 @@ async fn an_unparseable_error_body_still_yields_an_actionable_message() {
         status: 502,
         body: "<html>502 Bad Gateway</html>".to_string(),
         declared_length: None,
+        headers: Vec::new(),
     }])
     .await;
 
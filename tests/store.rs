@@ -0,0 +1,183 @@
+//! The same expectations run against every `ObjectStore` implementation.
+
+use bytes::Bytes;
+use cloudflare_r2_rs::{Error, ListOptions, LocalStore, MemoryStore, ObjectStore, PutOptions};
+
+/// Behaviour every store must share, so code written against the trait can
+/// move between them.
+async fn conformance(store: &dyn ObjectStore) {
+    // Round trip.
+    let put = store
+        .put(
+            "docs/readme.txt",
+            Bytes::from_static(b"hello world"),
+            PutOptions::new(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(put.key, "docs/readme.txt");
+    assert_eq!(store.get("docs/readme.txt").await.unwrap(), b"hello world");
+
+    // Ranges are inclusive, open-ended and clamped.
+    assert_eq!(
+        store
+            .get_range("docs/readme.txt", 0, Some(4))
+            .await
+            .unwrap(),
+        b"hello"
+    );
+    assert_eq!(
+        store.get_range("docs/readme.txt", 6, None).await.unwrap(),
+        b"world"
+    );
+    assert_eq!(
+        store
+            .get_range("docs/readme.txt", 6, Some(1000))
+            .await
+            .unwrap(),
+        b"world"
+    );
+    assert!(matches!(
+        store.get_range("docs/readme.txt", 5, Some(4)).await,
+        Err(Error::InvalidArgument { .. })
+    ));
+
+    // Metadata.
+    let head = store.head("docs/readme.txt").await.unwrap();
+    assert_eq!(head.content_length, 11);
+    assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+    assert_eq!(head.etag, put.etag);
+    assert!(head.last_modified.is_some());
+
+    // Missing keys.
+    assert!(matches!(
+        store.get("missing.txt").await,
+        Err(Error::ObjectNotFound { .. })
+    ));
+    assert!(store.head("missing.txt").await.unwrap_err().is_not_found());
+    assert!(!store.exists("missing.txt").await.unwrap());
+    assert!(store.exists("docs/readme.txt").await.unwrap());
+    assert!(matches!(
+        store.put("", Bytes::new(), PutOptions::new()).await,
+        Err(Error::InvalidArgument { .. })
+    ));
+
+    // Copies.
+    store
+        .copy("docs/readme.txt", "docs/copy.txt")
+        .await
+        .unwrap();
+    assert_eq!(store.get("docs/copy.txt").await.unwrap(), b"hello world");
+    assert!(store
+        .copy("missing.txt", "elsewhere.txt")
+        .await
+        .unwrap_err()
+        .is_not_found());
+
+    // Listings.
+    for key in ["logs/2026/01.txt", "logs/2026/02.txt", "logs/2027/01.txt"] {
+        store
+            .put(key, Bytes::from_static(b"x"), PutOptions::new())
+            .await
+            .unwrap();
+    }
+    let first = store
+        .list(ListOptions::new().prefix("logs/").max_keys(2))
+        .await
+        .unwrap();
+    assert!(first.is_truncated);
+    assert_eq!(first.objects.len(), 2);
+    let rest = store
+        .list(
+            ListOptions::new()
+                .prefix("logs/")
+                .continuation_token(first.next_continuation_token.unwrap()),
+        )
+        .await
+        .unwrap();
+    assert!(!rest.is_truncated);
+    assert_eq!(rest.objects[0].key, "logs/2027/01.txt");
+
+    let grouped = store
+        .list(ListOptions::new().prefix("logs/").delimiter("/"))
+        .await
+        .unwrap();
+    assert_eq!(grouped.common_prefixes, ["logs/2026/", "logs/2027/"]);
+    assert!(grouped.objects.is_empty());
+
+    let all: Vec<_> = store
+        .list_all(None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|object| object.key)
+        .collect();
+    assert_eq!(
+        all,
+        [
+            "docs/copy.txt",
+            "docs/readme.txt",
+            "logs/2026/01.txt",
+            "logs/2026/02.txt",
+            "logs/2027/01.txt",
+        ]
+    );
+
+    // Deletes, including of keys that are already gone.
+    store.delete("docs/copy.txt").await.unwrap();
+    store.delete("docs/copy.txt").await.unwrap();
+    assert!(!store.exists("docs/copy.txt").await.unwrap());
+    assert_eq!(store.list_all(Some("docs/")).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn memory_store_conforms() {
+    conformance(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn local_store_conforms() {
+    let dir = tempfile::tempdir().unwrap();
+    conformance(&LocalStore::new(dir.path())).await;
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn r2_client_conforms() {
+    let server = cloudflare_r2_rs::mock::MockServer::start().await.unwrap();
+    conformance(&server.client()).await;
+}
+
+#[tokio::test]
+async fn memory_store_keeps_put_options() {
+    let store = MemoryStore::new();
+    let options = PutOptions::new()
+        .content_type("application/json")
+        .cache_control("no-store")
+        .metadata("owner", "ops");
+    store
+        .put("state", Bytes::from_static(b"{}"), options)
+        .await
+        .unwrap();
+
+    let head = store.head("state").await.unwrap();
+    assert_eq!(head.content_type.as_deref(), Some("application/json"));
+    assert_eq!(head.cache_control.as_deref(), Some("no-store"));
+    assert_eq!(head.metadata.get("owner").map(String::as_str), Some("ops"));
+}
+
+#[tokio::test]
+async fn stores_are_interchangeable_behind_a_trait_object() {
+    let dir = tempfile::tempdir().unwrap();
+    let stores: Vec<Box<dyn ObjectStore>> = vec![
+        Box::new(MemoryStore::new()),
+        Box::new(LocalStore::new(dir.path())),
+    ];
+    for store in &stores {
+        store
+            .put("k", Bytes::from_static(b"v"), PutOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(store.get("k").await.unwrap(), b"v");
+    }
+}
@@ -0,0 +1,220 @@
+//! The `tracing` feature's spans, captured from real calls to the mock server.
+
+#![cfg(all(feature = "tracing", feature = "mock"))]
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use cloudflare_r2_rs::mock::{MockServer, MOCK_BUCKET};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const BODY: &str = "a body that must never be traced";
+
+/// A span opened by this crate, with every field recorded on it so far and
+/// the events emitted inside it.
+#[derive(Debug, Default)]
+struct Captured {
+    name: &'static str,
+    fields: BTreeMap<&'static str, String>,
+    events: Vec<BTreeMap<&'static str, String>>,
+}
+
+impl Captured {
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+/// Collects this crate's spans, ignoring the SDK's own.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<BTreeMap<u64, Captured>>>);
+
+impl Capture {
+    fn spans(&self, name: &str) -> Vec<Captured> {
+        let mut spans = self.0.lock().unwrap();
+        let ids: Vec<u64> = spans
+            .iter()
+            .filter(|(_, span)| span.name == name)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.iter().filter_map(|id| spans.remove(id)).collect()
+    }
+
+    /// Everything captured, as one string to search for leaks.
+    fn everything(&self) -> String {
+        format!("{:?}", self.0.lock().unwrap())
+    }
+}
+
+struct Fields<'a>(&'a mut BTreeMap<&'static str, String>);
+
+impl Visit for Fields<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+}
+
+fn ours(target: &str) -> bool {
+    target.starts_with("cloudflare_r2_rs")
+}
+
+impl<S> Layer<S> for Capture
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+        if !ours(attrs.metadata().target()) {
+            return;
+        }
+        let mut span = Captured {
+            name: attrs.metadata().name(),
+            ..Captured::default()
+        };
+        attrs.record(&mut Fields(&mut span.fields));
+        self.0.lock().unwrap().insert(id.into_u64(), span);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        if let Some(span) = self.0.lock().unwrap().get_mut(&id.into_u64()) {
+            values.record(&mut Fields(&mut span.fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !ours(event.metadata().target()) {
+            return;
+        }
+        let Some(parent) = ctx.event_span(event) else {
+            return;
+        };
+        let mut fields = BTreeMap::new();
+        event.record(&mut Fields(&mut fields));
+        if let Some(span) = self.0.lock().unwrap().get_mut(&parent.id().into_u64()) {
+            span.events.push(fields);
+        }
+    }
+}
+
+fn capture() -> (Capture, tracing::subscriber::DefaultGuard) {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(capture.clone());
+    (capture, tracing::subscriber::set_default(subscriber))
+}
+
+// The default subscriber is per thread, so these tests stay on the
+// current-thread runtime `tokio::test` provides.
+
+#[tokio::test]
+async fn puts_and_gets_record_bucket_key_status_and_bytes() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let (capture, _guard) = capture();
+
+    client.put_object("docs/a.txt", BODY).await.unwrap();
+    client
+        .get_object_range("docs/a.txt", 2, None)
+        .await
+        .unwrap();
+
+    // `put_object` delegates to `put_object_with`, whose span sends the request.
+    for (name, status, bytes) in [
+        ("put_object_with", "200", BODY.len()),
+        ("get_object_range", "206", BODY.len() - 2),
+    ] {
+        let spans = capture.spans(name);
+        assert_eq!(spans.len(), 1, "{name}: {spans:?}");
+        let span = &spans[0];
+        assert_eq!(span.field("bucket"), Some(MOCK_BUCKET), "{name}");
+        assert_eq!(span.field("key"), Some("docs/a.txt"), "{name}");
+        assert_eq!(span.field("status"), Some(status), "{name}");
+        assert_eq!(span.field("bytes"), Some(&*bytes.to_string()), "{name}");
+        assert!(span.events.is_empty(), "{name}: {:?}", span.events);
+    }
+}
+
+#[tokio::test]
+async fn failures_record_the_status_and_the_error() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let (capture, _guard) = capture();
+
+    let err = client.get_object("missing.txt").await.unwrap_err();
+    assert!(err.is_not_found());
+
+    // `get_object` reads through `get_object_stream`, which sends the request.
+    let stream = capture.spans("get_object_stream");
+    assert_eq!(stream.len(), 1, "{stream:?}");
+    assert_eq!(stream[0].field("key"), Some("missing.txt"));
+    assert_eq!(stream[0].field("status"), Some("404"));
+
+    let spans = capture.spans("get_object");
+    assert_eq!(spans.len(), 1, "{spans:?}");
+    let span = &spans[0];
+    assert_eq!(span.field("key"), Some("missing.txt"));
+    assert_eq!(span.field("bytes"), None);
+    assert_eq!(span.events.len(), 1, "{:?}", span.events);
+    assert!(span.events[0].contains_key("error"), "{:?}", span.events);
+}
+
+#[tokio::test]
+async fn spans_carry_no_credentials_or_bodies() {
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    let (capture, _guard) = capture();
+
+    client.put_object("docs/a.txt", BODY).await.unwrap();
+    client.get_object("docs/a.txt").await.unwrap();
+    client.get_object("missing.txt").await.unwrap_err();
+    client
+        .presign_get("docs/a.txt", std::time::Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    let everything = capture.everything();
+    assert!(everything.contains("docs/a.txt"), "{everything}");
+    for secret in [
+        "mock-access-key",
+        "mock-secret-key",
+        BODY,
+        "X-Amz-Signature",
+    ] {
+        assert!(!everything.contains(secret), "{secret} in {everything}");
+    }
+}
+
+#[tokio::test]
+async fn list_stream_opens_a_span_per_page() {
+    use cloudflare_r2_rs::ListOptions;
+    use futures::TryStreamExt;
+
+    let server = MockServer::start().await.unwrap();
+    let client = server.client();
+    for key in ["logs/a", "logs/b", "logs/c"] {
+        client.put_object(key, "x").await.unwrap();
+    }
+    let (capture, _guard) = capture();
+
+    let objects: Vec<_> = client
+        .list_stream(ListOptions::new().prefix("logs/").max_keys(2))
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(objects.len(), 3);
+
+    let spans = capture.spans("list_stream");
+    assert_eq!(spans.len(), 2, "{spans:?}");
+    for span in &spans {
+        assert_eq!(span.field("bucket"), Some(MOCK_BUCKET));
+        assert_eq!(span.field("prefix"), Some("Some(\"logs/\")"));
+    }
+}